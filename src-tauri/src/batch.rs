@@ -0,0 +1,260 @@
+use crate::types::{BatchProgress, GlobalProgress, ProgressState, TaskProgress, TaskStats, TaskStatus};
+use dashmap::DashMap;
+
+/// Batches larger than this get one summary notification instead of one per task
+pub const BATCH_NOTIFICATION_THRESHOLD: usize = 5;
+
+#[derive(Debug, Default)]
+struct BatchCounter {
+    /// Member tasks that haven't finished yet
+    remaining: usize,
+    /// Set once every member has been added
+    sealed: bool,
+}
+
+/// Tracks when every task of a batch has finished.
+///
+/// Members are added while the batch is being created and may finish before the
+/// batch is sealed, so completion is only reported once it is sealed.
+#[derive(Debug, Default)]
+pub struct BatchTracker {
+    batches: DashMap<String, BatchCounter>,
+}
+
+impl BatchTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a member task of a batch that is still being created
+    pub fn add(&self, batch_id: &str) {
+        self.batches.entry(batch_id.to_string()).or_default().remaining += 1;
+    }
+
+    /// Marks the batch as complete; returns true if all members already finished
+    pub fn seal(&self, batch_id: &str) -> bool {
+        let finished = match self.batches.get_mut(batch_id) {
+            Some(mut counter) => {
+                counter.sealed = true;
+                counter.remaining == 0
+            }
+            None => false,
+        };
+        if finished {
+            self.batches.remove(batch_id);
+        }
+        finished
+    }
+
+    /// Records a finished member; returns true for the last member of a sealed batch
+    pub fn finish(&self, batch_id: &str) -> bool {
+        let finished = match self.batches.get_mut(batch_id) {
+            Some(mut counter) => {
+                counter.remaining = counter.remaining.saturating_sub(1);
+                counter.sealed && counter.remaining == 0
+            }
+            None => false,
+        };
+        if finished {
+            self.batches.remove(batch_id);
+        }
+        finished
+    }
+}
+
+/// Totals for the tasks of a batch; None if no task belongs to it
+pub fn batch_progress<'a>(batch_id: &str, tasks: impl Iterator<Item = &'a TaskProgress>) -> Option<BatchProgress> {
+    let mut batch = BatchProgress {
+        batch_id: batch_id.to_string(),
+        ..Default::default()
+    };
+    let mut progress_sum = 0.0;
+
+    for task in tasks.filter(|t| t.batch_id == batch_id) {
+        batch.total += 1;
+        match task.status {
+            TaskStatus::Completed => batch.completed += 1,
+            TaskStatus::Failed => batch.failed += 1,
+            TaskStatus::Cancelled | TaskStatus::Interrupted => batch.cancelled += 1,
+            _ => {}
+        }
+        // Finished tasks count as done whatever their last reported progress was
+        progress_sum += match task.status {
+            _ if task.is_finished() => 100.0,
+            _ => task.progress,
+        };
+    }
+
+    if batch.total == 0 {
+        return None;
+    }
+    batch.overall_progress = progress_sum / batch.total as f32;
+    Some(batch)
+}
+
+/// Counts tasks by status and adds up what the running ones have transferred and how fast
+pub fn task_stats<'a>(tasks: impl Iterator<Item = &'a TaskProgress>) -> TaskStats {
+    let mut stats = TaskStats::default();
+    for task in tasks {
+        match task.status {
+            TaskStatus::Queued => stats.queue_depth += 1,
+            TaskStatus::Downloading => stats.downloading += 1,
+            TaskStatus::Processing => stats.processing += 1,
+            TaskStatus::Paused => stats.paused += 1,
+            TaskStatus::Completed => stats.completed += 1,
+            TaskStatus::Failed => stats.failed += 1,
+            TaskStatus::Cancelled | TaskStatus::Interrupted => stats.cancelled += 1,
+        }
+        if matches!(task.status, TaskStatus::Downloading | TaskStatus::Processing) {
+            stats.active_bytes += task.downloaded_bytes.unwrap_or(0);
+            stats.total_speed_bytes_per_sec += task.speed_bytes_per_sec.unwrap_or(0);
+        }
+    }
+    stats
+}
+
+/// Overall progress of the tasks that aren't finished, each weighted by its known or
+/// estimated size. Tasks of unknown size count as the average known one. Live recordings
+/// count as active but have no percentage to add to the average.
+pub fn global_progress<'a>(tasks: impl Iterator<Item = &'a TaskProgress>) -> GlobalProgress {
+    let active: Vec<_> = tasks.filter(|t| !t.is_finished()).collect();
+    if active.is_empty() {
+        return GlobalProgress::default();
+    }
+    let measured: Vec<(f64, Option<f64>)> = active
+        .iter()
+        .filter(|t| !t.is_live)
+        .map(|t| {
+            let size = t.total_bytes.or(t.estimated_bytes).filter(|&bytes| bytes > 0);
+            (t.progress.clamp(0.0, 100.0) as f64, size.map(|bytes| bytes as f64))
+        })
+        .collect();
+    let known: Vec<f64> = measured.iter().filter_map(|(_, size)| *size).collect();
+    let unknown_size = if known.is_empty() { 1.0 } else { known.iter().sum::<f64>() / known.len() as f64 };
+    let (done, total) = measured.iter().fold((0.0, 0.0), |(done, total), (progress, size)| {
+        let weight = size.unwrap_or(unknown_size);
+        (done + progress * weight, total + weight)
+    });
+    let average = if total > 0.0 { (done / total) as f32 } else { 0.0 };
+    let state = if active.iter().all(|t| t.status == TaskStatus::Paused) {
+        ProgressState::Paused
+    } else {
+        ProgressState::Running
+    };
+    GlobalProgress {
+        active_tasks: active.len(),
+        // Tenths are enough for an indicator and keep unchanged progress from looking new
+        overall_percent: (average * 10.0).round() / 10.0,
+        state,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(batch_id: &str, status: TaskStatus, progress: f32) -> TaskProgress {
+        TaskProgress {
+            batch_id: batch_id.to_string(),
+            status,
+            progress,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_batch_progress() {
+        let tasks = [
+            task("a", TaskStatus::Completed, 100.0),
+            task("a", TaskStatus::Failed, 30.0),
+            task("a", TaskStatus::Processing, 50.0),
+            task("a", TaskStatus::Queued, 0.0),
+            task("b", TaskStatus::Completed, 100.0),
+        ];
+
+        let batch = batch_progress("a", tasks.iter()).unwrap();
+        assert_eq!((batch.total, batch.completed, batch.failed, batch.cancelled), (4, 1, 1, 0));
+        assert_eq!(batch.overall_progress, 62.5);
+        assert!(batch_progress("missing", tasks.iter()).is_none());
+    }
+
+    #[test]
+    fn test_task_stats() {
+        let running = |bytes: Option<u64>, speed: Option<u64>| TaskProgress {
+            status: TaskStatus::Downloading,
+            downloaded_bytes: bytes,
+            speed_bytes_per_sec: speed,
+            ..Default::default()
+        };
+        let paused = TaskProgress {
+            status: TaskStatus::Paused,
+            downloaded_bytes: Some(500),
+            speed_bytes_per_sec: Some(50),
+            ..Default::default()
+        };
+        let tasks = [
+            running(Some(1_000), Some(200)),
+            running(Some(3_000), None),
+            running(None, Some(100)),
+            paused,
+            task("a", TaskStatus::Queued, 0.0),
+            task("a", TaskStatus::Queued, 0.0),
+            task("b", TaskStatus::Completed, 100.0),
+        ];
+
+        let stats = task_stats(tasks.iter());
+        assert_eq!((stats.queue_depth, stats.downloading, stats.paused, stats.completed), (2, 3, 1, 1));
+        // Paused tasks aren't transferring, so they add neither bytes nor speed
+        assert_eq!(stats.active_bytes, 4_000);
+        assert_eq!(stats.total_speed_bytes_per_sec, 300);
+        assert_eq!(task_stats([].iter()), TaskStats::default());
+    }
+
+    #[test]
+    fn test_global_progress() {
+        let tasks = [
+            task("a", TaskStatus::Downloading, 40.0),
+            task("a", TaskStatus::Queued, 0.0),
+            task("b", TaskStatus::Processing, 95.0),
+            task("b", TaskStatus::Completed, 100.0),
+            task("b", TaskStatus::Cancelled, 10.0),
+            task("b", TaskStatus::Failed, 70.0),
+        ];
+        let progress = global_progress(tasks.iter());
+        assert_eq!(progress.active_tasks, 3);
+        assert_eq!(progress.overall_percent, 45.0);
+        assert_eq!(progress.state, ProgressState::Running);
+
+        let paused = [task("a", TaskStatus::Paused, 33.33)];
+        let progress = global_progress(paused.iter());
+        assert_eq!((progress.overall_percent, progress.state), (33.3, ProgressState::Paused));
+
+        // A large download counts for more than a small one, and one of unknown size for the average
+        let sized = |progress: f32, bytes: Option<u64>| TaskProgress { total_bytes: bytes, ..task("c", TaskStatus::Downloading, progress) };
+        let weighted = [sized(10.0, Some(900)), sized(90.0, Some(100))];
+        assert_eq!(global_progress(weighted.iter()).overall_percent, 18.0);
+        let mixed = [sized(10.0, Some(900)), sized(90.0, Some(100)), sized(50.0, None)];
+        assert_eq!(global_progress(mixed.iter()).overall_percent, 28.7);
+
+        // With nothing left the indicator is told to clear
+        let idle = global_progress(tasks[3..].iter());
+        assert_eq!(idle, GlobalProgress { active_tasks: 0, overall_percent: 0.0, state: ProgressState::Idle });
+    }
+
+    #[test]
+    fn test_batch_tracker_reports_completion_once() {
+        let tracker = BatchTracker::new();
+        tracker.add("a");
+        tracker.add("a");
+        // Finishing before the batch is sealed doesn't complete it
+        assert!(!tracker.finish("a"));
+        assert!(!tracker.seal("a"));
+        assert!(tracker.finish("a"));
+        assert!(!tracker.finish("a"));
+
+        // Every member finished before sealing
+        tracker.add("b");
+        assert!(!tracker.finish("b"));
+        assert!(tracker.seal("b"));
+    }
+}
@@ -1,19 +1,32 @@
+use crate::config::Config;
 use crate::converter::ConversionManager;
 use crate::downloader::DownloadManager;
 use crate::system::*;
 use crate::types::*;
-use tauri::State;
+use std::sync::Arc;
+use tauri::{Emitter, Manager, State};
 use tracing::{info, error, instrument};
 use uuid::Uuid;
 
 pub struct AppState {
     pub download_manager: DownloadManager,
     pub conversion_manager: ConversionManager,
+    /// Shared with the managers, which check it before running yt-dlp or ffmpeg
+    pub tools: Arc<ToolPaths>,
+}
+
+impl AppState {
+    /// Every download and conversion task
+    pub fn all_tasks(&self) -> Vec<TaskProgress> {
+        let mut tasks = self.download_manager.get_all_tasks();
+        tasks.extend(self.conversion_manager.get_all_tasks());
+        tasks
+    }
 }
 
 #[tauri::command]
-#[instrument]
-pub async fn check_dependencies() -> Result<SystemInfo, String> {
+#[instrument(skip(state))]
+pub async fn check_dependencies(state: State<'_, AppState>) -> Result<SystemInfo, String> {
     let correlation_id = Uuid::new_v4().to_string();
     info!(
         correlation_id = correlation_id,
@@ -21,22 +34,56 @@ pub async fn check_dependencies() -> Result<SystemInfo, String> {
         "Checking system dependencies"
     );
     
-    let result = check_system_dependencies();
+    let result = state.tools.system_info().await;
     info!(
         correlation_id = correlation_id,
         has_ytdlp = result.has_ytdlp,
         has_ffmpeg = result.has_ffmpeg,
+        has_imagemagick = result.has_imagemagick,
         ytdlp_path = ?result.ytdlp_path,
         ffmpeg_path = ?result.ffmpeg_path,
+        imagemagick_path = ?result.imagemagick_path,
         "System dependency check completed"
     );
     
     Ok(result)
 }
 
+/// Checks the dependencies again instead of reusing the last check, e.g. after the
+/// user installed one themselves
+#[tauri::command]
+#[instrument(skip(state))]
+pub async fn refresh_dependencies(state: State<'_, AppState>) -> Result<SystemInfo, String> {
+    let result = state.tools.refresh().await;
+    info!(
+        has_ytdlp = result.has_ytdlp,
+        has_ffmpeg = result.has_ffmpeg,
+        has_imagemagick = result.has_imagemagick,
+        "System dependencies checked again"
+    );
+    Ok(result)
+}
+
+/// Installs ffmpeg with the platform's package manager, or explains how to
 #[tauri::command]
-#[instrument]
-pub async fn install_ytdlp_command() -> Result<String, String> {
+pub async fn install_ffmpeg(
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<InstallResult, String> {
+    let result = crate::system::install_ffmpeg(&app_handle, &state.tools).await;
+    info!(
+        installed = result.installed,
+        command = ?result.command,
+        message = ?result.message,
+        has_ffmpeg = result.system_info.has_ffmpeg,
+        "ffmpeg installation finished"
+    );
+    Ok(result)
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub async fn install_ytdlp_command(state: State<'_, AppState>) -> Result<YtdlpInstallResult, String> {
     let correlation_id = Uuid::new_v4().to_string();
     info!(
         correlation_id = correlation_id,
@@ -44,10 +91,12 @@ pub async fn install_ytdlp_command() -> Result<String, String> {
         "Installing yt-dlp dependency"
     );
     
-    match install_ytdlp() {
+    match install_ytdlp(&state.tools).await {
         Ok(result) => {
             info!(
                 correlation_id = correlation_id,
+                strategy = ?result.strategy,
+                version = %result.version,
                 "yt-dlp installation completed successfully"
             );
             Ok(result)
@@ -141,16 +190,82 @@ pub async fn cancel_download(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn set_task_priority(
+    task_id: String,
+    priority: i32,
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    state
+        .download_manager
+        .set_task_priority(&task_id, priority, &app_handle)
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn remove_task(
     task_id: String,
     state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let removed = state.download_manager.remove_task(&task_id) | state.conversion_manager.remove_task(&task_id);
+    if removed {
+        crate::events::emit_task_removed(&app_handle, vec![task_id]);
+    }
+    Ok(())
+}
+
+/// Removes every finished download and conversion, returning how many were removed
+#[tauri::command]
+pub async fn clear_finished_tasks(
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<usize, String> {
+    let mut removed = state.download_manager.clear_finished_tasks();
+    removed.extend(state.conversion_manager.clear_finished_tasks());
+    let count = removed.len();
+    crate::events::emit_task_removed(&app_handle, removed);
+    Ok(count)
+}
+
+#[tauri::command]
+pub async fn get_config(app_handle: tauri::AppHandle) -> Result<Config, String> {
+    let path = crate::config::config_path(&app_handle).map_err(|e| e.to_string())?;
+    Ok(crate::config::load_config(&path))
+}
+
+/// Saves the settings and sends them to every window as a `settings-changed` event.
+/// Notification, timeout and queue limit changes apply right away; concurrency from
+/// the next start.
+#[tauri::command]
+pub async fn set_config(
+    config: Config,
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
 ) -> Result<(), String> {
-    state.download_manager.remove_task(&task_id);
-    state.conversion_manager.remove_task(&task_id);
+    let path = crate::config::config_path(&app_handle).map_err(|e| e.to_string())?;
+    crate::config::save_config(&path, &config).map_err(|e| e.to_string())?;
+    crate::notifications::set_enabled(config.notifications);
+    state.download_manager.apply_config(&config);
+    state.conversion_manager.apply_config(&config);
+    let _ = app_handle.emit("settings-changed", &config);
     Ok(())
 }
 
+#[tauri::command]
+pub async fn get_history(
+    query: HistoryQuery,
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<HistoryPage, String> {
+    state
+        .download_manager
+        .get_history(query, &app_handle)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 #[instrument(skip(state, app_handle))]
 pub async fn start_conversion(
@@ -193,6 +308,120 @@ pub async fn start_conversion(
     }
 }
 
+#[tauri::command]
+#[instrument(skip(state, app_handle))]
+pub async fn convert_folder(
+    request: ConvertFolderRequest,
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<ConvertFolderResult, String> {
+    let correlation_id = Uuid::new_v4().to_string();
+    info!(
+        correlation_id = correlation_id,
+        command = "convert_folder",
+        folder = %request.folder,
+        dry_run = request.dry_run,
+        "Starting folder conversion request"
+    );
+    
+    match state.conversion_manager.convert_folder(request, app_handle).await {
+        Ok(result) => {
+            info!(
+                correlation_id = correlation_id,
+                matched = result.matched_files.len(),
+                task_count = result.task_ids.len(),
+                "Folder conversion request completed"
+            );
+            Ok(result)
+        }
+        Err(e) => {
+            error!(
+                correlation_id = correlation_id,
+                error = %e,
+                "Failed to convert folder"
+            );
+            Err(e.to_string())
+        }
+    }
+}
+
+/// Input extensions a conversion type accepts, for filtering the file picker
+#[tauri::command]
+pub async fn list_supported_input_formats(conversion_type: ConversionType) -> Result<Vec<String>, String> {
+    Ok(conversion_type.input_formats().into_iter().map(str::to_string).collect())
+}
+
+/// Checks dropped or picked files before converting them; reports each one's problem,
+/// kind and size
+#[tauri::command]
+pub async fn validate_files(paths: Vec<String>, conversion_type: ConversionType) -> Result<Vec<FileValidation>, String> {
+    crate::converter::validate_files(&paths, &conversion_type)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn probe_media(path: String) -> Result<MediaProbe, String> {
+    crate::converter::probe_media(std::path::Path::new(&path))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Lists a video's subtitle tracks and chapters, writing one out when requested
+#[tauri::command]
+pub async fn extract_streams(request: ExtractStreamsRequest) -> Result<ExtractStreamsResult, String> {
+    crate::converter::extract_streams(&request)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Estimated output bytes of each input file, as [input path, bytes] pairs. The same
+/// figures `estimate_conversion` reports, without its disk space check.
+#[tauri::command]
+pub async fn estimate_output_size(
+    request: ConvertRequest,
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<(std::path::PathBuf, u64)>, String> {
+    let estimate = estimate_conversion(request, state, app_handle).await?;
+    Ok(estimate.files.into_iter().map(|file| (file.input_file, file.estimated_bytes)).collect())
+}
+
+#[tauri::command]
+pub async fn estimate_conversion(
+    request: ConvertRequest,
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<ConversionEstimate, String> {
+    state
+        .conversion_manager
+        .estimate_conversion(request, &app_handle)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn save_preset(
+    name: String,
+    settings: PresetSettings,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let dir = crate::presets::presets_dir(&app_handle).map_err(|e| e.to_string())?;
+    crate::presets::save_preset(&dir, &ConversionPreset { name, settings }).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_presets(app_handle: tauri::AppHandle) -> Result<Vec<ConversionPreset>, String> {
+    let dir = crate::presets::presets_dir(&app_handle).map_err(|e| e.to_string())?;
+    crate::presets::list_presets(&dir).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_preset(name: String, app_handle: tauri::AppHandle) -> Result<(), String> {
+    let dir = crate::presets::presets_dir(&app_handle).map_err(|e| e.to_string())?;
+    crate::presets::delete_preset(&dir, &name).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn get_conversion_tasks(state: State<'_, AppState>) -> Result<Vec<TaskProgress>, String> {
     Ok(state.conversion_manager.get_all_tasks())
@@ -210,48 +439,124 @@ pub async fn cancel_conversion(
         .map_err(|e| e.to_string())
 }
 
+/// Totals for the tasks created by one download or conversion request
+#[tauri::command]
+pub async fn get_batch(
+    batch_id: String,
+    state: State<'_, AppState>,
+) -> Result<Option<BatchProgress>, String> {
+    Ok(state
+        .download_manager
+        .get_batch(&batch_id)
+        .or_else(|| state.conversion_manager.get_batch(&batch_id)))
+}
+
+/// Task counts, transferred bytes and combined speed across downloads and conversions
+#[tauri::command]
+pub async fn get_stats(state: State<'_, AppState>) -> Result<TaskStats, String> {
+    Ok(crate::batch::task_stats(state.all_tasks().iter()))
+}
+
+/// Unfinished downloads and conversions, so the frontend can ask before quitting
+#[tauri::command]
+pub async fn get_active_task_count(state: State<'_, AppState>) -> Result<usize, String> {
+    Ok(state.all_tasks().iter().filter(|task| !task.is_finished()).count())
+}
+
+/// Overall progress of unfinished tasks, the same figures `global-progress` sends
+#[tauri::command]
+pub async fn get_global_progress(state: State<'_, AppState>) -> Result<GlobalProgress, String> {
+    Ok(crate::batch::global_progress(state.all_tasks().iter()))
+}
+
+/// Folder the daily log files are written to
+#[tauri::command]
+pub async fn get_log_directory(app_handle: tauri::AppHandle) -> Result<String, String> {
+    let dir = app_handle.path().app_log_dir().map_err(|e| e.to_string())?;
+    Ok(dir.to_string_lossy().into_owned())
+}
+
+/// Zips recent logs and a dependency snapshot with home folders hidden into
+/// `destination`, for a bug report. Returns the path written.
+#[tauri::command]
+pub async fn export_logs(
+    destination: String,
+    days: Option<u32>,
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    let days = days.unwrap_or(crate::logging::DEFAULT_EXPORT_DAYS);
+    if days == 0 || days as usize > crate::logging::MAX_LOG_FILES {
+        return Err(format!("Days must be between 1 and {}, got {}", crate::logging::MAX_LOG_FILES, days));
+    }
+    let destination = crate::error::validation::sanitize_output_path(&destination)
+        .map_err(|e| e.to_string())?
+        .into_path();
+    let log_dir = app_handle.path().app_log_dir().map_err(|e| e.to_string())?;
+    let system_info = state.tools.system_info().await;
+
+    let written = destination.clone();
+    let log_count = tokio::task::spawn_blocking(move || {
+        crate::logging::export_logs(&log_dir, &written, days, &system_info)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    info!(destination = %destination.display(), log_count, days, "Exported logs");
+    Ok(destination.to_string_lossy().into_owned())
+}
+
 #[tauri::command]
 pub async fn select_directory() -> Result<String, String> {
     // This will be handled by the frontend using @tauri-apps/plugin-dialog
     Err("Use dialog plugin from frontend".to_string())
 }
 
+/// Checks a folder picked in the dialog right away, returning the path downloads and
+/// conversions will write to
+#[tauri::command]
+pub async fn validate_output_directory(path: String) -> Result<String, String> {
+    crate::error::validation::validate_output_directory(&path)
+        .await
+        .map(|dir| dir.to_string_lossy().into_owned())
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn open_folder(path: String) -> Result<(), String> {
-    use std::process::Command;
-    
-    // Extract directory path if a file path is provided
-    let folder_path = std::path::Path::new(&path);
-    let folder = if folder_path.is_file() {
-        folder_path.parent().unwrap_or(folder_path)
-    } else {
-        folder_path
-    };
-    
-    // Open folder based on OS
-    #[cfg(target_os = "linux")]
-    {
-        Command::new("xdg-open")
-            .arg(folder)
-            .spawn()
-            .map_err(|e| format!("Failed to open folder: {}", e))?;
-    }
-    
-    #[cfg(target_os = "macos")]
-    {
-        Command::new("open")
-            .arg(folder)
-            .spawn()
-            .map_err(|e| format!("Failed to open folder: {}", e))?;
-    }
-    
-    #[cfg(target_os = "windows")]
-    {
-        Command::new("explorer")
-            .arg(folder)
-            .spawn()
-            .map_err(|e| format!("Failed to open folder: {}", e))?;
-    }
-    
-    Ok(())
+    open_in_file_manager(std::path::Path::new(&path))
+}
+
+/// Shows a file selected in the file manager
+#[tauri::command]
+pub async fn reveal_file(path: String) -> Result<(), String> {
+    reveal_in_file_manager(std::path::Path::new(&path)).await
+}
+
+/// Copies a finished task's file to the clipboard, as its path or for pasting into
+/// a file manager
+#[tauri::command]
+pub async fn copy_to_clipboard(
+    task_id: String,
+    mode: ClipboardMode,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let task = state
+        .download_manager
+        .get_task(&task_id)
+        .or_else(|| state.conversion_manager.get_task(&task_id))
+        .ok_or_else(|| crate::error::MediaForgeError::TaskNotFound(task_id.clone()).to_string())?;
+    let file_path = task.file_path.ok_or_else(|| format!("{} has no file yet", task.name))?;
+    let file = crate::error::validation::sanitize_path(&file_path)
+        .ok()
+        .filter(|path| path.is_file())
+        .ok_or_else(|| format!("{} was moved or deleted", file_path))?;
+    copy_file_to_clipboard(&app_handle, &file, mode).await
+}
+
+/// Opens a file with its default application, e.g. plays a finished video
+#[tauri::command]
+pub async fn open_file(path: String) -> Result<(), String> {
+    open_with_default_app(std::path::Path::new(&path))
 }
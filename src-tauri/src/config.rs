@@ -0,0 +1,190 @@
+use crate::error::{validation, MediaForgeError};
+use crate::types::MediaFormat;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+/// File inside the app config dir holding the settings
+const CONFIG_FILE: &str = "config.json";
+
+/// Version of the settings file layout, bumped when a field is renamed or changes meaning
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Upper bound on `max_concurrent_downloads`; more mostly gets a client throttled
+pub const MAX_CONCURRENT_DOWNLOADS_LIMIT: usize = 10;
+
+/// Upper bound on `max_queue_size`
+pub const MAX_QUEUE_SIZE_LIMIT: usize = 10_000;
+
+/// User settings. Every field has a default, so missing or partial files load with
+/// the gaps filled in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Layout the file was written with; see `migrate`
+    pub version: u32,
+    /// Folder the download form starts with
+    pub download_path: String,
+    /// Format the download form starts with
+    pub download_format: MediaFormat,
+    /// Folder the conversion form starts with
+    pub conversion_output_path: String,
+    /// Downloads running at once; takes effect on the next start
+    pub max_concurrent_downloads: usize,
+    /// Unfinished tasks each of downloads and conversions may hold; requests adding more are refused
+    pub max_queue_size: usize,
+    /// Per-download timeout used when a request sets none; 0 disables it.
+    /// Takes effect on the next start.
+    pub download_timeout_secs: u64,
+    /// Per-conversion timeout used when a request sets none; 0 disables it.
+    /// Takes effect on the next start.
+    pub conversion_timeout_secs: u64,
+    /// Show desktop notifications when tasks finish
+    pub notifications: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            download_path: "~/Downloads".to_string(),
+            download_format: MediaFormat::Mp4,
+            conversion_output_path: "~/Downloads".to_string(),
+            max_concurrent_downloads: crate::queue::MAX_CONCURRENT_DOWNLOADS,
+            max_queue_size: crate::queue::MAX_QUEUE_SIZE,
+            download_timeout_secs: crate::downloader::DEFAULT_DOWNLOAD_TIMEOUT_SECS,
+            conversion_timeout_secs: crate::converter::DEFAULT_CONVERSION_TIMEOUT_SECS,
+            notifications: true,
+        }
+    }
+}
+
+impl Config {
+    pub fn validate(&self) -> Result<(), MediaForgeError> {
+        if !(1..=MAX_CONCURRENT_DOWNLOADS_LIMIT).contains(&self.max_concurrent_downloads) {
+            return Err(MediaForgeError::InvalidSettings(format!(
+                "Concurrent downloads must be between 1 and {}, got {}",
+                MAX_CONCURRENT_DOWNLOADS_LIMIT, self.max_concurrent_downloads
+            )));
+        }
+        if !(1..=MAX_QUEUE_SIZE_LIMIT).contains(&self.max_queue_size) {
+            return Err(MediaForgeError::InvalidSettings(format!(
+                "The queue limit must be between 1 and {}, got {}",
+                MAX_QUEUE_SIZE_LIMIT, self.max_queue_size
+            )));
+        }
+        validation::resolve_timeout(Some(self.download_timeout_secs), 0)?;
+        validation::resolve_timeout(Some(self.conversion_timeout_secs), 0)?;
+        // System folders are refused when a task starts, so a home folder under one
+        // (as /root is) doesn't make the defaults invalid
+        validation::resolve_path(&self.download_path)?;
+        validation::resolve_path(&self.conversion_output_path)?;
+        Ok(())
+    }
+}
+
+/// Brings settings written by an older version up to `CONFIG_VERSION`. Fields added
+/// since then need nothing here: they're missing from the file and take their defaults.
+fn migrate(mut value: serde_json::Value) -> serde_json::Value {
+    let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(0);
+    if version > CONFIG_VERSION as u64 {
+        log::warn!("Settings were saved by a newer MediaForge (version {}); unknown fields are ignored", version);
+        return value;
+    }
+    // Version 0 files predate the version field but have the same layout
+    if let Some(fields) = value.as_object_mut() {
+        fields.insert("version".to_string(), CONFIG_VERSION.into());
+    }
+    value
+}
+
+pub fn config_path(app: &tauri::AppHandle) -> Result<PathBuf, MediaForgeError> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join(CONFIG_FILE))
+        .map_err(|e| MediaForgeError::FileSystemError(format!("Could not locate the app config folder: {}", e)))
+}
+
+/// Reads the settings, falling back to defaults when the file is missing. A file
+/// that can't be parsed or holds invalid values is logged and ignored rather than
+/// stopping the app from starting; it is replaced on the next save.
+pub fn load_config(path: &Path) -> Config {
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(e) => {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("Could not read {:?}, using default settings: {}", path, e);
+            }
+            return Config::default();
+        }
+    };
+
+    let config = serde_json::from_slice(&data).and_then(|value| serde_json::from_value::<Config>(migrate(value)));
+    match config {
+        Ok(config) => match config.validate() {
+            Ok(()) => config,
+            Err(e) => {
+                log::warn!("Ignoring invalid settings in {:?}: {}", path, e);
+                Config::default()
+            }
+        },
+        Err(e) => {
+            log::warn!("Ignoring unreadable settings file {:?}: {}", path, e);
+            Config::default()
+        }
+    }
+}
+
+pub fn save_config(path: &Path, config: &Config) -> Result<(), MediaForgeError> {
+    config.validate()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let data = serde_json::to_vec_pretty(config)
+        .map_err(|e| MediaForgeError::FileSystemError(format!("Could not encode settings: {}", e)))?;
+    std::fs::write(path, data)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_config() {
+        let dir = std::env::temp_dir().join(format!("mediaforge-config-{}", uuid::Uuid::new_v4()));
+        let path = dir.join(CONFIG_FILE);
+
+        assert_eq!(load_config(&path), Config::default());
+
+        // Missing fields keep their defaults
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&path, r#"{"max_concurrent_downloads": 5, "notifications": false}"#).unwrap();
+        let config = load_config(&path);
+        assert_eq!(config.max_concurrent_downloads, 5);
+        assert!(!config.notifications);
+        assert_eq!(config.download_path, "~/Downloads");
+
+        // Broken or out-of-range files fall back to defaults
+        std::fs::write(&path, "{not json").unwrap();
+        assert_eq!(load_config(&path), Config::default());
+        std::fs::write(&path, r#"{"max_concurrent_downloads": 0}"#).unwrap();
+        assert_eq!(load_config(&path), Config::default());
+
+        // Files from before settings were versioned, or with fields since removed, still load
+        std::fs::write(&path, r#"{"notifications": false, "theme": "dark"}"#).unwrap();
+        let config = load_config(&path);
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert!(!config.notifications);
+
+        let config = Config { download_timeout_secs: 0, ..Config::default() };
+        save_config(&path, &config).unwrap();
+        assert_eq!(load_config(&path), config);
+        assert!(save_config(&path, &Config { max_concurrent_downloads: 11, ..Config::default() }).is_err());
+        assert!(save_config(&path, &Config { max_queue_size: 0, ..Config::default() }).is_err());
+        assert!(save_config(&path, &Config { download_path: "~/../etc".to_string(), ..Config::default() }).is_err());
+        assert!(Config { download_path: "/root/Downloads".to_string(), ..Config::default() }.validate().is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,46 +1,46 @@
+use crate::batch::{batch_progress, BatchTracker, BATCH_NOTIFICATION_THRESHOLD};
+use crate::config::Config;
+use crate::error::validation::{self, sanitize_output_path, sanitize_path, OutputPath};
 use crate::error::MediaForgeError;
+use crate::events::{self, TaskUpdateEmitter};
+use crate::hooks;
 use crate::notifications;
+use crate::process::{run_cancellable_command, CommandOutcome, OutputTail};
+use crate::system::{ImageMagick, ToolPaths};
+use crate::tasks::{self, TaskHandle};
 use crate::types::*;
 use dashmap::DashMap;
+use futures::stream::{self, StreamExt};
 use regex::Regex;
-use std::path::PathBuf;
-use std::process::Stdio;
-use std::sync::Arc;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
 use tauri::Emitter;
-use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command as TokioCommand;
-use tokio::task::JoinHandle;
 use tokio::time::Duration;
 use tokio_util::sync::CancellationToken;
 use tracing::{info, warn, error};
 use uuid::Uuid;
 
-/// Handle for managing conversion task lifecycle with cancellation support
-struct TaskHandle {
-    join_handle: JoinHandle<()>,
-    cancellation_token: CancellationToken,
-}
+/// Conversion timeout used when neither the request nor the settings specify one (2 hours)
+pub const DEFAULT_CONVERSION_TIMEOUT_SECS: u64 = 7200;
 
-impl TaskHandle {
-    fn new(join_handle: JoinHandle<()>, cancellation_token: CancellationToken) -> Self {
-        Self {
-            join_handle,
-            cancellation_token,
-        }
-    }
+/// Subfolder levels searched by folder conversion when no depth is given
+const DEFAULT_FOLDER_DEPTH: u32 = 8;
+const MAX_FOLDER_DEPTH: u32 = 32;
 
-    async fn cancel(self) -> Result<(), tokio::task::JoinError> {
-        self.cancellation_token.cancel();
-        self.join_handle.await
-    }
+/// Upper bound on the files one folder conversion can queue
+const MAX_FOLDER_FILES: usize = 5000;
 
-    fn is_cancelled(&self) -> bool {
-        self.cancellation_token.is_cancelled()
-    }
-}
+/// stderr lines kept to explain a failed FFmpeg or ImageMagick run
+const ERROR_OUTPUT_LINES: usize = 20;
+
+/// Minimum time between FFmpeg progress events for one task
+const PROGRESS_EMIT_INTERVAL: Duration = Duration::from_millis(500);
 
 /// Validates input file paths to ensure they exist and are not system files
-fn validate_input_file(file_path: &PathBuf) -> Result<(), MediaForgeError> {
+fn validate_input_path(file_path: &Path) -> Result<(), MediaForgeError> {
     // Check if file exists
     if !file_path.exists() {
         return Err(MediaForgeError::FileSystemError(
@@ -91,69 +91,392 @@ fn validate_input_file(file_path: &PathBuf) -> Result<(), MediaForgeError> {
     Ok(())
 }
 
-/// Sanitizes file paths to prevent path traversal and ensure paths are within allowed directories
-fn sanitize_path(path: &str) -> Result<PathBuf, MediaForgeError> {
-    // Expand tilde to home directory
-    let expanded_path = if path.starts_with("~/") {
-        let home = std::env::var("HOME")
-            .or_else(|_| std::env::var("USERPROFILE"))  // Windows support
-            .unwrap_or_else(|_| "/home".to_string());
-        path.replacen("~", &home, 1)
-    } else {
-        path.to_string()
-    };
+/// Validates input file paths to ensure they exist, are not system files and have an
+/// extension `conversion_type` accepts
+fn validate_input_file(file_path: &PathBuf, conversion_type: &ConversionType) -> Result<(), MediaForgeError> {
+    validate_input_path(file_path)?;
     
-    let path_buf = PathBuf::from(&expanded_path);
+    // Only formats the conversion handles; anything else would fail inside FFmpeg or
+    // ImageMagick with a far less helpful error
+    let formats = conversion_type.input_formats();
+    let extension = file_path.extension().and_then(|e| e.to_str()).map(str::to_lowercase);
+    if !extension.as_deref().is_some_and(|e| formats.contains(&e)) {
+        let kind = match conversion_type {
+            ConversionType::Image => "image",
+            ConversionType::Video => "video",
+            ConversionType::Audio => "audio",
+            ConversionType::Auto => "media",
+        };
+        return Err(MediaForgeError::InvalidSettings(format!(
+            "{} is not a supported {} file; supported formats: {}",
+            file_path.display(),
+            kind,
+            formats.join(", ")
+        )));
+    }
     
-    // Prevent path traversal attacks
-    for component in path_buf.components() {
-        if matches!(component, std::path::Component::ParentDir) {
-            return Err(MediaForgeError::InvalidSettings(
-                "Path traversal detected: '..' not allowed in paths".into()
-            ));
+    Ok(())
+}
+
+const FILENAME_PLACEHOLDERS: [&str; 6] = ["stem", "ext", "format", "date", "counter", "index"];
+
+/// Expands `{placeholder}`s in a filename template with `value`. The result must be a
+/// plain file name: no separators and not `.` or `..`.
+fn expand_filename_template(template: &str, value: impl Fn(&str) -> String) -> Result<String, MediaForgeError> {
+    let mut name = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        name.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let end = after.find('}').ok_or_else(|| {
+            MediaForgeError::InvalidSettings(format!("Unclosed '{{' in filename template '{}'", template))
+        })?;
+
+        let placeholder = &after[..end];
+        if !FILENAME_PLACEHOLDERS.contains(&placeholder) {
+            return Err(MediaForgeError::InvalidSettings(format!(
+                "Unknown placeholder '{{{}}}' in filename template; supported: {}",
+                placeholder,
+                FILENAME_PLACEHOLDERS.map(|p| format!("{{{}}}", p)).join(", ")
+            )));
         }
+        name.push_str(&value(placeholder));
+        rest = &after[end + 1..];
     }
-    
-    // Check for other dangerous path components
-    if expanded_path.contains("//") || expanded_path.contains("\\\\") {
+    name.push_str(rest);
+
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\', '\0']) {
+        return Err(MediaForgeError::InvalidSettings(format!(
+            "Filename template '{}' must produce a plain file name without path separators",
+            template
+        )));
+    }
+    Ok(name)
+}
+
+/// Checks a filename template before any task starts. In a batch every file needs a
+/// distinct name, so the template must use `{stem}`, `{counter}` or `{index}`.
+fn validate_filename_template(template: &str, batch: bool) -> Result<(), MediaForgeError> {
+    expand_filename_template(template, |_| "x".to_string())?;
+
+    if batch && !["{stem}", "{counter}", "{index}"].iter().any(|p| template.contains(p)) {
         return Err(MediaForgeError::InvalidSettings(
-            "Invalid path: double separators not allowed".into()
+            "Filename template needs {stem}, {counter} or {index} when converting several files".into()
         ));
     }
-    
-    // Ensure path is absolute or can be made absolute
-    let canonical_path = if path_buf.is_absolute() {
-        path_buf
+    Ok(())
+}
+
+/// Output file name for `input_file`, from the request's template if it has one
+fn output_file_name(request: &ConvertRequest, input_file: &Path, extension: &str) -> Result<String, MediaForgeError> {
+    let stem = input_file
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| MediaForgeError::InvalidSettings("Invalid input filename".to_string()))?;
+
+    let Some(template) = &request.filename_template else {
+        return Ok(format!("{}.{}", stem, extension));
+    };
+
+    let counter = request.input_files.iter().position(|f| f == input_file).map_or(1, |i| i + 1);
+    let input_format = input_file
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+
+    let name = expand_filename_template(template, |placeholder| match placeholder {
+        "stem" => stem.to_string(),
+        "ext" => extension.to_string(),
+        "format" => input_format.clone(),
+        "date" => date.clone(),
+        // Zero-padded so the outputs sort in batch order
+        "index" => format!("{:0width$}", counter, width = request.input_files.len().to_string().len()),
+        _ => counter.to_string(),
+    })?;
+
+    if template.contains("{ext}") {
+        Ok(name)
     } else {
-        std::env::current_dir()
-            .map_err(|e| MediaForgeError::FileSystemError(format!("Cannot get current directory: {}", e)))?
-            .join(path_buf)
+        Ok(format!("{}.{}", name, extension))
+    }
+}
+
+/// Where the output for `input_file` goes, and whether a file is already there for
+/// the conflict policy to deal with
+fn planned_output_path(request: &ConvertRequest, input_file: &Path, extension: &str) -> Result<OutputPath, MediaForgeError> {
+    let output_path = resolve_output_dir(request, input_file)?.join(output_file_name(request, input_file, extension)?);
+    sanitize_output_path(&output_path.to_string_lossy())
+}
+
+/// Applies the conflict policy to an output that may already exist. `None` means the
+/// existing file is kept. Replacing the input file itself is always refused.
+fn conflict_output_path(
+    output_path: OutputPath,
+    input_file: &Path,
+    policy: Option<ConflictPolicy>,
+) -> Result<Option<PathBuf>, MediaForgeError> {
+    let output_path = match output_path {
+        OutputPath::New(path) => return Ok(Some(path)),
+        OutputPath::Existing(path) => path,
     };
-    
-    // Verify the parent directory exists or can be created
-    if let Some(parent) = canonical_path.parent() {
-        if !parent.exists() {
-            std::fs::create_dir_all(parent)
-                .map_err(|e| MediaForgeError::FileSystemError(format!("Cannot create directory: {}", e)))?;
+
+    let is_input = match (output_path.canonicalize(), input_file.canonicalize()) {
+        (Ok(output), Ok(input)) => output == input,
+        _ => false,
+    };
+    match policy {
+        Some(ConflictPolicy::Skip) => Ok(None),
+        Some(ConflictPolicy::Rename) => Ok(Some(crate::downloader::free_output_path(&output_path))),
+        Some(ConflictPolicy::Overwrite) | None if is_input => Err(MediaForgeError::InvalidSettings(format!(
+            "Output {:?} is the input file; choose another name, folder or the rename policy",
+            output_path
+        ))),
+        Some(ConflictPolicy::Overwrite) | None => Ok(Some(output_path)),
+    }
+}
+
+/// Deepest directory containing every input file
+fn common_parent_dir(input_files: &[PathBuf]) -> PathBuf {
+    let mut parents = input_files.iter().filter_map(|f| f.parent());
+    let Some(first) = parents.next() else {
+        return PathBuf::new();
+    };
+
+    parents.fold(first.to_path_buf(), |common, parent| {
+        common
+            .components()
+            .zip(parent.components())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a)
+            .collect()
+    })
+}
+
+/// Output directory for `input_file` when mirroring the inputs' folder structure under `root`
+fn mirrored_output_dir(root: &Path, base: &Path, input_file: &Path) -> PathBuf {
+    let parent = input_file.parent().unwrap_or(Path::new(""));
+    let relative = parent.strip_prefix(base).unwrap_or(parent);
+
+    // Only keep plain folder names so the result can't escape `root`
+    let mut output_dir = root.to_path_buf();
+    output_dir.extend(relative.components().filter(|c| matches!(c, std::path::Component::Normal(_))));
+    output_dir
+}
+
+/// Directory the output for `input_file` is written to under the request's output mode.
+/// Missing directories are created.
+fn resolve_output_dir(request: &ConvertRequest, input_file: &Path) -> Result<PathBuf, MediaForgeError> {
+    let output_dir = match &request.output_mode {
+        OutputMode::SingleDirectory => PathBuf::from(&request.output_path),
+        OutputMode::AlongsideInput => input_file.parent().unwrap_or(Path::new("")).to_path_buf(),
+        OutputMode::MirrorTree { root } => {
+            let base = common_parent_dir(&request.input_files);
+            mirrored_output_dir(&sanitize_path(root)?, &base, input_file)
+        }
+    };
+
+    let output_dir = sanitize_path(&output_dir.to_string_lossy())?;
+    validation::create_dir(&output_dir)?;
+    Ok(output_dir)
+}
+
+/// Matches a file name against a pattern where `*` is any run of characters and `?` is
+/// one character. Case-insensitive, like extension checks elsewhere.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it is currently matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_n)) => {
+                    p = star_p;
+                    n = star_n + 1;
+                    backtrack = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
         }
     }
-    
-    // Ensure path is within reasonable bounds (not system directories)
-    let path_str = canonical_path.to_string_lossy();
-    if path_str.starts_with("/etc") 
-        || path_str.starts_with("/sys") 
-        || path_str.starts_with("/proc")
-        || path_str.starts_with("/boot")
-        || path_str.starts_with("/root")  // Unless we're root
-        || path_str.contains("/.ssh/")
-        || path_str.contains("/.gnupg/")
-    {
-        return Err(MediaForgeError::InvalidSettings(
-            "Access to system directories is not allowed".into()
-        ));
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Whether a file name passes the folder filter. Entries with wildcards are matched
+/// against the whole name, anything else is an extension.
+fn matches_folder_filter(file_name: &str, filter: &[String]) -> bool {
+    if filter.is_empty() {
+        return true;
     }
-    
-    Ok(canonical_path)
+
+    filter.iter().any(|entry| {
+        let entry = entry.trim();
+        if entry.contains(['*', '?']) {
+            wildcard_match(entry, file_name)
+        } else {
+            let extension = entry.trim_start_matches("*.").trim_start_matches('.');
+            Path::new(file_name)
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| e.eq_ignore_ascii_case(extension))
+        }
+    })
+}
+
+/// Walks `root` up to `max_depth` levels deep and returns the files that pass the
+/// filter, sorted. Hidden entries and symlinks are skipped, as are files rejected by
+/// the usual input checks for `conversion_type`. Blocking; run it off the async runtime.
+fn collect_folder_inputs(
+    root: &Path,
+    filter: &[String],
+    max_depth: u32,
+    conversion_type: &ConversionType,
+) -> Result<Vec<PathBuf>, MediaForgeError> {
+    let mut matches = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), 0)];
+
+    while let Some((dir, depth)) = pending.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            // The root must be readable; unreadable subfolders are skipped
+            Err(e) if dir == root => return Err(e.into()),
+            Err(e) => {
+                log::warn!("Skipping unreadable folder {:?}: {}", dir, e);
+                continue;
+            }
+        };
+
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with('.') {
+                continue;
+            }
+
+            // DirEntry::file_type doesn't follow symlinks, so links are neither entered nor matched
+            let Ok(file_type) = entry.file_type() else { continue };
+            let path = entry.path();
+
+            if file_type.is_dir() {
+                if depth < max_depth {
+                    pending.push((path, depth + 1));
+                }
+            } else if file_type.is_file() && matches_folder_filter(&name, filter) {
+                if let Err(e) = validate_input_file(&path, conversion_type) {
+                    log::warn!("Skipping {:?}: {}", path, e);
+                    continue;
+                }
+                if matches.len() == MAX_FOLDER_FILES {
+                    return Err(MediaForgeError::InvalidSettings(format!(
+                        "More than {} files match in {}; narrow the filter or depth",
+                        MAX_FOLDER_FILES,
+                        root.display()
+                    )));
+                }
+                matches.push(path);
+            }
+        }
+    }
+
+    matches.sort();
+    Ok(matches)
+}
+
+/// Paths checked per blocking call in `validate_files`, so a large drop is spread
+/// over the blocking pool rather than holding one thread throughout
+const VALIDATE_CHUNK_SIZE: usize = 256;
+
+/// ffprobe runs `validate_files` has going at once for inputs of unlisted types
+const VALIDATE_PROBE_CONCURRENCY: usize = 8;
+
+/// One path after the checks that need no ffprobe
+struct ListedValidation {
+    validation: FileValidation,
+    /// Set when the file is refused with this error unless its streams suit the conversion
+    unlisted: Option<MediaForgeError>,
+}
+
+impl ListedValidation {
+    /// Probing tells what an unlisted file is, and whether it can be converted after all
+    fn needs_probe(&self) -> bool {
+        self.unlisted.is_some() || (self.validation.detected_type.is_none() && self.validation.reason.is_none())
+    }
+}
+
+/// The path, extension and metadata checks of `validate_files`, all blocking
+fn validate_listed_file(path: &str, conversion_type: &ConversionType) -> ListedValidation {
+    let checked = sanitize_path(path).and_then(|file| validate_listed_input(&file, conversion_type).map(|unlisted| (file, unlisted)));
+    let (file, reason, unlisted) = match checked {
+        Ok((file, unlisted)) => (file, None, unlisted),
+        Err(e) => (PathBuf::from(path), Some(e.to_string()), None),
+    };
+    ListedValidation {
+        validation: FileValidation {
+            reason,
+            detected_type: file.extension().and_then(|e| e.to_str()).and_then(ConversionType::for_input_extension),
+            size_bytes: std::fs::metadata(&file).ok().filter(|m| m.is_file()).map(|m| m.len()),
+            path: file,
+        },
+        unlisted,
+    }
+}
+
+/// Checks each path the way a conversion would, so files that would be skipped can
+/// be shown before any task is created. The file system checks run on the blocking
+/// pool in chunks; files of unlisted types are then probed a few at a time.
+pub async fn validate_files(paths: &[String], conversion_type: &ConversionType) -> Result<Vec<FileValidation>, MediaForgeError> {
+    let mut listed = Vec::with_capacity(paths.len());
+    for chunk in paths.chunks(VALIDATE_CHUNK_SIZE) {
+        let chunk = chunk.to_vec();
+        let chunk_type = conversion_type.clone();
+        let checked = tokio::task::spawn_blocking(move || {
+            chunk.iter().map(|path| validate_listed_file(path, &chunk_type)).collect::<Vec<_>>()
+        })
+        .await
+        .map_err(|e| MediaForgeError::FileSystemError(format!("File validation failed: {}", e)))?;
+        listed.extend(checked);
+    }
+
+    let unprobed: Vec<(usize, PathBuf)> = listed
+        .iter()
+        .enumerate()
+        .filter(|(_, file)| file.needs_probe())
+        .map(|(index, file)| (index, file.validation.path.clone()))
+        .collect();
+    let probes: Vec<(usize, Option<ConversionType>)> = stream::iter(unprobed)
+        .map(|(index, path)| async move {
+            let probe = probe_media(&path).await;
+            (index, probe.ok().as_ref().and_then(conversion_type_for_probe))
+        })
+        .buffer_unordered(VALIDATE_PROBE_CONCURRENCY)
+        .collect()
+        .await;
+    for (index, detected) in probes {
+        let file = &mut listed[index];
+        if let Some(unlisted) = file.unlisted.take() {
+            if !accepts_probed_input(conversion_type, detected.as_ref()) {
+                file.validation.reason = Some(unlisted.to_string());
+            }
+        }
+        file.validation.detected_type = file.validation.detected_type.take().or(detected);
+    }
+
+    Ok(listed.into_iter().map(|file| file.validation).collect())
 }
 
 /// Validates image format for ImageMagick security (prevent dangerous delegates)
@@ -162,23 +485,29 @@ fn validate_image_format(input_path: &PathBuf, output_format: &str) -> Result<()
     if let Some(input_ext) = input_path.extension().and_then(|e| e.to_str()) {
         let input_ext_lower = input_ext.to_lowercase();
         
-        // Allowed input formats (avoid potentially dangerous formats)
-        let safe_input_formats = [
-            "png", "jpg", "jpeg", "webp", "gif", "bmp", "tiff", "tif",
-            "ico", "psd", // Common safe formats
-        ];
-        
-        if !safe_input_formats.contains(&input_ext_lower.as_str()) {
+        if !IMAGE_INPUT_FORMATS.contains(&input_ext_lower.as_str()) {
             return Err(MediaForgeError::InvalidSettings(
                 format!("Input image format not supported: .{}", input_ext_lower)
             ));
         }
+        
+        if input_ext_lower == "svg" {
+            validate_svg_file(input_path)?;
+        }
+        
+        if input_ext_lower == "pdf" && output_format.eq_ignore_ascii_case("pdf") {
+            return Err(MediaForgeError::InvalidSettings(
+                "PDF pages can only be extracted to image formats".into()
+            ));
+        }
     }
     
     // Validate output format
     let output_lower = output_format.to_lowercase();
     let safe_output_formats = [
-        "png", "jpg", "jpeg", "webp", "gif", "bmp", "tiff", "tif", "ico"
+        "png", "jpg", "jpeg", "webp", "gif", "bmp", "tiff", "tif", "ico", "avif", "heic",
+        "pdf", // All inputs are combined into a single document
+        "apng",
     ];
     
     if !safe_output_formats.contains(&output_lower.as_str()) {
@@ -190,1010 +519,6585 @@ fn validate_image_format(input_path: &PathBuf, output_format: &str) -> Result<()
     Ok(())
 }
 
-pub struct ConversionManager {
-    tasks: Arc<DashMap<String, TaskProgress>>,
-    task_handles: Arc<DashMap<String, TaskHandle>>,
+/// Largest SVG file accepted for rasterization (5MB)
+const MAX_SVG_SIZE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Highest rasterization density accepted; a 1000px-wide SVG at 1200 DPI is already
+/// over 13000px wide, and higher values quickly produce multi-gigabyte rasters
+const MAX_DENSITY: u32 = 1200;
+
+/// Highest output DPI accepted
+const MAX_DPI: u32 = 2400;
+
+/// Reads an SVG input and checks that it is safe to hand to ImageMagick
+fn validate_svg_file(path: &Path) -> Result<(), MediaForgeError> {
+    let size = std::fs::metadata(path)?.len();
+    if size > MAX_SVG_SIZE_BYTES {
+        return Err(MediaForgeError::InvalidSettings(format!(
+            "SVG file is too large ({:.1}MB, limit {}MB)",
+            size as f64 / 1024.0 / 1024.0,
+            MAX_SVG_SIZE_BYTES / 1024 / 1024
+        )));
+    }
+    
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        MediaForgeError::InvalidSettings(format!("SVG file is not valid UTF-8 text: {}", e))
+    })?;
+    validate_svg_content(&content)
 }
 
-impl ConversionManager {
-    pub fn new() -> Self {
-        Self {
-            tasks: Arc::new(DashMap::new()),
-            task_handles: Arc::new(DashMap::new()),
+/// Rejects SVG content that could make the renderer read other files or fetch URLs:
+/// entity declarations, a DOCTYPE with an internal subset, scripts, embedded foreign
+/// content and any `href`/`url()` that isn't a fragment or inline `data:` URI. The
+/// plain DOCTYPE that editors write is fine.
+fn validate_svg_content(content: &str) -> Result<(), MediaForgeError> {
+    let lower = content.to_lowercase();
+    
+    for marker in ["<!entity", "<script", "<foreignobject", "@import"] {
+        if lower.contains(marker) {
+            return Err(MediaForgeError::InvalidSettings(
+                format!("SVG contains unsupported content ({}...)", marker)
+            ));
         }
     }
-
-    pub fn create_task(&self, name: String) -> String {
-        let task_id = Uuid::new_v4().to_string();
-        let task = TaskProgress {
-            task_id: task_id.clone(),
-            name,
-            status: TaskStatus::Queued,
-            progress: 0.0,
-            speed: None,
-            eta: None,
-            error: None,
-            file_path: None,
-        };
-        self.tasks.insert(task_id.clone(), task);
-        task_id
+    
+    let internal_subset = Regex::new(r"<!doctype[^>\[]*\[").unwrap();
+    if internal_subset.is_match(&lower) {
+        return Err(MediaForgeError::InvalidSettings(
+            "SVG contains unsupported content (a DOCTYPE with declarations)".to_string()
+        ));
+    }
+    
+    let href = Regex::new(r#"href\s*=\s*["']([^"']*)["']"#).unwrap();
+    let css_url = Regex::new(r#"url\(\s*["']?([^"')]*)"#).unwrap();
+    let references = href
+        .captures_iter(&lower)
+        .chain(css_url.captures_iter(&lower))
+        .map(|c| c[1].trim().to_string());
+    
+    for reference in references {
+        if !reference.starts_with('#') && !reference.starts_with("data:") {
+            return Err(MediaForgeError::InvalidSettings(
+                format!("SVG references an external resource: {}", reference)
+            ));
+        }
     }
+    
+    Ok(())
+}
 
-    pub fn get_task(&self, task_id: &str) -> Option<TaskProgress> {
-        self.tasks.get(task_id).map(|t| t.clone())
+/// Output formats that can hold an animation
+const ANIMATED_IMAGE_FORMATS: [&str; 3] = ["gif", "webp", "apng"];
+
+/// How the frames of a multi-frame image input are handled
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FrameHandling {
+    /// Single-frame input, nothing special to do
+    Single,
+    /// Expand every frame to full size so the animation survives re-encoding
+    Animated,
+    /// Convert only the first frame
+    FirstFrame,
+}
+
+/// Decides how to handle an input with `frame_count` frames. The flag is true when
+/// frames are dropped without the user having asked for a still.
+fn frame_handling(frame_count: usize, output_format: &str, settings: Option<&ImageSettings>) -> (FrameHandling, bool) {
+    if frame_count <= 1 {
+        return (FrameHandling::Single, false);
     }
 
-    pub fn get_all_tasks(&self) -> Vec<TaskProgress> {
-        self.tasks.iter().map(|entry| entry.value().clone()).collect()
+    let wants_animation = settings.and_then(|s| s.animation).unwrap_or(true);
+    let output_animated = ANIMATED_IMAGE_FORMATS.contains(&output_format.to_lowercase().as_str());
+
+    match (wants_animation, output_animated) {
+        (true, true) => (FrameHandling::Animated, false),
+        (true, false) => (FrameHandling::FirstFrame, true),
+        (false, _) => (FrameHandling::FirstFrame, false),
     }
+}
 
-    pub fn update_task(&self, task_id: &str, update: impl FnOnce(&mut TaskProgress)) {
-        if let Some(mut task) = self.tasks.get_mut(task_id) {
-            update(&mut task);
+/// Adjusts ImageMagick arguments built for a single frame to the chosen frame handling
+fn apply_frame_handling(mut args: Vec<OsString>, input_file: &Path, handling: FrameHandling) -> Vec<OsString> {
+    let Some(input_index) = args.iter().position(|a| a == input_file.as_os_str()) else {
+        return args;
+    };
+
+    match handling {
+        FrameHandling::Single => {}
+        FrameHandling::Animated => {
+            // Frames of optimized animations only store what changed; coalesce first so
+            // per-frame operations and the new encoder see complete frames
+            args.insert(input_index + 1, "-coalesce".into());
+        }
+        FrameHandling::FirstFrame => {
+            let mut first_frame = input_file.as_os_str().to_owned();
+            first_frame.push("[0]");
+            args[input_index] = first_frame;
         }
     }
+    args
+}
 
-    pub fn remove_task(&self, task_id: &str) {
-        self.tasks.remove(task_id);
-    }
+/// Counts the frames in an image with ImageMagick; None if it can't be read
+async fn probe_frame_count(input_file: &Path) -> Option<usize> {
+    let output = ImageMagick::detect().await?
+        .tool("identify")
+        .args(["-format", "%n\n"])
+        .arg(input_file)
+        .output()
+        .await
+        .ok()?;
 
-    pub async fn cancel_task(&self, task_id: &str) -> Result<(), MediaForgeError> {
-        // Cancel the running task if it exists
-        if let Some((_, task_handle)) = self.task_handles.remove(task_id) {
-            info!(
-                task_id = task_id,
-                "Initiating conversion task cancellation"
-            );
-            
-            // Update status first
-            self.update_task(task_id, |task| {
-                task.status = TaskStatus::Cancelled;
-                task.error = Some("Task cancelled by user".to_string());
-            });
-            
-            // Actually cancel the running task
-            if let Err(e) = task_handle.cancel().await {
-                error!(
-                    task_id = task_id,
-                    error = ?e,
-                    "Failed to cancel running conversion task"
-                );
-                return Err(MediaForgeError::TaskNotFound(format!("Failed to cancel task: {:?}", e)));
-            }
-            
-            info!(
-                task_id = task_id,
-                "Conversion task cancelled successfully"
-            );
-        } else {
-            // Task might not be running anymore, just update status
-            self.update_task(task_id, |task| {
-                task.status = TaskStatus::Cancelled;
-                task.error = Some("Task cancelled by user".to_string());
-            });
-            info!(
-                task_id = task_id,
-                status = "not_running",
-                "Conversion task not running, marked as cancelled"
-            );
-        }
-        
+    String::from_utf8_lossy(&output.stdout).lines().next()?.trim().parse().ok()
+}
+
+/// Rasterization density for PDF pages when none is given, in DPI
+const DEFAULT_PDF_DENSITY: u32 = 150;
+
+/// Whether an image conversion combines all inputs into one PDF instead of
+/// converting each input separately
+fn is_pdf_combine(request: &ConvertRequest) -> bool {
+    request.conversion_type == ConversionType::Image && request.output_format.eq_ignore_ascii_case("pdf")
+}
+
+fn is_pdf_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("pdf"))
+}
+
+/// ImageMagick reads PDFs through Ghostscript, so make sure it is installed
+async fn ensure_ghostscript() -> Result<(), MediaForgeError> {
+    let gs = if cfg!(target_os = "windows") { "gswin64c" } else { "gs" };
+    let available = TokioCommand::new(gs)
+        .arg("--version")
+        .output()
+        .await
+        .map(|o| o.status.success())
+        .unwrap_or(false);
+
+    if available {
         Ok(())
+    } else {
+        Err(MediaForgeError::MissingDependency(
+            "Ghostscript is required to read PDF files; install it and try again".to_string()
+        ))
     }
+}
 
-    pub async fn start_conversion(
-        &self,
-        request: ConvertRequest,
-        app_handle: tauri::AppHandle,
-    ) -> Result<Vec<String>, MediaForgeError> {
-        info!(
-            file_count = request.input_files.len(),
-            output_format = ?request.output_format,
-            output_path = %request.output_path,
-            "Starting batch conversion"
-        );
-        
-        // Validate output path before processing any files
-        let _sanitized_output_path = sanitize_path(&request.output_path)?;
-        
-        let mut task_ids = Vec::new();
+/// Builds the ImageMagick arguments that combine images into one PDF, one page per
+/// input in list order
+fn build_pdf_combine_args(
+    input_files: &[PathBuf],
+    output_path: &Path,
+    settings: Option<&ImageSettings>,
+) -> Vec<OsString> {
+    let mut args: Vec<OsString> = Vec::new();
 
-        for input_file in request.input_files.iter() {
-            // Validate each input file before creating task
-            validate_input_file(input_file)?;
-            
-            // Additional validation for image files
-            if request.conversion_type == ConversionType::Image {
-                validate_image_format(input_file, &request.output_format)?;
-            }
-            
-            let file_name = input_file
-                .file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or("Unknown");
-            info!(
+    if let Some(density) = settings.and_then(|s| s.density) {
+        args.push("-density".into());
+        args.push(density.to_string().into());
+    }
+
+    args.extend(input_files.iter().map(OsString::from));
+
+    if let Some(quality) = settings.and_then(|s| s.quality) {
+        args.push("-quality".into());
+        args.push(quality.to_string().into());
+    }
+
+    if settings.is_some_and(|s| s.strip_metadata) {
+        args.push("-strip".into());
+    }
+
+    args.push(output_path.into());
+    args
+}
+
+/// Output path pattern for the pages extracted from a PDF, e.g. `scan-001.png`.
+/// ImageMagick expands `%03d` to the page number.
+fn pdf_page_output_pattern(output_dir: &Path, file_stem: &str, output_format: &str) -> PathBuf {
+    // A literal '%' in the name would be read as part of the page pattern
+    let file_stem = file_stem.replace('%', "%%");
+    output_dir.join(format!("{}-%03d.{}", file_stem, output_format))
+}
+
+/// Finds the page files written for a PDF extraction, in page order
+fn collect_pdf_pages(output_dir: &Path, file_stem: &str, output_format: &str) -> Vec<String> {
+    let prefix = format!("{}-", file_stem);
+    let suffix = format!(".{}", output_format);
+    let mut pages: Vec<String> = std::fs::read_dir(output_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| {
+            name.strip_prefix(&prefix)
+                .and_then(|rest| rest.strip_suffix(&suffix))
+                .is_some_and(|page| !page.is_empty() && page.chars().all(|c| c.is_ascii_digit()))
+        })
+        .collect();
+    pages.sort();
+    pages
+        .into_iter()
+        .map(|name| output_dir.join(name).to_string_lossy().to_string())
+        .collect()
+}
+
+/// Formats that ImageMagick only handles when built with an optional delegate,
+/// paired with the library that provides it
+const DELEGATE_IMAGE_FORMATS: [(&str, &str); 3] = [
+    ("avif", "libheif with AV1 support"),
+    ("heic", "libheif"),
+    ("heif", "libheif"),
+];
+
+/// Checks the output of `magick -list format` for read/write support of the
+/// delegate-backed formats used by a conversion.
+///
+/// Each format line looks like `     HEIC* HEIC      rw+   High Efficiency Image Format`,
+/// where the mode column lists `r` and `w` when reading and writing are available.
+fn check_image_delegates(
+    format_list: &str,
+    input_formats: &[String],
+    output_format: &str,
+) -> Result<(), MediaForgeError> {
+    let mode_of = |format: &str| {
+        format_list.lines().find_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?.trim_end_matches('*');
+            let _module = fields.next()?;
+            let mode = fields.next()?;
+            name.eq_ignore_ascii_case(format).then(|| mode.to_string())
+        })
+    };
+
+    let output_lower = output_format.to_lowercase();
+    let required = input_formats
+        .iter()
+        .map(|f| (f.to_lowercase(), 'r'))
+        .chain(std::iter::once((output_lower, 'w')));
+
+    for (format, access) in required {
+        let Some((_, delegate)) = DELEGATE_IMAGE_FORMATS.iter().find(|(name, _)| *name == format) else {
+            continue;
+        };
+        let supported = mode_of(&format).is_some_and(|mode| mode.contains(access));
+        if !supported {
+            let action = if access == 'r' { "read" } else { "write" };
+            return Err(MediaForgeError::MissingDependency(format!(
+                "ImageMagick cannot {} {} images: the {} delegate is not installed",
+                action,
+                format.to_uppercase(),
+                delegate
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Verifies that the installed ImageMagick can handle any AVIF/HEIC files in a conversion
+async fn ensure_image_delegates(input_files: &[PathBuf], output_format: &str) -> Result<(), MediaForgeError> {
+    let input_formats: Vec<String> = input_files
+        .iter()
+        .filter_map(|p| p.extension().and_then(|e| e.to_str()))
+        .map(|e| e.to_lowercase())
+        .collect();
+
+    let needs_delegate = input_formats
+        .iter()
+        .map(String::as_str)
+        .chain(std::iter::once(output_format.to_lowercase().as_str()))
+        .any(|f| DELEGATE_IMAGE_FORMATS.iter().any(|(name, _)| *name == f));
+    if !needs_delegate {
+        return Ok(());
+    }
+
+    let output = ensure_imagemagick().await?
+        .command()
+        .args(["-list", "format"])
+        .output()
+        .await
+        .map_err(|e| MediaForgeError::MissingDependency(format!("ImageMagick is not available: {}", e)))?;
+
+    check_image_delegates(&String::from_utf8_lossy(&output.stdout), &input_formats, output_format)
+}
+
+/// Maps the JPEG-style quality setting onto AVIF's scale, where the same visual
+/// quality is reached at noticeably lower values
+fn avif_quality(quality: u32) -> u32 {
+    (quality.min(100) * 3 / 4).max(1)
+}
+
+/// Watermark positions and their ffmpeg overlay x:y expressions (10px margin)
+const WATERMARK_POSITIONS: [(&str, &str); 9] = [
+    ("northwest", "10:10"),
+    ("north", "(W-w)/2:10"),
+    ("northeast", "W-w-10:10"),
+    ("west", "10:(H-h)/2"),
+    ("center", "(W-w)/2:(H-h)/2"),
+    ("east", "W-w-10:(H-h)/2"),
+    ("southwest", "10:H-h-10"),
+    ("south", "(W-w)/2:H-h-10"),
+    ("southeast", "W-w-10:H-h-10"),
+];
+
+/// Validates watermark settings and the watermark image itself
+fn validate_watermark(watermark: &WatermarkSettings) -> Result<(), MediaForgeError> {
+    if !WATERMARK_POSITIONS.iter().any(|(name, _)| *name == watermark.position) {
+        return Err(MediaForgeError::InvalidSettings(
+            format!(
+                "Invalid watermark position '{}' (supported: {})",
+                watermark.position,
+                WATERMARK_POSITIONS.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")
+            )
+        ));
+    }
+    
+    if !watermark.opacity.is_finite() || !(0.0..=1.0).contains(&watermark.opacity) {
+        return Err(MediaForgeError::InvalidSettings(
+            format!("Watermark opacity must be between 0 and 1, got {}", watermark.opacity)
+        ));
+    }
+    
+    if let Some(scale) = watermark.scale {
+        if !scale.is_finite() || scale <= 0.0 || scale > 10.0 {
+            return Err(MediaForgeError::InvalidSettings(
+                format!("Watermark scale must be greater than 0 and at most 10, got {}", scale)
+            ));
+        }
+    }
+    
+    validate_input_file(&watermark.path, &ConversionType::Image)?;
+    // Only the input side matters here: the watermark is composited, never written out
+    validate_image_format(&watermark.path, "png")
+}
+
+/// HDR to SDR: linearise, convert the primaries to BT.709, compress the highlights
+/// with the Hable curve, then encode as 8-bit BT.709 that any player shows correctly
+const TONEMAP_FILTERS: &str = "zscale=t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,\
+    tonemap=tonemap=hable:desat=0,zscale=t=bt709:m=bt709:r=tv,format=yuv420p";
+
+/// Transfer characteristics ffprobe reports for HDR video: PQ and HLG
+fn is_hdr_transfer(color_transfer: &str) -> bool {
+    matches!(color_transfer, "smpte2084" | "arib-std-b67")
+}
+
+/// Whether a conversion of a file described by `probe` should tone-map it to SDR
+fn needs_tonemap(probe: &MediaProbe, settings: Option<&VideoSettings>) -> bool {
+    let copy_streams = settings.is_some_and(|s| s.copy_streams);
+    let wanted = settings.and_then(|s| s.tonemap_sdr).unwrap_or(!copy_streams);
+    wanted && probe.streams.iter().any(|stream| stream.codec_type == "video" && stream.hdr)
+}
+
+/// Whether `ffmpeg -filters` or `-encoders` output lists `name`; lines read
+/// " T.C zscale  V->V  ..." and " V....D libwebp  libwebp WebP image ..."
+fn ffmpeg_list_contains(list: &str, name: &str) -> bool {
+    list.lines().any(|line| line.split_whitespace().nth(1) == Some(name))
+}
+
+/// Whether the installed ffmpeg lists `name` among its `-filters` or `-encoders`
+async fn ffmpeg_has(list: &str, name: &str) -> bool {
+    TokioCommand::new("ffmpeg")
+        .args(["-hide_banner", list])
+        .output()
+        .await
+        .is_ok_and(|o| ffmpeg_list_contains(&String::from_utf8_lossy(&o.stdout), name))
+}
+
+/// Tone mapping runs through zscale, which needs an ffmpeg built with libzimg
+async fn ensure_zscale() -> Result<(), MediaForgeError> {
+    if ffmpeg_has("-filters", "zscale").await {
+        Ok(())
+    } else {
+        Err(MediaForgeError::MissingDependency(
+            "HDR video needs the zscale filter to convert to SDR, but this ffmpeg build lacks it; \
+             install an ffmpeg built with libzimg or turn off HDR tone mapping".to_string()
+        ))
+    }
+}
+
+/// Animated image formats a video can be converted to, with the ffmpeg encoder each
+/// needs. GIF's is built in; the others depend on optional libraries.
+const ANIMATED_VIDEO_OUTPUTS: [(&str, &str); 3] = [("gif", "gif"), ("webp", "libwebp"), ("avif", "libaom-av1")];
+
+fn animated_output_encoder(output_format: &str) -> Option<&'static str> {
+    let format = output_format.to_lowercase();
+    ANIMATED_VIDEO_OUTPUTS.iter().find(|(name, _)| *name == format).map(|(_, encoder)| *encoder)
+}
+
+/// Animated images have no sound track and are always re-encoded
+fn validate_animated_output(output_format: &str, settings: &VideoSettings) -> Result<(), MediaForgeError> {
+    if animated_output_encoder(output_format).is_some() && (settings.copy_streams || replaces_audio(settings)) {
+        return Err(MediaForgeError::InvalidSettings(format!(
+            "Animated .{} output can't copy streams or take a sound track",
+            output_format.to_lowercase()
+        )));
+    }
+    Ok(())
+}
+
+/// Makes sure the installed ffmpeg can write an animated image format
+async fn ensure_animated_encoder(output_format: &str) -> Result<(), MediaForgeError> {
+    let Some(encoder) = animated_output_encoder(output_format) else {
+        return Ok(());
+    };
+    if ffmpeg_has("-encoders", encoder).await {
+        Ok(())
+    } else {
+        Err(MediaForgeError::MissingDependency(format!(
+            "Animated .{} output needs the {} encoder, but this ffmpeg build lacks it",
+            output_format.to_lowercase(),
+            encoder
+        )))
+    }
+}
+
+/// Image conversions run ImageMagick 7's `magick` or, without it, ImageMagick 6's `convert`
+async fn ensure_imagemagick() -> Result<ImageMagick, MediaForgeError> {
+    ImageMagick::detect().await.ok_or_else(|| {
+        MediaForgeError::MissingDependency(
+            "Image conversion needs ImageMagick, but neither magick nor convert was found; install ImageMagick".to_string()
+        )
+    })
+}
+
+/// Stabilization needs both vid.stab filters, which ffmpeg only has with libvidstab
+async fn ensure_vidstab() -> Result<(), MediaForgeError> {
+    if ffmpeg_has("-filters", "vidstabdetect").await && ffmpeg_has("-filters", "vidstabtransform").await {
+        Ok(())
+    } else {
+        Err(MediaForgeError::MissingDependency(
+            "Stabilization needs the vid.stab filters, but this ffmpeg build lacks them; \
+             install an ffmpeg built with libvidstab or turn off stabilization".to_string()
+        ))
+    }
+}
+
+/// Escapes a filter option value for `-vf`: once for the option, whose separator is
+/// `:`, and once more for the filter graph around it
+fn escape_filter_value(value: &str) -> String {
+    let escape = |text: &str, special: &[char]| {
+        text.chars().fold(String::new(), |mut escaped, c| {
+            if special.contains(&c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+            escaped
+        })
+    };
+    escape(&escape(value, &['\\', ':', '\'']), &['\\', '\'', '[', ']', ',', ';'])
+}
+
+/// Builds the ffmpeg arguments for the stabilization detect pass, which writes the
+/// camera motion to `transforms` for the encode to smooth out. It sees the same
+/// frames as the encode's stabilization filter, so deinterlacing comes first.
+fn build_stabilize_detect_args(input_file: &Path, transforms: &Path, settings: Option<&VideoSettings>) -> Vec<OsString> {
+    let mut filters = Vec::new();
+    if settings.is_some_and(|s| s.deinterlace) {
+        filters.push("yadif".to_string());
+    }
+    filters.push(format!(
+        "vidstabdetect=shakiness=5:result={}",
+        escape_filter_value(&transforms.to_string_lossy())
+    ));
+
+    let mut args: Vec<OsString> = vec!["-i".into(), input_file.into(), "-vf".into(), filters.join(",").into()];
+    args.extend(["-an", "-f", "null", "-progress", "pipe:1", "-y", "-"].map(OsString::from));
+    args
+}
+
+/// Filters applied to the main video stream, in order. They go in `-vf`, or in
+/// front of the overlay when a watermark needs `-filter_complex`. `transforms` is
+/// the result of a stabilization detect pass.
+fn video_stream_filters(settings: &VideoSettings, transforms: Option<&Path>) -> Vec<String> {
+    let mut filters = Vec::new();
+    
+    if settings.deinterlace {
+        // First, so the frame rate conversion works on whole frames
+        filters.push("yadif".to_string());
+    }
+    
+    if let Some(transforms) = transforms {
+        filters.push(format!(
+            "vidstabtransform=input={}:smoothing=10",
+            escape_filter_value(&transforms.to_string_lossy())
+        ));
+    }
+    
+    // Set per task once the input is known to be HDR
+    if settings.tonemap_sdr == Some(true) {
+        filters.push(TONEMAP_FILTERS.to_string());
+    }
+    
+    if let Some(fps) = settings.fps {
+        // The fps filter drops/duplicates frames evenly, unlike the output option -r
+        filters.push(format!("fps={}", fps));
+    }
+    
+    filters
+}
+
+/// Builds the ffmpeg filter graph overlaying a watermark (second input) on the video,
+/// after running the main stream through `stream_filters`
+fn watermark_filter_complex(watermark: &WatermarkSettings, stream_filters: &[String]) -> String {
+    let position = WATERMARK_POSITIONS
+        .iter()
+        .find(|(name, _)| *name == watermark.position)
+        .map(|(_, expr)| *expr)
+        .unwrap_or("W-w-10:H-h-10");
+    
+    let mut watermark_chain = format!("[1:v]format=rgba,colorchannelmixer=aa={}", watermark.opacity);
+    if let Some(scale) = watermark.scale {
+        watermark_chain.push_str(&format!(",scale=iw*{}:-1", scale));
+    }
+    
+    if stream_filters.is_empty() {
+        format!("{}[wm];[0:v][wm]overlay={}", watermark_chain, position)
+    } else {
+        format!(
+            "{}[wm];[0:v]{}[base];[base][wm]overlay={}",
+            watermark_chain,
+            stream_filters.join(","),
+            position
+        )
+    }
+}
+
+/// Codecs each video container can hold, by ffprobe codec name. Containers not listed
+/// (mkv) take anything.
+const CONTAINER_CODECS: [(&str, &[&str]); 8] = [
+    ("mp4", &["h264", "hevc", "av1", "vp9", "mpeg4", "mpeg2video", "mjpeg", "aac", "mp3", "ac3", "eac3", "opus", "flac", "alac", "mov_text"]),
+    ("mov", &["h264", "hevc", "mpeg4", "mpeg2video", "mjpeg", "prores", "aac", "mp3", "ac3", "alac", "pcm_s16le", "pcm_s24le", "mov_text"]),
+    ("webm", &["vp8", "vp9", "av1", "opus", "vorbis", "webvtt"]),
+    ("avi", &["h264", "mpeg4", "msmpeg4v3", "mjpeg", "mpeg2video", "mp3", "ac3", "pcm_s16le"]),
+    ("flv", &["h264", "flv1", "aac", "mp3"]),
+    ("wmv", &["wmv1", "wmv2", "wmv3", "vc1", "wmav1", "wmav2"]),
+    ("mpeg", &["mpeg1video", "mpeg2video", "mp2", "mp3", "ac3"]),
+    ("ogv", &["theora", "vorbis", "opus", "flac"]),
+];
+
+/// Containers that have no place for chapters; ffmpeg leaves them out
+const CHAPTERLESS_CONTAINERS: [&str; 7] = ["avi", "flv", "wmv", "mpeg", "gif", "webp", "avif"];
+
+fn preserves_metadata(settings: &VideoSettings) -> bool {
+    !settings.strip_metadata && settings.preserve_metadata != Some(false)
+}
+
+/// Note for a task whose input has chapters the output container can't keep
+fn lost_chapters_warning(probe: &MediaProbe, output_format: &str, settings: Option<&VideoSettings>) -> Option<String> {
+    let format = output_format.to_lowercase();
+    let preserving = settings.map_or(true, preserves_metadata);
+    (preserving && !probe.chapters.is_empty() && CHAPTERLESS_CONTAINERS.contains(&format.as_str())).then(|| {
+        format!("{} chapters left out; .{} can't hold chapters", probe.chapters.len(), format)
+    })
+}
+
+/// Containers that store subtitles as text; ffmpeg converts text subtitles for them
+/// but can't turn image-based subtitles into text
+const TEXT_SUBTITLE_CONTAINERS: [&str; 3] = ["mp4", "mov", "webm"];
+const BITMAP_SUBTITLE_CODECS: [&str; 4] = ["hdmv_pgs_subtitle", "dvd_subtitle", "dvb_subtitle", "xsub"];
+
+/// Checks an input's streams against the output container before converting.
+///
+/// Image-based subtitles headed for a text-subtitle container are an error either way.
+/// With stream copy, codecs the container can't hold return a warning and the file
+/// has to be re-encoded instead. Only the first stream of each type is checked, as
+/// that is the one ffmpeg maps by default.
+fn check_container_compatibility(
+    probe: &MediaProbe,
+    input_file: &Path,
+    output_format: &str,
+    copy_streams: bool,
+) -> Result<Option<String>, MediaForgeError> {
+    let format = output_format.to_lowercase();
+    let Some((_, codecs)) = CONTAINER_CODECS.iter().find(|(container, _)| *container == format) else {
+        return Ok(None);
+    };
+    let text_subtitles = TEXT_SUBTITLE_CONTAINERS.contains(&format.as_str());
+
+    let mut mismatches = Vec::new();
+    for stream_type in ["video", "audio", "subtitle"] {
+        let Some(stream) = probe.streams.iter().find(|s| s.codec_type == stream_type) else {
+            continue;
+        };
+        let codec = stream.codec_name.as_deref().unwrap_or("unknown");
+
+        if stream_type == "subtitle" {
+            if text_subtitles && BITMAP_SUBTITLE_CODECS.contains(&codec) {
+                return Err(MediaForgeError::InvalidSettings(format!(
+                    "{} has image-based subtitles ({}) that .{} can't hold; convert to MKV to keep them",
+                    input_file.display(),
+                    codec,
+                    format
+                )));
+            }
+            // Containers without subtitle support just leave them out
+            if !text_subtitles {
+                continue;
+            }
+        }
+
+        if copy_streams && !codecs.contains(&codec) {
+            mismatches.push(format!("{} {}", codec, stream_type));
+        }
+    }
+
+    if mismatches.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(format!(
+            "{} can't be copied into .{}; re-encoding instead",
+            mismatches.join(" and "),
+            format
+        )))
+    }
+}
+
+/// Output frame size bounds for video resolutions
+const MIN_VIDEO_DIMENSION: u32 = 16;
+const MAX_VIDEO_DIMENSION: u32 = 8192;
+
+/// Checks a video resolution is `WIDTHxHEIGHT` within sane bounds. ASCII digits only,
+/// since the value is passed straight to ffmpeg.
+fn validate_resolution(resolution: &str) -> Result<(), MediaForgeError> {
+    let invalid = || MediaForgeError::InvalidSettings(format!(
+        "Invalid video resolution '{}': expected WIDTHxHEIGHT between {} and {} pixels, e.g. 1280x720",
+        resolution, MIN_VIDEO_DIMENSION, MAX_VIDEO_DIMENSION
+    ));
+
+    let re = Regex::new(r"^([0-9]{1,5})x([0-9]{1,5})$").unwrap();
+    let caps = re.captures(resolution).ok_or_else(invalid)?;
+    let in_bounds = |dim: &str| {
+        dim.parse::<u32>()
+            .is_ok_and(|d| (MIN_VIDEO_DIMENSION..=MAX_VIDEO_DIMENSION).contains(&d))
+    };
+    if in_bounds(&caps[1]) && in_bounds(&caps[2]) {
+        Ok(())
+    } else {
+        Err(invalid())
+    }
+}
+
+/// Checks an ffmpeg video bitrate such as `2500k`, `5M` or `800000`
+fn validate_video_bitrate(bitrate: &str) -> Result<(), MediaForgeError> {
+    let re = Regex::new(r"^[1-9][0-9]{0,8}[kKmM]?$").unwrap();
+    if re.is_match(bitrate) {
+        Ok(())
+    } else {
+        Err(MediaForgeError::InvalidSettings(format!(
+            "Invalid video bitrate '{}': expected a number with an optional k or M suffix, e.g. 2500k",
+            bitrate
+        )))
+    }
+}
+
+/// Validates video settings before any ffmpeg process is spawned
+fn validate_video_settings(settings: &VideoSettings) -> Result<(), MediaForgeError> {
+    if let Some(resolution) = settings.resolution.as_deref().filter(|r| *r != "Keep Original") {
+        validate_resolution(resolution)?;
+    }
+    
+    if let Some(bitrate) = settings.bitrate.as_deref().filter(|b| *b != "Keep Original") {
+        validate_video_bitrate(bitrate)?;
+    }
+    
+    if settings.copy_streams {
+        let is_set = |value: &Option<String>| value.as_deref().is_some_and(|v| v != "Keep Original");
+        if is_set(&settings.resolution) || is_set(&settings.bitrate) || settings.fps.is_some()
+            || settings.deinterlace
+            || settings.stabilize
+            || settings.tonemap_sdr == Some(true)
+            || settings.watermark.is_some()
+        {
+            return Err(MediaForgeError::InvalidSettings(
+                "Resolution, bitrate, frame rate, deinterlacing, stabilization, tone mapping and watermark need re-encoding and can't be combined with stream copy".into()
+            ));
+        }
+    }
+    
+    if let Some(fps) = settings.fps {
+        if !fps.is_finite() || !(1.0..=240.0).contains(&fps) {
+            return Err(MediaForgeError::InvalidSettings(
+                format!("Frame rate must be between 1 and 240 fps, got {}", fps)
+            ));
+        }
+    }
+    
+    if let Some(watermark) = &settings.watermark {
+        validate_watermark(watermark)?;
+    }
+
+    if let Some(target_mb) = settings.target_size_mb {
+        if !(1..=100_000).contains(&target_mb) {
+            return Err(MediaForgeError::InvalidSettings(
+                format!("Target size must be between 1 and 100000 MB, got {}", target_mb)
+            ));
+        }
+        if settings.bitrate.as_deref().is_some_and(|b| b != "Keep Original") || settings.copy_streams {
+            return Err(MediaForgeError::InvalidSettings(
+                "A target size sets the bitrate itself and can't be combined with a bitrate or stream copy".into()
+            ));
+        }
+    }
+    if let Some(kbps) = settings.target_audio_kbps {
+        if !(8..=512).contains(&kbps) {
+            return Err(MediaForgeError::InvalidSettings(
+                format!("Target audio bitrate must be between 8 and 512 kbps, got {}", kbps)
+            ));
+        }
+    }
+
+    if let Some(interval) = settings.keyframe_interval {
+        if !(1..=MAX_KEYFRAME_INTERVAL).contains(&interval) {
+            return Err(MediaForgeError::InvalidSettings(format!(
+                "Keyframe interval must be between 1 and {} frames, got {}",
+                MAX_KEYFRAME_INTERVAL, interval
+            )));
+        }
+    }
+    let codec = profile_codec(settings)?;
+    if let Some(profile) = &settings.profile {
+        if codec.pixel_format(profile).is_none() {
+            let names: Vec<&str> = codec.profiles.iter().map(|(name, _)| *name).collect();
+            return Err(MediaForgeError::InvalidSettings(format!(
+                "Unknown {} profile '{}'; expected one of {}",
+                codec.name,
+                profile,
+                names.join(", ")
+            )));
+        }
+    }
+    if let Some(level) = &settings.level {
+        if !codec.levels.contains(&normalize_level(level)) {
+            return Err(MediaForgeError::InvalidSettings(format!("Unknown {} level '{}'", codec.name, level)));
+        }
+    }
+    if settings.copy_streams && (settings.keyframe_interval.is_some() || sets_encoder(settings)) {
+        return Err(MediaForgeError::InvalidSettings(
+            "Keyframe interval, codec, profile and level need re-encoding and can't be combined with stream copy".into()
+        ));
+    }
+    if settings.target_size_mb.is_some() && codec.encoder != H264.encoder {
+        return Err(MediaForgeError::InvalidSettings(format!(
+            "A target size uses the output format's default codec and can't be combined with {}",
+            codec.name
+        )));
+    }
+
+    if settings.strip_metadata && settings.preserve_metadata == Some(true) {
+        return Err(MediaForgeError::InvalidSettings(
+            "Metadata can't be both stripped and preserved".into()
+        ));
+    }
+    
+    validate_audio_mode(settings)
+}
+
+/// Checks `audio_mode` and that a replacement track comes with "replace" and only then
+fn validate_audio_mode(settings: &VideoSettings) -> Result<(), MediaForgeError> {
+    let mode = settings.audio_mode.as_deref().unwrap_or("keep");
+    if !AUDIO_MODES.contains(&mode) {
+        return Err(MediaForgeError::InvalidSettings(format!(
+            "Invalid audio mode '{}': expected one of {}",
+            mode,
+            AUDIO_MODES.join(", ")
+        )));
+    }
+
+    match (&settings.replacement_audio, mode) {
+        // The new track may come from a video as well as an audio file
+        (Some(path), "replace") => validate_input_file(path, &ConversionType::Auto),
+        (None, "replace") => Err(MediaForgeError::InvalidSettings(
+            "Replacing the audio needs a replacement audio file".into()
+        )),
+        (Some(_), _) => Err(MediaForgeError::InvalidSettings(
+            "A replacement audio file is only used with the 'replace' audio mode".into()
+        )),
+        (None, _) => Ok(()),
+    }
+}
+
+/// Makes sure the replacement audio file really has a sound track
+async fn ensure_replacement_audio(settings: &VideoSettings) -> Result<(), MediaForgeError> {
+    let Some(path) = settings.replacement_audio.as_deref().filter(|_| replaces_audio(settings)) else {
+        return Ok(());
+    };
+    let probe = probe_media(path).await?;
+    if probe.streams.iter().any(|stream| stream.codec_type == "audio") {
+        Ok(())
+    } else {
+        Err(MediaForgeError::InvalidSettings(format!(
+            "Replacement audio {} has no audio stream",
+            path.display()
+        )))
+    }
+}
+
+fn replaces_audio(settings: &VideoSettings) -> bool {
+    settings.audio_mode.as_deref() == Some("replace")
+}
+
+/// Audio bitrate set aside from a target size when none is given
+const DEFAULT_TARGET_AUDIO_KBPS: u32 = 128;
+/// Share of a target size left for the container's own data
+const TARGET_SIZE_OVERHEAD: f64 = 0.03;
+/// How far above its target an output may end up before the task gets a warning
+const TARGET_SIZE_TOLERANCE: f64 = 0.05;
+/// Lowest video bitrate worth encoding at; smaller targets are rejected
+const MIN_TARGET_VIDEO_KBPS: f64 = 50.0;
+
+/// One pass of a two-pass encode aimed at `VideoSettings::target_size_mb`
+struct TwoPassEncode {
+    pass: u8,
+    /// Prefix of the statistics files the first pass writes for the second
+    passlog: PathBuf,
+    codec: &'static str,
+    video_kbps: u32,
+    audio_kbps: u32,
+}
+
+/// Containers whose video is encoded with libx264 for a target size or an H.264
+/// profile, naming the encoder so it doesn't depend on ffmpeg's default
+const H264_CONTAINERS: [&str; 5] = ["mp4", "mkv", "mov", "avi", "flv"];
+
+/// Encoder for a target-size encode. Both passes have to name it, as the first one
+/// writes no file whose container would pick it.
+fn two_pass_codec(output_format: &str) -> Option<&'static str> {
+    let format = output_format.to_lowercase();
+    if H264_CONTAINERS.contains(&format.as_str()) {
+        Some("libx264")
+    } else if format == "webm" {
+        Some("libvpx-vp9")
+    } else {
+        None
+    }
+}
+
+/// A codec whose profile and level can be chosen
+struct ProfileCodec {
+    name: &'static str,
+    encoder: &'static str,
+    /// Profiles with the pixel format each one can hold. It is set along with the
+    /// profile, as the encoder otherwise keeps e.g. a 10-bit input's format and fails.
+    profiles: &'static [(&'static str, &'static str)],
+    /// Levels as the encoder accepts them; "3.0" is read as "3"
+    levels: &'static [&'static str],
+    containers: &'static [&'static str],
+}
+
+impl ProfileCodec {
+    fn pixel_format(&self, profile: &str) -> Option<&'static str> {
+        self.profiles.iter().find(|(name, _)| *name == profile).map(|(_, pix_fmt)| *pix_fmt)
+    }
+}
+
+const H264: ProfileCodec = ProfileCodec {
+    name: "H.264",
+    encoder: "libx264",
+    profiles: &[
+        ("baseline", "yuv420p"),
+        ("main", "yuv420p"),
+        ("high", "yuv420p"),
+        ("high10", "yuv420p10le"),
+        ("high422", "yuv422p"),
+        ("high444", "yuv444p"),
+    ],
+    levels: &[
+        "1", "1b", "1.1", "1.2", "1.3", "2", "2.1", "2.2", "3", "3.1",
+        "3.2", "4", "4.1", "4.2", "5", "5.1", "5.2", "6", "6.1", "6.2",
+    ],
+    containers: &H264_CONTAINERS,
+};
+
+const H265: ProfileCodec = ProfileCodec {
+    name: "H.265",
+    encoder: "libx265",
+    profiles: &[
+        ("main", "yuv420p"),
+        ("main10", "yuv420p10le"),
+        ("main422-10", "yuv422p10le"),
+        ("main444-8", "yuv444p"),
+        ("main444-10", "yuv444p10le"),
+    ],
+    levels: &["1", "2", "2.1", "3", "3.1", "4", "4.1", "5", "5.1", "5.2", "6", "6.1", "6.2"],
+    containers: &["mp4", "mkv", "mov"],
+};
+
+/// The codec `settings` encode with, H.264 unless H.265 is asked for
+fn profile_codec(settings: &VideoSettings) -> Result<&'static ProfileCodec, MediaForgeError> {
+    match settings.codec.as_deref().map(str::to_lowercase).as_deref() {
+        None | Some("h264") => Ok(&H264),
+        Some("h265") | Some("hevc") => Ok(&H265),
+        Some(other) => Err(MediaForgeError::InvalidSettings(format!(
+            "Unknown video codec '{}'; expected h264 or h265",
+            other
+        ))),
+    }
+}
+
+/// Whether `settings` pick the encoder, rather than leaving it to the container
+fn sets_encoder(settings: &VideoSettings) -> bool {
+    settings.codec.is_some() || settings.profile.is_some() || settings.level.is_some()
+}
+
+fn normalize_level(level: &str) -> &str {
+    level.strip_suffix(".0").unwrap_or(level)
+}
+
+/// Longest keyframe interval accepted, in frames
+const MAX_KEYFRAME_INTERVAL: u32 = 1000;
+
+fn target_audio_kbps(settings: &VideoSettings) -> u32 {
+    if settings.audio_mode.as_deref() == Some("remove") {
+        0
+    } else {
+        settings.target_audio_kbps.unwrap_or(DEFAULT_TARGET_AUDIO_KBPS)
+    }
+}
+
+/// Video bitrate that makes `duration_secs` of output, with its audio, about
+/// `target_mb` megabytes
+fn target_video_kbps(target_mb: u32, duration_secs: f64, audio_kbps: u32) -> Result<u32, MediaForgeError> {
+    let total_kbps = target_mb as f64 * 8_000.0 * (1.0 - TARGET_SIZE_OVERHEAD) / duration_secs;
+    let video_kbps = total_kbps - audio_kbps as f64;
+    if video_kbps.is_finite() && video_kbps >= MIN_TARGET_VIDEO_KBPS {
+        Ok(video_kbps as u32)
+    } else {
+        Err(MediaForgeError::InvalidSettings(format!(
+            "{} MB is too small for {:.0}s of video at {} kbps audio",
+            target_mb, duration_secs, audio_kbps
+        )))
+    }
+}
+
+/// Warning for an output that ended up clearly larger than its target size
+fn check_target_size(target_mb: u32, output_bytes: u64) -> Option<String> {
+    let limit = target_mb as f64 * 1_000_000.0 * (1.0 + TARGET_SIZE_TOLERANCE);
+    (output_bytes as f64 > limit).then(|| {
+        format!("Output is {:.1} MB, above the {} MB target", output_bytes as f64 / 1_000_000.0, target_mb)
+    })
+}
+
+/// Temporary folder for the files one encode passes to the next: two-pass statistics
+/// and stabilization transforms. Removed when dropped, so failed, cancelled and
+/// aborted encodes don't leave it behind.
+struct EncodeTempDir(PathBuf);
+
+impl EncodeTempDir {
+    fn create(task_id: &str) -> Result<Self, MediaForgeError> {
+        let dir = std::env::temp_dir().join(format!("mediaforge-encode-{}", task_id));
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| MediaForgeError::FileSystemError(format!("Cannot create directory: {}", e)))?;
+        Ok(Self(dir))
+    }
+
+    fn passlog_prefix(&self) -> PathBuf {
+        self.0.join("passlog")
+    }
+
+    fn transforms(&self) -> PathBuf {
+        self.0.join("transforms.trf")
+    }
+}
+
+impl Drop for EncodeTempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Builds the ffmpeg argument vector for a video conversion, or for one pass of a
+/// target-size encode
+fn build_video_args(
+    input_file: &Path,
+    output_path: &Path,
+    settings: Option<&VideoSettings>,
+    encode: Option<&TwoPassEncode>,
+    transforms: Option<&Path>,
+) -> Vec<OsString> {
+    let mut args: Vec<OsString> = vec!["-i".into(), input_file.into()];
+
+    // Apply video settings
+    if let Some(settings) = settings {
+        // Every input comes before the output options: the watermark is input 1, a
+        // replacement sound track the one after it
+        if let Some(watermark) = &settings.watermark {
+            args.push("-i".into());
+            args.push(watermark.path.as_os_str().into());
+        }
+        let replacement_audio = settings.replacement_audio.as_ref().filter(|_| replaces_audio(settings));
+        if let Some(audio) = replacement_audio {
+            args.push("-i".into());
+            args.push(audio.into());
+        }
+
+        if settings.copy_streams {
+            // Validation rules out every setting that would need re-encoding. A new
+            // sound track is encoded to suit the container instead of copied.
+            let codec = if replacement_audio.is_some() { "-c:v" } else { "-c" };
+            args.extend([codec, "copy"].map(OsString::from));
+        }
+
+        let stream_filters = video_stream_filters(settings, transforms);
+
+        // A watermark needs a filter graph, so the stream filters move into it
+        if let Some(watermark) = &settings.watermark {
+            let mut graph = watermark_filter_complex(watermark, &stream_filters);
+            if replacement_audio.is_some() {
+                // Labelled so it can be mapped alongside the new sound track
+                graph.push_str("[vout]");
+            }
+            args.push("-filter_complex".into());
+            args.push(graph.into());
+        } else if !stream_filters.is_empty() {
+            args.push("-vf".into());
+            args.push(stream_filters.join(",").into());
+        }
+
+        match settings.audio_mode.as_deref() {
+            Some("remove") => args.push("-an".into()),
+            Some("replace") => {
+                let video = if settings.watermark.is_some() { "[vout]" } else { "0:v" };
+                let audio_input = if settings.watermark.is_some() { 2 } else { 1 };
+                args.extend(["-map".into(), video.into(), "-map".into(), format!("{}:a:0", audio_input).into()]);
+                if settings.shortest {
+                    args.push("-shortest".into());
+                }
+            }
+            _ => {}
+        }
+
+        if settings.strip_metadata {
+            args.extend(["-map_metadata", "-1"].map(OsString::from));
+        } else if preserves_metadata(settings) {
+            // Named explicitly so they come from the video, never a watermark or new sound track
+            args.extend(["-map_metadata", "0", "-map_chapters", "0"].map(OsString::from));
+        } else {
+            args.extend(["-map_metadata", "-1", "-map_chapters", "-1"].map(OsString::from));
+        }
+
+        if let Some(resolution) = &settings.resolution {
+            if resolution != "Keep Original" {
+                args.push("-s".into());
+                args.push(resolution.into());
+            }
+        }
+
+        if let Some(bitrate) = &settings.bitrate {
+            if bitrate != "Keep Original" {
+                args.push("-b:v".into());
+                args.push(bitrate.into());
+            }
+        }
+
+        if let Some(interval) = settings.keyframe_interval {
+            args.push("-g".into());
+            args.push(interval.to_string().into());
+        }
+
+        if sets_encoder(settings) {
+            let codec = profile_codec(settings).unwrap_or(&H264);
+            // A target-size encode names the encoder itself
+            if encode.is_none() {
+                args.extend(["-c:v", codec.encoder].map(OsString::from));
+            }
+            if let Some(profile) = &settings.profile {
+                args.extend(["-profile:v".into(), profile.into(), "-pix_fmt".into()]);
+                args.push(codec.pixel_format(profile).unwrap_or("yuv420p").into());
+            }
+            if let Some(level) = &settings.level {
+                // libx265 ignores -level and takes it as one of its own parameters
+                if codec.encoder == H265.encoder {
+                    args.push("-x265-params".into());
+                    args.push(format!("level-idc={}", normalize_level(level)).into());
+                } else {
+                    args.push("-level:v".into());
+                    args.push(normalize_level(level).into());
+                }
+            }
+        }
+    }
+
+    let animated = output_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .and_then(animated_output_encoder);
+    if let Some(encoder) = animated {
+        // Loops forever, like a shared GIF; any sound track is dropped
+        if settings.and_then(|s| s.audio_mode.as_deref()) != Some("remove") {
+            args.push("-an".into());
+        }
+        args.extend(["-c:v", encoder, "-loop", "0"].map(OsString::from));
+    }
+
+    if let Some(encode) = encode {
+        args.extend(["-c:v", encode.codec, "-b:v"].map(OsString::from));
+        args.push(format!("{}k", encode.video_kbps).into());
+        args.extend(["-pass".into(), encode.pass.to_string().into(), "-passlogfile".into(), encode.passlog.clone().into()]);
+        if encode.pass == 1 {
+            // The first pass only gathers statistics for the second, so nothing is written
+            args.extend(["-an", "-f", "null", "-progress", "pipe:1", "-y", "-"].map(OsString::from));
+            return args;
+        }
+        if encode.audio_kbps > 0 {
+            args.push("-b:a".into());
+            args.push(format!("{}k", encode.audio_kbps).into());
+        }
+    }
+
+    // Progress monitoring
+    args.push("-progress".into());
+    args.push("pipe:1".into());
+    args.push("-y".into()); // Overwrite output files
+    args.push(output_path.into());
+    args
+}
+
+/// Checks an ImageMagick resize geometry: a percentage (`50%`), a width (`800`), a
+/// height (`x600`) or a box (`800x600`), optionally followed by one of the `!<>^` flags
+fn validate_resize_geometry(resize: &str) -> Result<(), MediaForgeError> {
+    let geometry = Regex::new(
+        r"^([1-9][0-9]{0,2}(\.[0-9]{1,2})?%|[1-9][0-9]{0,4}(x[1-9][0-9]{0,4})?[!<>^]?|x[1-9][0-9]{0,4}[!<>^]?)$"
+    ).unwrap();
+    if geometry.is_match(resize) {
+        Ok(())
+    } else {
+        Err(MediaForgeError::InvalidSettings(format!(
+            "Invalid resize geometry '{}': expected a percentage (50%), WIDTH, xHEIGHT or WIDTHxHEIGHT",
+            resize
+        )))
+    }
+}
+
+/// Values accepted in `VideoSettings::audio_mode`
+const AUDIO_MODES: [&str; 3] = ["keep", "remove", "replace"];
+
+/// Effects accepted in `ImageSettings::effects`, in the order they are applied
+const IMAGE_EFFECTS: [&str; 6] = ["grayscale", "sepia", "negate", "auto-level", "sharpen", "blur:<radius>"];
+
+/// Largest blur radius accepted, in pixels
+const MAX_BLUR_RADIUS: f32 = 100.0;
+
+/// Sizes stored in an ICO, largest first; the first is also the canvas the image is fitted to
+const ICO_SIZES: [u32; 4] = [256, 48, 32, 16];
+
+/// Values accepted in `ImageSettings::colorspace`, spelled the way ImageMagick expects
+const COLORSPACES: [&str; 3] = ["sRGB", "CMYK", "Gray"];
+
+/// Output formats that can't store transparency, so it is flattened onto a background
+const FORMATS_WITHOUT_ALPHA: [&str; 2] = ["jpg", "jpeg"];
+
+/// Background used for flattening when the request doesn't choose one
+const DEFAULT_BACKGROUND: &str = "#ffffff";
+
+#[derive(Debug, Clone, PartialEq)]
+enum ImageEffect {
+    Grayscale,
+    Sepia,
+    Negate,
+    AutoLevel,
+    Sharpen,
+    Blur(f32),
+}
+
+impl ImageEffect {
+    fn parse(effect: &str) -> Result<Self, MediaForgeError> {
+        match effect {
+            "grayscale" => Ok(Self::Grayscale),
+            "sepia" => Ok(Self::Sepia),
+            "negate" => Ok(Self::Negate),
+            "auto-level" => Ok(Self::AutoLevel),
+            "sharpen" => Ok(Self::Sharpen),
+            _ => match effect.strip_prefix("blur:") {
+                Some(radius) => {
+                    // Plain decimals only, so values like `inf` or `1e3` never reach magick
+                    let decimal = Regex::new(r"^[0-9]{1,3}(\.[0-9]{1,2})?$").unwrap();
+                    match radius.parse::<f32>() {
+                        Ok(r) if decimal.is_match(radius) && r > 0.0 && r <= MAX_BLUR_RADIUS => Ok(Self::Blur(r)),
+                        _ => Err(MediaForgeError::InvalidSettings(format!(
+                            "Invalid blur radius '{}': expected a number above 0 and up to {}",
+                            radius, MAX_BLUR_RADIUS
+                        ))),
+                    }
+                }
+                None => Err(MediaForgeError::InvalidSettings(format!(
+                    "Unknown image effect '{}' (supported: {})",
+                    effect,
+                    IMAGE_EFFECTS.join(", ")
+                ))),
+            },
+        }
+    }
+
+    /// Position in the fixed application order
+    fn rank(&self) -> usize {
+        match self {
+            Self::Grayscale => 0,
+            Self::Sepia => 1,
+            Self::Negate => 2,
+            Self::AutoLevel => 3,
+            Self::Sharpen => 4,
+            Self::Blur(_) => 5,
+        }
+    }
+
+    fn magick_args(&self) -> Vec<String> {
+        let args: &[&str] = match self {
+            Self::Grayscale => &["-colorspace", "Gray"],
+            Self::Sepia => &["-sepia-tone", "80%"],
+            Self::Negate => &["-negate"],
+            Self::AutoLevel => &["-auto-level"],
+            Self::Sharpen => &["-sharpen", "0x1"],
+            // A radius of 0 lets magick pick the kernel size for the sigma
+            Self::Blur(radius) => return vec!["-blur".to_string(), format!("0x{}", radius)],
+        };
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+}
+
+/// Parses effect names into their application order; repeats of an effect are dropped
+fn parse_image_effects(effects: &[String]) -> Result<Vec<ImageEffect>, MediaForgeError> {
+    let mut parsed = effects
+        .iter()
+        .map(|effect| ImageEffect::parse(effect.trim()))
+        .collect::<Result<Vec<_>, _>>()?;
+    parsed.sort_by_key(ImageEffect::rank);
+    parsed.dedup_by_key(|effect| effect.rank());
+    Ok(parsed)
+}
+
+/// Validates image editing settings so only well-formed values reach the magick command line
+fn validate_image_settings(settings: &ImageSettings) -> Result<(), MediaForgeError> {
+    if let Some(resize) = &settings.resize {
+        validate_resize_geometry(resize)?;
+    }
+    
+    if let Some(crop) = &settings.crop {
+        let geometry = Regex::new(r"^[0-9]{1,5}x[0-9]{1,5}([+-][0-9]{1,5}[+-][0-9]{1,5})?$").unwrap();
+        if !geometry.is_match(crop) {
+            return Err(MediaForgeError::InvalidSettings(
+                format!("Invalid crop geometry '{}': expected WIDTHxHEIGHT+X+Y", crop)
+            ));
+        }
+    }
+    
+    if let Some(rotate) = settings.rotate {
+        if !(-360..=360).contains(&rotate) {
+            return Err(MediaForgeError::InvalidSettings(
+                format!("Rotation must be between -360 and 360 degrees, got {}", rotate)
+            ));
+        }
+    }
+    
+    if let Some(flip) = &settings.flip {
+        if flip != "horizontal" && flip != "vertical" {
+            return Err(MediaForgeError::InvalidSettings(
+                format!("Flip must be 'horizontal' or 'vertical', got '{}'", flip)
+            ));
+        }
+    }
+    
+    if let Some(density) = settings.density {
+        if density == 0 || density > MAX_DENSITY {
+            return Err(MediaForgeError::InvalidSettings(
+                format!("Density must be between 1 and {} DPI, got {}", MAX_DENSITY, density)
+            ));
+        }
+    }
+    
+    if let Some(dpi) = settings.dpi {
+        if dpi == 0 || dpi > MAX_DPI {
+            return Err(MediaForgeError::InvalidSettings(
+                format!("DPI must be between 1 and {}, got {}", MAX_DPI, dpi)
+            ));
+        }
+    }
+    
+    if let Some(effort) = settings.effort {
+        if effort > 9 {
+            return Err(MediaForgeError::InvalidSettings(
+                format!("AVIF effort must be between 0 and 9, got {}", effort)
+            ));
+        }
+    }
+    
+    if let Some(watermark) = &settings.watermark {
+        validate_watermark(watermark)?;
+    }
+    
+    if let Some(effects) = &settings.effects {
+        parse_image_effects(effects)?;
+    }
+    
+    if let Some(colorspace) = &settings.colorspace {
+        if magick_colorspace(colorspace).is_none() {
+            return Err(MediaForgeError::InvalidSettings(
+                format!("Colorspace must be one of {}, got '{}'", COLORSPACES.join(", "), colorspace)
+            ));
+        }
+    }
+    
+    if settings.strip_icc && settings.keep_icc {
+        return Err(MediaForgeError::InvalidSettings(
+            "The ICC profile can't be both stripped and kept".into()
+        ));
+    }
+    
+    // The input's profile describes its old colors, so keeping it would misrender the output
+    if settings.keep_icc && settings.colorspace.is_some() {
+        return Err(MediaForgeError::InvalidSettings(
+            "The ICC profile can't be kept when converting the colorspace".into()
+        ));
+    }
+    
+    if let Some(background) = &settings.background {
+        let hex_color = Regex::new(r"^#([0-9A-Fa-f]{3}|[0-9A-Fa-f]{6})$").unwrap();
+        if !hex_color.is_match(background) {
+            return Err(MediaForgeError::InvalidSettings(
+                format!("Invalid background color '{}': expected #rgb or #rrggbb", background)
+            ));
+        }
+    }
+    
+    Ok(())
+}
+
+/// Maps a requested colorspace onto ImageMagick's name for it, ignoring case
+fn magick_colorspace(name: &str) -> Option<&'static str> {
+    COLORSPACES.iter().copied().find(|c| c.eq_ignore_ascii_case(name))
+}
+
+/// The standard sRGB ICC profile, written once to the cache directory for ImageMagick
+/// to convert profiled inputs into. None when it couldn't be written.
+fn srgb_profile_path() -> Option<&'static Path> {
+    static PATH: OnceLock<Option<PathBuf>> = OnceLock::new();
+    PATH.get_or_init(|| {
+        let profile = moxcms::ColorProfile::new_srgb()
+            .encode()
+            .map_err(|e| log::warn!("Failed to encode the sRGB profile: {:?}", e))
+            .ok()?;
+        let dir = dirs::cache_dir().unwrap_or_else(std::env::temp_dir).join("mediaforge");
+        let path = dir.join("sRGB.icc");
+        if std::fs::read(&path).ok().as_deref() != Some(profile.as_slice()) {
+            let written = std::fs::create_dir_all(&dir).and_then(|()| std::fs::write(&path, &profile));
+            if let Err(e) = written {
+                log::warn!("Failed to write the sRGB profile to {:?}: {}", path, e);
+                return None;
+            }
+        }
+        Some(path)
+    })
+    .as_deref()
+}
+
+/// Builds the ImageMagick argument vector for an image conversion.
+///
+/// Operations are applied in a fixed order: crop, rotate, flip, resize, effects, watermark.
+/// Outputs without alpha then have transparency flattened onto the background, the
+/// colorspace is converted, and output settings such as quality follow.
+fn build_image_args(
+    input_file: &Path,
+    output_path: &Path,
+    output_format: &str,
+    settings: Option<&ImageSettings>,
+) -> Vec<OsString> {
+    let mut args: Vec<OsString> = Vec::new();
+
+    // Density is a read setting, so it has to come before the input it applies to
+    if let Some(density) = settings.and_then(|s| s.density) {
+        args.push("-density".into());
+        args.push(density.to_string().into());
+    }
+
+    args.push(input_file.into());
+
+    if let Some(settings) = settings {
+        if let Some(crop) = &settings.crop {
+            args.push("-crop".into());
+            args.push(crop.into());
+            // Drop the virtual canvas left behind by the crop
+            args.push("+repage".into());
+        }
+
+        if let Some(rotate) = settings.rotate {
+            args.push("-rotate".into());
+            args.push(rotate.to_string().into());
+        }
+
+        match settings.flip.as_deref() {
+            // -flop mirrors left-right, -flip mirrors top-bottom
+            Some("horizontal") => args.push("-flop".into()),
+            Some("vertical") => args.push("-flip".into()),
+            _ => {}
+        }
+    }
+
+    // Icons are square; fit the image to the largest icon size and pad the rest, and
+    // the smaller sizes are generated from that when writing
+    if output_format == "ico" {
+        let canvas = format!("{0}x{0}", ICO_SIZES[0]);
+        args.push("-resize".into());
+        args.push(canvas.clone().into());
+        args.extend(["-background", "none", "-gravity", "center", "-extent"].map(OsString::from));
+        args.push(canvas.into());
+        log::info!("ICO format detected, writing sizes {:?}", ICO_SIZES);
+    } else if let Some(resize) = settings.and_then(|s| s.resize.as_ref()) {
+        log::info!("Applying image resize: {}", resize);
+        args.push("-resize".into());
+        args.push(resize.into());
+    }
+
+    // Applied before the watermark so it keeps its own colors
+    if let Some(effects) = settings.and_then(|s| s.effects.as_deref()) {
+        for effect in parse_image_effects(effects).unwrap_or_default() {
+            args.extend(effect.magick_args().into_iter().map(OsString::from));
+        }
+    }
+
+    if let Some(watermark) = settings.and_then(|s| s.watermark.as_ref()) {
+        args.push("(".into());
+        args.push(watermark.path.as_os_str().into());
+        if let Some(scale) = watermark.scale {
+            args.push("-resize".into());
+            args.push(format!("{}%", scale * 100.0).into());
+        }
+        args.extend(["-alpha", "set", "-channel", "A", "-evaluate", "multiply"].map(OsString::from));
+        args.push(watermark.opacity.to_string().into());
+        args.push("+channel".into());
+        args.push(")".into());
+        args.push("-gravity".into());
+        args.push(watermark.position.as_str().into());
+        args.extend(["-geometry", "+10+10", "-composite"].map(OsString::from));
+    }
+
+    // Without this, magick leaves whatever color sits under transparent pixels (often black)
+    if FORMATS_WITHOUT_ALPHA.contains(&output_format.to_lowercase().as_str()) {
+        let background = settings.and_then(|s| s.background.as_deref()).unwrap_or(DEFAULT_BACKGROUND);
+        args.push("-background".into());
+        args.push(background.into());
+        args.extend(["-alpha", "remove", "-alpha", "off"].map(OsString::from));
+    }
+
+    // Last of the pixel operations so effects and the watermark work in the input's colors
+    let colorspace = settings.and_then(|s| s.colorspace.as_deref()).and_then(magick_colorspace);
+    if let Some(colorspace) = colorspace {
+        // A transform through the input's embedded profile keeps its colors, where
+        // -colorspace alone converts CMYK naively. Inputs without a profile are only
+        // tagged with this one, which is removed again below.
+        if let Some(profile) = srgb_profile_path().filter(|_| colorspace == "sRGB") {
+            args.push("-profile".into());
+            args.push(profile.into());
+        }
+        args.push("-colorspace".into());
+        args.push(colorspace.into());
+    }
+
+    // After the input, -density only sets the resolution recorded in the output
+    if let Some(dpi) = settings.and_then(|s| s.dpi) {
+        args.extend(["-units", "PixelsPerInch", "-density"].map(OsString::from));
+        args.push(dpi.to_string().into());
+    }
+
+    let is_avif = output_format.eq_ignore_ascii_case("avif");
+
+    let optimize = settings.is_some_and(|s| s.optimize);
+    let quality = settings
+        .and_then(|s| s.quality)
+        .or_else(|| optimized_quality(output_format).filter(|_| optimize));
+    if let Some(quality) = quality {
+        log::info!("Applying image quality: {}", quality);
+        let quality = if is_avif { avif_quality(quality) } else { quality };
+        args.push("-quality".into());
+        args.push(quality.to_string().into());
+    }
+
+    if let Some(effort) = settings.and_then(|s| s.effort).filter(|_| is_avif) {
+        // libheif's speed runs the other way: 0 is the slowest, best-compressing preset
+        args.push("-define".into());
+        args.push(format!("heic:speed={}", 9 - effort.min(9)).into());
+    }
+
+    let optimize_args = if optimize {
+        image_optimize_args(output_format)
+    } else {
+        &[]
+    };
+    let strip = settings.is_some_and(|s| s.strip_metadata) || optimize_args.contains(&"-strip");
+    if strip && settings.is_some_and(|s| s.keep_icc) {
+        // Removes every profile except the ICC one
+        args.extend(["+profile", "!icc,*"].map(OsString::from));
+    } else if strip {
+        args.push("-strip".into());
+    } else if colorspace.is_some() || settings.is_some_and(|s| s.strip_icc) {
+        args.extend(["+profile", "icc"].map(OsString::from));
+    }
+    args.extend(optimize_args.iter().filter(|&&arg| arg != "-strip").map(OsString::from));
+
+    if output_format == "ico" {
+        args.push("-define".into());
+        args.push(ico_auto_resize().into());
+    }
+
+    args.push(output_path.into());
+    args
+}
+
+/// The `-define` value that makes ImageMagick write every size in `ICO_SIZES`
+fn ico_auto_resize() -> String {
+    let sizes: Vec<String> = ICO_SIZES.iter().map(|size| size.to_string()).collect();
+    format!("icon:auto-resize={}", sizes.join(","))
+}
+
+/// ImageMagick 6 releases from before `icon:auto-resize` ignore it and write a single
+/// size, so for them the smaller sizes are added as extra frames of the output instead
+fn legacy_ico_args(mut args: Vec<OsString>) -> Vec<OsString> {
+    let Some(index) = args.iter().position(|arg| *arg == *ico_auto_resize()) else {
+        return args;
+    };
+    let frames = ICO_SIZES[1..].iter().flat_map(|size| {
+        ["(", "-clone", "0", "-resize"]
+            .map(OsString::from)
+            .into_iter()
+            .chain([format!("{0}x{0}", size).into(), ")".into()])
+    });
+    args.splice(index - 1..=index, frames.collect::<Vec<_>>());
+    args
+}
+
+/// Size reductions applied by `ImageSettings::optimize`. JPEG gets the chroma
+/// subsampling most encoders already use for photos and progressive encoding, WebP
+/// the slowest, best-compressing method.
+fn image_optimize_args(output_format: &str) -> &'static [&'static str] {
+    match output_format.to_lowercase().as_str() {
+        "png" => &["-strip", "-define", "png:compression-level=9"],
+        "jpg" | "jpeg" => &["-strip", "-interlace", "Plane", "-sampling-factor", "4:2:0"],
+        "webp" => &["-define", "webp:method=6"],
+        _ => &[],
+    }
+}
+
+/// Quality used by `ImageSettings::optimize` when none is set: small files without
+/// visible artifacts in photos. PNG is lossless, so it has none.
+fn optimized_quality(output_format: &str) -> Option<u32> {
+    match output_format.to_lowercase().as_str() {
+        "jpg" | "jpeg" => Some(82),
+        "webp" => Some(80),
+        _ => None,
+    }
+}
+
+/// Warning on a task whose output already existed and was left alone
+const KEPT_EXISTING_OUTPUT: &str = "Output already exists; kept the existing file";
+
+/// Where a task writes its output, once the conflict policy has been applied
+enum OutputClaim {
+    /// Free to write to
+    Claimed(PathBuf),
+    /// Already there and kept; the task completes without converting
+    KeptExisting,
+}
+
+/// What a finished conversion task did about its output
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ConversionOutcome {
+    /// This task wrote the output
+    Converted,
+    /// The output already existed and was kept, so nothing was converted
+    KeptExisting,
+}
+
+/// Deletes a converted input after checking that every output is on disk, isn't
+/// empty and isn't the input itself
+fn delete_source_file(source: &Path, outputs: &[PathBuf]) -> Result<(), MediaForgeError> {
+    if outputs.is_empty() {
+        return Err(MediaForgeError::FileSystemError("No output file was recorded".to_string()));
+    }
+    let source_path = std::fs::canonicalize(source)?;
+    for output in outputs {
+        let metadata = std::fs::metadata(output).map_err(|_| {
+            MediaForgeError::FileSystemError(format!("Output {} is missing", output.display()))
+        })?;
+        if metadata.len() == 0 {
+            return Err(MediaForgeError::FileSystemError(format!("Output {} is empty", output.display())));
+        }
+        if std::fs::canonicalize(output)? == source_path {
+            return Err(MediaForgeError::FileSystemError("The output is the source file".to_string()));
+        }
+    }
+    std::fs::remove_file(&source_path)?;
+    Ok(())
+}
+
+/// Note for an optimized conversion to a format `optimize` has no settings for
+fn optimize_warning(request: &ConvertRequest) -> Option<String> {
+    let optimize = request.image_settings.as_ref().is_some_and(|s| s.optimize);
+    (request.conversion_type == ConversionType::Image && optimize && image_optimize_args(&request.output_format).is_empty()).then(|| {
+        format!(
+            "Optimization only applies to PNG, JPEG and WebP; the .{} output was written as usual",
+            request.output_format.to_lowercase()
+        )
+    })
+}
+
+/// Validates audio output format against the formats ffmpeg is configured to produce
+fn validate_audio_format(output_format: &str) -> Result<(), MediaForgeError> {
+    let output_lower = output_format.to_lowercase();
+    let safe_output_formats = [
+        "mp3", "m4a", "aac", "flac", "ogg", "opus", "wav", "wma"
+    ];
+    
+    if !safe_output_formats.contains(&output_lower.as_str()) {
+        return Err(MediaForgeError::InvalidSettings(
+            format!(
+                "Output audio format not supported: '{}' (supported: {})",
+                output_format,
+                safe_output_formats.join(", ")
+            )
+        ));
+    }
+    
+    Ok(())
+}
+
+/// Returns the ffmpeg codec arguments for an audio output format
+fn audio_codec_args(output_format: &str, settings: Option<&AudioSettings>) -> Vec<String> {
+    let bit_depth = settings.and_then(|s| s.bit_depth);
+    let mut args: Vec<String> = Vec::new();
+    
+    match output_format.to_lowercase().as_str() {
+        "mp3" => args.extend(["-c:a".into(), "libmp3lame".into()]),
+        "m4a" | "aac" => args.extend(["-c:a".into(), "aac".into()]),
+        "ogg" => args.extend(["-c:a".into(), "libvorbis".into()]),
+        "opus" => args.extend(["-c:a".into(), "libopus".into()]),
+        "wma" => args.extend(["-c:a".into(), "wmav2".into()]),
+        "wav" => {
+            let codec = if bit_depth == Some(24) { "pcm_s24le" } else { "pcm_s16le" };
+            args.extend(["-c:a".into(), codec.into()]);
+        }
+        "flac" => {
+            args.extend(["-c:a".into(), "flac".into()]);
+            if let Some(level) = settings.and_then(|s| s.compression_level) {
+                args.extend(["-compression_level".into(), level.to_string()]);
+            }
+            match bit_depth {
+                Some(24) => args.extend([
+                    "-sample_fmt".into(), "s32".into(),
+                    "-bits_per_raw_sample".into(), "24".into(),
+                ]),
+                Some(16) => args.extend(["-sample_fmt".into(), "s16".into()]),
+                _ => {}
+            }
+        }
+        _ => {}
+    }
+    
+    args
+}
+
+/// Audio containers that can carry embedded album art
+const COVER_ART_FORMATS: [&str; 3] = ["mp3", "m4a", "flac"];
+
+/// Validates audio settings ranges before any ffmpeg process is spawned
+fn validate_audio_settings(settings: &AudioSettings, output_format: &str) -> Result<(), MediaForgeError> {
+    if let Some(bitrate) = &settings.bitrate {
+        // kbps; the k suffix is added when building the ffmpeg arguments
+        let re = Regex::new(r"^[1-9][0-9]{0,3}$").unwrap();
+        if !re.is_match(bitrate) {
+            return Err(MediaForgeError::InvalidSettings(
+                format!("Invalid audio bitrate '{}': expected kbps as a plain number, e.g. 192", bitrate)
+            ));
+        }
+    }
+    
+    if let Some(channels) = settings.channels {
+        if channels != 1 && channels != 2 {
+            return Err(MediaForgeError::InvalidSettings(
+                format!("Audio channels must be 1 (mono) or 2 (stereo), got {}", channels)
+            ));
+        }
+    }
+    
+    if let Some(volume) = settings.volume {
+        if !volume.is_finite() || !(0.1..=10.0).contains(&volume) {
+            return Err(MediaForgeError::InvalidSettings(
+                format!("Audio volume must be between 0.1 and 10.0, got {}", volume)
+            ));
+        }
+    }
+    
+    for (name, fade) in [("Fade-in", settings.fade_in_secs), ("Fade-out", settings.fade_out_secs)] {
+        if let Some(fade) = fade {
+            if !fade.is_finite() || fade < 0.0 {
+                return Err(MediaForgeError::InvalidSettings(
+                    format!("{} must be a non-negative number of seconds, got {}", name, fade)
+                ));
+            }
+        }
+    }
+    
+    let format = output_format.to_lowercase();
+    
+    if let Some(bit_depth) = settings.bit_depth {
+        if bit_depth != 16 && bit_depth != 24 {
+            return Err(MediaForgeError::InvalidSettings(
+                format!("Audio bit depth must be 16 or 24, got {}", bit_depth)
+            ));
+        }
+        if format != "wav" && format != "flac" {
+            return Err(MediaForgeError::InvalidSettings(
+                "Audio bit depth only applies to wav and flac outputs".into()
+            ));
+        }
+    }
+    
+    if let Some(level) = settings.compression_level {
+        if format != "flac" {
+            return Err(MediaForgeError::InvalidSettings(
+                "Compression level only applies to flac outputs".into()
+            ));
+        }
+        if level > 12 {
+            return Err(MediaForgeError::InvalidSettings(
+                format!("FLAC compression level must be between 0 and 12, got {}", level)
+            ));
+        }
+    }
+    
+    if let Some(metadata) = &settings.metadata {
+        for (key, value) in metadata.tags() {
+            if value.chars().any(|c| c == '\0') {
+                return Err(MediaForgeError::InvalidSettings(
+                    format!("Audio tag '{}' contains invalid characters", key)
+                ));
+            }
+        }
+    }
+    
+    if let Some(cover_art) = &settings.cover_art {
+        if !COVER_ART_FORMATS.contains(&format.as_str()) {
+            return Err(MediaForgeError::InvalidSettings(
+                format!("Cover art is only supported for {} outputs", COVER_ART_FORMATS.join(", "))
+            ));
+        }
+        validate_input_file(cover_art, &ConversionType::Image)?;
+        // Only the input side matters here: the cover is embedded without re-encoding
+        validate_image_format(cover_art, "jpg")?;
+    }
+    
+    Ok(())
+}
+
+/// Audio filter chain for the settings. `trim` is the part of the input kept after
+/// cutting silence. A fade-out needs the (trimmed) clip duration and is skipped when
+/// it isn't known.
+fn audio_filters(settings: &AudioSettings, duration_secs: Option<f64>, trim: Option<(f64, f64)>) -> Vec<String> {
+    let mut filters = Vec::new();
+
+    if let Some((start, end)) = trim {
+        // Restart timestamps at zero so the fades line up with the trimmed clip
+        filters.push(format!("atrim=start={:.3}:end={:.3}", start, end));
+        filters.push("asetpts=PTS-STARTPTS".to_string());
+    }
+
+    if let Some(volume) = settings.volume {
+        filters.push(format!("volume={}", volume));
+    }
+
+    if let Some(fade_in) = settings.fade_in_secs.filter(|&d| d > 0.0) {
+        filters.push(format!("afade=t=in:st=0:d={}", fade_in));
+    }
+
+    if let (Some(fade_out), Some(duration)) = (settings.fade_out_secs.filter(|&d| d > 0.0), duration_secs) {
+        let start = format!("{:.3}", (duration - f64::from(fade_out)).max(0.0));
+        let start = start.trim_end_matches('0').trim_end_matches('.');
+        filters.push(format!("afade=t=out:st={}:d={}", start, fade_out));
+    }
+
+    filters
+}
+
+/// Checks fades against the clip duration once it is known
+fn validate_fade_lengths(settings: &AudioSettings, duration_secs: f64) -> Result<(), MediaForgeError> {
+    for (name, fade) in [("Fade-in", settings.fade_in_secs), ("Fade-out", settings.fade_out_secs)] {
+        if let Some(fade) = fade {
+            if f64::from(fade) > duration_secs {
+                return Err(MediaForgeError::InvalidSettings(format!(
+                    "{} of {}s is longer than the {:.1}s clip",
+                    name, fade, duration_secs
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Level below which audio counts as silence when trimming
+const SILENCE_THRESHOLD: &str = "-50dB";
+
+/// Shortest quiet stretch that is trimmed, in seconds
+const MIN_SILENCE_SECS: f64 = 0.5;
+
+/// Part of a `duration_secs` long clip left after cutting leading and trailing
+/// silence, from ffmpeg's `silencedetect` log. `None` when there is nothing to cut
+/// or the clip is silent throughout.
+fn parse_silence_trim(log: &str, duration_secs: f64) -> Option<(f64, f64)> {
+    let value = |line: &str, key: &str| -> Option<f64> {
+        let rest = &line[line.find(key)? + key.len()..];
+        rest.split_whitespace().next()?.parse().ok()
+    };
+
+    // (start, end) of each silence; the end is missing when it runs to the end of the clip
+    let mut silences: Vec<(f64, Option<f64>)> = Vec::new();
+    for line in log.lines() {
+        if let Some(start) = value(line, "silence_start:") {
+            silences.push((start, None));
+        } else if let Some(end) = value(line, "silence_end:") {
+            if let Some(last) = silences.last_mut() {
+                last.1 = Some(end);
+            }
+        }
+    }
+
+    // Small tolerance for timestamps that don't land exactly on the clip's edges
+    let edge = 0.05;
+    let start = match silences.first() {
+        Some(&(start, Some(end))) if start <= edge => end,
+        _ => 0.0,
+    };
+    let end = match silences.last() {
+        Some(&(start, end)) if end.map_or(true, |end| end >= duration_secs - edge) => start,
+        _ => duration_secs,
+    };
+
+    (end > start && (start > 0.0 || end < duration_secs)).then_some((start, end))
+}
+
+/// Finds the leading and trailing silence to cut with one decoding pass
+async fn detect_silence_trim(input_file: &Path, duration_secs: f64) -> Result<Option<(f64, f64)>, MediaForgeError> {
+    let output = TokioCommand::new("ffmpeg")
+        .args(["-hide_banner", "-nostats", "-i"])
+        .arg(input_file)
+        .args(["-vn", "-af"])
+        .arg(format!("silencedetect=noise={}:d={}", SILENCE_THRESHOLD, MIN_SILENCE_SECS))
+        .args(["-f", "null", "-"])
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| MediaForgeError::from_spawn_error("ffmpeg", e, MediaForgeError::FFmpegError))?;
+    if !output.status.success() {
+        return Err(MediaForgeError::FFmpegError(format!(
+            "Silence detection failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(parse_silence_trim(&String::from_utf8_lossy(&output.stderr), duration_secs))
+}
+
+/// Builds the ffmpeg argument vector for an audio conversion
+fn build_audio_args(
+    input_file: &Path,
+    output_path: &Path,
+    output_format: &str,
+    settings: Option<&AudioSettings>,
+    duration_secs: Option<f64>,
+    trim: Option<(f64, f64)>,
+) -> Vec<OsString> {
+    let mut args: Vec<OsString> = vec!["-i".into(), input_file.into()];
+
+    let cover_art = settings.and_then(|s| s.cover_art.as_ref());
+    if let Some(cover_art) = cover_art {
+        args.push("-i".into());
+        args.push(cover_art.into());
+        args.extend(["-map", "0:a", "-map", "1:v", "-c:v", "copy", "-disposition:v", "attached_pic"].map(OsString::from));
+        if output_format.eq_ignore_ascii_case("mp3") {
+            // ID3v2.3 has the widest player support for embedded pictures
+            args.extend(["-id3v2_version", "3"].map(OsString::from));
+        }
+    }
+
+    if settings.is_some_and(|s| s.strip_metadata) {
+        args.extend(["-map_metadata", "-1"].map(OsString::from));
+    }
+
+    args.extend(audio_codec_args(output_format, settings).into_iter().map(OsString::from));
+
+    // Apply audio settings
+    if let Some(settings) = settings {
+        if let Some(bitrate) = &settings.bitrate {
+            args.push("-b:a".into());
+            args.push(format!("{}k", bitrate).into());
+        }
+
+        if let Some(sample_rate) = &settings.sample_rate {
+            args.push("-ar".into());
+            args.push(sample_rate.into());
+        }
+
+        if let Some(channels) = settings.channels {
+            args.push("-ac".into());
+            args.push(channels.to_string().into());
+        }
+
+        let filters = audio_filters(settings, duration_secs, trim);
+        if !filters.is_empty() {
+            args.push("-af".into());
+            args.push(filters.join(",").into());
+        }
+
+        // Each tag is its own argument, so values never pass through a shell
+        if let Some(metadata) = &settings.metadata {
+            for (key, value) in metadata.tags() {
+                args.push("-metadata".into());
+                args.push(format!("{}={}", key, value).into());
+            }
+        }
+    }
+
+    if cover_art.is_none() {
+        args.push("-vn".into()); // No video
+    }
+    args.extend(["-progress", "pipe:1"].map(OsString::from));
+    args.push("-y".into());
+    args.push(output_path.into());
+    args
+}
+
+/// Reads a number ffprobe may report either as a JSON number or as a string
+fn probe_number<T: std::str::FromStr>(value: &serde_json::Value) -> Option<T> {
+    match value {
+        serde_json::Value::String(s) => s.parse().ok(),
+        serde_json::Value::Number(n) => n.to_string().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Parses a frame rate such as "30000/1001"; "0/0" means unknown
+fn parse_frame_rate(rate: &str) -> Option<f64> {
+    let (num, den) = rate.split_once('/')?;
+    let (num, den) = (num.parse::<f64>().ok()?, den.parse::<f64>().ok()?);
+    (num > 0.0 && den > 0.0).then_some(num / den)
+}
+
+/// Parses the output of `ffprobe -print_format json -show_format -show_streams`
+fn parse_media_probe(json: &str) -> Result<MediaProbe, MediaForgeError> {
+    let value: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| MediaForgeError::FFmpegError(format!("Unreadable ffprobe output: {}", e)))?;
+    let format = &value["format"];
+
+    let streams = value["streams"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|stream| {
+            let field_order = stream["field_order"].as_str().filter(|order| *order != "unknown").map(str::to_string);
+            let color_transfer = stream["color_transfer"].as_str().map(str::to_string);
+            StreamProbe {
+                index: probe_number(&stream["index"]).unwrap_or_default(),
+                codec_type: stream["codec_type"].as_str().unwrap_or("unknown").to_string(),
+                codec_name: stream["codec_name"].as_str().map(str::to_string),
+                width: probe_number(&stream["width"]),
+                height: probe_number(&stream["height"]),
+                frame_rate: stream["avg_frame_rate"].as_str().and_then(parse_frame_rate),
+                interlaced: matches!(field_order.as_deref(), Some("tt" | "bb" | "tb" | "bt")),
+                field_order,
+                hdr: color_transfer.as_deref().is_some_and(is_hdr_transfer),
+                color_transfer,
+                bit_rate: probe_number(&stream["bit_rate"]),
+                channels: probe_number(&stream["channels"]),
+                sample_rate: probe_number(&stream["sample_rate"]),
+            }
+        })
+        .collect();
+
+    Ok(MediaProbe {
+        container: format["format_name"].as_str().unwrap_or_default().to_string(),
+        duration_secs: probe_number(&format["duration"]),
+        size_bytes: probe_number(&format["size"]),
+        bit_rate: probe_number(&format["bit_rate"]),
+        streams,
+        chapters: parse_chapters(&value),
+    })
+}
+
+/// Describes a local media file: container, duration and per-stream details
+pub async fn probe_media(path: &Path) -> Result<MediaProbe, MediaForgeError> {
+    // Any extension, since probing is how unlisted ones are recognised
+    validate_input_path(path)?;
+
+    let output = TokioCommand::new("ffprobe")
+        .args(["-v", "quiet", "-print_format", "json", "-show_format", "-show_streams", "-show_chapters"])
+        .arg(path)
+        .output()
+        .await
+        .map_err(|e| MediaForgeError::from_spawn_error("ffprobe", e, MediaForgeError::FFmpegError))?;
+
+    if !output.status.success() {
+        return Err(MediaForgeError::FFmpegError(format!(
+            "ffprobe could not read {}; it may not be a media file",
+            path.display()
+        )));
+    }
+
+    parse_media_probe(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parses the subtitle tracks and chapters from the output of
+/// `ffprobe -print_format json -show_streams -select_streams s -show_chapters`
+fn parse_extractable_streams(json: &str) -> Result<(Vec<SubtitleTrack>, Vec<Chapter>), MediaForgeError> {
+    let value: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| MediaForgeError::FFmpegError(format!("Unreadable ffprobe output: {}", e)))?;
+
+    let subtitles = value["streams"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|stream| stream["codec_type"] == "subtitle")
+        .enumerate()
+        .map(|(index, stream)| {
+            let codec_name = stream["codec_name"].as_str().map(str::to_string);
+            SubtitleTrack {
+                index: index as u32,
+                stream_index: probe_number(&stream["index"]).unwrap_or_default(),
+                text: codec_name.as_deref().is_some_and(|codec| !BITMAP_SUBTITLE_CODECS.contains(&codec)),
+                codec_name,
+                // "und" is how containers say the language wasn't set
+                language: stream["tags"]["language"].as_str().filter(|lang| *lang != "und").map(str::to_string),
+                title: stream["tags"]["title"].as_str().map(str::to_string),
+                forced: probe_number::<u32>(&stream["disposition"]["forced"]) == Some(1),
+            }
+        })
+        .collect();
+
+    Ok((subtitles, parse_chapters(&value)))
+}
+
+/// Reads the chapters from ffprobe JSON output made with `-show_chapters`
+fn parse_chapters(value: &serde_json::Value) -> Vec<Chapter> {
+    value["chapters"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|chapter| Chapter {
+            start_secs: probe_number(&chapter["start_time"]).unwrap_or_default(),
+            end_secs: probe_number(&chapter["end_time"]).unwrap_or_default(),
+            title: chapter["tags"]["title"].as_str().map(str::to_string),
+        })
+        .collect()
+}
+
+/// File name for an extracted track or chapter list: `<stem>.<language>.srt`
+/// (`<stem>.track<N>.srt` when the language is unknown) or `<stem>.chapters.txt`
+fn extracted_file_name(input_file: &Path, target: &ExtractTarget, subtitles: &[SubtitleTrack]) -> String {
+    let stem = input_file.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    match target {
+        ExtractTarget::Subtitle(index) => {
+            // Tags come from the file, so only plain language codes make it into the name
+            let language = subtitles
+                .get(*index as usize)
+                .and_then(|track| track.language.as_deref())
+                .filter(|lang| !lang.is_empty() && lang.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'));
+            match language {
+                Some(language) => format!("{}.{}.srt", stem, language),
+                None => format!("{}.track{}.srt", stem, index),
+            }
+        }
+        ExtractTarget::Chapters => format!("{}.chapters.txt", stem),
+    }
+}
+
+/// Lists a video's subtitle tracks and chapters. When the request names a target,
+/// also writes that subtitle track as SRT or the chapters as an FFmpeg metadata
+/// file, next to the input unless an output folder is given. Existing files are
+/// never overwritten; the new one gets a numbered name instead.
+pub async fn extract_streams(request: &ExtractStreamsRequest) -> Result<ExtractStreamsResult, MediaForgeError> {
+    let input_file = PathBuf::from(&request.input_file);
+    validate_input_media(&input_file, &ConversionType::Video).await?;
+
+    let output = TokioCommand::new("ffprobe")
+        .args(["-v", "quiet", "-print_format", "json", "-show_streams", "-select_streams", "s", "-show_chapters"])
+        .arg(&input_file)
+        .output()
+        .await
+        .map_err(|e| MediaForgeError::from_spawn_error("ffprobe", e, MediaForgeError::FFmpegError))?;
+    if !output.status.success() {
+        return Err(MediaForgeError::FFmpegError(format!(
+            "ffprobe could not read {}; it may not be a media file",
+            input_file.display()
+        )));
+    }
+    let (subtitles, chapters) = parse_extractable_streams(&String::from_utf8_lossy(&output.stdout))?;
+
+    let Some(target) = &request.target else {
+        return Ok(ExtractStreamsResult { subtitles, chapters, output_path: None });
+    };
+
+    let target_args: Vec<String> = match target {
+        ExtractTarget::Subtitle(index) => {
+            let track = subtitles.get(*index as usize).ok_or_else(|| {
+                MediaForgeError::InvalidSettings(format!(
+                    "{} has no subtitle track {}; it has {}",
+                    input_file.display(),
+                    index,
+                    subtitles.len()
+                ))
+            })?;
+            if !track.text {
+                return Err(MediaForgeError::InvalidSettings(format!(
+                    "Subtitle track {} is image-based ({}) and can't be written as SRT",
+                    index,
+                    track.codec_name.as_deref().unwrap_or("unknown")
+                )));
+            }
+            vec!["-map".into(), format!("0:s:{}", index), "-c:s".into(), "srt".into()]
+        }
+        ExtractTarget::Chapters => {
+            if chapters.is_empty() {
+                return Err(MediaForgeError::InvalidSettings(format!("{} has no chapters", input_file.display())));
+            }
+            vec!["-f".into(), "ffmetadata".into()]
+        }
+    };
+
+    let output_dir = match &request.output_dir {
+        Some(dir) => sanitize_path(dir)?,
+        None => input_file.parent().unwrap_or(Path::new("")).to_path_buf(),
+    };
+    validation::create_dir(&output_dir)?;
+    let mut output_path = output_dir.join(extracted_file_name(&input_file, target, &subtitles));
+    if output_path.exists() {
+        output_path = crate::downloader::free_output_path(&output_path);
+    }
+
+    let output = TokioCommand::new("ffmpeg")
+        .args(["-hide_banner", "-nostats", "-i"])
+        .arg(&input_file)
+        .args(&target_args)
+        .arg("-y")
+        .arg(&output_path)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| MediaForgeError::from_spawn_error("ffmpeg", e, MediaForgeError::FFmpegError))?;
+    if !output.status.success() {
+        let _ = validation::cleanup_on_error(&output_path).await;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let mut tail = OutputTail::new(ERROR_OUTPUT_LINES);
+        stderr.lines().for_each(|line| tail.push(line));
+        return Err(MediaForgeError::FFmpegError(format!("Extraction failed: {}", tail.join())));
+    }
+
+    log::info!("Extracted {:?} from {:?} to {:?}", target, input_file, output_path);
+    Ok(ExtractStreamsResult { subtitles, chapters, output_path: Some(output_path) })
+}
+
+/// Reads a media file's duration in seconds with ffprobe
+pub(crate) async fn probe_duration_secs(input_file: &Path) -> Result<f64, MediaForgeError> {
+    let output = TokioCommand::new("ffprobe")
+        .args(["-v", "error", "-show_entries", "format=duration", "-of", "default=noprint_wrappers=1:nokey=1"])
+        .arg(input_file)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| MediaForgeError::from_spawn_error("ffprobe", e, MediaForgeError::FFmpegError))?;
+
+    if !output.status.success() {
+        return Err(MediaForgeError::FFmpegError(format!(
+            "ffprobe could not read {}: {}",
+            input_file.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|d| d.is_finite() && *d >= 0.0)
+        .ok_or_else(|| MediaForgeError::FFmpegError(
+            format!("No duration reported for {}", input_file.display())
+        ))
+}
+
+/// Reads an image's pixel dimensions with ImageMagick (first frame only)
+async fn probe_image_dimensions(input_file: &Path) -> Option<(u64, u64)> {
+    let mut first_frame = input_file.as_os_str().to_owned();
+    first_frame.push("[0]");
+    let output = ImageMagick::detect().await?
+        .tool("identify")
+        .args(["-format", "%w %h"])
+        .arg(first_frame)
+        .output()
+        .await
+        .ok()?;
+
+    let text = String::from_utf8_lossy(&output.stdout);
+    let (width, height) = text.trim().split_once(' ')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+/// Parses an ffmpeg bitrate such as `2M`, `2500k` or `800000` into bits per second
+fn parse_bitrate_bps(bitrate: &str) -> Option<u64> {
+    let bitrate = bitrate.trim();
+    let (number, multiplier) = match bitrate.chars().last()? {
+        'k' | 'K' => (&bitrate[..bitrate.len() - 1], 1_000.0),
+        'm' | 'M' => (&bitrate[..bitrate.len() - 1], 1_000_000.0),
+        _ => (bitrate, 1.0),
+    };
+    let value: f64 = number.parse().ok()?;
+    (value.is_finite() && value > 0.0).then_some((value * multiplier) as u64)
+}
+
+/// An input's bitrates and frame size, which `prevent_upscale` keeps outputs within
+#[derive(Debug, Clone, Default, PartialEq)]
+struct SourceLimits {
+    video_bps: Option<u64>,
+    audio_bps: Option<u64>,
+    frame_size: Option<(u32, u32)>,
+}
+
+impl SourceLimits {
+    /// Stream bitrates where the container gives them; otherwise the overall bitrate,
+    /// less the audio's for video
+    fn from_probe(probe: &MediaProbe) -> Self {
+        let video = probe.streams.iter().find(|s| s.codec_type == "video" && s.width.is_some());
+        let audio = probe.streams.iter().find(|s| s.codec_type == "audio");
+        let audio_bps = audio.and_then(|a| a.bit_rate).or(if video.is_none() { probe.bit_rate } else { None });
+        let video_bps = video.and_then(|v| {
+            v.bit_rate.or_else(|| Some(probe.bit_rate?.saturating_sub(audio_bps.unwrap_or(0))).filter(|bps| *bps > 0))
+        });
+        Self {
+            video_bps,
+            audio_bps,
+            frame_size: video.and_then(|v| Some((v.width?, v.height?))),
+        }
+    }
+}
+
+/// Lowers the bitrates and frame size a `prevent_upscale` request asks for to the
+/// source's, returning a note for each change
+fn apply_source_limits(request: &mut ConvertRequest, limits: &SourceLimits) -> Vec<String> {
+    let lower_bitrate = |bitrate: &mut Option<String>, source_bps: Option<u64>, kind: &str| {
+        let requested = bitrate.as_deref()?;
+        let source_bps = source_bps.filter(|source| parse_bitrate_bps(requested).is_some_and(|bps| bps > *source))?;
+        let capped = format!("{}k", (source_bps / 1000).max(1));
+        let note = format!("Lowered the {} bitrate from {} to the source's {}", kind, requested, capped);
+        *bitrate = Some(capped);
+        Some(note)
+    };
+
+    let mut notes = Vec::new();
+    if let Some(settings) = request.video_settings.as_mut().filter(|s| s.prevent_upscale) {
+        notes.extend(lower_bitrate(&mut settings.bitrate, limits.video_bps, "video"));
+        let requested_size = settings.resolution.as_deref().and_then(|r| {
+            let (width, height) = r.split_once('x')?;
+            Some((width.parse::<u32>().ok()?, height.parse::<u32>().ok()?))
+        });
+        if let (Some((width, height)), Some((source_width, source_height))) = (requested_size, limits.frame_size) {
+            if width > source_width || height > source_height {
+                notes.push(format!(
+                    "Kept the source's {}x{} instead of upscaling to {}x{}",
+                    source_width, source_height, width, height
+                ));
+                settings.resolution = None;
+            }
+        }
+    }
+    if let Some(settings) = request.audio_settings.as_mut().filter(|s| s.prevent_upscale) {
+        notes.extend(lower_bitrate(&mut settings.bitrate, limits.audio_bps, "audio"));
+    }
+    notes
+}
+
+/// Audio bitrate ffmpeg's encoders use when none is given, in kbps
+fn default_audio_bitrate_kbps(format: &str) -> u64 {
+    match format.to_lowercase().as_str() {
+        "opus" => 96,
+        "ogg" => 112,
+        _ => 128,
+    }
+}
+
+/// Estimates audio output size from the settings and the input duration
+fn estimate_audio_bytes(duration_secs: f64, output_format: &str, settings: Option<&AudioSettings>) -> u64 {
+    let format = output_format.to_lowercase();
+    let channels = settings.and_then(|s| s.channels).unwrap_or(2) as f64;
+    let sample_rate = settings
+        .and_then(|s| s.sample_rate.as_deref())
+        .and_then(|r| r.parse::<f64>().ok())
+        .unwrap_or(44_100.0);
+    let bytes_per_sample = if settings.and_then(|s| s.bit_depth) == Some(24) { 3.0 } else { 2.0 };
+    let pcm_bytes_per_sec = sample_rate * channels * bytes_per_sample;
+
+    let bytes_per_sec = match format.as_str() {
+        "wav" => pcm_bytes_per_sec,
+        // Typical lossless compression ratio for music
+        "flac" => pcm_bytes_per_sec * 0.6,
+        _ => {
+            let kbps = settings
+                .and_then(|s| s.bitrate.as_deref())
+                .and_then(|b| b.parse::<u64>().ok())
+                .unwrap_or_else(|| default_audio_bitrate_kbps(&format));
+            kbps as f64 * 1000.0 / 8.0
+        }
+    };
+
+    (bytes_per_sec * duration_secs).round() as u64
+}
+
+/// Estimates video output size from the target bitrate and the input duration.
+/// Without a bitrate the output is assumed to be about the size of the input.
+fn estimate_video_bytes(duration_secs: f64, input_bytes: u64, settings: Option<&VideoSettings>) -> u64 {
+    if let Some(target_mb) = settings.and_then(|s| s.target_size_mb) {
+        return target_mb as u64 * 1_000_000;
+    }
+    let video_bps = settings
+        .and_then(|s| s.bitrate.as_deref())
+        .filter(|b| *b != "Keep Original")
+        .and_then(parse_bitrate_bps);
+
+    match video_bps {
+        Some(bps) => {
+            // ffmpeg re-encodes audio at its default 128 kbps
+            let total_bps = bps + 128_000;
+            (total_bps as f64 / 8.0 * duration_secs).round() as u64
+        }
+        None => input_bytes,
+    }
+}
+
+/// Rough average encoded size per pixel for an image format
+fn image_bytes_per_pixel(format: &str, quality: Option<u32>) -> f64 {
+    // JPEG-like formats scale with quality: ~0.1 B/px at q10 to ~0.5 B/px at q100
+    let lossy = 0.05 + quality.unwrap_or(85).min(100) as f64 / 100.0 * 0.45;
+    match format.to_lowercase().as_str() {
+        "jpg" | "jpeg" => lossy,
+        "webp" => lossy * 0.7,
+        "avif" | "heic" | "heif" => lossy * 0.5,
+        "png" | "psd" => 2.0,
+        "gif" => 0.6,
+        "bmp" | "tiff" | "tif" => 3.0,
+        "ico" => 4.0,
+        _ => 1.0,
+    }
+}
+
+/// Pixel count after applying an ImageMagick resize geometry (`50%`, `800x600`, `800`, `x600`).
+/// Geometry sizes fit within the box and keep the aspect ratio.
+fn resized_pixel_count(width: u64, height: u64, resize: Option<&str>) -> u64 {
+    let (w, h) = (width as f64, height as f64);
+    let scale = match resize.map(str::trim) {
+        Some(r) if r.ends_with('%') => r.trim_end_matches('%').parse::<f64>().ok().map(|p| p / 100.0),
+        Some(r) => {
+            let (rw, rh) = r.split_once('x').unwrap_or((r, ""));
+            let sx = rw.parse::<f64>().ok().map(|rw| rw / w);
+            let sy = rh.trim_end_matches(['!', '>', '<', '^']).parse::<f64>().ok().map(|rh| rh / h);
+            match (sx, sy) {
+                (Some(sx), Some(sy)) => Some(sx.min(sy)),
+                (Some(s), None) | (None, Some(s)) => Some(s),
+                (None, None) => None,
+            }
+        }
+        None => None,
+    }
+    .unwrap_or(1.0);
+
+    (w * scale * h * scale).round() as u64
+}
+
+/// Estimates image output size, from pixel dimensions when known and otherwise by
+/// scaling the input size by the formats' relative compression
+fn estimate_image_bytes(
+    input_bytes: u64,
+    input_format: &str,
+    output_format: &str,
+    dimensions: Option<(u64, u64)>,
+    settings: Option<&ImageSettings>,
+) -> u64 {
+    let quality = settings.and_then(|s| s.quality);
+    let output_bpp = image_bytes_per_pixel(output_format, quality);
+
+    let pixels = if output_format.eq_ignore_ascii_case("ico") {
+        Some(ICO_SIZES.iter().map(|size| (size * size) as u64).sum())
+    } else {
+        dimensions.map(|(w, h)| resized_pixel_count(w, h, settings.and_then(|s| s.resize.as_deref())))
+    };
+
+    match pixels {
+        Some(pixels) => (pixels as f64 * output_bpp).round() as u64,
+        None => {
+            let input_bpp = image_bytes_per_pixel(input_format, None);
+            (input_bytes as f64 * output_bpp / input_bpp).round() as u64
+        }
+    }
+}
+
+/// Shortfall in output duration tolerated by verification: 2% of the input, at least 1s
+const VERIFY_DURATION_TOLERANCE: f64 = 0.02;
+const VERIFY_MIN_TOLERANCE_SECS: f64 = 1.0;
+
+/// Whether a finished conversion's output should be verified
+fn should_verify_output(request: &ConvertRequest) -> bool {
+    request
+        .verify_output
+        .unwrap_or(request.conversion_type != ConversionType::Image)
+}
+
+/// Compares output and input durations; a much shorter output means ffmpeg stopped
+/// writing early even though it exited successfully
+fn check_output_duration(input_secs: f64, output_secs: f64) -> Result<(), String> {
+    let tolerance = (input_secs * VERIFY_DURATION_TOLERANCE).max(VERIFY_MIN_TOLERANCE_SECS);
+    if output_secs <= 0.0 {
+        return Err("output has no duration".to_string());
+    }
+    if output_secs + tolerance < input_secs {
+        return Err(format!("output runs {:.1}s but the input runs {:.1}s", output_secs, input_secs));
+    }
+    Ok(())
+}
+
+/// Checks that the output kept the input's chapters, when they are being preserved
+/// and the container can hold them
+async fn check_output_chapters(request: &ConvertRequest, input_file: &Path, output_path: &Path) -> Result<(), String> {
+    let preserving = request.video_settings.as_ref().map_or(true, preserves_metadata);
+    if !preserving || CHAPTERLESS_CONTAINERS.contains(&request.output_format.to_lowercase().as_str()) {
+        return Ok(());
+    }
+    // An input ffprobe can't read has no chapters to compare against
+    let Ok(input) = probe_media(input_file).await else {
+        return Ok(());
+    };
+    if input.chapters.is_empty() {
+        return Ok(());
+    }
+    let output = probe_media(output_path).await.map_err(|e| e.to_string())?;
+    if output.chapters.len() < input.chapters.len() {
+        return Err(format!(
+            "output has {} of the input's {} chapters",
+            output.chapters.len(),
+            input.chapters.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that a converted file is usable: not empty, decodable, and for audio and
+/// video about as long as the input. Failures are retryable, as they usually come
+/// from an interrupted write.
+///
+/// `expected_secs` is how long the output should run when that isn't the input's
+/// length, e.g. after silence trimming.
+async fn verify_output(
+    request: &ConvertRequest,
+    input_file: &Path,
+    output_path: &Path,
+    expected_secs: Option<f64>,
+) -> Result<(), MediaForgeError> {
+    let failed = |reason: String| {
+        MediaForgeError::TemporaryError(format!("Output verification failed for {}: {}", output_path.display(), reason))
+    };
+
+    let size = tokio::fs::metadata(output_path)
+        .await
+        .map_err(|e| failed(e.to_string()))?
+        .len();
+    if size == 0 {
+        return Err(failed("output file is empty".to_string()));
+    }
+
+    // ffmpeg before 7.1 can't decode animated WebP, so only its size can be checked
+    if request.conversion_type == ConversionType::Video && request.output_format.eq_ignore_ascii_case("webp") {
+        return Ok(());
+    }
+
+    match request.conversion_type {
+        ConversionType::Video | ConversionType::Audio => {
+            let output_secs = probe_duration_secs(output_path)
+                .await
+                .map_err(|e| failed(e.to_string()))?;
+            // Inputs without a readable duration can only be checked for decoding. A
+            // replaced sound track can lengthen the output, or cut it with `shortest`.
+            let replaced_audio = request.video_settings.as_ref().is_some_and(replaces_audio);
+            let expected_secs = match expected_secs {
+                Some(secs) => Some(secs),
+                None => probe_duration_secs(input_file).await.ok(),
+            };
+            if let Some(expected_secs) = expected_secs.filter(|_| !replaced_audio) {
+                check_output_duration(expected_secs, output_secs).map_err(failed)?;
+            }
+            if request.conversion_type == ConversionType::Video {
+                check_output_chapters(request, input_file, output_path).await.map_err(failed)?;
+            }
+        }
+        ConversionType::Image => {
+            let output = ensure_imagemagick()
+                .await
+                .map_err(|e| failed(e.to_string()))?
+                .tool("identify")
+                .arg("-ping")
+                .arg(output_path)
+                .output()
+                .await
+                .map_err(|e| failed(format!("could not run ImageMagick: {}", e)))?;
+            if !output.status.success() {
+                return Err(failed(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+            }
+        }
+        // Tasks only ever run with the type of their input
+        ConversionType::Auto => {}
+    }
+
+    Ok(())
+}
+
+/// Estimates the output size of one input file.
+/// Audio and video use bitrate × duration; images use a per-pixel estimate.
+async fn estimate_file_bytes(request: &ConvertRequest, input_file: &Path) -> Result<u64, MediaForgeError> {
+    validate_input_media(input_file, &request.conversion_type).await?;
+    let typed;
+    let request = if request.conversion_type == ConversionType::Auto {
+        let conversion_type = detect_conversion_type(input_file).await.ok_or_else(|| {
+            MediaForgeError::InvalidSettings(format!("{} is not a supported image, video or audio file", input_file.display()))
+        })?;
+        typed = typed_request(request, conversion_type, vec![input_file.to_path_buf()]).map_err(MediaForgeError::InvalidSettings)?;
+        &typed
+    } else {
+        request
+    };
+    let input_bytes = input_file.metadata()?.len();
+
+    let estimate = match request.conversion_type {
+        ConversionType::Video => {
+            let duration = probe_duration_secs(input_file).await?;
+            estimate_video_bytes(duration, input_bytes, request.video_settings.as_ref())
+        }
+        ConversionType::Audio => {
+            let duration = probe_duration_secs(input_file).await?;
+            estimate_audio_bytes(duration, &request.output_format, request.audio_settings.as_ref())
+        }
+        ConversionType::Image => {
+            let input_format = input_file
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or_default();
+            let dimensions = probe_image_dimensions(input_file).await;
+            estimate_image_bytes(
+                input_bytes,
+                input_format,
+                &request.output_format,
+                dimensions,
+                request.image_settings.as_ref(),
+            )
+        }
+        ConversionType::Auto => unreachable!("resolved to the input's type above"),
+    };
+
+    Ok(estimate)
+}
+
+/// Nearest existing directory the conversion will write into, for checking free space
+/// without creating anything. Files converted alongside their inputs are assumed to
+/// share the first input's disk.
+fn estimate_target_dir(request: &ConvertRequest) -> Result<PathBuf, MediaForgeError> {
+    let dir = match &request.output_mode {
+        OutputMode::SingleDirectory => sanitize_path(&request.output_path)?,
+        OutputMode::MirrorTree { root } => sanitize_path(root)?,
+        OutputMode::AlongsideInput => request
+            .input_files
+            .first()
+            .and_then(|f| f.parent())
+            .map(Path::to_path_buf)
+            .unwrap_or_default(),
+    };
+
+    Ok(dir
+        .ancestors()
+        .find(|a| a.is_dir())
+        .map(Path::to_path_buf)
+        .unwrap_or(dir))
+}
+
+/// Codecs ffprobe reports for still pictures such as cover art
+const PICTURE_CODECS: [&str; 6] = ["mjpeg", "png", "bmp", "webp", "tiff", "gif"];
+
+/// Video for moving pictures, audio for sound alone or with cover art. A lone picture
+/// with an unknown extension isn't something ImageMagick would be given.
+fn conversion_type_for_probe(probe: &MediaProbe) -> Option<ConversionType> {
+    // ffprobe reads any text file as a terminal recording
+    if probe.container == "tty" {
+        return None;
+    }
+    let is_picture = |stream: &StreamProbe| stream.codec_name.as_deref().is_some_and(|c| PICTURE_CODECS.contains(&c));
+    if probe.streams.iter().any(|s| s.codec_type == "video" && !is_picture(s)) {
+        Some(ConversionType::Video)
+    } else if probe.streams.iter().any(|s| s.codec_type == "audio") {
+        Some(ConversionType::Audio)
+    } else {
+        None
+    }
+}
+
+/// How an `Auto` conversion treats `input_file`: by extension, or by its streams when
+/// the extension isn't one of the listed formats
+async fn detect_conversion_type(input_file: &Path) -> Option<ConversionType> {
+    let by_extension = input_file
+        .extension()
+        .and_then(|e| e.to_str())
+        .and_then(ConversionType::for_input_extension);
+    match by_extension {
+        Some(conversion_type) => Some(conversion_type),
+        None => conversion_type_for_probe(&probe_media(input_file).await.ok()?),
+    }
+}
+
+/// `validate_input_media` up to the point ffprobe is needed: `Ok(None)` when the file
+/// is accepted, `Ok(Some(err))` when only its streams can still make it acceptable
+/// (failing with `err` otherwise), and `Err` when it is refused outright
+fn validate_listed_input(
+    file_path: &PathBuf,
+    conversion_type: &ConversionType,
+) -> Result<Option<MediaForgeError>, MediaForgeError> {
+    let Err(unlisted) = validate_input_file(file_path, conversion_type) else {
+        return Ok(None);
+    };
+    validate_input_path(file_path)?;
+    let listed_elsewhere = file_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .and_then(ConversionType::for_input_extension)
+        .is_some();
+    if listed_elsewhere || *conversion_type == ConversionType::Image {
+        return Err(unlisted);
+    }
+    Ok(Some(unlisted))
+}
+
+/// Whether an input of an unlisted type, whose streams make it `detected`, suits the conversion
+fn accepts_probed_input(conversion_type: &ConversionType, detected: Option<&ConversionType>) -> bool {
+    // Sound can be taken from a video, but a video needs moving pictures
+    matches!(
+        (conversion_type, detected),
+        (ConversionType::Video, Some(ConversionType::Video)) | (ConversionType::Audio | ConversionType::Auto, Some(_))
+    )
+}
+
+/// `validate_input_file`, except that an audio or video input whose extension isn't
+/// listed is accepted when ffprobe finds streams the conversion can use. The file
+/// system checks run on the blocking pool.
+async fn validate_input_media(file_path: &Path, conversion_type: &ConversionType) -> Result<(), MediaForgeError> {
+    let (listed_path, listed_type) = (file_path.to_path_buf(), conversion_type.clone());
+    let listed = tokio::task::spawn_blocking(move || validate_listed_input(&listed_path, &listed_type))
+        .await
+        .map_err(|e| MediaForgeError::FileSystemError(format!("File validation failed: {}", e)))?;
+    let Some(unlisted) = listed? else {
+        return Ok(());
+    };
+    let detected = probe_media(file_path).await.ok().as_ref().and_then(conversion_type_for_probe);
+    if accepts_probed_input(conversion_type, detected.as_ref()) {
+        Ok(())
+    } else {
+        Err(unlisted)
+    }
+}
+
+/// The part of an `Auto` request covering `input_files`, all of `conversion_type`.
+/// Fails when no output format was chosen for that type.
+fn typed_request(
+    request: &ConvertRequest,
+    conversion_type: ConversionType,
+    input_files: Vec<PathBuf>,
+) -> Result<ConvertRequest, String> {
+    let formats = request.auto_formats.clone().unwrap_or_default();
+    let (format, kind) = match conversion_type {
+        ConversionType::Image => (formats.image, "image"),
+        ConversionType::Video => (formats.video, "video"),
+        ConversionType::Audio => (formats.audio, "audio"),
+        ConversionType::Auto => (None, "unclassified"),
+    };
+    let output_format = format.filter(|f| !f.is_empty()).ok_or_else(|| format!("No output format chosen for {} files", kind))?;
+    Ok(ConvertRequest {
+        input_files,
+        conversion_type,
+        output_format,
+        ..request.clone()
+    })
+}
+
+/// Splits an `Auto` request into one typed request per kind of input, in input order
+/// within each. Inputs that can't be converted come back with the reason.
+async fn split_auto_request(request: &ConvertRequest) -> (Vec<ConvertRequest>, Vec<(PathBuf, String)>) {
+    let mut groups = [ConversionType::Image, ConversionType::Video, ConversionType::Audio].map(|t| (t, Vec::new()));
+    let mut rejected = Vec::new();
+    for input_file in &request.input_files {
+        let detected = detect_conversion_type(input_file).await;
+        match groups.iter_mut().find(|(conversion_type, _)| Some(conversion_type) == detected.as_ref()) {
+            Some((_, files)) => files.push(input_file.clone()),
+            None => rejected.push((
+                input_file.clone(),
+                format!("{} is not a supported image, video or audio file", input_file.display()),
+            )),
+        }
+    }
+
+    let mut requests = Vec::new();
+    for (conversion_type, input_files) in groups.into_iter().filter(|(_, files)| !files.is_empty()) {
+        match typed_request(request, conversion_type, input_files.clone()) {
+            Ok(typed) => requests.push(typed),
+            Err(reason) => rejected.extend(input_files.into_iter().map(|f| (f, reason.clone()))),
+        }
+    }
+    (requests, rejected)
+}
+
+/// Whether the request converts (or, for `Auto` inputs of an unlisted type, probes)
+/// anything with ffmpeg
+fn needs_ffmpeg(request: &ConvertRequest) -> bool {
+    match request.conversion_type {
+        ConversionType::Image => false,
+        ConversionType::Video | ConversionType::Audio => true,
+        ConversionType::Auto => request.input_files.iter().any(|input_file| {
+            input_file.extension().and_then(|e| e.to_str()).and_then(ConversionType::for_input_extension)
+                != Some(ConversionType::Image)
+        }),
+    }
+}
+
+/// `start_conversion` splits `Auto` requests by input type, so no task gets one
+fn auto_task_error() -> MediaForgeError {
+    MediaForgeError::InvalidSettings("Auto conversions must be split by input type before they run".into())
+}
+
+/// A validated request with what its tasks need to know about their inputs
+struct ConversionPlan {
+    request: ConvertRequest,
+    task_inputs: Vec<PathBuf>,
+    copy_fallbacks: Vec<Option<String>>,
+    tonemaps: Vec<bool>,
+    chapter_warnings: Vec<Option<String>>,
+    source_limits: Vec<Option<SourceLimits>>,
+    optimize_note: Option<String>,
+    combine_into_pdf: bool,
+    timeout_secs: Option<u64>,
+}
+
+pub struct ConversionManager {
+    tasks: Arc<DashMap<String, TaskProgress>>,
+    task_handles: Arc<DashMap<String, TaskHandle>>,
+    batches: Arc<BatchTracker>,
+    /// Timeout for requests that don't set one; 0 disables it. Follows the settings.
+    default_timeout_secs: Arc<AtomicU64>,
+    /// Most unfinished tasks held at once. Follows the settings.
+    max_queue_size: Arc<AtomicUsize>,
+    tools: Arc<ToolPaths>,
+    updates: Arc<TaskUpdateEmitter>,
+    /// Set once the app quits, so tasks cancelled from then on count as interrupted
+    shutting_down: Arc<AtomicBool>,
+}
+
+impl ConversionManager {
+    pub fn new(config: &Config, tools: Arc<ToolPaths>) -> Self {
+        Self {
+            tasks: Arc::new(DashMap::new()),
+            task_handles: Arc::new(DashMap::new()),
+            batches: Arc::new(BatchTracker::new()),
+            default_timeout_secs: Arc::new(AtomicU64::new(config.conversion_timeout_secs)),
+            max_queue_size: Arc::new(AtomicUsize::new(config.max_queue_size)),
+            tools,
+            updates: Arc::new(TaskUpdateEmitter::default()),
+            shutting_down: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Picks up changed settings
+    pub fn apply_config(&self, config: &Config) {
+        self.default_timeout_secs.store(config.conversion_timeout_secs, Ordering::Relaxed);
+        self.max_queue_size.store(config.max_queue_size, Ordering::Relaxed);
+    }
+
+    /// Refuses a request that would take the unfinished tasks past the queue limit
+    fn check_queue_capacity(&self, adding: usize) -> Result<(), MediaForgeError> {
+        let active = self.tasks.iter().filter(|task| !task.is_finished()).count();
+        crate::queue::check_capacity(active, adding, self.max_queue_size.load(Ordering::Relaxed))
+    }
+
+    pub fn create_task(&self, name: String) -> String {
+        let task_id = Uuid::new_v4().to_string();
+        let task = TaskProgress {
+            task_id: task_id.clone(),
+            name,
+            status: TaskStatus::Queued,
+            ..Default::default()
+        };
+        self.tasks.insert(task_id.clone(), task);
+        task_id
+    }
+
+    pub fn get_task(&self, task_id: &str) -> Option<TaskProgress> {
+        self.tasks.get(task_id).map(|t| t.clone())
+    }
+
+    pub fn get_all_tasks(&self) -> Vec<TaskProgress> {
+        self.tasks.iter().map(|entry| entry.value().clone()).collect()
+    }
+
+    pub fn update_task(&self, task_id: &str, update: impl FnOnce(&mut TaskProgress)) {
+        if let Some(mut task) = self.tasks.get_mut(task_id) {
+            let previous = task.status.clone();
+            update(&mut task);
+            task.record_status_change(&previous, unix_millis());
+        }
+    }
+
+    /// Returns whether the task was in the list
+    pub fn remove_task(&self, task_id: &str) -> bool {
+        self.tasks.remove(task_id).is_some()
+    }
+
+    /// Removes every completed, failed and cancelled task, returning their ids
+    pub fn clear_finished_tasks(&self) -> Vec<String> {
+        let mut removed = Vec::new();
+        self.tasks.retain(|task_id, task| {
+            if task.is_finished() {
+                removed.push(task_id.clone());
+            }
+            !task.is_finished()
+        });
+        removed
+    }
+
+    /// Totals for the tasks created by one `start_conversion` call
+    pub fn get_batch(&self, batch_id: &str) -> Option<BatchProgress> {
+        batch_progress(batch_id, self.get_all_tasks().iter())
+    }
+
+    /// Copies an FFmpeg progress update onto the task. Updates closer together than
+    /// `PROGRESS_EMIT_INTERVAL` are dropped, except the final one.
+    fn record_ffmpeg_progress(
+        &self,
+        task_id: &str,
+        update: FFmpegProgressUpdate,
+        last_emit: &mut Option<std::time::Instant>,
+        app_handle: &tauri::AppHandle,
+    ) {
+        if !update.finished && last_emit.is_some_and(|at| at.elapsed() < PROGRESS_EMIT_INTERVAL) {
+            return;
+        }
+        *last_emit = Some(std::time::Instant::now());
+
+        self.update_task(task_id, |task| {
+            if let Some(percent) = update.percent {
+                task.progress = percent;
+            }
+            task.speed = update.speed.map(|speed| format_ffmpeg_speed(speed, update.fps));
+            task.eta_seconds = update.eta_secs;
+            task.eta = update.eta_secs.map(format_eta);
+        });
+        self.updates.emit(app_handle, self.get_task(task_id));
+    }
+
+    /// Tasks in large batches leave notifying to the batch summary
+    fn notify_individually(&self, task: &TaskProgress) -> bool {
+        self.tasks.iter().filter(|t| t.batch_id == task.batch_id).count() <= BATCH_NOTIFICATION_THRESHOLD
+    }
+
+    fn emit_batch_update(&self, batch_id: &str, app_handle: &tauri::AppHandle) -> Option<BatchProgress> {
+        let batch = self.get_batch(batch_id)?;
+        let _ = app_handle.emit("batch-update", &batch);
+        Some(batch)
+    }
+
+    /// Sends the summary notification for a finished batch that was too large for
+    /// per-task notifications
+    fn notify_batch_complete(&self, batch: Option<BatchProgress>, app_handle: &tauri::AppHandle) {
+        let Some(batch) = batch.filter(|b| b.total > BATCH_NOTIFICATION_THRESHOLD) else {
+            return;
+        };
+        let target = self
+            .get_all_tasks()
+            .into_iter()
+            .filter(|t| t.batch_id == batch.batch_id && t.status == TaskStatus::Completed)
+            .find_map(|t| t.file_path)
+            .map(PathBuf::from);
+        notifications::send_batch_complete_notification(app_handle, "Conversions Complete", "Converted", &batch, target);
+    }
+
+    /// Called exactly once when a task ends, whatever its final state
+    fn finish_batch_task(&self, batch_id: &str, app_handle: &tauri::AppHandle) {
+        let batch = self.emit_batch_update(batch_id, app_handle);
+        if self.batches.finish(batch_id) {
+            self.notify_batch_complete(batch, app_handle);
+        }
+    }
+
+    pub async fn cancel_task(&self, task_id: &str) -> Result<(), MediaForgeError> {
+        // Cancel the running task if it exists
+        if let Some((_, task_handle)) = self.task_handles.remove(task_id) {
+            info!(
+                task_id = task_id,
+                "Initiating conversion task cancellation"
+            );
+            
+            // Update status first
+            self.update_task(task_id, |task| {
+                task.status = TaskStatus::Cancelled;
+                task.error = Some("Task cancelled by user".to_string());
+            });
+            
+            // Actually cancel the running task
+            if let Err(e) = task_handle.cancel().await {
+                error!(
+                    task_id = task_id,
+                    error = ?e,
+                    "Failed to cancel running conversion task"
+                );
+                return Err(MediaForgeError::TaskNotFound(format!("Failed to cancel task: {:?}", e)));
+            }
+            
+            info!(
+                task_id = task_id,
+                "Conversion task cancelled successfully"
+            );
+        } else {
+            // Task might not be running anymore, just update status
+            self.update_task(task_id, |task| {
+                task.status = TaskStatus::Cancelled;
+                task.error = Some("Task cancelled by user".to_string());
+            });
+            info!(
+                task_id = task_id,
+                status = "not_running",
+                "Conversion task not running, marked as cancelled"
+            );
+        }
+        
+        Ok(())
+    }
+
+    /// Stops every running conversion when the app quits, along with its ffmpeg or
+    /// ImageMagick process or post-process command. Returns how many tasks were interrupted.
+    pub async fn shutdown(&self, timeout: Duration) -> usize {
+        self.shutting_down.store(true, Ordering::Relaxed);
+        tasks::interrupt_all(&self.task_handles, &self.tasks, timeout, "Conversions").await
+    }
+
+    /// Converts every file under a folder that passes the filter. With `dry_run` the
+    /// matches are returned without starting any tasks.
+    pub async fn convert_folder(
+        &self,
+        request: ConvertFolderRequest,
+        app_handle: tauri::AppHandle,
+    ) -> Result<ConvertFolderResult, MediaForgeError> {
+        let root = sanitize_path(&request.folder)?;
+        if !root.is_dir() {
+            return Err(MediaForgeError::FileSystemError(format!("Not a folder: {}", root.display())));
+        }
+
+        let max_depth = request.max_depth.unwrap_or(DEFAULT_FOLDER_DEPTH);
+        if max_depth > MAX_FOLDER_DEPTH {
+            return Err(MediaForgeError::InvalidSettings(format!(
+                "Folder depth must be at most {}, got {}",
+                MAX_FOLDER_DEPTH, max_depth
+            )));
+        }
+
+        let filter = request.filter.clone();
+        let walk_root = root.clone();
+        let conversion_type = request.conversion.conversion_type.clone();
+        let matched_files =
+            tokio::task::spawn_blocking(move || collect_folder_inputs(&walk_root, &filter, max_depth, &conversion_type))
+            .await
+            .map_err(|e| MediaForgeError::FileSystemError(format!("Folder scan failed: {}", e)))??;
+
+        info!(
+            folder = %root.display(),
+            matched = matched_files.len(),
+            dry_run = request.dry_run,
+            "Folder scan completed"
+        );
+
+        if request.dry_run || matched_files.is_empty() {
+            return Ok(ConvertFolderResult {
+                matched_files,
+                task_ids: Vec::new(),
+            });
+        }
+
+        let conversion = ConvertRequest {
+            input_files: matched_files.clone(),
+            ..request.conversion
+        };
+        let task_ids = self.start_conversion(conversion, app_handle).await?;
+
+        Ok(ConvertFolderResult {
+            matched_files,
+            task_ids,
+        })
+    }
+
+    /// Estimates the output size of each input file and checks that the output
+    /// location has room for all of them
+    pub async fn estimate_conversion(
+        &self,
+        request: ConvertRequest,
+        app_handle: &tauri::AppHandle,
+    ) -> Result<ConversionEstimate, MediaForgeError> {
+        let request = &crate::presets::resolve_preset(app_handle, request)?;
+        let mut files = Vec::with_capacity(request.input_files.len());
+
+        for input_file in &request.input_files {
+            files.push(FileEstimate {
+                input_file: input_file.clone(),
+                estimated_bytes: estimate_file_bytes(request, input_file).await?,
+            });
+        }
+
+        let total_bytes = files.iter().map(|f| f.estimated_bytes).sum();
+        let target_dir = estimate_target_dir(request)?;
+        let disk_space_error = validation::validate_disk_space(&target_dir, Some(total_bytes))
+            .await
+            .err()
+            .map(|e| e.to_string());
+
+        Ok(ConversionEstimate {
+            files,
+            total_bytes,
+            disk_space_error,
+        })
+    }
+
+    pub async fn start_conversion(
+        &self,
+        request: ConvertRequest,
+        app_handle: tauri::AppHandle,
+    ) -> Result<Vec<String>, MediaForgeError> {
+        let request = crate::presets::resolve_preset(&app_handle, request)?;
+        info!(
+            file_count = request.input_files.len(),
+            output_format = ?request.output_format,
+            output_path = %request.output_path,
+            "Starting batch conversion"
+        );
+        // Combining into a PDF is one task however many images there are
+        self.check_queue_capacity(if is_pdf_combine(&request) { 1 } else { request.input_files.len() })?;
+
+        if needs_ffmpeg(&request) {
+            self.tools.require_ffmpeg().await?;
+        }
+
+        // An Auto request runs as one request per kind of input, all in the same batch
+        let (requests, unclassified) = if request.conversion_type == ConversionType::Auto {
+            split_auto_request(&request).await
+        } else {
+            (vec![request], Vec::new())
+        };
+        // Every request is validated before any task is created so a batch is never left half-created
+        let mut plans = Vec::with_capacity(requests.len());
+        for request in requests {
+            plans.push(self.plan_conversion(request).await?);
+        }
+
+        let batch_id = Uuid::new_v4().to_string();
+        let mut task_ids = Vec::new();
+        for (input_file, reason) in unclassified {
+            task_ids.push(self.add_failed_task(&input_file, reason, &batch_id, &app_handle));
+        }
+        for plan in plans {
+            task_ids.extend(self.spawn_conversion_tasks(plan, &batch_id, &app_handle));
+        }
+
+        let batch = self.emit_batch_update(&batch_id, &app_handle);
+        if self.batches.seal(&batch_id) {
+            // Every task already finished while the batch was being created
+            self.notify_batch_complete(batch, &app_handle);
+        }
+
+        Ok(task_ids)
+    }
+
+    /// Validates a typed request and gathers what its tasks need to know about their inputs
+    async fn plan_conversion(&self, request: ConvertRequest) -> Result<ConversionPlan, MediaForgeError> {
+        // Validate output location before processing any files
+        match &request.output_mode {
+            OutputMode::SingleDirectory => {
+                sanitize_path(&request.output_path)?;
+            }
+            OutputMode::MirrorTree { root } => {
+                sanitize_path(root)?;
+            }
+            // Checked per file, since every input has its own folder
+            OutputMode::AlongsideInput => {}
+        }
+        if let Some(template) = &request.filename_template {
+            validate_filename_template(template, request.input_files.len() > 1 && !is_pdf_combine(&request))?;
+        }
+        if let Some(command) = &request.post_process_command {
+            hooks::validate_post_process_command(command)?;
+        }
+        let timeout_secs = validation::resolve_timeout(request.timeout_secs, self.default_timeout_secs.load(Ordering::Relaxed))?;
+        
+        if request.conversion_type == ConversionType::Image {
+            if let Some(settings) = &request.image_settings {
+                validate_image_settings(settings)?;
+            }
+            ensure_image_delegates(&request.input_files, &request.output_format).await?;
+            if request.input_files.iter().any(|f| is_pdf_file(f)) {
+                ensure_ghostscript().await?;
+            }
+        }
+        
+        if request.conversion_type == ConversionType::Video {
+            if let Some(settings) = &request.video_settings {
+                validate_video_settings(settings)?;
+                if settings.target_size_mb.is_some() && two_pass_codec(&request.output_format).is_none() {
+                    return Err(MediaForgeError::InvalidSettings(format!(
+                        "A target size isn't supported for .{} output",
+                        request.output_format.to_lowercase()
+                    )));
+                }
+                validate_animated_output(&request.output_format, settings)?;
+                let codec = profile_codec(settings)?;
+                let format = request.output_format.to_lowercase();
+                if sets_encoder(settings) && !codec.containers.contains(&format.as_str()) {
+                    return Err(MediaForgeError::InvalidSettings(format!(
+                        "{} needs {} output, not .{}",
+                        codec.name,
+                        codec.containers.join(", "),
+                        format
+                    )));
+                }
+                if codec.encoder == H265.encoder && !ffmpeg_has("-encoders", codec.encoder).await {
+                    return Err(MediaForgeError::MissingDependency(format!(
+                        "H.265 output needs the {} encoder, but this ffmpeg build lacks it",
+                        codec.encoder
+                    )));
+                }
+                ensure_replacement_audio(settings).await?;
+                if settings.stabilize {
+                    ensure_vidstab().await?;
+                }
+            }
+            ensure_animated_encoder(&request.output_format).await?;
+        }
+        
+        if request.conversion_type == ConversionType::Image {
+            ensure_imagemagick().await?;
+        }
+        
+        if request.conversion_type == ConversionType::Audio {
+            validate_audio_format(&request.output_format)?;
+            if let Some(settings) = &request.audio_settings {
+                validate_audio_settings(settings, &request.output_format)?;
+            }
+        }
+        
+        // Combining images into a PDF produces one output from every input, so it
+        // runs as a single task; everything else gets a task per input file
+        let combine_into_pdf = is_pdf_combine(&request);
+        if combine_into_pdf {
+            if request.input_files.is_empty() {
+                return Err(MediaForgeError::InvalidSettings("No images to combine into a PDF".to_string()));
+            }
+            for input_file in request.input_files.iter() {
+                validate_input_file(input_file, &ConversionType::Image)?;
+                validate_image_format(input_file, &request.output_format)?;
+            }
+        }
+        let task_inputs = if combine_into_pdf {
+            request.input_files[..1].to_vec()
+        } else {
+            request.input_files.clone()
+        };
+        
+        // Validate every input before creating any task so a batch is never left half-created
+        for input_file in task_inputs.iter() {
+            validate_input_media(input_file, &request.conversion_type).await?;
+            
+            // Additional validation for image files
+            if request.conversion_type == ConversionType::Image {
+                validate_image_format(input_file, &request.output_format)?;
+            }
+        }
+        
+        // Catch codec/container mismatches here instead of as a late ffmpeg error, and
+        // find the HDR inputs that get tone-mapped
+        let mut copy_fallbacks = vec![None; task_inputs.len()];
+        let mut tonemaps = vec![false; task_inputs.len()];
+        let mut chapter_warnings = vec![None; task_inputs.len()];
+        let mut source_limits = vec![None; task_inputs.len()];
+        if request.conversion_type == ConversionType::Video {
+            let copy_streams = request.video_settings.as_ref().is_some_and(|s| s.copy_streams);
+            for (i, input_file) in task_inputs.iter().enumerate() {
+                // If the file can't be probed, ffmpeg still reports any problem itself
+                if let Ok(probe) = probe_media(input_file).await {
+                    copy_fallbacks[i] = check_container_compatibility(&probe, input_file, &request.output_format, copy_streams)?;
+                    tonemaps[i] = needs_tonemap(&probe, request.video_settings.as_ref());
+                    chapter_warnings[i] = lost_chapters_warning(&probe, &request.output_format, request.video_settings.as_ref());
+                    source_limits[i] = Some(SourceLimits::from_probe(&probe));
+                }
+            }
+            if tonemaps.contains(&true) {
+                ensure_zscale().await?;
+            }
+        }
+        if request.conversion_type == ConversionType::Audio && request.audio_settings.as_ref().is_some_and(|s| s.prevent_upscale) {
+            for (i, input_file) in task_inputs.iter().enumerate() {
+                if let Ok(probe) = probe_media(input_file).await {
+                    source_limits[i] = Some(SourceLimits::from_probe(&probe));
+                }
+            }
+        }
+        
+        let optimize_note = optimize_warning(&request);
+        Ok(ConversionPlan {
+            request,
+            task_inputs,
+            copy_fallbacks,
+            tonemaps,
+            chapter_warnings,
+            source_limits,
+            optimize_note,
+            combine_into_pdf,
+            timeout_secs,
+        })
+    }
+
+    /// Records an input that can't be converted as a failed task of the batch, so the
+    /// rest of the batch still runs
+    fn add_failed_task(&self, input_file: &Path, reason: String, batch_id: &str, app_handle: &tauri::AppHandle) -> String {
+        let file_name = input_file.file_name().and_then(|n| n.to_str()).unwrap_or("Unknown");
+        warn!(input_file = %input_file.display(), reason = %reason, "Not converting input");
+        let task_id = self.create_task(format!("Converting {}", file_name));
+        self.update_task(&task_id, |task| {
+            task.status = TaskStatus::Failed;
+            task.error = Some(reason.clone());
+            task.batch_id = batch_id.to_string();
+        });
+        self.batches.add(batch_id);
+        self.batches.finish(batch_id);
+        events::emit_task_created(app_handle, self.get_task(&task_id));
+        self.updates.emit(app_handle, self.get_task(&task_id));
+        let cause = MediaForgeError::ConversionError(reason.clone());
+        events::emit_task_failed(app_handle, &task_id, reason, &cause);
+        task_id
+    }
+
+    /// Creates and starts a task for each input of a validated request
+    fn spawn_conversion_tasks(&self, plan: ConversionPlan, batch_id: &str, app_handle: &tauri::AppHandle) -> Vec<String> {
+        let ConversionPlan {
+            request,
+            task_inputs,
+            copy_fallbacks,
+            tonemaps,
+            chapter_warnings,
+            source_limits,
+            optimize_note,
+            combine_into_pdf,
+            timeout_secs,
+        } = plan;
+        let mut task_ids = Vec::new();
+
+        let inputs = task_inputs.iter().zip(copy_fallbacks).zip(tonemaps).zip(chapter_warnings).zip(source_limits);
+        for ((((input_file, copy_fallback), tonemap), chapter_warning), limits) in inputs {
+            let file_name = input_file
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("Unknown");
+            info!(
                 input_file = %input_file.display(),
                 file_name = file_name,
                 conversion_type = ?request.conversion_type,
                 "Creating conversion task"
             );
-            let task_id = self.create_task(format!("Converting {}", file_name));
-            task_ids.push(task_id.clone());
+            let task_name = if combine_into_pdf {
+                format!("Combining {} images into PDF", request.input_files.len())
+            } else {
+                format!("Converting {}", file_name)
+            };
+            let task_id = self.create_task(task_name);
+            task_ids.push(task_id.clone());
+
+            // Set task to Processing status BEFORE spawning to prevent race condition
+            self.update_task(&task_id, |task| {
+                task.status = TaskStatus::Processing;
+                task.batch_id = batch_id.to_string();
+            });
+            events::emit_task_created(app_handle, self.get_task(&task_id));
+            self.batches.add(batch_id);
+
+            let manager = self.clone();
+            let mut req = request.clone();
+            if let Some(warning) = copy_fallback {
+                log::warn!("{}: {}", input_file.display(), warning);
+                if let Some(settings) = req.video_settings.as_mut() {
+                    settings.copy_streams = false;
+                }
+                self.update_task(&task_id, |task| task.warning = Some(warning));
+            }
+            if let Some(lost) = chapter_warning {
+                log::warn!("{}: {}", input_file.display(), lost);
+                self.update_task(&task_id, |task| task.append_warning(lost));
+            }
+            if let Some(note) = optimize_note.clone() {
+                self.update_task(&task_id, |task| task.append_warning(note));
+            }
+            if request.conversion_type == ConversionType::Video {
+                req.video_settings.get_or_insert_with(VideoSettings::default).tonemap_sdr = Some(tonemap);
+            }
+            for note in limits.map(|limits| apply_source_limits(&mut req, &limits)).unwrap_or_default() {
+                info!(input_file = %input_file.display(), note = %note, "Capped settings at the source's");
+                self.update_task(&task_id, |task| task.append_warning(note));
+            }
+            // A combined PDF is built from every input
+            let sources = if combine_into_pdf { req.input_files.clone() } else { vec![input_file.clone()] };
+            let input_file = input_file.clone();
+            let app_handle = app_handle.clone();
+            let app_handle_clone2 = app_handle.clone();
+            let task_id_clone = task_id.clone();
+            let batch_id_clone = batch_id.to_string();
+            
+            // Create cancellation token for this task
+            let cancellation_token = CancellationToken::new();
+            let cancellation_token_clone = cancellation_token.clone();
+
+            let join_handle = tokio::spawn(async move {
+                info!(
+                    task_id = task_id_clone,
+                    input_file = %input_file.display(),
+                    "Spawned conversion task worker"
+                );
+                
+                // Run the conversion and its post-process command with timeout and cancellation support
+                let conversion = async {
+                    let outcome = manager.convert_single_cancellable(&task_id_clone, &input_file, &req, app_handle.clone(), cancellation_token_clone.clone()).await?;
+                    manager.post_process(&task_id_clone, &req, &app_handle, &cancellation_token_clone).await?;
+                    Ok::<_, MediaForgeError>(outcome)
+                };
+                let result = tokio::select! {
+                    result = conversion => {
+                        result
+                    }
+                    _ = cancellation_token_clone.cancelled() => {
+                        info!(
+                            task_id = task_id_clone,
+                            input_file = %input_file.display(),
+                            "Conversion task was cancelled by user"
+                        );
+                        manager.update_task(&task_id_clone, |task| {
+                            task.mark_cancelled(manager.shutting_down.load(Ordering::Relaxed));
+                        });
+                        // Clean up task handle on cancellation
+                        manager.task_handles.remove(&task_id_clone);
+                        manager.finish_batch_task(&batch_id_clone, &app_handle_clone2);
+                        return;
+                    }
+                    _ = validation::sleep_for_timeout(timeout_secs) => {
+                        let timeout = timeout_secs.unwrap_or_default();
+                        warn!(
+                            task_id = task_id_clone,
+                            input_file = %input_file.display(),
+                            timeout_seconds = timeout,
+                            "Conversion task timed out"
+                        );
+                        let message = format!("Conversion timed out after {}", validation::format_timeout(timeout));
+                        manager.update_task(&task_id_clone, |task| {
+                            task.status = TaskStatus::Failed;
+                            task.error = Some(message.clone());
+                        });
+                        let cause = MediaForgeError::TemporaryError(message.clone());
+                        events::emit_task_failed(&app_handle_clone2, &task_id_clone, message, &cause);
+                        // Clean up task handle on timeout
+                        manager.task_handles.remove(&task_id_clone);
+                        manager.finish_batch_task(&batch_id_clone, &app_handle_clone2);
+                        return;
+                    }
+                };
+                
+                match result {
+                    Err(e) => {
+                        error!(
+                            task_id = task_id_clone,
+                            input_file = %input_file.display(),
+                            error = %e,
+                            error_type = std::any::type_name_of_val(&e),
+                            "Conversion task failed with error"
+                        );
+                        manager.update_task(&task_id_clone, |task| {
+                            task.status = TaskStatus::Failed;
+                            task.error = Some(e.to_string());
+                        });
+                        // Clean up task handle on error
+                        manager.task_handles.remove(&task_id_clone);
+                        crate::system::emit_dependency_missing(&e, &task_id_clone, &app_handle_clone2);
+                        events::emit_task_failed(&app_handle_clone2, &task_id_clone, e.to_string(), &e);
+                    }
+                    Ok(outcome) => {
+                        manager.complete_task(&task_id_clone, outcome, &app_handle_clone2);
+                        if req.delete_source_on_success {
+                            match outcome {
+                                ConversionOutcome::Converted => manager.delete_sources(&task_id_clone, &sources),
+                                // The existing output may be an unrelated file, so it doesn't replace the source
+                                ConversionOutcome::KeptExisting => {
+                                    info!(task_id = task_id_clone, "Kept the source files since the output wasn't converted")
+                                }
+                            }
+                        }
+                    }
+                }
+                
+                // Emit final task update
+                manager.updates.emit(&app_handle_clone2, manager.get_task(&task_id_clone));
+                manager.finish_batch_task(&batch_id_clone, &app_handle_clone2);
+            });
+            
+            // Store the task handle for cancellation
+            let task_handle = TaskHandle::new(join_handle, cancellation_token);
+            self.task_handles.insert(task_id.clone(), task_handle);
+        }
+
+        task_ids
+    }
+
+    /// Marks a task as completed once its conversion and post-process command succeeded,
+    /// so that nothing it started outlives its handle
+    fn complete_task(&self, task_id: &str, outcome: ConversionOutcome, app_handle: &tauri::AppHandle) {
+        self.update_task(task_id, |task| {
+            task.status = TaskStatus::Completed;
+            task.progress = 100.0;
+        });
+        self.task_handles.remove(task_id);
+
+        // Send notification unless the output was kept or the batch gets a summary instead
+        if outcome == ConversionOutcome::KeptExisting {
+            return;
+        }
+        if let Some(task) = self.get_task(task_id).filter(|task| self.notify_individually(task)) {
+            if let Some(output) = &task.file_path {
+                notifications::send_conversion_complete_notification(app_handle, &task.name, Path::new(output));
+            }
+        }
+    }
+
+    /// Runs the request's post-process command once the conversion succeeded, while the
+    /// task is still processing. Its failure fails the task unless the request only wants
+    /// a warning.
+    async fn post_process(
+        &self,
+        task_id: &str,
+        request: &ConvertRequest,
+        app_handle: &tauri::AppHandle,
+        cancellation_token: &CancellationToken,
+    ) -> Result<(), MediaForgeError> {
+        let Some(command) = &request.post_process_command else { return Ok(()) };
+        let Some(task) = self.get_task(task_id) else { return Ok(()) };
+        self.updates.emit(app_handle, Some(task.clone()));
+        hooks::post_process_task(
+            command,
+            request.post_process_ignore_failure,
+            &task,
+            cancellation_token,
+            MediaForgeError::ConversionError,
+            |warning| self.update_task(task_id, |task| task.append_warning(warning)),
+        )
+        .await
+    }
+
+    /// Deletes a completed task's inputs for `delete_source_on_success`. A source that
+    /// can't be shown to be safe to delete is kept with a warning on the task.
+    fn delete_sources(&self, task_id: &str, sources: &[PathBuf]) {
+        let Some(task) = self.get_task(task_id) else { return };
+        if task.status != TaskStatus::Completed {
+            return;
+        }
+        let outputs: Vec<PathBuf> = task.file_path.iter().chain(&task.output_files).map(PathBuf::from).collect();
+        for source in sources {
+            match delete_source_file(source, &outputs) {
+                Ok(()) => info!(task_id, source = %source.display(), "Deleted source file after conversion"),
+                Err(e) => {
+                    warn!(task_id, source = %source.display(), error = %e, "Kept source file after conversion");
+                    self.update_task(task_id, |task| {
+                        task.append_warning(format!("Kept source {}: {}", source.display(), e));
+                    });
+                }
+            }
+        }
+    }
+
+    /// Applies the request's conflict policy to `output_path`. When the existing file is
+    /// kept, it becomes the task's output.
+    fn claim_output_path(
+        &self,
+        task_id: &str,
+        input_file: &Path,
+        output_path: OutputPath,
+        request: &ConvertRequest,
+        app_handle: &tauri::AppHandle,
+    ) -> Result<OutputClaim, MediaForgeError> {
+        let existing = output_path.path().to_path_buf();
+        let claimed = conflict_output_path(output_path, input_file, request.conflict_policy)?;
+        if claimed.is_none() {
+            log::info!("Output {:?} already exists, skipping conversion", existing);
+            self.update_task(task_id, |task| {
+                task.progress = 100.0;
+                task.warning = Some(KEPT_EXISTING_OUTPUT.to_string());
+                task.file_path = Some(existing.to_string_lossy().to_string());
+            });
+            self.updates.emit(app_handle, self.get_task(task_id));
+        }
+        Ok(claimed.map_or(OutputClaim::KeptExisting, OutputClaim::Claimed))
+    }
+
+    async fn convert_single(
+        &self,
+        task_id: &str,
+        input_file: &PathBuf,
+        request: &ConvertRequest,
+        app_handle: tauri::AppHandle,
+    ) -> Result<(), MediaForgeError> {
+        self.update_task(task_id, |task| {
+            task.status = TaskStatus::Processing;
+        });
+
+        match request.conversion_type {
+            ConversionType::Image => {
+                let output_path = planned_output_path(request, input_file, &request.output_format)?.into_path();
+                self.convert_image(task_id, input_file, &output_path, request, app_handle, &CancellationToken::new()).await
+            }
+            ConversionType::Video => {
+                self.convert_video(task_id, input_file, request, app_handle).await
+            }
+            ConversionType::Audio => {
+                let output_path = planned_output_path(request, input_file, &request.output_format)?.into_path();
+                self.convert_audio(task_id, input_file, &output_path, request, app_handle, &CancellationToken::new()).await
+            }
+            ConversionType::Auto => Err(auto_task_error()),
+        }
+    }
+
+    async fn convert_single_cancellable(
+        &self,
+        task_id: &str,
+        input_file: &PathBuf,
+        request: &ConvertRequest,
+        app_handle: tauri::AppHandle,
+        cancellation_token: CancellationToken,
+    ) -> Result<ConversionOutcome, MediaForgeError> {
+        // Task status is already set to Processing before spawn to prevent race condition
+        
+        // Attach the size estimate for the UI; video also sizes its disk space check with it
+        let estimated_bytes = if is_pdf_combine(request) {
+            let mut total = 0;
+            for file in &request.input_files {
+                total += estimate_file_bytes(request, file).await.unwrap_or(0);
+            }
+            Some(total)
+        } else {
+            estimate_file_bytes(request, input_file).await.ok()
+        };
+        self.update_task(task_id, |task| task.estimated_bytes = estimated_bytes);
+        self.updates.emit(&app_handle, self.get_task(task_id));
+        
+        match request.conversion_type {
+            ConversionType::Image => {
+                self.convert_image_cancellable(task_id, input_file, request, app_handle, cancellation_token).await
+            }
+            ConversionType::Video => {
+                self.convert_video_cancellable(task_id, input_file, request, app_handle, cancellation_token).await
+            }
+            ConversionType::Audio => {
+                self.convert_audio_cancellable(task_id, input_file, request, app_handle, cancellation_token).await
+            }
+            ConversionType::Auto => Err(auto_task_error()),
+        }
+    }
+
+    async fn convert_image(
+        &self,
+        task_id: &str,
+        input_file: &PathBuf,
+        output_path: &Path,
+        request: &ConvertRequest,
+        app_handle: tauri::AppHandle,
+        cancellation_token: &CancellationToken,
+    ) -> Result<(), MediaForgeError> {
+        // Re-validate inputs (defensive programming)
+        validate_input_file(input_file, &ConversionType::Image)?;
+        validate_image_format(input_file, &request.output_format)?;
+        if let Some(settings) = &request.image_settings {
+            validate_image_settings(settings)?;
+        }
+        
+        let output_dir = output_path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let output_path = output_path.to_path_buf();
+        // Pages extracted from a PDF are numbered after the output name, e.g. scan-001.png
+        let page_stem = output_path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string();
+
+        let magick = ensure_imagemagick().await?;
+        let mut cmd = magick.command();
+        // Report progress on stderr while loading, processing and saving
+        cmd.arg("-monitor");
+        let extract_pages = is_pdf_file(input_file);
+        if extract_pages {
+            // Each page becomes its own file; without a density pages come out at 72 DPI
+            let mut settings = request.image_settings.clone().unwrap_or_default();
+            settings.density.get_or_insert(DEFAULT_PDF_DENSITY);
+            let mut args = build_image_args(
+                input_file,
+                &pdf_page_output_pattern(&output_dir, &page_stem, &request.output_format),
+                &request.output_format,
+                Some(&settings),
+            );
+            // Number pages from 1; the option has to precede the output path
+            let page_pattern = args.pop().unwrap_or_default();
+            args.extend(["-scene", "1"].map(OsString::from));
+            args.push(page_pattern);
+            cmd.args(args);
+        } else {
+            let frame_count = probe_frame_count(input_file).await.unwrap_or(1);
+            let (handling, frames_dropped) =
+                frame_handling(frame_count, &request.output_format, request.image_settings.as_ref());
+            if frames_dropped {
+                let warning = format!(
+                    "Animated input has {} frames; only the first frame was converted because .{} can't hold an animation",
+                    frame_count, request.output_format
+                );
+                log::warn!("{}", warning);
+                self.update_task(task_id, |task| {
+                    task.warning = Some(warning);
+                });
+            }
+
+            let args = build_image_args(
+                input_file,
+                &output_path,
+                &request.output_format,
+                request.image_settings.as_ref(),
+            );
+            let args = apply_frame_handling(args, input_file, handling);
+            cmd.args(if magick == ImageMagick::Legacy { legacy_ico_args(args) } else { args });
+        }
+
+        log::info!("Starting image conversion: {:?}", cmd);
+        log::info!("Output path: {:?}", output_path);
+        
+        let mut progress = 0.0;
+        let mut error_output = OutputTail::new(ERROR_OUTPUT_LINES);
+        let outcome = run_cancellable_command(cmd, cancellation_token, |_| {}, |line| {
+            if !self.record_magick_progress(task_id, line, &mut progress, &app_handle) {
+                error_output.push(line);
+            }
+        })
+        .await
+        .map_err(|e| {
+            log::error!("Failed to run ImageMagick: {}", e);
+            MediaForgeError::from_spawn_error(magick.program(), e, MediaForgeError::ConversionError)
+        })?;
+        let status = match outcome {
+            CommandOutcome::Exited(status) => status,
+            CommandOutcome::Cancelled => {
+                return Err(MediaForgeError::ConversionError("Conversion was cancelled".to_string()));
+            }
+        };
+
+        if status.success() {
+            log::info!("Image conversion completed successfully: {:?}", output_path);
+            let pages = if extract_pages {
+                collect_pdf_pages(&output_dir, &page_stem, &request.output_format)
+            } else {
+                Vec::new()
+            };
+            let output_path = pages.first().map(PathBuf::from).unwrap_or(output_path);
+            if should_verify_output(request) {
+                if let Err(e) = verify_output(request, input_file, &output_path, None).await {
+                    let _ = validation::cleanup_on_error(&output_path).await;
+                    return Err(e);
+                }
+            }
+
+            let input_size = std::fs::metadata(input_file).map(|m| m.len()).ok();
+            let output_size = if pages.is_empty() {
+                std::fs::metadata(&output_path).map(|m| m.len()).ok()
+            } else {
+                pages.iter().map(|page| std::fs::metadata(page).map(|m| m.len()).ok()).sum()
+            };
+            // A larger result is kept, but the user is told the optimization didn't pay off
+            let optimize = request.image_settings.as_ref().is_some_and(|s| s.optimize);
+            let grew = match (input_size, output_size) {
+                (Some(input), Some(output)) if optimize && output > input => Some(format!(
+                    "Optimized output ({} bytes) is larger than the input ({} bytes)",
+                    output, input
+                )),
+                _ => None,
+            };
+            self.update_task(task_id, |task| {
+                task.progress = 100.0;
+                task.file_path = Some(output_path.to_string_lossy().to_string());
+                task.output_files = pages;
+                task.input_size = input_size;
+                task.output_size = output_size;
+                if let Some(grew) = grew {
+                    task.append_warning(grew);
+                }
+            });
+            
+            self.updates.emit(&app_handle, self.get_task(task_id));
+            Ok(())
+        } else {
+            let error = error_output.join();
+            log::error!("ImageMagick conversion failed: {}", error);
+            Err(MediaForgeError::ConversionError(format!("ImageMagick failed: {}", error)))
+        }
+    }
+
+    /// Records a line of ImageMagick's `-monitor` output as task progress. Returns
+    /// false for any other line, such as an error message.
+    fn record_magick_progress(&self, task_id: &str, line: &str, progress: &mut f32, app_handle: &tauri::AppHandle) -> bool {
+        let Some((phase, percent)) = parse_magick_progress(line) else {
+            return false;
+        };
+        let overall = magick_overall_progress(&phase, percent);
+        // Multi-page inputs restart each phase; never move backwards
+        if overall > *progress {
+            *progress = overall;
+            self.update_task(task_id, |task| task.progress = overall);
+            self.updates.emit(app_handle, self.get_task(task_id));
+        }
+        true
+    }
+
+    /// Combines all input images into a single PDF, one page per image in input order
+    async fn combine_images_to_pdf(
+        &self,
+        task_id: &str,
+        input_files: &[PathBuf],
+        request: &ConvertRequest,
+        app_handle: tauri::AppHandle,
+        cancellation_token: &CancellationToken,
+    ) -> Result<ConversionOutcome, MediaForgeError> {
+        // Re-validate inputs (defensive programming)
+        for input_file in input_files {
+            validate_input_file(input_file, &ConversionType::Image)?;
+            validate_image_format(input_file, &request.output_format)?;
+        }
+        if let Some(settings) = &request.image_settings {
+            validate_image_settings(settings)?;
+        }
+
+        // The document is named after the first image
+        let first_input = input_files
+            .first()
+            .ok_or_else(|| MediaForgeError::InvalidSettings("No input files".to_string()))?;
+        let output_path = planned_output_path(request, first_input, "pdf")?;
+        let OutputClaim::Claimed(output_path) = self.claim_output_path(task_id, first_input, output_path, request, &app_handle)? else {
+            return Ok(ConversionOutcome::KeptExisting);
+        };
+
+        let magick = ensure_imagemagick().await?;
+        let mut cmd = magick.command();
+        cmd.args(build_pdf_combine_args(input_files, &output_path, request.image_settings.as_ref()));
+        log::info!("Combining {} images into PDF: {:?}", input_files.len(), output_path);
+
+        let mut error_output = OutputTail::new(ERROR_OUTPUT_LINES);
+        let outcome = run_cancellable_command(cmd, cancellation_token, |_| {}, |line| error_output.push(line))
+            .await
+            .map_err(|e| {
+                log::error!("Failed to run ImageMagick: {}", e);
+                MediaForgeError::from_spawn_error(magick.program(), e, MediaForgeError::ConversionError)
+            })?;
+        let status = match outcome {
+            CommandOutcome::Exited(status) => status,
+            CommandOutcome::Cancelled => {
+                return Err(MediaForgeError::ConversionError("Conversion was cancelled".to_string()));
+            }
+        };
+
+        if !status.success() {
+            let error = error_output.join();
+            log::error!("ImageMagick PDF creation failed: {}", error);
+            return Err(MediaForgeError::ConversionError(format!("ImageMagick failed: {}", error)));
+        }
+
+        self.update_task(task_id, |task| {
+            task.progress = 100.0;
+            task.file_path = Some(output_path.to_string_lossy().to_string());
+        });
+
+        self.updates.emit(&app_handle, self.get_task(task_id));
+        Ok(ConversionOutcome::Converted)
+    }
+
+    async fn convert_video(
+        &self,
+        task_id: &str,
+        input_file: &PathBuf,
+        request: &ConvertRequest,
+        app_handle: tauri::AppHandle,
+    ) -> Result<(), MediaForgeError> {
+        // Re-validate inputs (defensive programming)
+        validate_input_media(input_file, &ConversionType::Video).await?;
+        
+        let output_dir = resolve_output_dir(request, input_file)?;
+        let output_path = output_dir.join(output_file_name(request, input_file, &request.output_format)?);
+
+        log::info!("Starting video conversion from {:?} to {:?}", input_file, output_path);
+        self.convert_video_attempt(task_id, input_file, request, &output_path, app_handle, CancellationToken::new()).await
+    }
+
+    async fn convert_video_cancellable(
+        &self,
+        task_id: &str,
+        input_file: &PathBuf,
+        request: &ConvertRequest,
+        app_handle: tauri::AppHandle,
+        cancellation_token: CancellationToken,
+    ) -> Result<ConversionOutcome, MediaForgeError> {
+        // Re-validate inputs (defensive programming)
+        validate_input_media(input_file, &ConversionType::Video).await?;
+        
+        let output_path = planned_output_path(request, input_file, &request.output_format)?;
+        let OutputClaim::Claimed(output_path) = self.claim_output_path(task_id, input_file, output_path, request, &app_handle)? else {
+            return Ok(ConversionOutcome::KeptExisting);
+        };
+        let output_dir = output_path.parent().unwrap_or(Path::new("")).to_path_buf();
+
+        // Validate disk space and permissions before starting
+        let estimated_size = self
+            .get_task(task_id)
+            .and_then(|task| task.estimated_bytes)
+            .or_else(|| input_file.metadata().map(|m| m.len() * 2).ok()) // Without an estimate, assume 2x input size
+            .unwrap_or(500 * 1024 * 1024); // Default 500MB
+        crate::error::validation::validate_disk_space(&output_dir, Some(estimated_size)).await?;
+        crate::error::validation::validate_write_permissions(&output_dir).await?;
+
+        log::info!("Starting cancellable video conversion from {:?} to {:?}", input_file, output_path);
+        
+        // Use retry mechanism for conversion operations (filesystem errors mainly)
+        let retry_config = crate::error::RetryConfig::for_filesystem();
+        let conversion_result = crate::error::retry_async(retry_config, || {
+            self.convert_video_attempt(task_id, input_file, request, &output_path, app_handle.clone(), cancellation_token.clone())
+        }, |attempt, max_attempts, previous_error| {
+            tasks::record_attempt(&self.tasks, &self.updates, &app_handle, task_id, attempt, max_attempts, previous_error);
+        }).await;
+        
+        // Cleanup on failure
+        if let Err(ref error) = conversion_result {
+            log::error!("Video conversion failed after retries for task {}: {}", task_id, error);
+            let _ = crate::error::validation::cleanup_on_error(&output_path).await;
+        }
+        
+        conversion_result.map(|()| ConversionOutcome::Converted)
+    }
+
+    /// Runs one FFmpeg encode, copying its progress onto the task. Returns the exit
+    /// status and the last lines FFmpeg wrote to stderr.
+    async fn run_ffmpeg_encode(
+        &self,
+        task_id: &str,
+        args: Vec<OsString>,
+        mut tracker: FFmpegProgress,
+        cancellation_token: &CancellationToken,
+        app_handle: &tauri::AppHandle,
+    ) -> Result<(std::process::ExitStatus, String), MediaForgeError> {
+        let mut cmd = TokioCommand::new("ffmpeg");
+        cmd.args(args);
+        log::info!("FFmpeg command: {:?}", cmd);
+
+        let mut last_emit = None;
+        let mut error_output = OutputTail::new(ERROR_OUTPUT_LINES);
+        let outcome = run_cancellable_command(
+            cmd,
+            cancellation_token,
+            |line| {
+                if let Some(update) = tracker.parse_line(line) {
+                    self.record_ffmpeg_progress(task_id, update, &mut last_emit, app_handle);
+                }
+            },
+            |line| error_output.push(line),
+        )
+        .await
+        .map_err(|e| MediaForgeError::from_spawn_error("ffmpeg", e, MediaForgeError::FFmpegError))?;
+        match outcome {
+            CommandOutcome::Exited(status) => Ok((status, error_output.join())),
+            CommandOutcome::Cancelled => {
+                log::info!("Killed FFmpeg process for cancelled conversion task {}", task_id);
+                Err(MediaForgeError::FFmpegError("Conversion was cancelled".to_string()))
+            }
+        }
+    }
+
+    async fn convert_video_attempt(
+        &self,
+        task_id: &str,
+        input_file: &PathBuf,
+        request: &ConvertRequest,
+        output_path: &PathBuf,
+        app_handle: tauri::AppHandle,
+        cancellation_token: CancellationToken,
+    ) -> Result<(), MediaForgeError> {
+        log::info!("Attempting video conversion: {:?} -> {:?}", input_file, output_path);
+
+        // Percentages and ETAs need the input duration; without it only speed is shown
+        let duration_secs = probe_duration_secs(input_file).await.ok();
+        let settings = request.video_settings.as_ref();
+        let target = settings.and_then(|s| s.target_size_mb.map(|target_mb| (s, target_mb)));
+        let stabilize = settings.is_some_and(|s| s.stabilize);
+
+        // Stabilizing and two-pass encodes run ffmpeg more than once, handing files on
+        // through a folder that lives as long as this attempt
+        let temp_dir = if stabilize || target.is_some() {
+            Some(EncodeTempDir::create(task_id)?)
+        } else {
+            None
+        };
+        let transforms = temp_dir.as_ref().filter(|_| stabilize).map(EncodeTempDir::transforms);
+
+        let mut runs = Vec::new();
+        if let Some(transforms) = &transforms {
+            runs.push(build_stabilize_detect_args(input_file, transforms, settings));
+        }
+        match (target, &temp_dir) {
+            (Some((settings, target_mb)), Some(temp_dir)) => {
+                let duration = duration_secs.ok_or_else(|| MediaForgeError::InvalidSettings(format!(
+                    "A target size needs the input's duration, which ffprobe can't read for {}",
+                    input_file.display()
+                )))?;
+                let audio_kbps = target_audio_kbps(settings);
+                let mut encode = TwoPassEncode {
+                    pass: 1,
+                    passlog: temp_dir.passlog_prefix(),
+                    codec: two_pass_codec(&request.output_format).unwrap_or("libx264"),
+                    video_kbps: target_video_kbps(target_mb, duration, audio_kbps)?,
+                    audio_kbps,
+                };
+                log::info!("Encoding in two passes at {} kbps for a {} MB target", encode.video_kbps, target_mb);
+                runs.push(build_video_args(input_file, output_path, Some(settings), Some(&encode), transforms.as_deref()));
+                encode.pass = 2;
+                runs.push(build_video_args(input_file, output_path, Some(settings), Some(&encode), transforms.as_deref()));
+            }
+            _ => runs.push(build_video_args(input_file, output_path, settings, None, transforms.as_deref())),
+        }
+
+        // Each run fills an equal share of the progress bar; a failed one ends the attempt
+        let run_count = runs.len();
+        let mut outcome = None;
+        for (i, args) in runs.into_iter().enumerate() {
+            let share = 100.0 / run_count as f32;
+            let tracker = FFmpegProgress::spanning(duration_secs, share * i as f32, share * (i + 1) as f32);
+            let (status, error_output) =
+                self.run_ffmpeg_encode(task_id, args, tracker, &cancellation_token, &app_handle).await?;
+            let failed = !status.success();
+            outcome = Some((status, error_output));
+            if failed {
+                break;
+            }
+        }
+        let (status, error_output) = outcome.expect("a video conversion runs ffmpeg at least once");
+
+        if status.success() {
+            // A failed check is retried; the output is deleted once retries run out
+            if should_verify_output(request) {
+                verify_output(request, input_file, output_path, None).await?;
+            }
+            
+            let size_warning = target.and_then(|(_, target_mb)| {
+                let output_bytes = std::fs::metadata(output_path).ok()?.len();
+                check_target_size(target_mb, output_bytes)
+            });
+            
+            log::info!("Video conversion completed successfully: {:?}", output_path);
+            self.update_task(task_id, |task| {
+                task.progress = 100.0;
+                task.file_path = Some(output_path.to_string_lossy().to_string());
+                if let Some(warning) = size_warning {
+                    task.append_warning(warning);
+                }
+            });
+            
+            self.updates.emit(&app_handle, self.get_task(task_id));
+            Ok(())
+        } else {
+            // Enhanced error classification for FFmpeg
+            let error_message = format!("Conversion failed with exit code {:?}: {}", status.code(), error_output);
+            let error = Self::classify_ffmpeg_error(&error_message, status.code());
+            
+            log::error!("FFmpeg failed for task {}: {} (retryable: {})", 
+                       task_id, error, error.is_retryable());
+            
+            Err(error)
+        }
+    }
+
+    async fn convert_audio(
+        &self,
+        task_id: &str,
+        input_file: &PathBuf,
+        output_path: &Path,
+        request: &ConvertRequest,
+        app_handle: tauri::AppHandle,
+        cancellation_token: &CancellationToken,
+    ) -> Result<(), MediaForgeError> {
+        // Re-validate inputs (defensive programming)
+        validate_input_media(input_file, &ConversionType::Audio).await?;
+        validate_audio_format(&request.output_format)?;
+        
+        log::info!("Starting audio conversion from {:?} to {:?}", input_file, output_path);
+
+        // Fades are checked against, and the fade-out placed by, the clip duration,
+        // which silence trimming shortens
+        let (duration_secs, trim) = match &request.audio_settings {
+            Some(settings) if settings.fade_in_secs.is_some() || settings.fade_out_secs.is_some() || settings.trim_silence => {
+                let mut duration = probe_duration_secs(input_file).await?;
+                let trim = if settings.trim_silence {
+                    detect_silence_trim(input_file, duration).await?
+                } else {
+                    None
+                };
+                if let Some((start, end)) = trim {
+                    duration = end - start;
+                }
+                validate_fade_lengths(settings, duration)?;
+                (Some(duration), trim)
+            }
+            _ => (None, None),
+        };
+
+        let args = build_audio_args(
+            input_file,
+            output_path,
+            &request.output_format,
+            request.audio_settings.as_ref(),
+            duration_secs,
+            trim,
+        );
+
+        // A trimmed clip's progress runs to its trimmed length, not the input's
+        let duration_secs = match duration_secs {
+            Some(duration) => Some(duration),
+            None => probe_duration_secs(input_file).await.ok(),
+        };
+        let (status, error_output) = self
+            .run_ffmpeg_encode(task_id, args, FFmpegProgress::new(duration_secs), cancellation_token, &app_handle)
+            .await?;
+
+        if status.success() {
+            if should_verify_output(request) {
+                let trimmed_secs = trim.map(|(start, end)| end - start);
+                if let Err(e) = verify_output(request, input_file, output_path, trimmed_secs).await {
+                    let _ = validation::cleanup_on_error(output_path).await;
+                    return Err(e);
+                }
+            }
+            
+            log::info!("Audio conversion completed successfully: {:?}", output_path);
+            self.update_task(task_id, |task| {
+                task.progress = 100.0;
+                task.file_path = Some(output_path.to_string_lossy().to_string());
+            });
+            
+            self.updates.emit(&app_handle, self.get_task(task_id));
+            Ok(())
+        } else {
+            let error_message = format!("Conversion failed with exit code {:?}: {}", status.code(), error_output);
+            let error = Self::classify_ffmpeg_error(&error_message, status.code());
+            log::error!("Audio conversion failed for task {}: {} (retryable: {})", task_id, error, error.is_retryable());
+            Err(error)
+        }
+    }
+    
+    /// Classifies FFmpeg errors to determine if they're retryable  
+    pub fn classify_ffmpeg_error(message: &str, exit_code: Option<i32>) -> MediaForgeError {
+        let msg_lower = message.to_lowercase();
+        
+        // Temporary filesystem errors (retryable)
+        if msg_lower.contains("resource temporarily unavailable") ||
+           msg_lower.contains("device busy") ||
+           msg_lower.contains("interrupted system call") ||
+           exit_code == Some(1)  // Generic failure that might be temporary
+        {
+            MediaForgeError::TemporaryError(message.to_string())
+        }
+        // Disk space errors  
+        else if msg_lower.contains("no space left") ||
+                msg_lower.contains("disk full") ||
+                msg_lower.contains("write error")
+        {
+            MediaForgeError::DiskSpaceError(message.to_string())
+        }
+        // Permission errors
+        else if msg_lower.contains("permission denied") ||
+                msg_lower.contains("access denied") ||
+                exit_code == Some(126)  // Permission denied
+        {
+            MediaForgeError::PermissionError(message.to_string())
+        }
+        // Invalid format/codec errors (not retryable)
+        else if msg_lower.contains("invalid data") ||
+                msg_lower.contains("unsupported") ||
+                msg_lower.contains("no decoder") ||
+                msg_lower.contains("invalid file format") ||
+                exit_code == Some(255)  // FFmpeg generic error
+        {
+            MediaForgeError::FFmpegError(message.to_string())
+        }
+        // Default to retryable for other cases
+        else {
+            MediaForgeError::TemporaryError(message.to_string())
+        }
+    }
+
+    async fn convert_image_cancellable(
+        &self,
+        task_id: &str,
+        input_file: &PathBuf,
+        request: &ConvertRequest,
+        app_handle: tauri::AppHandle,
+        cancellation_token: CancellationToken,
+    ) -> Result<ConversionOutcome, MediaForgeError> {
+        let result = if is_pdf_combine(request) {
+            self.combine_images_to_pdf(task_id, &request.input_files, request, app_handle, &cancellation_token).await
+        } else {
+            // Claimed once, so a retry doesn't mistake a partial output for an existing file.
+            // PDF pages get numbered names of their own.
+            let output_path = planned_output_path(request, input_file, &request.output_format)?;
+            let claim = if is_pdf_file(input_file) {
+                OutputClaim::Claimed(output_path.into_path())
+            } else {
+                self.claim_output_path(task_id, input_file, output_path, request, &app_handle)?
+            };
+            match claim {
+                // Retried like video so that outputs failing verification get another attempt
+                OutputClaim::Claimed(output_path) => {
+                    let retry_config = crate::error::RetryConfig::for_filesystem();
+                    crate::error::retry_async(retry_config, || {
+                        self.convert_image(task_id, input_file, &output_path, request, app_handle.clone(), &cancellation_token)
+                    }, |attempt, max_attempts, previous_error| {
+                        tasks::record_attempt(&self.tasks, &self.updates, &app_handle, task_id, attempt, max_attempts, previous_error);
+                    }).await
+                    .map(|()| ConversionOutcome::Converted)
+                }
+                OutputClaim::KeptExisting => Ok(ConversionOutcome::KeptExisting),
+            }
+        };
+
+        result
+    }
+
+    async fn convert_audio_cancellable(
+        &self,
+        task_id: &str,
+        input_file: &PathBuf,
+        request: &ConvertRequest,
+        app_handle: tauri::AppHandle,
+        cancellation_token: CancellationToken,
+    ) -> Result<ConversionOutcome, MediaForgeError> {
+        let output_path = planned_output_path(request, input_file, &request.output_format)?;
+        let OutputClaim::Claimed(output_path) = self.claim_output_path(task_id, input_file, output_path, request, &app_handle)? else {
+            return Ok(ConversionOutcome::KeptExisting);
+        };
+        // Retried like video so that outputs failing verification get another attempt
+        let retry_config = crate::error::RetryConfig::for_filesystem();
+        let result = crate::error::retry_async(retry_config, || {
+            self.convert_audio(task_id, input_file, &output_path, request, app_handle.clone(), &cancellation_token)
+        }, |attempt, max_attempts, previous_error| {
+            tasks::record_attempt(&self.tasks, &self.updates, &app_handle, task_id, attempt, max_attempts, previous_error);
+        }).await;
+
+        result.map(|()| ConversionOutcome::Converted)
+    }
+}
+
+impl Clone for ConversionManager {
+    fn clone(&self) -> Self {
+        Self {
+            tasks: Arc::clone(&self.tasks),
+            task_handles: Arc::clone(&self.task_handles),
+            batches: Arc::clone(&self.batches),
+            default_timeout_secs: Arc::clone(&self.default_timeout_secs),
+            max_queue_size: Arc::clone(&self.max_queue_size),
+            tools: Arc::clone(&self.tools),
+            updates: Arc::clone(&self.updates),
+            shutting_down: Arc::clone(&self.shutting_down),
+        }
+    }
+}
+
+/// One block of FFmpeg's `-progress` output, summarised for the task
+#[derive(Debug, Clone, PartialEq)]
+struct FFmpegProgressUpdate {
+    /// `None` when the input duration is unknown
+    percent: Option<f32>,
+    /// Encoding speed as a multiple of real time
+    speed: Option<f64>,
+    fps: Option<f64>,
+    eta_secs: Option<u64>,
+    /// Set on the last block, written when FFmpeg finishes
+    finished: bool,
+}
+
+/// Accumulates FFmpeg's `-progress` output, which arrives as blocks of `key=value`
+/// lines ending with `progress=continue` (or `progress=end` for the last one)
+struct FFmpegProgress {
+    duration_secs: Option<f64>,
+    out_time_secs: f64,
+    fps: Option<f64>,
+    speed: Option<f64>,
+    /// Part of the task's progress this encode covers, in percent
+    span: (f32, f32),
+}
+
+impl FFmpegProgress {
+    fn new(duration_secs: Option<f64>) -> Self {
+        Self::spanning(duration_secs, 0.0, 100.0)
+    }
+
+    /// Tracks an encode that is one step of a task, reported as `start` to `end` percent
+    fn spanning(duration_secs: Option<f64>, start: f32, end: f32) -> Self {
+        Self {
+            duration_secs: duration_secs.filter(|&d| d > 0.0),
+            out_time_secs: 0.0,
+            fps: None,
+            speed: None,
+            span: (start, end),
+        }
+    }
+
+    /// Feeds one line; returns an update at the end of each block
+    fn parse_line(&mut self, line: &str) -> Option<FFmpegProgressUpdate> {
+        let (key, value) = line.trim().split_once('=')?;
+        let value = value.trim();
+        match key {
+            // Despite its name, out_time_ms is in microseconds like out_time_us.
+            // Both read N/A until the first frame is written.
+            "out_time_us" | "out_time_ms" => {
+                if let Ok(us) = value.parse::<i64>() {
+                    self.out_time_secs = us.max(0) as f64 / 1_000_000.0;
+                }
+            }
+            "fps" => self.fps = value.parse::<f64>().ok().filter(|fps| *fps > 0.0),
+            // Reported as e.g. `1.35x`, or `N/A` before encoding starts
+            "speed" => {
+                self.speed = value
+                    .strip_suffix('x')
+                    .and_then(|speed| speed.trim().parse::<f64>().ok())
+                    .filter(|speed| *speed > 0.0)
+            }
+            "progress" => return Some(self.update(value == "end")),
+            _ => {}
+        }
+        None
+    }
+
+    fn update(&self, finished: bool) -> FFmpegProgressUpdate {
+        let remaining_secs = self.duration_secs.map(|duration| (duration - self.out_time_secs).max(0.0));
+        FFmpegProgressUpdate {
+            percent: self.duration_secs.map(|duration| {
+                let (start, end) = self.span;
+                start + (self.out_time_secs / duration).min(1.0) as f32 * (end - start)
+            }),
+            speed: self.speed,
+            fps: self.fps,
+            eta_secs: remaining_secs
+                .zip(self.speed)
+                .map(|(remaining, speed)| (remaining / speed).round() as u64),
+            finished,
+        }
+    }
+}
+
+/// Formats a speed such as `1.35x (32 fps)` for display
+fn format_ffmpeg_speed(speed: f64, fps: Option<f64>) -> String {
+    match fps {
+        Some(fps) => format!("{:.2}x ({:.0} fps)", speed, fps),
+        None => format!("{:.2}x", speed),
+    }
+}
+
+/// Formats seconds as `MM:SS` or `HH:MM:SS`, the form yt-dlp uses for download ETAs
+pub(crate) fn format_eta(secs: u64) -> String {
+    let (hours, mins, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{:02}:{:02}:{:02}", hours, mins, secs)
+    } else {
+        format!("{:02}:{:02}", mins, secs)
+    }
+}
+
+/// Parses an ImageMagick `-monitor` line such as
+/// `load image[photo.jpg]: 99 of 200, 49% complete` into its phase and percentage
+fn parse_magick_progress(line: &str) -> Option<(String, f32)> {
+    static PROGRESS: OnceLock<Regex> = OnceLock::new();
+    let re = PROGRESS.get_or_init(|| Regex::new(r"^([^\[:]+)(\[.*\])?: \d+ of \d+, (\d+)% complete").unwrap());
+    let caps = re.captures(line.trim())?;
+    let percent = caps[3].parse::<f32>().ok()?.min(100.0);
+    Some((caps[1].trim().to_lowercase(), percent))
+}
+
+/// Maps a `-monitor` phase's percentage onto the whole conversion: loading takes the
+/// first 30%, saving the last 30% and any processing in between the rest. Progress
+/// stays below 100% until the conversion is confirmed done.
+fn magick_overall_progress(phase: &str, percent: f32) -> f32 {
+    let (start, span) = if phase.contains("load") || phase.contains("read") || phase.contains("decode") {
+        (0.0, 30.0)
+    } else if phase.contains("save") || phase.contains("write") || phase.contains("encode") {
+        (70.0, 30.0)
+    } else {
+        (30.0, 40.0)
+    };
+    (start + span * percent / 100.0).min(99.0)
+}
+
+#[cfg(test)]
+mod magick_progress_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_magick_progress() {
+        assert_eq!(
+            parse_magick_progress("load image[/tmp/photo.jpg]: 99 of 200, 49% complete"),
+            Some(("load image".to_string(), 49.0))
+        );
+        assert_eq!(
+            parse_magick_progress("Resize/Image: 599 of 600, 100% complete"),
+            Some(("resize/image".to_string(), 100.0))
+        );
+        assert_eq!(parse_magick_progress("magick: unable to open image 'x.jpg'"), None);
+    }
+
+    #[test]
+    fn test_magick_overall_progress() {
+        assert_eq!(magick_overall_progress("load image", 50.0), 15.0);
+        assert_eq!(magick_overall_progress("resize image", 50.0), 50.0);
+        assert_eq!(magick_overall_progress("save image", 50.0), 85.0);
+        assert_eq!(magick_overall_progress("save image", 100.0), 99.0);
+    }
+}
+
+#[cfg(test)]
+mod ffmpeg_progress_tests {
+    use super::*;
+
+    fn feed(tracker: &mut FFmpegProgress, block: &str) -> Vec<FFmpegProgressUpdate> {
+        block.lines().filter_map(|line| tracker.parse_line(line)).collect()
+    }
+
+    #[test]
+    fn test_ffmpeg_progress_blocks() {
+        let mut tracker = FFmpegProgress::new(Some(120.0));
+
+        // Before the first frame everything reads N/A
+        let updates = feed(&mut tracker, "frame=0\nfps=0.00\nout_time_us=N/A\nout_time_ms=N/A\nspeed=N/A\nprogress=continue\n");
+        assert_eq!(updates, vec![FFmpegProgressUpdate {
+            percent: Some(0.0),
+            speed: None,
+            fps: None,
+            eta_secs: None,
+            finished: false,
+        }]);
+
+        let updates = feed(
+            &mut tracker,
+            "frame=720\nfps=48.00\nbitrate=1520.3kbits/s\nout_time_us=30000000\nout_time_ms=30000000\n\
+             out_time=00:00:30.000000\ndup_frames=0\nspeed=1.5x\nprogress=continue\n",
+        );
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].percent, Some(25.0));
+        assert_eq!(updates[0].speed, Some(1.5));
+        assert_eq!(updates[0].fps, Some(48.0));
+        // 90s of input left at 1.5x real time
+        assert_eq!(updates[0].eta_secs, Some(60));
+
+        let updates = feed(&mut tracker, "out_time_us=120500000\nspeed= 2.01x\nprogress=end\n");
+        assert_eq!(updates[0].percent, Some(100.0));
+        assert_eq!(updates[0].eta_secs, Some(0));
+        assert!(updates[0].finished);
+    }
+
+    #[test]
+    fn test_ffmpeg_progress_without_duration() {
+        let mut tracker = FFmpegProgress::new(None);
+        let updates = feed(&mut tracker, "out_time_us=5000000\nspeed=0.8x\nprogress=continue\n");
+        assert_eq!(updates[0].percent, None);
+        assert_eq!(updates[0].speed, Some(0.8));
+        assert_eq!(updates[0].eta_secs, None);
+
+        // Lines that aren't key=value pairs are ignored
+        assert_eq!(tracker.parse_line("[mp4 @ 0x55] Starting second pass"), None);
+    }
+
+    #[test]
+    fn test_ffmpeg_progress_spanning() {
+        // The second pass of a two-pass encode fills the upper half
+        let mut tracker = FFmpegProgress::spanning(Some(10.0), 50.0, 100.0);
+        let updates = feed(&mut tracker, "out_time_us=5000000\nprogress=continue\nout_time_us=10000000\nprogress=end\n");
+        assert_eq!(updates[0].percent, Some(75.0));
+        assert_eq!(updates[1].percent, Some(100.0));
+    }
+
+    #[test]
+    fn test_format_ffmpeg_progress() {
+        assert_eq!(format_ffmpeg_speed(0.8, None), "0.80x");
+        assert_eq!(format_ffmpeg_speed(1.346, Some(32.4)), "1.35x (32 fps)");
+        assert_eq!(format_eta(59), "00:59");
+        assert_eq!(format_eta(240), "04:00");
+        assert_eq!(format_eta(3725), "01:02:05");
+    }
+}
+
+#[cfg(test)]
+mod error_recovery_tests {
+    use super::*;
+    use crate::error::{MediaForgeError, RetryConfig, retry_async};
+    
+    #[test]
+    fn test_error_classification() {
+        // Test retryable errors
+        let network_error = MediaForgeError::NetworkError("Connection timeout".to_string());
+        assert!(network_error.is_retryable());
+        
+        let temp_error = MediaForgeError::TemporaryError("Resource unavailable".to_string());
+        assert!(temp_error.is_retryable());
+        
+        // Test non-retryable errors
+        let invalid_url = MediaForgeError::InvalidUrl("Not a valid YouTube URL".to_string());
+        assert!(!invalid_url.is_retryable());
+        
+        let invalid_settings = MediaForgeError::InvalidSettings("Bad format".to_string());
+        assert!(!invalid_settings.is_retryable());
+    }
+    
+    #[test]
+    fn test_ytdlp_error_classification() {
+        use crate::downloader::DownloadManager;
+        
+        // Network errors (retryable)
+        let network_err = DownloadManager::classify_ytdlp_error("network timeout occurred", Some(1));
+        assert!(network_err.is_retryable());
+        
+        // Permission errors (not retryable) 
+        let private_err = DownloadManager::classify_ytdlp_error("private video not available", Some(2));
+        assert!(!private_err.is_retryable());
+        
+        // Disk space errors
+        let disk_err = DownloadManager::classify_ytdlp_error("no space left on device", None);
+        assert!(!disk_err.is_retryable()); // Disk space errors shouldn't be retried immediately
+    }
+    
+    #[test]  
+    fn test_ffmpeg_error_classification() {
+        // Temporary errors (retryable)
+        let temp_err = ConversionManager::classify_ffmpeg_error("resource temporarily unavailable", Some(1));
+        assert!(temp_err.is_retryable());
+        
+        // Permission errors (not retryable)
+        let perm_err = ConversionManager::classify_ffmpeg_error("permission denied", Some(126));
+        assert!(!perm_err.is_retryable());
+        
+        // Invalid format errors (not retryable)
+        let format_err = ConversionManager::classify_ffmpeg_error("unsupported codec", Some(255));
+        assert!(!format_err.is_retryable());
+    }
+    
+    #[test]
+    fn test_retry_config() {
+        let config = RetryConfig::for_network();
+        assert_eq!(config.max_attempts, 5);
+        assert_eq!(config.base_delay, 3);
+        
+        // Test exponential backoff
+        assert_eq!(config.calculate_delay(1), 3);  // Base delay for first retry
+        assert_eq!(config.calculate_delay(2), 6);  // 2x base delay 
+        assert_eq!(config.calculate_delay(3), 12); // 4x base delay
+        assert!(config.calculate_delay(10) <= config.max_delay); // Capped at max
+    }
+    
+    #[tokio::test]
+    async fn test_retry_mechanism() {
+        let mut call_count = 0;
+        let config = RetryConfig {
+            max_attempts: 3,
+            base_delay: 0, // No delay for testing
+            max_delay: 0,
+            exponential_backoff: false,
+        };
+        
+        // Test successful retry
+        let result = retry_async(config.clone(), || {
+            call_count += 1;
+            async move {
+                if call_count < 3 {
+                    Err(MediaForgeError::NetworkError("Temporary failure".to_string()))
+                } else {
+                    Ok("Success".to_string())
+                }
+            }
+        }, |_, _, _| {}).await;
+        
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), "Success");
+        assert_eq!(call_count, 3);
+        
+        // Test non-retryable error  
+        let result2: Result<String, MediaForgeError> = retry_async(config, || async {
+            Err(MediaForgeError::InvalidUrl("Bad URL".to_string()))
+        }, |_, _, _| {}).await;
+        
+        assert!(result2.is_err());
+        // Should fail immediately without retries for non-retryable errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_util::sync::CancellationToken;
+
+    fn args_to_strings(args: Vec<OsString>) -> Vec<String> {
+        args.into_iter().map(|a| a.to_string_lossy().to_string()).collect()
+    }
+
+    fn audio_settings() -> AudioSettings {
+        AudioSettings {
+            bitrate: None,
+            sample_rate: None,
+            channels: None,
+            volume: None,
+            metadata: None,
+            cover_art: None,
+            bit_depth: None,
+            compression_level: None,
+            strip_metadata: false,
+            fade_in_secs: None,
+            fade_out_secs: None,
+            trim_silence: false,
+            prevent_upscale: false,
+        }
+    }
+
+    fn image_settings() -> ImageSettings {
+        ImageSettings {
+            quality: None,
+            resize: None,
+            crop: None,
+            rotate: None,
+            flip: None,
+            watermark: None,
+            strip_metadata: false,
+            effort: None,
+            density: None,
+            animation: None,
+            effects: None,
+            background: None,
+            dpi: None,
+            optimize: false,
+            colorspace: None,
+            strip_icc: false,
+            keep_icc: false,
+        }
+    }
+
+    fn watermark(position: &str) -> WatermarkSettings {
+        WatermarkSettings {
+            path: PathBuf::from("/tmp/logo.png"),
+            position: position.to_string(),
+            opacity: 0.5,
+            scale: Some(0.5),
+        }
+    }
+
+    #[test]
+    fn test_watermark_filter_complex() {
+        assert_eq!(
+            watermark_filter_complex(&watermark("southeast"), &[]),
+            "[1:v]format=rgba,colorchannelmixer=aa=0.5,scale=iw*0.5:-1[wm];[0:v][wm]overlay=W-w-10:H-h-10"
+        );
+        let unscaled = WatermarkSettings { scale: None, ..watermark("center") };
+        assert_eq!(
+            watermark_filter_complex(&unscaled, &[]),
+            "[1:v]format=rgba,colorchannelmixer=aa=0.5[wm];[0:v][wm]overlay=(W-w)/2:(H-h)/2"
+        );
+    }
+
+    #[test]
+    fn test_build_video_args_with_watermark() {
+        let settings = VideoSettings {
+            resolution: Some("1280x720".to_string()),
+            bitrate: Some("2M".to_string()),
+            watermark: Some(watermark("northwest")),
+            ..Default::default()
+        };
+        let args = args_to_strings(build_video_args(
+            Path::new("/tmp/in.mp4"),
+            Path::new("/tmp/out.mp4"),
+            Some(&settings),
+            None,
+            None,
+        ));
+        assert_eq!(
+            args,
+            vec![
+                "-i", "/tmp/in.mp4",
+                "-i", "/tmp/logo.png",
+                "-filter_complex", "[1:v]format=rgba,colorchannelmixer=aa=0.5,scale=iw*0.5:-1[wm];[0:v][wm]overlay=10:10",
+                "-map_metadata", "0", "-map_chapters", "0",
+                "-s", "1280x720",
+                "-b:v", "2M",
+                "-progress", "pipe:1",
+                "-y", "/tmp/out.mp4",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_video_args_fps() {
+        let settings = VideoSettings { fps: Some(30.0), ..Default::default() };
+        assert_eq!(
+            args_to_strings(build_video_args(Path::new("/tmp/in.mp4"), Path::new("/tmp/out.mp4"), Some(&settings), None, None)),
+            vec!["-i", "/tmp/in.mp4", "-vf", "fps=30", "-map_metadata", "0", "-map_chapters", "0", "-progress", "pipe:1", "-y", "/tmp/out.mp4"]
+        );
+
+        assert!(validate_video_settings(&VideoSettings { fps: Some(23.976), ..Default::default() }).is_ok());
+        assert!(validate_video_settings(&VideoSettings { fps: Some(0.0), ..Default::default() }).is_err());
+        assert!(validate_video_settings(&VideoSettings { fps: Some(241.0), ..Default::default() }).is_err());
+        assert!(validate_video_settings(&VideoSettings { fps: Some(f32::NAN), ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn test_build_video_args_deinterlace() {
+        // yadif runs before the frame rate change; -s scales the filtered frames last
+        let settings = VideoSettings {
+            deinterlace: true,
+            fps: Some(25.0),
+            resolution: Some("1280x720".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            args_to_strings(build_video_args(Path::new("/tmp/in.mp4"), Path::new("/tmp/out.mp4"), Some(&settings), None, None)),
+            vec!["-i", "/tmp/in.mp4", "-vf", "yadif,fps=25", "-map_metadata", "0", "-map_chapters", "0", "-s", "1280x720", "-progress", "pipe:1", "-y", "/tmp/out.mp4"]
+        );
+
+        let settings = VideoSettings { deinterlace: true, copy_streams: true, ..Default::default() };
+        assert!(validate_video_settings(&settings).is_err());
+    }
+
+    #[test]
+    fn test_build_video_args_audio_mode() {
+        let run = |settings: &VideoSettings| {
+            args_to_strings(build_video_args(Path::new("/tmp/in.mp4"), Path::new("/tmp/out.mp4"), Some(settings), None, None))
+        };
+
+        let mute = VideoSettings { audio_mode: Some("remove".to_string()), copy_streams: true, ..Default::default() };
+        assert_eq!(run(&mute), vec!["-i", "/tmp/in.mp4", "-c", "copy", "-an", "-map_metadata", "0", "-map_chapters", "0", "-progress", "pipe:1", "-y", "/tmp/out.mp4"]);
+
+        let replace = VideoSettings {
+            audio_mode: Some("replace".to_string()),
+            replacement_audio: Some(PathBuf::from("/tmp/song.mp3")),
+            shortest: true,
+            copy_streams: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            run(&replace),
+            vec![
+                "-i", "/tmp/in.mp4", "-i", "/tmp/song.mp3", "-c:v", "copy",
+                "-map", "0:v", "-map", "1:a:0", "-shortest",
+                "-map_metadata", "0", "-map_chapters", "0",
+                "-progress", "pipe:1", "-y", "/tmp/out.mp4",
+            ]
+        );
+
+        // The watermark takes input 1, so the sound track moves to input 2
+        let watermarked = VideoSettings {
+            watermark: Some(WatermarkSettings { scale: None, ..watermark("southeast") }),
+            copy_streams: false,
+            shortest: false,
+            ..replace
+        };
+        assert_eq!(
+            run(&watermarked),
+            vec![
+                "-i", "/tmp/in.mp4", "-i", "/tmp/logo.png", "-i", "/tmp/song.mp3",
+                "-filter_complex", "[1:v]format=rgba,colorchannelmixer=aa=0.5[wm];[0:v][wm]overlay=W-w-10:H-h-10[vout]",
+                "-map", "[vout]", "-map", "2:a:0",
+                "-map_metadata", "0", "-map_chapters", "0",
+                "-progress", "pipe:1", "-y", "/tmp/out.mp4",
+            ]
+        );
+
+        let invalid = |audio_mode: Option<&str>, replacement: Option<&str>| {
+            validate_audio_mode(&VideoSettings {
+                audio_mode: audio_mode.map(str::to_string),
+                replacement_audio: replacement.map(PathBuf::from),
+                ..Default::default()
+            })
+            .is_err()
+        };
+        assert!(!invalid(None, None));
+        assert!(!invalid(Some("remove"), None));
+        assert!(invalid(Some("mute"), None));
+        assert!(invalid(Some("replace"), None));
+        assert!(invalid(Some("keep"), Some("/tmp/song.mp3")));
+    }
+
+    #[test]
+    fn test_build_video_args_tonemap() {
+        let settings = VideoSettings {
+            deinterlace: true,
+            tonemap_sdr: Some(true),
+            fps: Some(30.0),
+            ..Default::default()
+        };
+        let args = args_to_strings(build_video_args(Path::new("/tmp/in.mov"), Path::new("/tmp/out.mp4"), Some(&settings), None, None));
+        assert_eq!(
+            args[3],
+            "yadif,zscale=t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,\
+             tonemap=tonemap=hable:desat=0,zscale=t=bt709:m=bt709:r=tv,format=yuv420p,fps=30"
+        );
+
+        let mut hdr = probe_with(&[("video", "hevc"), ("audio", "aac")]);
+        assert!(!needs_tonemap(&hdr, None));
+        hdr.streams[0].hdr = true;
+        assert!(needs_tonemap(&hdr, None));
+        assert!(!needs_tonemap(&hdr, Some(&VideoSettings { tonemap_sdr: Some(false), ..Default::default() })));
+        assert!(!needs_tonemap(&hdr, Some(&VideoSettings { copy_streams: true, ..Default::default() })));
+
+        let filters = " ... yadif             V->V       Deinterlace the input image.\n \
+                       ... zscale            V->V       Apply resizing, colorspace and bit depth conversion.\n";
+        assert!(ffmpeg_list_contains(filters, "zscale"));
+        assert!(!ffmpeg_list_contains(filters, "tonemap"));
+    }
+
+    #[test]
+    fn test_stabilize_args() {
+        let settings = VideoSettings { stabilize: true, deinterlace: true, fps: Some(30.0), ..Default::default() };
+        let transforms = Path::new("/tmp/mediaforge-encode-1/transforms.trf");
+        assert_eq!(
+            args_to_strings(build_stabilize_detect_args(Path::new("/tmp/shaky.mp4"), transforms, Some(&settings))),
+            vec![
+                "-i", "/tmp/shaky.mp4", "-vf", "yadif,vidstabdetect=shakiness=5:result=/tmp/mediaforge-encode-1/transforms.trf",
+                "-an", "-f", "null", "-progress", "pipe:1", "-y", "-",
+            ]
+        );
+        let args = args_to_strings(build_video_args(
+            Path::new("/tmp/shaky.mp4"),
+            Path::new("/tmp/out.mp4"),
+            Some(&settings),
+            None,
+            Some(transforms),
+        ));
+        assert_eq!(
+            args[..4],
+            [
+                "-i", "/tmp/shaky.mp4", "-vf",
+                "yadif,vidstabtransform=input=/tmp/mediaforge-encode-1/transforms.trf:smoothing=10,fps=30",
+            ]
+        );
+
+        // Windows paths have a drive colon and backslashes, special at both levels
+        assert_eq!(escape_filter_value(r"C:\Temp\t.trf"), r"C\\:\\\\Temp\\\\t.trf");
+        assert_eq!(escape_filter_value("it's,odd"), r"it\\\'s\,odd");
+
+        let copying = VideoSettings { stabilize: true, copy_streams: true, ..Default::default() };
+        assert!(validate_video_settings(&copying).is_err());
+    }
+
+    #[test]
+    fn test_h264_profile_and_keyframes() {
+        let run = |settings: &VideoSettings| {
+            args_to_strings(build_video_args(Path::new("/tmp/in.mov"), Path::new("/tmp/out.mp4"), Some(settings), None, None))
+        };
+        // A YouTube upload: a keyframe every two seconds at 30 fps
+        let youtube = VideoSettings {
+            resolution: Some("1920x1080".to_string()),
+            bitrate: Some("8M".to_string()),
+            keyframe_interval: Some(60),
+            profile: Some("high".to_string()),
+            level: Some("4.2".to_string()),
+            ..Default::default()
+        };
+        assert!(validate_video_settings(&youtube).is_ok());
+        assert_eq!(
+            run(&youtube)[6..],
+            [
+                "-s", "1920x1080", "-b:v", "8M", "-g", "60", "-c:v", "libx264", "-profile:v", "high",
+                "-pix_fmt", "yuv420p", "-level:v", "4.2", "-progress", "pipe:1", "-y", "/tmp/out.mp4",
+            ]
+        );
+
+        // An old TV's player: baseline at standard definition
+        let old_tv = VideoSettings {
+            resolution: Some("720x576".to_string()),
+            keyframe_interval: Some(25),
+            profile: Some("baseline".to_string()),
+            level: Some("3.0".to_string()),
+            ..Default::default()
+        };
+        assert!(validate_video_settings(&old_tv).is_ok());
+        assert_eq!(
+            run(&old_tv)[6..],
+            [
+                "-s", "720x576", "-g", "25", "-c:v", "libx264", "-profile:v", "baseline",
+                "-pix_fmt", "yuv420p", "-level:v", "3", "-progress", "pipe:1", "-y", "/tmp/out.mp4",
+            ]
+        );
+
+        let ten_bit = VideoSettings { profile: Some("high10".to_string()), ..Default::default() };
+        assert!(run(&ten_bit).windows(2).any(|w| w == ["-pix_fmt", "yuv420p10le"]));
+        assert!(validate_video_settings(&VideoSettings { profile: Some("extended".to_string()), ..Default::default() }).is_err());
+        assert!(validate_video_settings(&VideoSettings { level: Some("7".to_string()), ..Default::default() }).is_err());
+        assert!(validate_video_settings(&VideoSettings { keyframe_interval: Some(0), ..Default::default() }).is_err());
+        let copying = VideoSettings { copy_streams: true, keyframe_interval: Some(60), ..Default::default() };
+        assert!(validate_video_settings(&copying).is_err());
+    }
+
+    #[test]
+    fn test_h265_profile_and_level() {
+        let run = |settings: &VideoSettings| {
+            args_to_strings(build_video_args(Path::new("/tmp/in.mov"), Path::new("/tmp/out.mkv"), Some(settings), None, None))
+        };
+        // A 10-bit HDR master for a 4K TV
+        let hdr = VideoSettings {
+            codec: Some("h265".to_string()),
+            profile: Some("main10".to_string()),
+            level: Some("5.1".to_string()),
+            ..Default::default()
+        };
+        assert!(validate_video_settings(&hdr).is_ok());
+        assert_eq!(
+            run(&hdr)[6..],
+            [
+                "-c:v", "libx265", "-profile:v", "main10", "-pix_fmt", "yuv420p10le",
+                "-x265-params", "level-idc=5.1", "-progress", "pipe:1", "-y", "/tmp/out.mkv",
+            ]
+        );
+
+        // The codec alone picks the encoder; "4.0" is read as "4"
+        let plain = VideoSettings { codec: Some("HEVC".to_string()), level: Some("4.0".to_string()), ..Default::default() };
+        assert!(validate_video_settings(&plain).is_ok());
+        assert_eq!(run(&plain)[6..10], ["-c:v", "libx265", "-x265-params", "level-idc=4"]);
+
+        let h265 = |profile: Option<&str>, level: Option<&str>| VideoSettings {
+            codec: Some("h265".to_string()),
+            profile: profile.map(str::to_string),
+            level: level.map(str::to_string),
+            ..Default::default()
+        };
+        // H.264-only profiles and levels
+        assert!(validate_video_settings(&h265(Some("high"), None)).is_err());
+        assert!(validate_video_settings(&h265(None, Some("1b"))).is_err());
+        assert!(validate_video_settings(&h265(Some("main444-10"), Some("6.2"))).is_ok());
+        assert!(validate_video_settings(&VideoSettings { codec: Some("vp9".to_string()), ..Default::default() }).is_err());
+        assert!(validate_video_settings(&VideoSettings { target_size_mb: Some(25), ..h265(None, None) }).is_err());
+        assert!(validate_video_settings(&VideoSettings { copy_streams: true, ..h265(None, None) }).is_err());
+    }
+
+    #[test]
+    fn test_animated_image_output() {
+        let settings = VideoSettings {
+            fps: Some(12.0),
+            resolution: Some("480x270".to_string()),
+            preserve_metadata: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(
+            args_to_strings(build_video_args(Path::new("/tmp/clip.mp4"), Path::new("/tmp/clip.webp"), Some(&settings), None, None)),
+            vec![
+                "-i", "/tmp/clip.mp4", "-vf", "fps=12", "-map_metadata", "-1", "-map_chapters", "-1", "-s", "480x270",
+                "-an", "-c:v", "libwebp", "-loop", "0", "-progress", "pipe:1", "-y", "/tmp/clip.webp",
+            ]
+        );
+        let muted = VideoSettings { audio_mode: Some("remove".to_string()), ..Default::default() };
+        let args = args_to_strings(build_video_args(Path::new("/tmp/clip.mp4"), Path::new("/tmp/clip.AVIF"), Some(&muted), None, None));
+        assert_eq!(args.iter().filter(|a| *a == "-an").count(), 1);
+        assert!(args.windows(2).any(|w| w == ["-c:v", "libaom-av1"]));
+
+        assert_eq!(animated_output_encoder("GIF"), Some("gif"));
+        assert_eq!(animated_output_encoder("mp4"), None);
+        let copying = VideoSettings { copy_streams: true, ..Default::default() };
+        assert!(validate_animated_output("webp", &copying).is_err());
+        assert!(validate_animated_output("mkv", &copying).is_ok());
+        let replacing = VideoSettings { audio_mode: Some("replace".to_string()), ..Default::default() };
+        assert!(validate_animated_output("gif", &replacing).is_err());
+
+        let encoders = " V....D libaom-av1           libaom AV1 (codec av1)\n V....D libwebp_anim         libwebp WebP image (codec webp)\n";
+        assert!(ffmpeg_list_contains(encoders, "libaom-av1"));
+        assert!(!ffmpeg_list_contains(encoders, "libwebp"));
+    }
+
+    #[test]
+    fn test_target_size_two_pass() {
+        // 25 MB over 100s: 2000 kbps in total, less 3% overhead and 128 kbps of audio
+        assert_eq!(target_video_kbps(25, 100.0, 128).unwrap(), 1812);
+        assert!(target_video_kbps(1, 600.0, 128).is_err());
+
+        let settings = VideoSettings { target_size_mb: Some(25), ..Default::default() };
+        let mut encode = TwoPassEncode {
+            pass: 1,
+            passlog: PathBuf::from("/tmp/passlog"),
+            codec: "libx264",
+            video_kbps: 1812,
+            audio_kbps: 128,
+        };
+        let run = |encode: &TwoPassEncode| {
+            args_to_strings(build_video_args(Path::new("/tmp/in.mp4"), Path::new("/tmp/out.mp4"), Some(&settings), Some(encode), None))
+        };
+        assert_eq!(
+            run(&encode)[6..],
+            [
+                "-c:v", "libx264", "-b:v", "1812k", "-pass", "1", "-passlogfile", "/tmp/passlog",
+                "-an", "-f", "null", "-progress", "pipe:1", "-y", "-",
+            ]
+        );
+        encode.pass = 2;
+        assert_eq!(
+            run(&encode)[6..],
+            [
+                "-c:v", "libx264", "-b:v", "1812k", "-pass", "2", "-passlogfile", "/tmp/passlog",
+                "-b:a", "128k", "-progress", "pipe:1", "-y", "/tmp/out.mp4",
+            ]
+        );
+
+        let with_bitrate = VideoSettings { bitrate: Some("2M".to_string()), ..settings.clone() };
+        assert!(validate_video_settings(&with_bitrate).is_err());
+        assert!(validate_video_settings(&VideoSettings { target_size_mb: Some(0), ..Default::default() }).is_err());
+        assert!(validate_video_settings(&settings).is_ok());
+        assert_eq!(two_pass_codec("WEBM"), Some("libvpx-vp9"));
+        assert_eq!(two_pass_codec("gif"), None);
+        assert_eq!(estimate_video_bytes(100.0, 1 << 30, Some(&settings)), 25_000_000);
+
+        assert_eq!(check_target_size(25, 26_000_000), None);
+        assert_eq!(check_target_size(25, 27_000_000).as_deref(), Some("Output is 27.0 MB, above the 25 MB target"));
+
+        // The temporary folder goes away with its guard
+        let temp_dir = EncodeTempDir::create(&Uuid::new_v4().to_string()).unwrap();
+        std::fs::write(temp_dir.passlog_prefix(), b"stats").unwrap();
+        let dir = temp_dir.0.clone();
+        drop(temp_dir);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_build_video_args_fps_with_watermark() {
+        // Both need the main stream's filter chain, so fps moves into the filter graph
+        let settings = VideoSettings {
+            fps: Some(30.0),
+            watermark: Some(WatermarkSettings { scale: None, ..watermark("southeast") }),
+            ..Default::default()
+        };
+        let args = args_to_strings(build_video_args(Path::new("/tmp/in.mp4"), Path::new("/tmp/out.mp4"), Some(&settings), None, None));
+        assert!(!args.contains(&"-vf".to_string()));
+        assert_eq!(
+            args,
+            vec![
+                "-i", "/tmp/in.mp4",
+                "-i", "/tmp/logo.png",
+                "-filter_complex", "[1:v]format=rgba,colorchannelmixer=aa=0.5[wm];[0:v]fps=30[base];[base][wm]overlay=W-w-10:H-h-10",
+                "-map_metadata", "0", "-map_chapters", "0",
+                "-progress", "pipe:1",
+                "-y", "/tmp/out.mp4",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_image_args_with_watermark() {
+        let settings = ImageSettings { watermark: Some(watermark("southeast")), ..image_settings() };
+        let args = args_to_strings(build_image_args(
+            Path::new("/tmp/in.png"),
+            Path::new("/tmp/out.png"),
+            "png",
+            Some(&settings),
+        ));
+        assert_eq!(
+            args,
+            vec![
+                "/tmp/in.png",
+                "(", "/tmp/logo.png", "-resize", "50%",
+                "-alpha", "set", "-channel", "A", "-evaluate", "multiply", "0.5", "+channel", ")",
+                "-gravity", "southeast", "-geometry", "+10+10", "-composite",
+                "/tmp/out.png",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_watermark_rejects_bad_values() {
+        // Position and opacity are checked before the file itself
+        assert!(validate_watermark(&watermark("top-left")).is_err());
+        assert!(validate_watermark(&WatermarkSettings { opacity: 1.5, ..watermark("center") }).is_err());
+        assert!(validate_watermark(&WatermarkSettings { opacity: f32::NAN, ..watermark("center") }).is_err());
+        assert!(validate_watermark(&WatermarkSettings { scale: Some(0.0), ..watermark("center") }).is_err());
+    }
+
+    #[test]
+    fn test_build_image_args_operation_order() {
+        let settings = ImageSettings {
+            quality: Some(85),
+            resize: Some("50%".to_string()),
+            crop: Some("800x600+10+20".to_string()),
+            rotate: Some(90),
+            flip: Some("horizontal".to_string()),
+            watermark: None,
+            strip_metadata: false,
+            effort: None,
+            density: None,
+            animation: None,
+            effects: Some(vec!["blur:1.5".to_string(), "grayscale".to_string()]),
+            background: None,
+            dpi: Some(300),
+            optimize: false,
+            colorspace: None,
+            strip_icc: false,
+            keep_icc: false,
+        };
+        let args = args_to_strings(build_image_args(
+            Path::new("/tmp/in.png"),
+            Path::new("/tmp/out.jpg"),
+            "jpg",
+            Some(&settings),
+        ));
+        assert_eq!(
+            args,
+            vec![
+                "/tmp/in.png",
+                "-crop", "800x600+10+20", "+repage",
+                "-rotate", "90",
+                "-flop",
+                "-resize", "50%",
+                "-colorspace", "Gray",
+                "-blur", "0x1.5",
+                "-background", "#ffffff", "-alpha", "remove", "-alpha", "off",
+                "-units", "PixelsPerInch", "-density", "300",
+                "-quality", "85",
+                "/tmp/out.jpg",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_image_args_ico_sizes() {
+        let args = build_image_args(Path::new("/tmp/logo.png"), Path::new("/tmp/logo.ico"), "ico", None);
+        assert_eq!(
+            args_to_strings(args.clone()),
+            vec![
+                "/tmp/logo.png",
+                "-resize", "256x256", "-background", "none", "-gravity", "center", "-extent", "256x256",
+                "-define", "icon:auto-resize=256,48,32,16",
+                "/tmp/logo.ico",
+            ]
+        );
+        // Without auto-resize the smaller sizes are written as extra frames
+        assert_eq!(
+            args_to_strings(legacy_ico_args(args))[9..],
+            [
+                "(", "-clone", "0", "-resize", "48x48", ")",
+                "(", "-clone", "0", "-resize", "32x32", ")",
+                "(", "-clone", "0", "-resize", "16x16", ")",
+                "/tmp/logo.ico",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_image_args_flattens_transparency() {
+        let flatten_args = |format: &str, background: Option<&str>| {
+            let settings = ImageSettings { background: background.map(String::from), ..image_settings() };
+            let args = args_to_strings(build_image_args(
+                Path::new("/tmp/logo.png"),
+                Path::new(&format!("/tmp/logo.{}", format)),
+                format,
+                Some(&settings),
+            ));
+            args.windows(6)
+                .find(|w| w[0] == "-background")
+                .map(|w| w.join(" "))
+        };
+
+        assert_eq!(flatten_args("jpg", None).as_deref(), Some("-background #ffffff -alpha remove -alpha off"));
+        assert_eq!(flatten_args("JPEG", Some("#000")).as_deref(), Some("-background #000 -alpha remove -alpha off"));
+        assert_eq!(flatten_args("webp", Some("#000000")), None);
+        assert_eq!(flatten_args("png", None), None);
+
+        assert!(validate_image_settings(&ImageSettings { background: Some("#1a2B3c".to_string()), ..image_settings() }).is_ok());
+        for color in ["white", "#12345", "#ffffff; rm", "ffffff", "#gggggg"] {
+            assert!(
+                validate_image_settings(&ImageSettings { background: Some(color.to_string()), ..image_settings() }).is_err(),
+                "{} should be rejected", color
+            );
+        }
+    }
 
-            // Set task to Processing status BEFORE spawning to prevent race condition
-            self.update_task(&task_id, |task| {
-                task.status = TaskStatus::Processing;
-            });
+    #[test]
+    fn test_parse_image_effects() {
+        let effects = |list: &[&str]| parse_image_effects(&list.iter().map(|e| e.to_string()).collect::<Vec<_>>());
 
-            let manager = self.clone();
-            let req = request.clone();
-            let input_file = input_file.clone();
-            let app_handle = app_handle.clone();
-            let app_handle_clone2 = app_handle.clone();
-            let task_id_clone = task_id.clone();
-            
-            // Create cancellation token for this task
-            let cancellation_token = CancellationToken::new();
-            let cancellation_token_clone = cancellation_token.clone();
+        assert_eq!(
+            effects(&["sharpen", "sepia", "blur:2", "sepia"]).unwrap(),
+            vec![ImageEffect::Sepia, ImageEffect::Sharpen, ImageEffect::Blur(2.0)]
+        );
+        assert_eq!(effects(&["blur:0.5"]).unwrap()[0].magick_args(), vec!["-blur", "0x0.5"]);
 
-            let join_handle = tokio::spawn(async move {
-                info!(
-                    task_id = task_id_clone,
-                    input_file = %input_file.display(),
-                    "Spawned conversion task worker"
-                );
-                
-                // Run the conversion with timeout and cancellation support
-                let result = tokio::select! {
-                    result = manager.convert_single_cancellable(&task_id_clone, &input_file, &req, app_handle.clone(), cancellation_token_clone.clone()) => {
-                        result
-                    }
-                    _ = cancellation_token_clone.cancelled() => {
-                        info!(
-                            task_id = task_id_clone,
-                            input_file = %input_file.display(),
-                            "Conversion task was cancelled by user"
-                        );
-                        manager.update_task(&task_id_clone, |task| {
-                            task.status = TaskStatus::Cancelled;
-                            task.error = Some("Task was cancelled by user".to_string());
-                        });
-                        // Clean up task handle on cancellation
-                        manager.task_handles.remove(&task_id_clone);
-                        return;
-                    }
-                    _ = tokio::time::sleep(Duration::from_secs(7200)) => { // 2 hour timeout for conversions
-                        warn!(
-                            task_id = task_id_clone,
-                            input_file = %input_file.display(),
-                            timeout_seconds = 7200,
-                            "Conversion task timed out"
-                        );
-                        manager.update_task(&task_id_clone, |task| {
-                            task.status = TaskStatus::Failed;
-                            task.error = Some("Conversion timed out after 2 hours".to_string());
-                        });
-                        // Clean up task handle on timeout
-                        manager.task_handles.remove(&task_id_clone);
-                        return;
-                    }
-                };
-                
-                if let Err(e) = result {
-                    error!(
-                        task_id = task_id_clone,
-                        input_file = %input_file.display(),
-                        error = %e,
-                        error_type = std::any::type_name_of_val(&e),
-                        "Conversion task failed with error"
-                    );
-                    manager.update_task(&task_id_clone, |task| {
-                        task.status = TaskStatus::Failed;
-                        task.error = Some(e.to_string());
-                    });
-                    // Clean up task handle on error
-                    manager.task_handles.remove(&task_id_clone);
-                }
-                
-                // Emit final task update
-                let _ = app_handle_clone2.emit("task-update", manager.get_task(&task_id_clone));
-            });
-            
-            // Store the task handle for cancellation
-            let task_handle = TaskHandle::new(join_handle, cancellation_token);
-            self.task_handles.insert(task_id.clone(), task_handle);
+        for radius in ["blur:0", "blur:101", "blur:", "blur:-1", "blur:inf", "blur:1e2", "blur:2 -write x"] {
+            assert!(effects(&[radius]).is_err(), "{} should be rejected", radius);
         }
+        let err = effects(&["posterize"]).unwrap_err().to_string();
+        assert!(err.contains("grayscale, sepia, negate, auto-level, sharpen, blur:<radius>"), "{}", err);
+        assert!(validate_image_settings(&ImageSettings {
+            effects: Some(vec!["Grayscale".to_string()]),
+            ..image_settings()
+        }).is_err());
+    }
 
-        Ok(task_ids)
+    #[test]
+    fn test_validate_image_settings_edits() {
+        assert!(validate_image_settings(&image_settings()).is_ok());
+        assert!(validate_image_settings(&ImageSettings { crop: Some("800x600".to_string()), ..image_settings() }).is_ok());
+        assert!(validate_image_settings(&ImageSettings { crop: Some("800x600-10+20".to_string()), ..image_settings() }).is_ok());
+        assert!(validate_image_settings(&ImageSettings { crop: Some("800x600 -write /tmp/x".to_string()), ..image_settings() }).is_err());
+        assert!(validate_image_settings(&ImageSettings { crop: Some("-write".to_string()), ..image_settings() }).is_err());
+        assert!(validate_image_settings(&ImageSettings { rotate: Some(-360), ..image_settings() }).is_ok());
+        assert!(validate_image_settings(&ImageSettings { rotate: Some(361), ..image_settings() }).is_err());
+        assert!(validate_image_settings(&ImageSettings { flip: Some("vertical".to_string()), ..image_settings() }).is_ok());
+        assert!(validate_image_settings(&ImageSettings { flip: Some("diagonal".to_string()), ..image_settings() }).is_err());
     }
 
-    async fn convert_single(
-        &self,
-        task_id: &str,
-        input_file: &PathBuf,
-        request: &ConvertRequest,
-        app_handle: tauri::AppHandle,
-    ) -> Result<(), MediaForgeError> {
-        self.update_task(task_id, |task| {
-            task.status = TaskStatus::Processing;
-        });
+    #[test]
+    fn test_validate_resize_geometry() {
+        for resize in ["50%", "12.5%", "800", "x600", "800x600", "800x600!", "1920x1920>", "100x100^"] {
+            assert!(validate_resize_geometry(resize).is_ok(), "{:?} should be accepted", resize);
+        }
+        for resize in [
+            "", "0x600", "800x", "800x600!!", "-800x600", "800x600 -write /tmp/x", " 800x600", "800x600\n",
+            "800 x 600", "800×600", "８００x600", "50 %", "0%", "abc",
+        ] {
+            assert!(validate_resize_geometry(resize).is_err(), "{:?} should be rejected", resize);
+        }
+        let err = validate_image_settings(&ImageSettings { resize: Some("720p".to_string()), ..image_settings() }).unwrap_err();
+        assert!(err.to_string().contains("resize"));
+    }
 
-        match request.conversion_type {
-            ConversionType::Image => {
-                self.convert_image(task_id, input_file, request, app_handle).await
-            }
-            ConversionType::Video => {
-                self.convert_video(task_id, input_file, request, app_handle).await
-            }
-            ConversionType::Audio => {
-                self.convert_audio(task_id, input_file, request, app_handle).await
-            }
+    #[test]
+    fn test_validate_resolution_and_bitrate() {
+        assert!(validate_resolution("1280x720").is_ok());
+        assert!(validate_resolution("7680x4320").is_ok());
+        for resolution in ["720", "1280X720", "1280x720 ", "1280×720", "١٢٨٠x720", "8x8", "99999x720", "1280x720,scale"] {
+            assert!(validate_resolution(resolution).is_err(), "{:?} should be rejected", resolution);
+        }
+
+        for bitrate in ["2500k", "5M", "800000"] {
+            assert!(validate_video_bitrate(bitrate).is_ok(), "{:?} should be accepted", bitrate);
         }
+        for bitrate in ["5 Mbps", "5Mb", "", "0k", " 5M", "5M\t", "５M", "-5M", "5M -y"] {
+            assert!(validate_video_bitrate(bitrate).is_err(), "{:?} should be rejected", bitrate);
+        }
+
+        let settings = VideoSettings { bitrate: Some("5 Mbps".to_string()), ..Default::default() };
+        assert!(validate_video_settings(&settings).unwrap_err().to_string().contains("video bitrate"));
+        let kept = VideoSettings {
+            resolution: Some("Keep Original".to_string()),
+            bitrate: Some("Keep Original".to_string()),
+            ..Default::default()
+        };
+        assert!(validate_video_settings(&kept).is_ok());
+
+        let audio = AudioSettings { bitrate: Some("192k".to_string()), ..Default::default() };
+        assert!(validate_audio_settings(&audio, "mp3").unwrap_err().to_string().contains("audio bitrate"));
     }
 
-    async fn convert_single_cancellable(
-        &self,
-        task_id: &str,
-        input_file: &PathBuf,
-        request: &ConvertRequest,
-        app_handle: tauri::AppHandle,
-        cancellation_token: CancellationToken,
-    ) -> Result<(), MediaForgeError> {
-        // Task status is already set to Processing before spawn to prevent race condition
-        
-        match request.conversion_type {
-            ConversionType::Image => {
-                self.convert_image_cancellable(task_id, input_file, request, app_handle, cancellation_token).await
-            }
-            ConversionType::Video => {
-                self.convert_video_cancellable(task_id, input_file, request, app_handle, cancellation_token).await
-            }
-            ConversionType::Audio => {
-                self.convert_audio_cancellable(task_id, input_file, request, app_handle, cancellation_token).await
-            }
+    fn ffmpeg_available() -> bool {
+        std::process::Command::new("ffmpeg")
+            .arg("-version")
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    }
+
+    fn magick_available() -> bool {
+        std::process::Command::new("magick")
+            .arg("-version")
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    }
+
+    /// A request with every optional setting left unset, for tests to fill in with `..`
+    fn test_request(conversion_type: ConversionType, output_format: &str) -> ConvertRequest {
+        ConvertRequest {
+            input_files: Vec::new(),
+            conversion_type,
+            output_format: output_format.to_string(),
+            output_path: "/tmp".to_string(),
+            output_mode: OutputMode::SingleDirectory,
+            video_settings: None,
+            audio_settings: None,
+            image_settings: None,
+            timeout_secs: None,
+            filename_template: None,
+            conflict_policy: None,
+            verify_output: None,
+            preset: None,
+            auto_formats: None,
+            delete_source_on_success: false,
+            post_process_command: None,
+            post_process_ignore_failure: false,
         }
     }
 
-    async fn convert_image(
-        &self,
-        task_id: &str,
-        input_file: &PathBuf,
-        request: &ConvertRequest,
-        app_handle: tauri::AppHandle,
-    ) -> Result<(), MediaForgeError> {
-        // Re-validate inputs (defensive programming)
-        validate_input_file(input_file)?;
-        validate_image_format(input_file, &request.output_format)?;
-        
-        let file_stem = input_file
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .ok_or_else(|| MediaForgeError::InvalidSettings("Invalid input filename".to_string()))?;
+    #[test]
+    fn test_build_audio_args_combined() {
+        let settings = AudioSettings {
+            bitrate: Some("192".to_string()),
+            sample_rate: Some("44100".to_string()),
+            channels: Some(1),
+            volume: Some(1.5),
+            metadata: None,
+            cover_art: None,
+            bit_depth: None,
+            compression_level: None,
+            strip_metadata: false,
+            fade_in_secs: None,
+            fade_out_secs: None,
+            trim_silence: false,
+            prevent_upscale: false,
+        };
+        let args = build_audio_args(
+            Path::new("/tmp/in.wav"),
+            Path::new("/tmp/out.mp3"),
+            "mp3",
+            Some(&settings),
+            None,
+            None,
+        );
+        assert_eq!(
+            args_to_strings(args),
+            vec![
+                "-i", "/tmp/in.wav",
+                "-c:a", "libmp3lame",
+                "-b:a", "192k",
+                "-ar", "44100",
+                "-ac", "1",
+                "-af", "volume=1.5",
+                "-vn", "-progress", "pipe:1", "-y", "/tmp/out.mp3",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_audio_settings_ranges() {
+        assert!(validate_audio_settings(&audio_settings(), "mp3").is_ok());
+        assert!(validate_audio_settings(&AudioSettings { channels: Some(2), volume: Some(1.0), ..audio_settings() }, "mp3").is_ok());
+        assert!(validate_audio_settings(&AudioSettings { channels: Some(0), ..audio_settings() }, "mp3").is_err());
+        assert!(validate_audio_settings(&AudioSettings { channels: Some(6), ..audio_settings() }, "mp3").is_err());
+        assert!(validate_audio_settings(&AudioSettings { volume: Some(f32::NAN), ..audio_settings() }, "mp3").is_err());
+        assert!(validate_audio_settings(&AudioSettings { volume: Some(-1.0), ..audio_settings() }, "mp3").is_err());
+        assert!(validate_audio_settings(&AudioSettings { volume: Some(10.5), ..audio_settings() }, "mp3").is_err());
+    }
+
+    #[test]
+    fn test_audio_fades() {
+        let settings = AudioSettings {
+            volume: Some(1.5),
+            fade_in_secs: Some(2.0),
+            fade_out_secs: Some(3.5),
+            ..audio_settings()
+        };
+        let args = args_to_strings(build_audio_args(
+            Path::new("/tmp/in.wav"),
+            Path::new("/tmp/out.mp3"),
+            "mp3",
+            Some(&settings),
+            Some(30.25),
+            None,
+        ));
+        assert!(args
+            .windows(2)
+            .any(|w| w == ["-af", "volume=1.5,afade=t=in:st=0:d=2,afade=t=out:st=26.75:d=3.5"]));
+
+        // Without a duration the fade-out can't be placed
+        assert_eq!(audio_filters(&settings, None, None), vec!["volume=1.5", "afade=t=in:st=0:d=2"]);
+
+        // Silence trimming shortens the clip the fades are placed on
+        assert_eq!(
+            audio_filters(&settings, Some(20.0), Some((1.5, 21.5))),
+            vec![
+                "atrim=start=1.500:end=21.500", "asetpts=PTS-STARTPTS",
+                "volume=1.5", "afade=t=in:st=0:d=2", "afade=t=out:st=16.5:d=3.5",
+            ]
+        );
+
+        assert!(validate_fade_lengths(&settings, 30.25).is_ok());
+        assert!(validate_fade_lengths(&settings, 3.0).is_err());
+        assert!(validate_audio_settings(&AudioSettings { fade_in_secs: Some(-1.0), ..audio_settings() }, "mp3").is_err());
+        assert!(validate_audio_settings(&AudioSettings { fade_out_secs: Some(f32::INFINITY), ..audio_settings() }, "mp3").is_err());
+    }
+
+    #[test]
+    fn test_parse_silence_trim() {
+        let log = "[silencedetect @ 0x55d1] silence_start: 0\n\
+                   [silencedetect @ 0x55d1] silence_end: 1.52 | silence_duration: 1.52\n\
+                   size=N/A time=00:00:30.00 bitrate=N/A speed= 900x\n\
+                   [silencedetect @ 0x55d1] silence_start: 12.3\n\
+                   [silencedetect @ 0x55d1] silence_end: 13.1 | silence_duration: 0.8\n\
+                   [silencedetect @ 0x55d1] silence_start: 27.75\n";
+        assert_eq!(parse_silence_trim(log, 30.0), Some((1.52, 27.75)));
+
+        // Trailing silence reported with an end at the end of the clip
+        let log = "[silencedetect @ 0x1] silence_start: 28\n[silencedetect @ 0x1] silence_end: 30 | silence_duration: 2\n";
+        assert_eq!(parse_silence_trim(log, 30.0), Some((0.0, 28.0)));
 
-        // Use sanitized output path
-        let output_dir = sanitize_path(&request.output_path)?;
-        let output_path = output_dir.join(format!("{}.{}", file_stem, request.output_format));
+        // Only silence in the middle, or nothing but silence, leaves the clip alone
+        let log = "[silencedetect @ 0x1] silence_start: 10\n[silencedetect @ 0x1] silence_end: 11 | silence_duration: 1\n";
+        assert_eq!(parse_silence_trim(log, 30.0), None);
+        assert_eq!(parse_silence_trim("[silencedetect @ 0x1] silence_start: 0\n", 30.0), None);
+    }
 
-        // Use ImageMagick 7+ for image conversion (just 'magick', not 'magick convert')
-        let mut cmd = TokioCommand::new("magick");
-        cmd.arg(input_file);
+    #[test]
+    fn test_build_audio_args_metadata_and_cover() {
+        let settings = AudioSettings {
+            metadata: Some(AudioMetadata {
+                title: Some("Song; rm -rf /".to_string()),
+                artist: Some("Artist".to_string()),
+                ..Default::default()
+            }),
+            cover_art: Some(PathBuf::from("/tmp/cover.jpg")),
+            ..audio_settings()
+        };
+        let args = args_to_strings(build_audio_args(
+            Path::new("/tmp/in.wav"),
+            Path::new("/tmp/out.mp3"),
+            "mp3",
+            Some(&settings),
+            None,
+            None,
+        ));
+        assert_eq!(
+            args,
+            vec![
+                "-i", "/tmp/in.wav",
+                "-i", "/tmp/cover.jpg",
+                "-map", "0:a", "-map", "1:v", "-c:v", "copy", "-disposition:v", "attached_pic",
+                "-id3v2_version", "3",
+                "-c:a", "libmp3lame",
+                "-metadata", "title=Song; rm -rf /",
+                "-metadata", "artist=Artist",
+                "-progress", "pipe:1", "-y", "/tmp/out.mp3",
+            ]
+        );
+    }
 
-        // Special handling for ICO format - must resize to valid icon sizes
-        if request.output_format == "ico" {
-            // ICO format has size limits, resize to 256x256 (max standard size)
-            cmd.arg("-resize").arg("256x256");
-            log::info!("ICO format detected, resizing to 256x256");
+    #[test]
+    fn test_validate_audio_format() {
+        for format in ["mp3", "m4a", "aac", "flac", "ogg", "opus", "wav", "wma", "MP3"] {
+            assert!(validate_audio_format(format).is_ok(), "{} should be allowed", format);
         }
+        assert!(validate_audio_format("mp3 ").is_err());
+        assert!(validate_audio_format("exe").is_err());
+        assert!(validate_audio_format("").is_err());
+    }
 
-        // Apply image settings
-        if let Some(settings) = &request.image_settings {
-            if let Some(quality) = settings.quality {
-                cmd.arg("-quality").arg(quality.to_string());
-                log::info!("Applying image quality: {}", quality);
-            }
+    #[test]
+    fn test_audio_codec_args_lossless() {
+        let settings = AudioSettings { bit_depth: Some(24), compression_level: Some(8), ..audio_settings() };
+        assert_eq!(
+            audio_codec_args("flac", Some(&settings)),
+            vec!["-c:a", "flac", "-compression_level", "8", "-sample_fmt", "s32", "-bits_per_raw_sample", "24"]
+        );
+        assert_eq!(audio_codec_args("wav", Some(&settings)), vec!["-c:a", "pcm_s24le"]);
+        assert_eq!(audio_codec_args("wav", None), vec!["-c:a", "pcm_s16le"]);
+        assert_eq!(audio_codec_args("opus", None), vec!["-c:a", "libopus"]);
+    }
 
-            if let Some(resize) = &settings.resize {
-                // Skip resize if ICO format (already handled above)
-                if request.output_format != "ico" {
-                    log::info!("Applying image resize: {}", resize);
-                    cmd.arg("-resize").arg(resize);
-                }
-            }
+    #[test]
+    fn test_validate_audio_settings_lossless_options() {
+        let depth = AudioSettings { bit_depth: Some(24), ..audio_settings() };
+        assert!(validate_audio_settings(&depth, "flac").is_ok());
+        assert!(validate_audio_settings(&depth, "mp3").is_err());
+        assert!(validate_audio_settings(&AudioSettings { bit_depth: Some(20), ..audio_settings() }, "wav").is_err());
+        assert!(validate_audio_settings(&AudioSettings { compression_level: Some(13), ..audio_settings() }, "flac").is_err());
+        assert!(validate_audio_settings(&AudioSettings { compression_level: Some(5), ..audio_settings() }, "ogg").is_err());
+    }
+
+    #[test]
+    fn test_cover_art_rejected_for_unsupported_format() {
+        let settings = AudioSettings {
+            cover_art: Some(PathBuf::from("/tmp/cover.jpg")),
+            ..audio_settings()
+        };
+        assert!(validate_audio_settings(&settings, "wav").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_audio_tags_written_to_output() {
+        if !ffmpeg_available() {
+            return;
         }
+        let dir = std::env::temp_dir().join(format!("mediaforge-tags-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("in.wav");
+        let output = dir.join("out.mp3");
 
-        cmd.arg(&output_path);
+        let status = TokioCommand::new("ffmpeg")
+            .args(["-f", "lavfi", "-i", "sine=frequency=440:duration=1", "-y"])
+            .arg(&input)
+            .output()
+            .await
+            .unwrap()
+            .status;
+        assert!(status.success());
 
-        log::info!("Starting image conversion: {:?}", cmd);
-        log::info!("Output path: {:?}", output_path);
-        
-        let output = cmd.output().await.map_err(|e| {
-            log::error!("Failed to run ImageMagick: {}", e);
-            MediaForgeError::ConversionError(format!("Failed to run ImageMagick: {}", e))
-        })?;
+        let settings = AudioSettings {
+            metadata: Some(AudioMetadata {
+                title: Some("Test Title".to_string()),
+                artist: Some("Test Artist".to_string()),
+                album: Some("Test Album".to_string()),
+                ..Default::default()
+            }),
+            ..audio_settings()
+        };
+        let status = TokioCommand::new("ffmpeg")
+            .args(build_audio_args(&input, &output, "mp3", Some(&settings), None, None))
+            .output()
+            .await
+            .unwrap()
+            .status;
+        assert!(status.success());
 
-        if output.status.success() {
-            log::info!("Image conversion completed successfully: {:?}", output_path);
-            self.update_task(task_id, |task| {
-                task.status = TaskStatus::Completed;
-                task.progress = 100.0;
-                task.file_path = Some(output_path.to_string_lossy().to_string());
-            });
-            
-            // Send notification
-            if let Some(task) = self.get_task(task_id) {
-                notifications::send_conversion_complete_notification(&app_handle, &task.name);
+        let probe = TokioCommand::new("ffprobe")
+            .args(["-v", "quiet", "-print_format", "json", "-show_format"])
+            .arg(&output)
+            .output()
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&probe.stdout).unwrap();
+        let tags = &json["format"]["tags"];
+        assert_eq!(tags["title"], "Test Title");
+        assert_eq!(tags["artist"], "Test Artist");
+        assert_eq!(tags["album"], "Test Album");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    const FORMAT_LIST_WITH_HEIF: &str = "   Format  Module    Mode  Description
+-------------------------------------------------------------------------------
+     AVIF  HEIC      rw+   AV1 Image File Format (1.17.6)
+     HEIC  HEIC      rw+   High Efficiency Image Format (1.17.6)
+     HEIF  HEIC      rw+   High Efficiency Image Format (1.17.6)
+     JPEG* JPEG      rw-   Joint Photographic Experts Group JFIF format (80)
+      PNG* PNG       rw+   Portable Network Graphics (libpng 1.6.43)
+";
+
+    const FORMAT_LIST_READ_ONLY_HEIC: &str = "   Format  Module    Mode  Description
+-------------------------------------------------------------------------------
+     HEIC  HEIC      r--   High Efficiency Image Format (1.17.6)
+     JPEG* JPEG      rw-   Joint Photographic Experts Group JFIF format (80)
+      PNG* PNG       rw+   Portable Network Graphics (libpng 1.6.43)
+";
+
+    const FORMAT_LIST_WITHOUT_HEIF: &str = "   Format  Module    Mode  Description
+-------------------------------------------------------------------------------
+     JPEG* JPEG      rw-   Joint Photographic Experts Group JFIF format (80)
+      PNG* PNG       rw+   Portable Network Graphics (libpng 1.6.43)
+";
+
+    #[test]
+    fn test_check_image_delegates() {
+        let heic = vec!["heic".to_string()];
+        let png = vec!["png".to_string()];
+
+        assert!(check_image_delegates(FORMAT_LIST_WITH_HEIF, &heic, "avif").is_ok());
+        assert!(check_image_delegates(FORMAT_LIST_WITHOUT_HEIF, &png, "jpg").is_ok());
+        assert!(check_image_delegates(FORMAT_LIST_READ_ONLY_HEIC, &heic, "jpg").is_ok());
+
+        match check_image_delegates(FORMAT_LIST_WITHOUT_HEIF, &heic, "jpg") {
+            Err(MediaForgeError::MissingDependency(msg)) => {
+                assert!(msg.contains("read HEIC"));
+                assert!(msg.contains("libheif"));
             }
-            
-            let _ = app_handle.emit("task-update", self.get_task(task_id));
-            Ok(())
-        } else {
-            let error = String::from_utf8_lossy(&output.stderr);
-            log::error!("ImageMagick conversion failed: {}", error);
-            Err(MediaForgeError::ConversionError(format!(
-                "ImageMagick failed: {}",
-                error
-            )))
+            other => panic!("expected MissingDependency, got {:?}", other),
+        }
+        match check_image_delegates(FORMAT_LIST_READ_ONLY_HEIC, &png, "heic") {
+            Err(MediaForgeError::MissingDependency(msg)) => assert!(msg.contains("write HEIC")),
+            other => panic!("expected MissingDependency, got {:?}", other),
         }
+        assert!(matches!(
+            check_image_delegates(FORMAT_LIST_WITHOUT_HEIF, &png, "avif"),
+            Err(MediaForgeError::MissingDependency(_))
+        ));
+    }
+
+    #[test]
+    fn test_avif_quality_and_effort_args() {
+        let settings = ImageSettings { quality: Some(80), effort: Some(7), ..image_settings() };
+        assert_eq!(
+            args_to_strings(build_image_args(Path::new("/tmp/in.heic"), Path::new("/tmp/out.avif"), "avif", Some(&settings))),
+            vec!["/tmp/in.heic", "-quality", "60", "-define", "heic:speed=2", "/tmp/out.avif"]
+        );
+        // Effort only applies to AVIF output and quality is passed through unchanged elsewhere
+        assert_eq!(
+            args_to_strings(build_image_args(Path::new("/tmp/in.heic"), Path::new("/tmp/out.jpg"), "jpg", Some(&settings))),
+            vec!["/tmp/in.heic", "-background", "#ffffff", "-alpha", "remove", "-alpha", "off", "-quality", "80", "/tmp/out.jpg"]
+        );
+        assert_eq!(avif_quality(0), 1);
+        assert_eq!(avif_quality(100), 75);
+        assert!(validate_image_settings(&ImageSettings { effort: Some(10), ..image_settings() }).is_err());
+    }
+
+    #[test]
+    fn test_svg_density_args() {
+        let settings = ImageSettings {
+            density: Some(300),
+            resize: Some("512x512".to_string()),
+            ..image_settings()
+        };
+        assert_eq!(
+            args_to_strings(build_image_args(Path::new("/tmp/logo.svg"), Path::new("/tmp/logo.png"), "png", Some(&settings))),
+            vec!["-density", "300", "/tmp/logo.svg", "-resize", "512x512", "/tmp/logo.png"]
+        );
+        assert!(validate_image_settings(&ImageSettings { density: Some(1200), ..image_settings() }).is_ok());
+        assert!(validate_image_settings(&ImageSettings { density: Some(1201), ..image_settings() }).is_err());
+        assert!(validate_image_settings(&ImageSettings { density: Some(0), ..image_settings() }).is_err());
     }
 
-    async fn convert_video(
-        &self,
-        task_id: &str,
-        input_file: &PathBuf,
-        request: &ConvertRequest,
-        app_handle: tauri::AppHandle,
-    ) -> Result<(), MediaForgeError> {
-        // Re-validate inputs (defensive programming)
-        validate_input_file(input_file)?;
-        
-        let file_stem = input_file
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .ok_or_else(|| MediaForgeError::InvalidSettings("Invalid input filename".to_string()))?;
+    #[test]
+    fn test_validate_svg_content() {
+        let safe = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">
+            <defs><linearGradient id="g"/></defs>
+            <rect fill="url(#g)" width="10" height="10"/>
+            <use xlink:href="#g"/>
+            <image href="data:image/png;base64,iVBORw0KGgo="/>
+        </svg>"##;
+        assert!(validate_svg_content(safe).is_ok());
 
-        // Use sanitized output path
-        let output_dir = sanitize_path(&request.output_path)?;
-        let output_path = output_dir.join(format!("{}.{}", file_stem, request.output_format));
+        // The DOCTYPE Inkscape and Illustrator write
+        let doctype = r#"<?xml version="1.0"?>
+            <!DOCTYPE svg PUBLIC "-//W3C//DTD SVG 1.1//EN" "http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd">
+            <svg xmlns="http://www.w3.org/2000/svg"><rect width="10" height="10"/></svg>"#;
+        assert!(validate_svg_content(doctype).is_ok());
 
-        log::info!("Starting video conversion from {:?} to {:?}", input_file, output_path);
+        let entity = r#"<?xml version="1.0"?><!DOCTYPE svg [<!ENTITY x SYSTEM "file:///etc/passwd">]><svg>&x;</svg>"#;
+        assert!(validate_svg_content(entity).is_err());
+        let subset = r#"<!DOCTYPE svg SYSTEM "x.dtd" [ <!ATTLIST svg a CDATA "b"> ]><svg/>"#;
+        assert!(validate_svg_content(subset).is_err());
+        assert!(validate_svg_content(r#"<svg><image xlink:href="file:///etc/passwd"/></svg>"#).is_err());
+        assert!(validate_svg_content(r#"<svg><image href='https://example.com/a.png'/></svg>"#).is_err());
+        assert!(validate_svg_content(r#"<svg><rect style="fill: url('/home/user/x.svg#p')"/></svg>"#).is_err());
+        assert!(validate_svg_content(r#"<svg><script>alert(1)</script></svg>"#).is_err());
+    }
 
-        let mut cmd = TokioCommand::new("ffmpeg");
-        cmd.arg("-i").arg(input_file);
+    #[test]
+    fn test_parse_bitrate_bps() {
+        assert_eq!(parse_bitrate_bps("2M"), Some(2_000_000));
+        assert_eq!(parse_bitrate_bps("2500k"), Some(2_500_000));
+        assert_eq!(parse_bitrate_bps("1.5M"), Some(1_500_000));
+        assert_eq!(parse_bitrate_bps("800000"), Some(800_000));
+        assert_eq!(parse_bitrate_bps("Keep Original"), None);
+        assert_eq!(parse_bitrate_bps(""), None);
+    }
 
-        // Apply video settings
-        if let Some(settings) = &request.video_settings {
-            if let Some(resolution) = &settings.resolution {
-                if resolution != "Keep Original" {
-                    cmd.arg("-s").arg(resolution);
-                }
-            }
+    #[test]
+    fn test_estimate_audio_and_video_bytes() {
+        // 192 kbps for 60 s = 1.44 MB
+        let mp3 = AudioSettings { bitrate: Some("192".to_string()), ..audio_settings() };
+        assert_eq!(estimate_audio_bytes(60.0, "mp3", Some(&mp3)), 1_440_000);
+        // 44.1 kHz stereo 16-bit PCM for 10 s
+        assert_eq!(estimate_audio_bytes(10.0, "wav", None), 1_764_000);
+        assert_eq!(estimate_audio_bytes(60.0, "opus", None), 720_000);
 
-            if let Some(bitrate) = &settings.bitrate {
-                if bitrate != "Keep Original" {
-                    cmd.arg("-b:v").arg(bitrate);
-                }
-            }
+        // (2 Mbps video + 128 kbps audio) for 100 s
+        let video = VideoSettings { bitrate: Some("2M".to_string()), ..Default::default() };
+        assert_eq!(estimate_video_bytes(100.0, 5_000_000, Some(&video)), 26_600_000);
+        assert_eq!(estimate_video_bytes(100.0, 5_000_000, None), 5_000_000);
+    }
+
+    #[test]
+    fn test_check_output_duration() {
+        assert!(check_output_duration(600.0, 599.5).is_ok());
+        // 2% of a 10 minute input
+        assert!(check_output_duration(600.0, 589.0).is_ok());
+        assert!(check_output_duration(600.0, 580.0).is_err());
+        // Short clips get at least a second
+        assert!(check_output_duration(5.0, 4.2).is_ok());
+        assert!(check_output_duration(5.0, 0.0).is_err());
+        assert_eq!(
+            check_output_duration(3600.0, 1200.0).unwrap_err(),
+            "output runs 1200.0s but the input runs 3600.0s"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_trimmed_output_passes_verification() {
+        if !ffmpeg_available() {
+            return;
         }
+        let dir = std::env::temp_dir().join(format!("mediaforge-trim-verify-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("in.wav");
+        let output = dir.join("out.wav");
 
-        // Progress monitoring
-        cmd.arg("-progress").arg("pipe:1");
-        cmd.arg("-y"); // Overwrite output files
-        cmd.arg(&output_path);
+        // A second of tone between two seconds of silence on either side
+        let status = TokioCommand::new("ffmpeg")
+            .args(["-f", "lavfi", "-i", "anullsrc=r=44100:cl=mono:d=2"])
+            .args(["-f", "lavfi", "-i", "sine=frequency=440:sample_rate=44100:duration=1"])
+            .args(["-f", "lavfi", "-i", "anullsrc=r=44100:cl=mono:d=2"])
+            .args(["-filter_complex", "[0][1][2]concat=n=3:v=0:a=1", "-y"])
+            .arg(&input)
+            .output()
+            .await
+            .unwrap()
+            .status;
+        assert!(status.success());
 
-        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+        let duration = probe_duration_secs(&input).await.unwrap();
+        let trim = detect_silence_trim(&input, duration).await.unwrap();
+        let (start, end) = trim.expect("the silence is detected");
+        let settings = AudioSettings { trim_silence: true, ..audio_settings() };
+        let status = TokioCommand::new("ffmpeg")
+            .args(build_audio_args(&input, &output, "wav", Some(&settings), Some(end - start), trim))
+            .output()
+            .await
+            .unwrap()
+            .status;
+        assert!(status.success());
 
-        log::info!("FFmpeg command: {:?}", cmd);
+        let request = ConvertRequest { audio_settings: Some(settings), ..test_request(ConversionType::Audio, "wav") };
+        assert!(verify_output(&request, &input, &output, Some(end - start)).await.is_ok());
+        // Measured against the untrimmed input the same output looks cut short
+        assert!(verify_output(&request, &input, &output, None).await.is_err());
 
-        let mut child = cmd.spawn().map_err(|e| {
-            log::error!("Failed to spawn FFmpeg: {}", e);
-            MediaForgeError::FFmpegError(format!("Failed to spawn FFmpeg: {}", e))
-        })?;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 
-        let stdout = child.stdout.take().ok_or_else(|| {
-            MediaForgeError::FFmpegError("Failed to capture stdout".to_string())
-        })?;
+    #[test]
+    fn test_estimate_target_dir() {
+        let existing = std::env::temp_dir();
+        let mut request = ConvertRequest {
+            input_files: vec![existing.join("photo.jpg")],
+            output_path: existing.join("mediaforge-missing/nested").to_string_lossy().into_owned(),
+            ..test_request(ConversionType::Image, "png")
+        };
+        // Missing output folders resolve to their nearest existing ancestor
+        assert_eq!(estimate_target_dir(&request).unwrap(), existing);
 
-        let stderr = child.stderr.take().ok_or_else(|| {
-            MediaForgeError::FFmpegError("Failed to capture stderr".to_string())
-        })?;
+        request.output_mode = OutputMode::AlongsideInput;
+        assert_eq!(estimate_target_dir(&request).unwrap(), existing);
+    }
 
-        let manager = self.clone();
-        let task_id_clone = task_id.to_string();
-        let app_handle_clone = app_handle.clone();
+    #[test]
+    fn test_estimate_image_bytes() {
+        assert_eq!(resized_pixel_count(4000, 3000, None), 12_000_000);
+        assert_eq!(resized_pixel_count(4000, 3000, Some("50%")), 3_000_000);
+        assert_eq!(resized_pixel_count(4000, 3000, Some("800x800")), 480_000);
+        assert_eq!(resized_pixel_count(4000, 3000, Some("x300")), 120_000);
 
-        // Parse FFmpeg progress
-        tokio::spawn(async move {
-            let reader = BufReader::new(stdout);
-            let mut lines = reader.lines();
+        // A 12 MP photo to JPEG lands in the low megabytes
+        let jpeg = estimate_image_bytes(20_000_000, "png", "jpg", Some((4000, 3000)), None);
+        assert!((2_000_000..8_000_000).contains(&jpeg));
+        // Icons always hold the same sizes, 256x256 down to 16x16
+        assert_eq!(estimate_image_bytes(1_000_000, "png", "ico", None, None), 276_480);
+        // Unknown dimensions scale the input by relative compression
+        assert!(estimate_image_bytes(1_000_000, "png", "webp", None, None) < 1_000_000);
+    }
 
-            while let Ok(Some(line)) = lines.next_line().await {
-                if let Some(progress) = parse_ffmpeg_progress(&line) {
-                    manager.update_task(&task_id_clone, |task| {
-                        task.progress = progress;
-                    });
+    #[test]
+    fn test_output_file_name_template() {
+        let mut request = ConvertRequest {
+            input_files: vec![PathBuf::from("/media/a.MOV"), PathBuf::from("/media/b.mov")],
+            ..test_request(ConversionType::Video, "mp4")
+        };
+        let second = Path::new("/media/b.mov");
+        assert_eq!(output_file_name(&request, second, "mp4").unwrap(), "b.mp4");
 
-                    let _ = app_handle_clone.emit("task-update", manager.get_task(&task_id_clone));
-                }
-            }
-        });
+        request.filename_template = Some("{stem}_1080p.{ext}".to_string());
+        assert_eq!(output_file_name(&request, second, "mp4").unwrap(), "b_1080p.mp4");
 
-        // Capture stderr for error logging
-        let task_id_stderr = task_id.to_string();
-        tokio::spawn(async move {
-            let reader = BufReader::new(stderr);
-            let mut lines = reader.lines();
-            let mut error_output = String::new();
+        // The extension is added when the template leaves it out
+        request.filename_template = Some("{counter}-{stem}-from-{format}".to_string());
+        assert_eq!(output_file_name(&request, second, "mp4").unwrap(), "2-b-from-mov.mp4");
+        assert_eq!(output_file_name(&request, Path::new("/media/a.MOV"), "mp4").unwrap(), "1-a-from-mov.mp4");
 
-            while let Ok(Some(line)) = lines.next_line().await {
-                error_output.push_str(&line);
-                error_output.push('\n');
-            }
+        request.filename_template = Some("{date}.{ext}".to_string());
+        let name = output_file_name(&request, second, "mp4").unwrap();
+        assert_eq!(name.len(), "YYYY-MM-DD.mp4".len());
 
-            if !error_output.is_empty() {
-                log::error!("FFmpeg stderr for {}: {}", task_id_stderr, error_output);
-            }
-        });
+        // Padded to the width of the batch size
+        request.input_files.extend((3..=10).map(|n| PathBuf::from(format!("/media/{}.mov", n))));
+        request.filename_template = Some("clip_{index}".to_string());
+        assert_eq!(output_file_name(&request, second, "mp4").unwrap(), "clip_02.mp4");
+    }
 
-        let status = child.wait().await.map_err(|e| {
-            log::error!("Failed to wait for FFmpeg: {}", e);
-            MediaForgeError::FFmpegError(format!("Failed to wait for FFmpeg: {}", e))
-        })?;
+    #[test]
+    fn test_conflict_output_path() {
+        let dir = std::env::temp_dir().join(format!("mediaforge-convert-conflict-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("clip.mp4");
+        let output = dir.join("clip.webm");
+        std::fs::write(&input, b"").unwrap();
+        let planned = |path: &PathBuf| sanitize_output_path(&path.to_string_lossy()).unwrap();
 
-        if status.success() {
-            log::info!("Video conversion completed successfully: {:?}", output_path);
-            self.update_task(task_id, |task| {
-                task.status = TaskStatus::Completed;
-                task.progress = 100.0;
-                task.file_path = Some(output_path.to_string_lossy().to_string());
-            });
-            
-            // Send notification
-            if let Some(task) = self.get_task(task_id) {
-                notifications::send_conversion_complete_notification(&app_handle, &task.name);
-            }
-            
-            let _ = app_handle.emit("task-update", self.get_task(task_id));
-            Ok(())
-        } else {
-            log::error!("Video conversion failed with exit code: {:?}", status.code());
-            Err(MediaForgeError::FFmpegError(
-                format!("Conversion failed with exit code: {:?}", status.code()),
-            ))
+        assert_eq!(conflict_output_path(planned(&output), &input, Some(ConflictPolicy::Skip)).unwrap(), Some(output.clone()));
+
+        std::fs::write(&output, b"").unwrap();
+        assert_eq!(conflict_output_path(planned(&output), &input, None).unwrap(), Some(output.clone()));
+        assert_eq!(conflict_output_path(planned(&output), &input, Some(ConflictPolicy::Skip)).unwrap(), None);
+        assert_eq!(
+            conflict_output_path(planned(&output), &input, Some(ConflictPolicy::Rename)).unwrap(),
+            Some(dir.join("clip (1).webm"))
+        );
+
+        // Writing over the input is never allowed
+        assert!(conflict_output_path(planned(&input), &input, Some(ConflictPolicy::Overwrite)).is_err());
+        assert_eq!(
+            conflict_output_path(planned(&input), &input, Some(ConflictPolicy::Rename)).unwrap(),
+            Some(dir.join("clip (1).mp4"))
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_validate_filename_template() {
+        assert!(validate_filename_template("{stem}_converted.{ext}", true).is_ok());
+        assert!(validate_filename_template("export_{counter}", true).is_ok());
+        assert!(validate_filename_template("export_{index}", true).is_ok());
+        assert!(validate_filename_template("cover.{ext}", false).is_ok());
+        // Every file in a batch would get the same name
+        assert!(validate_filename_template("cover.{ext}", true).is_err());
+
+        for template in ["{resolution}_{stem}", "{stem", "../{stem}", "sub/{stem}", "a\\{stem}", "..", ""] {
+            let err = validate_filename_template(template, false).unwrap_err();
+            assert!(matches!(err, MediaForgeError::InvalidSettings(_)), "{}", template);
         }
     }
 
-    async fn convert_video_cancellable(
-        &self,
-        task_id: &str,
-        input_file: &PathBuf,
-        request: &ConvertRequest,
-        app_handle: tauri::AppHandle,
-        cancellation_token: CancellationToken,
-    ) -> Result<(), MediaForgeError> {
-        // Re-validate inputs (defensive programming)
-        validate_input_file(input_file)?;
-        
-        let file_stem = input_file
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .ok_or_else(|| MediaForgeError::InvalidSettings("Invalid input filename".to_string()))?;
+    #[test]
+    fn test_parse_media_probe() {
+        let json = r#"{
+            "streams": [
+                {"index": 0, "codec_name": "h264", "codec_type": "video", "width": 1920, "height": 1080,
+                 "avg_frame_rate": "30000/1001", "field_order": "tt", "color_transfer": "smpte2084",
+                 "bit_rate": "4500000"},
+                {"index": 1, "codec_name": "aac", "codec_type": "audio", "sample_rate": "48000",
+                 "channels": 2, "avg_frame_rate": "0/0", "bit_rate": "128000"}
+            ],
+            "format": {"format_name": "mov,mp4,m4a,3gp,3g2,mj2", "duration": "12.345000",
+                       "size": "7150000", "bit_rate": "4633500"}
+        }"#;
 
-        // Use sanitized output path
-        let output_dir = sanitize_path(&request.output_path)?;
-        let output_path = output_dir.join(format!("{}.{}", file_stem, request.output_format));
+        let probe = parse_media_probe(json).unwrap();
+        assert_eq!(probe.container, "mov,mp4,m4a,3gp,3g2,mj2");
+        assert_eq!(probe.duration_secs, Some(12.345));
+        assert_eq!(probe.size_bytes, Some(7_150_000));
+        assert_eq!(probe.streams.len(), 2);
 
-        // Validate disk space and permissions before starting
-        let estimated_size = input_file.metadata()
-            .map(|m| m.len() * 2) // Estimate 2x input size for conversion
-            .unwrap_or(500 * 1024 * 1024); // Default 500MB
-        crate::error::validation::validate_disk_space(&output_dir, Some(estimated_size)).await?;
-        crate::error::validation::validate_write_permissions(&output_dir).await?;
+        let video = &probe.streams[0];
+        assert_eq!((video.width, video.height), (Some(1920), Some(1080)));
+        assert!((video.frame_rate.unwrap() - 29.97).abs() < 0.01);
+        assert_eq!(video.bit_rate, Some(4_500_000));
+        assert!(video.interlaced);
+        assert!(video.hdr);
 
-        log::info!("Starting cancellable video conversion from {:?} to {:?}", input_file, output_path);
-        
-        // Use retry mechanism for conversion operations (filesystem errors mainly)
-        let retry_config = crate::error::RetryConfig::for_filesystem();
-        let conversion_result = crate::error::retry_async(retry_config, || {
-            self.convert_video_attempt(task_id, input_file, request, &output_path, app_handle.clone(), cancellation_token.clone())
-        }).await;
-        
-        // Cleanup on failure
-        if let Err(ref error) = conversion_result {
-            log::error!("Video conversion failed after retries for task {}: {}", task_id, error);
-            let _ = crate::error::validation::cleanup_on_error(&output_path).await;
-        }
-        
-        conversion_result
+        let audio = &probe.streams[1];
+        assert_eq!(audio.codec_name.as_deref(), Some("aac"));
+        assert_eq!((audio.channels, audio.sample_rate), (Some(2), Some(48000)));
+        assert_eq!(audio.frame_rate, None);
+        assert!(!audio.interlaced);
+        assert!(!audio.hdr);
+
+        assert!(parse_media_probe("not json").is_err());
     }
 
-    async fn convert_video_attempt(
-        &self,
-        task_id: &str,
-        input_file: &PathBuf,
-        request: &ConvertRequest,
-        output_path: &PathBuf,
-        app_handle: tauri::AppHandle,
-        cancellation_token: CancellationToken,
-    ) -> Result<(), MediaForgeError> {
-        log::info!("Attempting video conversion: {:?} -> {:?}", input_file, output_path);
+    #[test]
+    fn test_parse_extractable_streams() {
+        let json = r#"{
+            "streams": [
+                {"index": 2, "codec_name": "subrip", "codec_type": "subtitle",
+                 "disposition": {"forced": 0}, "tags": {"language": "eng", "title": "English"}},
+                {"index": 3, "codec_name": "hdmv_pgs_subtitle", "codec_type": "subtitle",
+                 "disposition": {"forced": 1}, "tags": {"language": "und"}}
+            ],
+            "chapters": [
+                {"id": 0, "start_time": "0.000000", "end_time": "90.500000", "tags": {"title": "Intro"}},
+                {"id": 1, "start_time": "90.500000", "end_time": "300.000000"}
+            ]
+        }"#;
+        let (subtitles, chapters) = parse_extractable_streams(json).unwrap();
+        assert_eq!(subtitles, vec![
+            SubtitleTrack {
+                index: 0,
+                stream_index: 2,
+                codec_name: Some("subrip".to_string()),
+                language: Some("eng".to_string()),
+                title: Some("English".to_string()),
+                forced: false,
+                text: true,
+            },
+            SubtitleTrack {
+                index: 1,
+                stream_index: 3,
+                codec_name: Some("hdmv_pgs_subtitle".to_string()),
+                language: None,
+                title: None,
+                forced: true,
+                text: false,
+            },
+        ]);
+        assert_eq!(chapters, vec![
+            Chapter { start_secs: 0.0, end_secs: 90.5, title: Some("Intro".to_string()) },
+            Chapter { start_secs: 90.5, end_secs: 300.0, title: None },
+        ]);
 
-        let mut cmd = TokioCommand::new("ffmpeg");
-        cmd.arg("-i").arg(input_file);
+        let input = Path::new("/videos/movie.mkv");
+        assert_eq!(extracted_file_name(input, &ExtractTarget::Subtitle(0), &subtitles), "movie.eng.srt");
+        assert_eq!(extracted_file_name(input, &ExtractTarget::Subtitle(1), &subtitles), "movie.track1.srt");
+        assert_eq!(extracted_file_name(input, &ExtractTarget::Chapters, &subtitles), "movie.chapters.txt");
 
-        // Apply video settings
-        if let Some(settings) = &request.video_settings {
-            if let Some(resolution) = &settings.resolution {
-                if resolution != "Keep Original" {
-                    cmd.arg("-s").arg(resolution);
-                }
-            }
+        // A language tag can't steer the file elsewhere
+        let hostile = [SubtitleTrack { language: Some("../../x".to_string()), ..subtitles[0].clone() }];
+        assert_eq!(extracted_file_name(input, &ExtractTarget::Subtitle(0), &hostile), "movie.track0.srt");
+    }
 
-            if let Some(bitrate) = &settings.bitrate {
-                if bitrate != "Keep Original" {
-                    cmd.arg("-b:v").arg(bitrate);
-                }
-            }
+    fn probe_with(streams: &[(&str, &str)]) -> MediaProbe {
+        MediaProbe {
+            container: "matroska,webm".to_string(),
+            duration_secs: None,
+            size_bytes: None,
+            bit_rate: None,
+            chapters: Vec::new(),
+            streams: streams
+                .iter()
+                .enumerate()
+                .map(|(index, (codec_type, codec))| StreamProbe {
+                    index: index as u32,
+                    codec_type: codec_type.to_string(),
+                    codec_name: Some(codec.to_string()),
+                    width: None,
+                    height: None,
+                    frame_rate: None,
+                    field_order: None,
+                    interlaced: false,
+                    color_transfer: None,
+                    hdr: false,
+                    bit_rate: None,
+                    channels: None,
+                    sample_rate: None,
+                })
+                .collect(),
         }
+    }
 
-        // Progress monitoring
-        cmd.arg("-progress").arg("pipe:1");
-        cmd.arg("-y"); // Overwrite output files
-        cmd.arg(&output_path);
+    #[test]
+    fn test_delete_source_file() {
+        let dir = std::env::temp_dir().join(format!("mediaforge-delete-source-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("clip.mov");
+        std::fs::write(&source, b"source").unwrap();
+        let output = dir.join("clip.mp4");
 
-        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+        // Missing, empty and in-place outputs all keep the source
+        assert!(delete_source_file(&source, &[]).is_err());
+        assert!(delete_source_file(&source, std::slice::from_ref(&output)).is_err());
+        std::fs::write(&output, b"").unwrap();
+        assert!(delete_source_file(&source, std::slice::from_ref(&output)).is_err());
+        assert!(delete_source_file(&source, &[dir.join(".").join("clip.mov")]).is_err());
+        assert!(source.exists());
 
-        log::info!("FFmpeg command: {:?}", cmd);
+        std::fs::write(&output, b"converted").unwrap();
+        delete_source_file(&source, std::slice::from_ref(&output)).unwrap();
+        assert!(!source.exists());
+        assert!(output.exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 
-        let mut child = cmd.spawn().map_err(|e| {
-            log::error!("Failed to spawn FFmpeg: {}", e);
-            MediaForgeError::FFmpegError(format!("Failed to spawn FFmpeg: {}", e))
-        })?;
+    #[tokio::test]
+    async fn test_validate_files() {
+        let dir = std::env::temp_dir().join(format!("mediaforge-validate-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let video = dir.join("clip.mp4");
+        std::fs::write(&video, b"not really a video").unwrap();
+        let notes = dir.join("notes.txt");
+        std::fs::write(&notes, b"notes").unwrap();
+        let paths: Vec<String> = [&video, &notes, &dir.join("gone.mp3")]
+            .iter()
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect();
 
-        let stdout = child.stdout.take().ok_or_else(|| {
-            MediaForgeError::FFmpegError("Failed to capture stdout".to_string())
-        })?;
+        let results = validate_files(&paths, &ConversionType::Auto).await.unwrap();
+        assert_eq!(results[0].reason, None);
+        assert_eq!(results[0].detected_type, Some(ConversionType::Video));
+        assert_eq!(results[0].size_bytes, Some(18));
+        assert!(results[1].reason.as_deref().unwrap().contains("not a supported media file"));
+        assert_eq!(results[1].detected_type, None);
+        assert!(results[2].reason.as_deref().unwrap().contains("does not exist"));
+        assert_eq!(results[2].detected_type, Some(ConversionType::Audio));
+        assert_eq!(results[2].size_bytes, None);
 
-        let _stderr = child.stderr.take().ok_or_else(|| {
-            MediaForgeError::FFmpegError("Failed to capture stderr".to_string())
-        })?;
+        // Audio can be taken from a video, but not from an image
+        let results = validate_files(&paths[..1], &ConversionType::Audio).await.unwrap();
+        assert_eq!(results[0].reason, None);
+        assert_eq!(results[0].detected_type, Some(ConversionType::Video));
+        let photo = dir.join("photo.jpg");
+        std::fs::write(&photo, b"not really a photo").unwrap();
+        let results = validate_files(&[photo.to_string_lossy().into_owned()], &ConversionType::Audio).await.unwrap();
+        assert!(results[0].reason.as_deref().unwrap().contains("not a supported audio file"));
+        assert_eq!(results[0].detected_type, Some(ConversionType::Image));
 
-        let manager = self.clone();
-        let task_id_str = task_id.to_string();
-        let task_id_clone = task_id_str.clone();
-        let app_handle_clone = app_handle.clone();
-        let cancellation_token_clone = cancellation_token.clone();
-
-        // Parse FFmpeg progress with cancellation support
-        let progress_handle = tokio::spawn(async move {
-            let reader = BufReader::new(stdout);
-            let mut lines = reader.lines();
-
-            loop {
-                tokio::select! {
-                    result = lines.next_line() => {
-                        match result {
-                            Ok(Some(line)) => {
-                                if let Some(progress) = parse_ffmpeg_progress(&line) {
-                                    manager.update_task(&task_id_clone, |task| {
-                                        task.progress = progress;
-                                    });
-
-                                    let _ = app_handle_clone.emit("task-update", manager.get_task(&task_id_clone));
-                                }
-                            }
-                            Ok(None) => break, // EOF
-                            Err(_) => break,   // Error reading
-                        }
-                    }
-                    _ = cancellation_token_clone.cancelled() => {
-                        log::info!("Progress parsing cancelled for conversion task {}", task_id_str);
-                        break;
-                    }
-                }
-            }
-        });
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 
-        // Wait for process completion or cancellation
-        let status = tokio::select! {
-            status = child.wait() => {
-                status.map_err(|e| {
-                    MediaForgeError::FFmpegError(format!("Failed to wait for FFmpeg: {}", e))
-                })?
-            }
-            _ = cancellation_token.cancelled() => {
-                log::info!("Killing FFmpeg process for cancelled conversion task {}", task_id);
-                // Kill the child process
-                if let Err(e) = child.kill().await {
-                    log::error!("Failed to kill FFmpeg process: {}", e);
-                }
-                // Wait briefly for cleanup
-                let _ = tokio::time::timeout(Duration::from_secs(5), child.wait()).await;
-                
-                // Cancel progress parsing
-                progress_handle.abort();
-                
-                return Err(MediaForgeError::FFmpegError("Conversion was cancelled".to_string()));
+    #[tokio::test]
+    async fn test_validate_files_probes_unlisted_types() {
+        let dir = std::env::temp_dir().join(format!("mediaforge-validate-probe-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut paths = Vec::new();
+        for name in ["notes.abc", "data.xyz"] {
+            std::fs::write(dir.join(name), b"plain text").unwrap();
+            paths.push(dir.join(name));
+        }
+        // Real audio under extensions no conversion lists is accepted once probed
+        let with_audio = ffmpeg_available();
+        if with_audio {
+            for name in ["tone.snd", "tone.aud"] {
+                let status = TokioCommand::new("ffmpeg")
+                    .args(["-f", "lavfi", "-i", "sine=frequency=440:duration=1", "-f", "wav", "-y"])
+                    .arg(dir.join(name))
+                    .output()
+                    .await
+                    .unwrap()
+                    .status;
+                assert!(status.success());
+                paths.push(dir.join(name));
             }
-        };
-
-        // Cancel progress parsing since process completed
-        progress_handle.abort();
+        }
+        let paths: Vec<String> = paths.iter().map(|p| p.to_string_lossy().into_owned()).collect();
 
-        if status.success() {
-            log::info!("Video conversion completed successfully: {:?}", output_path);
-            self.update_task(task_id, |task| {
-                task.status = TaskStatus::Completed;
-                task.progress = 100.0;
-                task.file_path = Some(output_path.to_string_lossy().to_string());
-            });
-            
-            // Clean up task handle since task completed
-            self.task_handles.remove(task_id);
-            
-            // Send notification
-            if let Some(task) = self.get_task(task_id) {
-                notifications::send_conversion_complete_notification(&app_handle, &task.name);
+        let results = validate_files(&paths, &ConversionType::Audio).await.unwrap();
+        assert_eq!(results.len(), paths.len());
+        // Results keep the input order however the probes finish
+        for (result, path) in results.iter().zip(&paths) {
+            assert_eq!(result.path, PathBuf::from(path));
+        }
+        for result in &results[..2] {
+            assert!(result.reason.as_deref().unwrap().contains("not a supported audio file"));
+            assert_eq!(result.detected_type, None);
+        }
+        if with_audio {
+            for result in &results[2..] {
+                assert_eq!(result.reason, None);
+                assert_eq!(result.detected_type, Some(ConversionType::Audio));
             }
-            
-            let _ = app_handle.emit("task-update", self.get_task(task_id));
-            Ok(())
-        } else {
-            // Clean up task handle on failure too
-            self.task_handles.remove(task_id);
-            
-            // Enhanced error classification for FFmpeg
-            let error_message = format!("Conversion failed with exit code: {:?}", status.code());
-            let error = Self::classify_ffmpeg_error(&error_message, status.code());
-            
-            log::error!("FFmpeg failed for task {}: {} (retryable: {})", 
-                       task_id, error, error.is_retryable());
-            
-            Err(error)
         }
+
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 
-    async fn convert_audio(
-        &self,
-        task_id: &str,
-        input_file: &PathBuf,
-        request: &ConvertRequest,
-        app_handle: tauri::AppHandle,
-    ) -> Result<(), MediaForgeError> {
-        // Re-validate inputs (defensive programming)
-        validate_input_file(input_file)?;
-        
-        let file_stem = input_file
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .ok_or_else(|| MediaForgeError::InvalidSettings("Invalid input filename".to_string()))?;
+    #[test]
+    fn test_needs_ffmpeg() {
+        let request = |conversion_type: ConversionType, inputs: &[&str]| ConvertRequest {
+            input_files: inputs.iter().map(PathBuf::from).collect(),
+            ..test_request(conversion_type, "")
+        };
+        assert!(!needs_ffmpeg(&request(ConversionType::Image, &["a.mp4"])));
+        assert!(needs_ffmpeg(&request(ConversionType::Audio, &["a.flac"])));
+        assert!(!needs_ffmpeg(&request(ConversionType::Auto, &["a.jpg", "b.PNG"])));
+        assert!(needs_ffmpeg(&request(ConversionType::Auto, &["a.jpg", "b.mkv"])));
+        // Unlisted inputs are classified by probing them
+        assert!(needs_ffmpeg(&request(ConversionType::Auto, &["a.jpg", "b.xyz"])));
+    }
 
-        // Use sanitized output path
-        let output_dir = sanitize_path(&request.output_path)?;
-        let output_path = output_dir.join(format!("{}.{}", file_stem, request.output_format));
+    #[tokio::test]
+    async fn test_split_auto_request() {
+        let dir = std::env::temp_dir().join(format!("mediaforge-auto-{}", Uuid::new_v4()));
+        let file = |name: &str| dir.join(name);
+        let request = ConvertRequest {
+            input_files: vec![file("a.jpg"), file("b.MP4"), file("c.flac"), file("d.png"), file("e.xyz")],
+            output_path: dir.to_string_lossy().into_owned(),
+            auto_formats: Some(AutoFormats {
+                image: Some("webp".to_string()),
+                video: Some("webm".to_string()),
+                audio: None,
+            }),
+            ..test_request(ConversionType::Auto, "")
+        };
 
-        log::info!("Starting audio conversion from {:?} to {:?}", input_file, output_path);
+        let (typed, rejected) = split_auto_request(&request).await;
+        let groups: Vec<_> = typed
+            .iter()
+            .map(|r| (r.conversion_type.clone(), r.output_format.as_str(), r.input_files.clone()))
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                (ConversionType::Image, "webp", vec![file("a.jpg"), file("d.png")]),
+                (ConversionType::Video, "webm", vec![file("b.MP4")]),
+            ]
+        );
+        assert_eq!(
+            rejected,
+            [
+                (file("e.xyz"), format!("{} is not a supported image, video or audio file", file("e.xyz").display())),
+                (file("c.flac"), "No output format chosen for audio files".to_string()),
+            ]
+        );
 
-        let mut cmd = TokioCommand::new("ffmpeg");
-        cmd.arg("-i").arg(input_file);
+        // An unlisted extension is judged by its streams; cover art alone doesn't make a video
+        let song = probe_with(&[("audio", "mp3"), ("video", "mjpeg")]);
+        assert_eq!(conversion_type_for_probe(&song), Some(ConversionType::Audio));
+        let clip = probe_with(&[("video", "h264"), ("audio", "aac")]);
+        assert_eq!(conversion_type_for_probe(&clip), Some(ConversionType::Video));
+        assert_eq!(conversion_type_for_probe(&probe_with(&[("subtitle", "subrip")])), None);
+        let text = MediaProbe { container: "tty".to_string(), ..probe_with(&[("video", "ansi")]) };
+        assert_eq!(conversion_type_for_probe(&text), None);
+    }
 
-        // Apply audio settings
-        if let Some(settings) = &request.audio_settings {
-            if let Some(bitrate) = &settings.bitrate {
-                cmd.arg("-b:a").arg(format!("{}k", bitrate));
-            }
+    #[test]
+    fn test_apply_source_limits() {
+        // A 720p clip at 2.5 Mb/s overall, its container giving no stream bitrates but the audio's
+        let mut probe = probe_with(&[("video", "h264"), ("audio", "aac")]);
+        probe.bit_rate = Some(2_628_000);
+        probe.streams[0].width = Some(1280);
+        probe.streams[0].height = Some(720);
+        probe.streams[1].bit_rate = Some(128_000);
+        let limits = SourceLimits::from_probe(&probe);
+        assert_eq!(
+            limits,
+            SourceLimits { video_bps: Some(2_500_000), audio_bps: Some(128_000), frame_size: Some((1280, 720)) }
+        );
 
-            if let Some(sample_rate) = &settings.sample_rate {
-                cmd.arg("-ar").arg(sample_rate);
-            }
-        }
+        let mut request = ConvertRequest {
+            input_files: vec![PathBuf::from("/tmp/in.mp4")],
+            video_settings: Some(VideoSettings {
+                bitrate: Some("8M".to_string()),
+                resolution: Some("1920x1080".to_string()),
+                ..Default::default()
+            }),
+            ..test_request(ConversionType::Video, "mp4")
+        };
+        // Off by default
+        assert!(apply_source_limits(&mut request.clone(), &limits).is_empty());
 
-        cmd.arg("-vn"); // No video
-        cmd.arg("-y");
-        cmd.arg(&output_path);
+        request.video_settings.as_mut().unwrap().prevent_upscale = true;
+        let notes = apply_source_limits(&mut request, &limits);
+        assert_eq!(
+            notes,
+            [
+                "Lowered the video bitrate from 8M to the source's 2500k",
+                "Kept the source's 1280x720 instead of upscaling to 1920x1080",
+            ]
+        );
+        let settings = request.video_settings.as_ref().unwrap();
+        assert_eq!((settings.bitrate.as_deref(), settings.resolution.as_deref()), (Some("2500k"), None));
+        // Settings already within the source stay as they are
+        assert!(apply_source_limits(&mut request, &limits).is_empty());
 
-        log::info!("FFmpeg audio command: {:?}", cmd);
+        // A 128k MP3 isn't worth re-encoding at 320k
+        let mut song = probe_with(&[("audio", "mp3")]);
+        song.bit_rate = Some(128_000);
+        request.conversion_type = ConversionType::Audio;
+        request.video_settings = None;
+        request.audio_settings = Some(AudioSettings { bitrate: Some("320k".to_string()), prevent_upscale: true, ..Default::default() });
+        let notes = apply_source_limits(&mut request, &SourceLimits::from_probe(&song));
+        assert_eq!(notes, ["Lowered the audio bitrate from 320k to the source's 128k"]);
+    }
 
-        let output = cmd.output().await.map_err(|e| {
-            log::error!("Failed to run FFmpeg for audio: {}", e);
-            MediaForgeError::FFmpegError(format!("Failed to run FFmpeg: {}", e))
-        })?;
+    #[test]
+    fn test_check_container_compatibility() {
+        let input = Path::new("/tmp/in.mkv");
+        let av1 = probe_with(&[("video", "av1"), ("audio", "opus")]);
 
-        if output.status.success() {
-            log::info!("Audio conversion completed successfully: {:?}", output_path);
-            self.update_task(task_id, |task| {
-                task.status = TaskStatus::Completed;
-                task.progress = 100.0;
-                task.file_path = Some(output_path.to_string_lossy().to_string());
-            });
-            
-            // Send notification
-            if let Some(task) = self.get_task(task_id) {
-                notifications::send_conversion_complete_notification(&app_handle, &task.name);
-            }
-            
-            let _ = app_handle.emit("task-update", self.get_task(task_id));
-            Ok(())
-        } else {
-            let error = String::from_utf8_lossy(&output.stderr);
-            log::error!("Audio conversion failed: {}", error);
-            Err(MediaForgeError::FFmpegError(format!("FFmpeg failed: {}", error)))
-        }
-    }
-    
-    /// Classifies FFmpeg errors to determine if they're retryable  
-    pub fn classify_ffmpeg_error(message: &str, exit_code: Option<i32>) -> MediaForgeError {
-        let msg_lower = message.to_lowercase();
-        
-        // Temporary filesystem errors (retryable)
-        if msg_lower.contains("resource temporarily unavailable") ||
-           msg_lower.contains("device busy") ||
-           msg_lower.contains("interrupted system call") ||
-           exit_code == Some(1)  // Generic failure that might be temporary
-        {
-            MediaForgeError::TemporaryError(message.to_string())
-        }
-        // Disk space errors  
-        else if msg_lower.contains("no space left") ||
-                msg_lower.contains("disk full") ||
-                msg_lower.contains("write error")
-        {
-            MediaForgeError::DiskSpaceError(message.to_string())
-        }
-        // Permission errors
-        else if msg_lower.contains("permission denied") ||
-                msg_lower.contains("access denied") ||
-                exit_code == Some(126)  // Permission denied
-        {
-            MediaForgeError::PermissionError(message.to_string())
-        }
-        // Invalid format/codec errors (not retryable)
-        else if msg_lower.contains("invalid data") ||
-                msg_lower.contains("unsupported") ||
-                msg_lower.contains("no decoder") ||
-                msg_lower.contains("invalid file format") ||
-                exit_code == Some(255)  // FFmpeg generic error
-        {
-            MediaForgeError::FFmpegError(message.to_string())
-        }
-        // Default to retryable for other cases
-        else {
-            MediaForgeError::TemporaryError(message.to_string())
-        }
+        // Re-encoding handles any codec
+        assert_eq!(check_container_compatibility(&av1, input, "avi", false).unwrap(), None);
+        // Stream copy falls back to re-encoding
+        assert_eq!(
+            check_container_compatibility(&av1, input, "avi", true).unwrap().as_deref(),
+            Some("av1 video and opus audio can't be copied into .avi; re-encoding instead")
+        );
+        assert_eq!(check_container_compatibility(&av1, input, "MP4", true).unwrap(), None);
+        assert_eq!(check_container_compatibility(&av1, input, "mkv", true).unwrap(), None);
+
+        // Text subtitles are converted, image-based ones can't be
+        let ass = probe_with(&[("video", "h264"), ("subtitle", "ass")]);
+        assert_eq!(check_container_compatibility(&ass, input, "mp4", false).unwrap(), None);
+        assert!(check_container_compatibility(&ass, input, "mp4", true).unwrap().is_some());
+        assert_eq!(check_container_compatibility(&ass, input, "avi", true).unwrap(), None);
+
+        let pgs = probe_with(&[("video", "h264"), ("subtitle", "hdmv_pgs_subtitle")]);
+        let err = check_container_compatibility(&pgs, input, "mp4", false).unwrap_err();
+        assert!(err.to_string().contains("convert to MKV"));
     }
 
-    async fn convert_image_cancellable(
-        &self,
-        task_id: &str,
-        input_file: &PathBuf,
-        request: &ConvertRequest,
-        app_handle: tauri::AppHandle,
-        _cancellation_token: CancellationToken,
-    ) -> Result<(), MediaForgeError> {
-        // For now, delegate to existing method
-        // TODO: Add proper cancellation support for ImageMagick processes
-        let result = self.convert_image(task_id, input_file, request, app_handle).await;
-        
-        // Clean up task handle on completion
-        if result.is_ok() {
-            self.task_handles.remove(task_id);
-        } else {
-            // Clean up task handle on failure too
-            self.task_handles.remove(task_id);
-        }
-        
-        result
+    #[test]
+    fn test_copy_streams_args() {
+        let settings = VideoSettings { copy_streams: true, strip_metadata: true, ..Default::default() };
+        assert_eq!(
+            args_to_strings(build_video_args(Path::new("/tmp/in.mkv"), Path::new("/tmp/out.mp4"), Some(&settings), None, None)),
+            vec!["-i", "/tmp/in.mkv", "-c", "copy", "-map_metadata", "-1", "-progress", "pipe:1", "-y", "/tmp/out.mp4"]
+        );
+
+        assert!(validate_video_settings(&settings).is_ok());
+        let resized = VideoSettings { resolution: Some("1280x720".to_string()), ..settings.clone() };
+        assert!(validate_video_settings(&resized).is_err());
+        let kept = VideoSettings { resolution: Some("Keep Original".to_string()), ..settings };
+        assert!(validate_video_settings(&kept).is_ok());
     }
 
-    async fn convert_audio_cancellable(
-        &self,
-        task_id: &str,
-        input_file: &PathBuf,
-        request: &ConvertRequest,
-        app_handle: tauri::AppHandle,
-        _cancellation_token: CancellationToken,
-    ) -> Result<(), MediaForgeError> {
-        // For now, delegate to existing method
-        // TODO: Add proper cancellation support for FFmpeg audio processes
-        let result = self.convert_audio(task_id, input_file, request, app_handle).await;
-        
-        // Clean up task handle on completion
-        if result.is_ok() {
-            self.task_handles.remove(task_id);
-        } else {
-            // Clean up task handle on failure too
-            self.task_handles.remove(task_id);
-        }
-        
-        result
+    #[test]
+    fn test_common_parent_dir() {
+        let files = |paths: &[&str]| paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+
+        assert_eq!(
+            common_parent_dir(&files(&["/media/a/x.mp4", "/media/a/b/y.mp4", "/media/c/z.mp4"])),
+            PathBuf::from("/media")
+        );
+        assert_eq!(common_parent_dir(&files(&["/media/a/x.mp4"])), PathBuf::from("/media/a"));
+        assert_eq!(common_parent_dir(&[]), PathBuf::new());
     }
-}
 
-impl Clone for ConversionManager {
-    fn clone(&self) -> Self {
-        Self {
-            tasks: Arc::clone(&self.tasks),
-            task_handles: Arc::clone(&self.task_handles),
-        }
+    #[test]
+    fn test_mirrored_output_dir() {
+        let root = Path::new("/out");
+        let base = Path::new("/media");
+
+        assert_eq!(
+            mirrored_output_dir(root, base, Path::new("/media/a/b/y.mp4")),
+            PathBuf::from("/out/a/b")
+        );
+        assert_eq!(mirrored_output_dir(root, base, Path::new("/media/x.mp4")), PathBuf::from("/out"));
+        // Inputs outside the base never put the output outside the root
+        assert_eq!(
+            mirrored_output_dir(root, base, Path::new("/other/x.mp4")),
+            PathBuf::from("/out/other")
+        );
     }
-}
 
-/// FFmpeg progress tracker to calculate actual percentage based on duration
-struct FFmpegProgress {
-    duration_ms: Option<u64>,
-    current_ms: u64,
-}
+    #[test]
+    fn test_matches_folder_filter() {
+        let filter = vec!["mp4".to_string(), ".MKV".to_string(), "clip_??.*".to_string()];
 
-impl FFmpegProgress {
-    fn new() -> Self {
-        Self {
-            duration_ms: None,
-            current_ms: 0,
-        }
+        assert!(matches_folder_filter("movie.MP4", &filter));
+        assert!(matches_folder_filter("movie.mkv", &filter));
+        assert!(matches_folder_filter("clip_01.mov", &filter));
+        assert!(!matches_folder_filter("clip_001.mov", &filter));
+        assert!(!matches_folder_filter("movie.mov", &filter));
+        assert!(matches_folder_filter("anything.txt", &[]));
+
+        assert!(wildcard_match("*.tar.*", "backup.tar.gz"));
+        assert!(wildcard_match("a*b*c", "axxbyyc"));
+        assert!(!wildcard_match("a*b*c", "axxbyy"));
     }
-    
-    fn parse_line(&mut self, line: &str) -> Option<f32> {
-        // Parse duration from FFmpeg output (appears early in the output)
-        // Format: "Duration: 00:01:23.45, start: 0.000000, bitrate: 1234 kb/s"
-        if line.contains("Duration:") {
-            let re = Regex::new(r"Duration: (\d+):(\d+):(\d+)\.(\d+)").unwrap();
-            if let Some(caps) = re.captures(line) {
-                let hours: u64 = caps[1].parse().unwrap_or(0);
-                let mins: u64 = caps[2].parse().unwrap_or(0);
-                let secs: u64 = caps[3].parse().unwrap_or(0);
-                let ms: u64 = caps[4].parse().unwrap_or(0) * 10; // Convert to ms
-                
-                self.duration_ms = Some((hours * 3600 + mins * 60 + secs) * 1000 + ms);
-                log::info!("FFmpeg detected duration: {} ms", self.duration_ms.unwrap());
-            }
+
+    #[test]
+    fn test_collect_folder_inputs() {
+        let temp = tempfile::Builder::new().prefix("mediaforge_folder_").tempdir().unwrap();
+        let root = temp.path().to_path_buf();
+        for dir in ["a/b/c", ".hidden"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
         }
-        
-        // Parse current time from progress output
-        // Format: "out_time_ms=12345678"
-        if line.starts_with("out_time_ms=") {
-            if let Some(ms_str) = line.strip_prefix("out_time_ms=") {
-                self.current_ms = ms_str.trim().parse().unwrap_or(0);
-                
-                // Calculate percentage if we have duration
-                if let Some(total) = self.duration_ms {
-                    if total > 0 {
-                        let progress = (self.current_ms as f32 / total as f32 * 100.0).min(100.0);
-                        return Some(progress);
-                    }
-                }
-            }
+        for file in ["top.mp4", "a/one.mp4", "a/b/c/deep.mp4", ".hidden/secret.mp4", "a/song.mp3", "a/notes.txt", "a/run.sh"] {
+            std::fs::write(root.join(file), b"").unwrap();
         }
-        
-        None
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(root.join("a"), root.join("link")).unwrap();
+
+        let mp4 = vec!["mp4".to_string()];
+        assert_eq!(
+            collect_folder_inputs(&root, &mp4, 8, &ConversionType::Video).unwrap(),
+            vec![root.join("a/b/c/deep.mp4"), root.join("a/one.mp4"), root.join("top.mp4")]
+        );
+        assert_eq!(
+            collect_folder_inputs(&root, &mp4, 1, &ConversionType::Video).unwrap(),
+            vec![root.join("a/one.mp4"), root.join("top.mp4")]
+        );
+        // Files the conversion doesn't take are dropped even when nothing is filtered
+        assert_eq!(collect_folder_inputs(&root, &[], 8, &ConversionType::Video).unwrap().len(), 3);
+        let all = collect_folder_inputs(&root, &[], 8, &ConversionType::Auto).unwrap();
+        assert!(all.contains(&root.join("a/song.mp3")));
+        assert!(!all.contains(&root.join("a/notes.txt")));
+        assert!(!all.contains(&root.join("a/run.sh")));
     }
-}
 
-fn parse_ffmpeg_progress(line: &str) -> Option<f32> {
-    // This is a simplified version - in practice, you'd want to maintain 
-    // a progress tracker per task. For now, we'll do basic parsing.
-    if line.starts_with("out_time_ms=") {
-        // Try to extract meaningful progress
-        if let Some(ms_str) = line.strip_prefix("out_time_ms=") {
-            if let Ok(current_ms) = ms_str.trim().parse::<u64>() {
-                // Rough heuristic: assume most conversions are < 10 minutes
-                // This is not accurate but better than always 50%
-                let estimated_total_ms = 600_000; // 10 minutes
-                let progress = (current_ms as f32 / estimated_total_ms as f32 * 100.0).min(100.0);
-                if progress > 0.0 && progress <= 100.0 {
-                    return Some(progress);
-                }
-            }
+    #[test]
+    fn test_frame_handling() {
+        assert_eq!(frame_handling(1, "webp", None), (FrameHandling::Single, false));
+        assert_eq!(frame_handling(12, "webp", None), (FrameHandling::Animated, false));
+        assert_eq!(frame_handling(12, "APNG", None), (FrameHandling::Animated, false));
+        // Single-frame outputs keep the first frame and report it
+        assert_eq!(frame_handling(12, "jpg", None), (FrameHandling::FirstFrame, true));
+        // An explicit still is not a surprise
+        let still = ImageSettings { animation: Some(false), ..image_settings() };
+        assert_eq!(frame_handling(12, "webp", Some(&still)), (FrameHandling::FirstFrame, false));
+        assert_eq!(frame_handling(12, "png", Some(&still)), (FrameHandling::FirstFrame, false));
+    }
+
+    #[test]
+    fn test_apply_frame_handling() {
+        let settings = ImageSettings { resize: Some("50%".to_string()), ..image_settings() };
+        let input = Path::new("/tmp/anim.gif");
+        let args = || build_image_args(input, Path::new("/tmp/anim.webp"), "webp", Some(&settings));
+
+        assert_eq!(
+            args_to_strings(apply_frame_handling(args(), input, FrameHandling::Animated)),
+            vec!["/tmp/anim.gif", "-coalesce", "-resize", "50%", "/tmp/anim.webp"]
+        );
+        assert_eq!(
+            args_to_strings(apply_frame_handling(args(), input, FrameHandling::FirstFrame)),
+            vec!["/tmp/anim.gif[0]", "-resize", "50%", "/tmp/anim.webp"]
+        );
+        assert_eq!(apply_frame_handling(args(), input, FrameHandling::Single), args());
+    }
+
+    #[test]
+    fn test_build_pdf_combine_args_keeps_input_order() {
+        let inputs = vec![PathBuf::from("/tmp/scan-2.jpg"), PathBuf::from("/tmp/scan-1.jpg"), PathBuf::from("/tmp/scan-3.png")];
+        let settings = ImageSettings { quality: Some(90), ..image_settings() };
+        assert_eq!(
+            args_to_strings(build_pdf_combine_args(&inputs, Path::new("/tmp/scan-2.pdf"), Some(&settings))),
+            vec!["/tmp/scan-2.jpg", "/tmp/scan-1.jpg", "/tmp/scan-3.png", "-quality", "90", "/tmp/scan-2.pdf"]
+        );
+    }
+
+    #[test]
+    fn test_pdf_format_rules() {
+        assert!(validate_image_format(&PathBuf::from("/tmp/scan.jpg"), "pdf").is_ok());
+        assert!(validate_image_format(&PathBuf::from("/tmp/doc.pdf"), "png").is_ok());
+        assert!(validate_image_format(&PathBuf::from("/tmp/doc.PDF"), "pdf").is_err());
+
+        let request = ConvertRequest {
+            input_files: vec![PathBuf::from("/tmp/a.jpg"), PathBuf::from("/tmp/b.jpg")],
+            ..test_request(ConversionType::Image, "PDF")
+        };
+        assert!(is_pdf_combine(&request));
+        assert!(!is_pdf_combine(&ConvertRequest { output_format: "png".to_string(), ..request }));
+    }
+
+    #[test]
+    fn test_pdf_page_outputs() {
+        assert_eq!(
+            pdf_page_output_pattern(Path::new("/tmp/out"), "100% report", "png"),
+            PathBuf::from("/tmp/out/100%% report-%03d.png")
+        );
+
+        let dir = std::env::temp_dir().join(format!("mediaforge-pages-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["doc-002.png", "doc-001.png", "doc-010.png", "doc-notes.png", "doc.png", "other-001.png"] {
+            std::fs::write(dir.join(name), b"").unwrap();
         }
+        let pages = collect_pdf_pages(&dir, "doc", "png");
+        let names: Vec<_> = pages
+            .iter()
+            .map(|p| Path::new(p).file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, vec!["doc-001.png", "doc-002.png", "doc-010.png"]);
+        let _ = std::fs::remove_dir_all(&dir);
     }
-    None
-}
 
-#[cfg(test)]
-mod error_recovery_tests {
-    use super::*;
-    use crate::error::{MediaForgeError, RetryConfig, retry_async};
-    
     #[test]
-    fn test_error_classification() {
-        // Test retryable errors
-        let network_error = MediaForgeError::NetworkError("Connection timeout".to_string());
-        assert!(network_error.is_retryable());
-        
-        let temp_error = MediaForgeError::TemporaryError("Resource unavailable".to_string());
-        assert!(temp_error.is_retryable());
-        
-        // Test non-retryable errors
-        let invalid_url = MediaForgeError::InvalidUrl("Not a valid YouTube URL".to_string());
-        assert!(!invalid_url.is_retryable());
-        
-        let invalid_settings = MediaForgeError::InvalidSettings("Bad format".to_string());
-        assert!(!invalid_settings.is_retryable());
+    fn test_image_optimize_args() {
+        let optimize_args = |format: &str, strip_metadata: bool| {
+            let settings = ImageSettings { optimize: true, strip_metadata, ..image_settings() };
+            args_to_strings(build_image_args(
+                Path::new("/tmp/in.png"),
+                Path::new(&format!("/tmp/out.{}", format)),
+                format,
+                Some(&settings),
+            ))
+        };
+
+        assert_eq!(
+            optimize_args("png", false),
+            vec!["/tmp/in.png", "-strip", "-define", "png:compression-level=9", "/tmp/out.png"]
+        );
+        // -strip is only passed once when metadata stripping is also asked for
+        assert_eq!(
+            optimize_args("jpg", true)[7..],
+            ["-quality", "82", "-strip", "-interlace", "Plane", "-sampling-factor", "4:2:0", "/tmp/out.jpg"]
+        );
+        assert_eq!(
+            optimize_args("webp", false),
+            vec!["/tmp/in.png", "-quality", "80", "-define", "webp:method=6", "/tmp/out.webp"]
+        );
+        assert_eq!(optimize_args("gif", false), vec!["/tmp/in.png", "/tmp/out.gif"]);
+
+        // A quality that was asked for wins over the default
+        let settings = ImageSettings { optimize: true, quality: Some(60), ..image_settings() };
+        let args = args_to_strings(build_image_args(Path::new("/tmp/in.png"), Path::new("/tmp/out.webp"), "webp", Some(&settings)));
+        assert_eq!(args[1..3], ["-quality", "60"]);
     }
-    
+
     #[test]
-    fn test_ytdlp_error_classification() {
-        use crate::downloader::DownloadManager;
-        
-        // Network errors (retryable)
-        let network_err = DownloadManager::classify_ytdlp_error("network timeout occurred", Some(1));
-        assert!(network_err.is_retryable());
-        
-        // Permission errors (not retryable) 
-        let private_err = DownloadManager::classify_ytdlp_error("private video not available", Some(2));
-        assert!(!private_err.is_retryable());
-        
-        // Disk space errors
-        let disk_err = DownloadManager::classify_ytdlp_error("no space left on device", None);
-        assert!(!disk_err.is_retryable()); // Disk space errors shouldn't be retried immediately
+    fn test_strip_metadata_args() {
+        let image = ImageSettings { strip_metadata: true, quality: Some(80), ..image_settings() };
+        assert_eq!(
+            args_to_strings(build_image_args(Path::new("/tmp/in.jpg"), Path::new("/tmp/out.jpg"), "jpg", Some(&image))),
+            vec!["/tmp/in.jpg", "-background", "#ffffff", "-alpha", "remove", "-alpha", "off", "-quality", "80", "-strip", "/tmp/out.jpg"]
+        );
+
+        let audio = AudioSettings { strip_metadata: true, ..audio_settings() };
+        assert_eq!(
+            args_to_strings(build_audio_args(Path::new("/tmp/in.wav"), Path::new("/tmp/out.ogg"), "ogg", Some(&audio), None, None)),
+            vec!["-i", "/tmp/in.wav", "-map_metadata", "-1", "-c:a", "libvorbis", "-vn", "-progress", "pipe:1", "-y", "/tmp/out.ogg"]
+        );
+
+        let video = VideoSettings { strip_metadata: true, ..Default::default() };
+        assert_eq!(
+            args_to_strings(build_video_args(Path::new("/tmp/in.mp4"), Path::new("/tmp/out.mp4"), Some(&video), None, None)),
+            vec!["-i", "/tmp/in.mp4", "-map_metadata", "-1", "-progress", "pipe:1", "-y", "/tmp/out.mp4"]
+        );
     }
-    
-    #[test]  
-    fn test_ffmpeg_error_classification() {
-        // Temporary errors (retryable)
-        let temp_err = ConversionManager::classify_ffmpeg_error("resource temporarily unavailable", Some(1));
-        assert!(temp_err.is_retryable());
-        
-        // Permission errors (not retryable)
-        let perm_err = ConversionManager::classify_ffmpeg_error("permission denied", Some(126));
-        assert!(!perm_err.is_retryable());
-        
-        // Invalid format errors (not retryable)
-        let format_err = ConversionManager::classify_ffmpeg_error("unsupported codec", Some(255));
-        assert!(!format_err.is_retryable());
+
+    #[test]
+    fn test_colorspace_and_icc_args() {
+        let run = |settings: &ImageSettings| {
+            args_to_strings(build_image_args(Path::new("/tmp/cmyk.tif"), Path::new("/tmp/out.png"), "png", Some(settings)))
+        };
+
+        // CMYK to sRGB goes through the sRGB profile, then drops the profile that came with the pixels
+        let profile = srgb_profile_path().unwrap().to_string_lossy().to_string();
+        let to_srgb = ImageSettings { colorspace: Some("srgb".to_string()), ..image_settings() };
+        assert!(validate_image_settings(&to_srgb).is_ok());
+        assert_eq!(
+            run(&to_srgb),
+            vec!["/tmp/cmyk.tif", "-profile", &profile, "-colorspace", "sRGB", "+profile", "icc", "/tmp/out.png"]
+        );
+
+        let stripped = ImageSettings { strip_metadata: true, ..to_srgb.clone() };
+        assert_eq!(run(&stripped), vec!["/tmp/cmyk.tif", "-profile", &profile, "-colorspace", "sRGB", "-strip", "/tmp/out.png"]);
+
+        let to_gray = ImageSettings { colorspace: Some("gray".to_string()), ..image_settings() };
+        assert_eq!(run(&to_gray), vec!["/tmp/cmyk.tif", "-colorspace", "Gray", "+profile", "icc", "/tmp/out.png"]);
+
+        let strip_icc = ImageSettings { strip_icc: true, ..image_settings() };
+        assert_eq!(run(&strip_icc), vec!["/tmp/cmyk.tif", "+profile", "icc", "/tmp/out.png"]);
+
+        let keep_icc = ImageSettings { strip_metadata: true, keep_icc: true, ..image_settings() };
+        assert_eq!(run(&keep_icc), vec!["/tmp/cmyk.tif", "+profile", "!icc,*", "/tmp/out.png"]);
+
+        assert!(validate_image_settings(&ImageSettings { colorspace: Some("LAB".to_string()), ..image_settings() }).is_err());
+        assert!(validate_image_settings(&ImageSettings { strip_icc: true, keep_icc: true, ..image_settings() }).is_err());
+        assert!(validate_image_settings(&ImageSettings { keep_icc: true, ..to_srgb }).is_err());
     }
-    
+
     #[test]
-    fn test_retry_config() {
-        let config = RetryConfig::for_network();
-        assert_eq!(config.max_attempts, 5);
-        assert_eq!(config.base_delay, 3);
-        
-        // Test exponential backoff
-        assert_eq!(config.calculate_delay(1), 3);  // Base delay for first retry
-        assert_eq!(config.calculate_delay(2), 6);  // 2x base delay 
-        assert_eq!(config.calculate_delay(3), 12); // 4x base delay
-        assert!(config.calculate_delay(10) <= config.max_delay); // Capped at max
+    fn test_preserve_metadata() {
+        let run = |settings: &VideoSettings| {
+            args_to_strings(build_video_args(Path::new("/tmp/in.mkv"), Path::new("/tmp/out.mp4"), Some(settings), None, None))
+        };
+        let dropped = VideoSettings { preserve_metadata: Some(false), ..Default::default() };
+        assert_eq!(
+            run(&dropped),
+            vec!["-i", "/tmp/in.mkv", "-map_metadata", "-1", "-map_chapters", "-1", "-progress", "pipe:1", "-y", "/tmp/out.mp4"]
+        );
+        let conflicting = VideoSettings { strip_metadata: true, preserve_metadata: Some(true), ..Default::default() };
+        assert!(validate_video_settings(&conflicting).is_err());
+
+        let mut probe = probe_with(&[("video", "h264")]);
+        assert_eq!(lost_chapters_warning(&probe, "avi", None), None);
+        probe.chapters = vec![
+            Chapter { start_secs: 0.0, end_secs: 2.0, title: None },
+            Chapter { start_secs: 2.0, end_secs: 4.0, title: None },
+        ];
+        assert_eq!(lost_chapters_warning(&probe, "AVI", None).as_deref(), Some("2 chapters left out; .avi can't hold chapters"));
+        assert_eq!(lost_chapters_warning(&probe, "mp4", None), None);
+        assert_eq!(lost_chapters_warning(&probe, "avi", Some(&dropped)), None);
     }
-    
+
     #[tokio::test]
-    async fn test_retry_mechanism() {
-        let mut call_count = 0;
-        let config = RetryConfig {
-            max_attempts: 3,
-            base_delay: 0, // No delay for testing
-            max_delay: 0,
-            exponential_backoff: false,
+    async fn test_chapters_survive_conversion() {
+        if !ffmpeg_available() {
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!("mediaforge-chapters-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let metadata = dir.join("chapters.txt");
+        std::fs::write(
+            &metadata,
+            ";FFMETADATA1\ntitle=Fixture\n\
+             [CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=1000\ntitle=One\n\
+             [CHAPTER]\nTIMEBASE=1/1000\nSTART=1000\nEND=2000\ntitle=Two\n",
+        )
+        .unwrap();
+
+        // A two-second fixture with two chapters
+        let input = dir.join("in.mkv");
+        let status = TokioCommand::new("ffmpeg")
+            .args(["-f", "lavfi", "-i", "testsrc=duration=2:size=64x64:rate=10", "-i"])
+            .arg(&metadata)
+            .args(["-map", "0:v", "-map_metadata", "1", "-map_chapters", "1", "-c:v", "mpeg4", "-y"])
+            .arg(&input)
+            .output()
+            .await
+            .unwrap()
+            .status;
+        assert!(status.success());
+
+        let output = dir.join("out.mp4");
+        let status = TokioCommand::new("ffmpeg")
+            .args(build_video_args(&input, &output, Some(&VideoSettings::default()), None, None))
+            .output()
+            .await
+            .unwrap()
+            .status;
+        assert!(status.success());
+
+        let probe = probe_media(&output).await.unwrap();
+        let titles: Vec<_> = probe.chapters.iter().map(|c| c.title.as_deref()).collect();
+        assert_eq!(titles, [Some("One"), Some("Two")]);
+
+        let request = ConvertRequest {
+            input_files: vec![input.clone()],
+            output_path: dir.to_string_lossy().into_owned(),
+            ..test_request(ConversionType::Video, "mp4")
         };
-        
-        // Test successful retry
-        let result = retry_async(config.clone(), || {
-            call_count += 1;
-            async move {
-                if call_count < 3 {
-                    Err(MediaForgeError::NetworkError("Temporary failure".to_string()))
-                } else {
-                    Ok("Success".to_string())
-                }
-            }
-        }).await;
-        
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), "Success");
-        assert_eq!(call_count, 3);
-        
-        // Test non-retryable error  
-        let result2: Result<String, MediaForgeError> = retry_async(config, || async {
-            Err(MediaForgeError::InvalidUrl("Bad URL".to_string()))
-        }).await;
-        
-        assert!(result2.is_err());
-        // Should fail immediately without retries for non-retryable errors
+        assert!(check_output_chapters(&request, &input, &output).await.is_ok());
+
+        let _ = std::fs::remove_dir_all(&dir);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tokio_util::sync::CancellationToken;
+    #[tokio::test]
+    async fn test_image_metadata_stripped_from_output() {
+        if !magick_available() {
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!("mediaforge-strip-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("in.jpg");
+        let output = dir.join("out.jpg");
+
+        // A JPEG carrying a comment and an EXIF profile, like a camera photo would
+        let status = TokioCommand::new("magick")
+            .args(["-size", "16x16", "xc:red", "-set", "comment", "Taken at 51.5N 0.1W", "-set", "exif:Make", "TestCam"])
+            .arg(&input)
+            .output()
+            .await
+            .unwrap()
+            .status;
+        assert!(status.success());
+
+        let settings = ImageSettings { strip_metadata: true, ..image_settings() };
+        let status = TokioCommand::new("magick")
+            .args(build_image_args(&input, &output, "jpg", Some(&settings)))
+            .output()
+            .await
+            .unwrap()
+            .status;
+        assert!(status.success());
+
+        let identify = TokioCommand::new("magick")
+            .args(["identify", "-verbose"])
+            .arg(&output)
+            .output()
+            .await
+            .unwrap();
+        let verbose = String::from_utf8_lossy(&identify.stdout);
+        assert!(!verbose.contains("comment:"));
+        assert!(!verbose.contains("exif:"));
+        assert!(!verbose.contains("Profile-exif"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_cmyk_converted_to_srgb() {
+        let profile = srgb_profile_path().expect("the sRGB profile can be written");
+        let bytes = std::fs::read(profile).unwrap();
+        assert_eq!(&bytes[36..40], b"acsp");
+
+        if !magick_available() {
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!("mediaforge-icc-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let settings = ImageSettings { colorspace: Some("sRGB".to_string()), ..image_settings() };
+
+        // A CMYK TIFF without a profile, and a PNG that carries the sRGB one
+        let cmyk = dir.join("cmyk.tif");
+        let tagged = dir.join("tagged.png");
+        for (input, setup) in [(&cmyk, vec!["-colorspace", "CMYK"]), (&tagged, vec!["-profile", profile.to_str().unwrap()])] {
+            let status = TokioCommand::new("magick")
+                .args(["-size", "8x8", "xc:#00FFFF"])
+                .args(setup)
+                .arg(input)
+                .output()
+                .await
+                .unwrap()
+                .status;
+            assert!(status.success());
+
+            let output = dir.join("out.png");
+            let convert = TokioCommand::new("magick")
+                .args(build_image_args(input, &output, "png", Some(&settings)))
+                .output()
+                .await
+                .unwrap();
+            assert!(convert.status.success(), "{}", String::from_utf8_lossy(&convert.stderr));
+
+            // The cyan survives the conversion and no profile is left on the output
+            let identify = TokioCommand::new("magick")
+                .args(["identify", "-format", "%[colorspace] %[fx:int(255*p{0,0}.r)],%[fx:int(255*p{0,0}.g)],%[fx:int(255*p{0,0}.b)]"])
+                .arg(&output)
+                .output()
+                .await
+                .unwrap();
+            let info = String::from_utf8_lossy(&identify.stdout).to_string();
+            let (colorspace, rgb) = info.split_once(' ').unwrap();
+            assert_eq!(colorspace, "sRGB", "{:?}", input);
+            let rgb: Vec<i32> = rgb.split(',').map(|c| c.parse().unwrap()).collect();
+            assert!(rgb[0] < 16 && rgb[1] > 239 && rgb[2] > 239, "{:?}: {}", input, info);
+
+            let verbose = TokioCommand::new("magick").args(["identify", "-verbose"]).arg(&output).output().await.unwrap();
+            assert!(!String::from_utf8_lossy(&verbose.stdout).contains("Profile-icc"), "{:?}", input);
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_image_dpi_written_to_output() {
+        assert!(validate_image_settings(&ImageSettings { dpi: Some(2400), ..image_settings() }).is_ok());
+        assert!(validate_image_settings(&ImageSettings { dpi: Some(0), ..image_settings() }).is_err());
+        assert!(validate_image_settings(&ImageSettings { dpi: Some(2401), ..image_settings() }).is_err());
+
+        if !magick_available() {
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!("mediaforge-dpi-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("in.png");
+        let output = dir.join("out.png");
+
+        let status = TokioCommand::new("magick")
+            .args(["-size", "32x24", "xc:blue", "-units", "PixelsPerInch", "-density", "72"])
+            .arg(&input)
+            .output()
+            .await
+            .unwrap()
+            .status;
+        assert!(status.success());
+
+        let settings = ImageSettings { dpi: Some(300), ..image_settings() };
+        let status = TokioCommand::new("magick")
+            .args(build_image_args(&input, &output, "png", Some(&settings)))
+            .output()
+            .await
+            .unwrap()
+            .status;
+        assert!(status.success());
+
+        // The resolution changes but the pixels don't
+        let identify = TokioCommand::new("magick")
+            .args(["identify", "-units", "PixelsPerInch", "-format", "%x %y %w %h"])
+            .arg(&output)
+            .output()
+            .await
+            .unwrap();
+        let info = String::from_utf8_lossy(&identify.stdout);
+        let fields: Vec<&str> = info.split_whitespace().collect();
+        assert_eq!(fields.len(), 4, "{}", info);
+        assert_eq!(fields[0].parse::<f64>().unwrap().round(), 300.0);
+        assert_eq!(fields[1].parse::<f64>().unwrap().round(), 300.0);
+        assert_eq!(&fields[2..], ["32", "24"]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 
     #[test]
     fn test_conversion_task_handle_creation() {
@@ -1222,7 +7126,7 @@ mod tests {
     fn test_conversion_manager_task_handles() {
         let rt = tokio::runtime::Runtime::new().unwrap();
         rt.block_on(async {
-            let manager = ConversionManager::new();
+            let manager = ConversionManager::new(&Config::default(), Arc::default());
             
             // Create a task
             let task_id = manager.create_task("Test Conversion".to_string());
@@ -1252,7 +7156,7 @@ mod tests {
     fn test_child_process_cleanup() {
         let rt = tokio::runtime::Runtime::new().unwrap();
         rt.block_on(async {
-            let manager = ConversionManager::new();
+            let manager = ConversionManager::new(&Config::default(), Arc::default());
             
             // Create a task
             let task_id = manager.create_task("Test Process Cleanup".to_string());
@@ -1287,7 +7191,7 @@ mod tests {
     fn test_cancel_conversion_task() {
         let rt = tokio::runtime::Runtime::new().unwrap();
         rt.block_on(async {
-            let manager = ConversionManager::new();
+            let manager = ConversionManager::new(&Config::default(), Arc::default());
             
             // Create a task
             let task_id = manager.create_task("Test Cancellation".to_string());
@@ -1303,4 +7207,33 @@ mod tests {
             }
         });
     }
+
+    #[test]
+    fn test_shutdown_is_bounded() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let manager = ConversionManager::new(&Config::default(), Arc::default());
+
+            // One task stops when cancelled, the other ignores the token
+            let cooperative = manager.create_task("Cooperative".to_string());
+            let token = CancellationToken::new();
+            let watched = token.clone();
+            let join_handle = tokio::spawn(async move { watched.cancelled().await });
+            manager.task_handles.insert(cooperative.clone(), TaskHandle::new(join_handle, token));
+
+            let stuck = manager.create_task("Stuck".to_string());
+            let join_handle = tokio::spawn(async { tokio::time::sleep(Duration::from_secs(3600)).await });
+            manager.task_handles.insert(stuck.clone(), TaskHandle::new(join_handle, CancellationToken::new()));
+
+            let started = std::time::Instant::now();
+            assert_eq!(manager.shutdown(Duration::from_millis(100)).await, 2);
+            assert!(started.elapsed() < Duration::from_secs(5));
+            assert!(manager.task_handles.is_empty());
+            for task_id in [&cooperative, &stuck] {
+                let task = manager.get_task(task_id).unwrap();
+                assert_eq!(task.status, TaskStatus::Interrupted);
+                assert_eq!(task.error.as_deref(), Some(INTERRUPTED_BY_EXIT));
+            }
+        });
+    }
 }
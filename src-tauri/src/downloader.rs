@@ -1,50 +1,46 @@
-use crate::error::MediaForgeError;
+use crate::batch::{batch_progress, BatchTracker, BATCH_NOTIFICATION_THRESHOLD};
+use crate::config::Config;
+use crate::error::validation::{self, sanitize_path};
+use crate::error::{http_error_status, CircuitBreaker, MediaForgeError};
+use crate::events::{self, TaskUpdateEmitter};
+use crate::history::{self, DownloadHistory};
+use crate::hooks;
 use crate::notifications;
+use crate::process::{run_cancellable_command, CommandOutcome};
+use crate::queue::TaskQueue;
+use crate::system::ToolPaths;
+use crate::tasks::{self, TaskHandle};
 use crate::types::*;
 use dashmap::DashMap;
 use regex::Regex;
-use std::path::PathBuf;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use tauri::Emitter;
-use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command as TokioCommand;
-use tokio::task::JoinHandle;
 use tokio::time::Duration;
 use tokio_util::sync::CancellationToken;
 use tracing::{info, warn, error};
 use uuid::Uuid;
 
-/// Task handle for managing async download operations
-#[derive(Debug)]
-struct TaskHandle {
-    join_handle: JoinHandle<()>,
-    cancellation_token: CancellationToken,
-}
+/// Download timeout used when neither the request nor the settings specify one (1 hour)
+pub const DEFAULT_DOWNLOAD_TIMEOUT_SECS: u64 = 3600;
 
-impl TaskHandle {
-    fn new(join_handle: JoinHandle<()>, cancellation_token: CancellationToken) -> Self {
-        Self {
-            join_handle,
-            cancellation_token,
-        }
-    }
-    
-    /// Cancel the task and wait for it to complete
-    async fn cancel(self) -> Result<(), tokio::task::JoinError> {
-        self.cancellation_token.cancel();
-        self.join_handle.await
-    }
-    
-    /// Check if the task is cancelled
-    fn is_cancelled(&self) -> bool {
-        self.cancellation_token.is_cancelled()
-    }
+/// Subtitle language used when the request does not specify any
+const DEFAULT_SUBTITLE_LANG: &str = "en";
+
+/// What a finished download task did about its output
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DownloadOutcome {
+    Downloaded,
+    /// The file was already on disk and kept under the skip policy
+    KeptExisting,
 }
 
-/// Validates YouTube URL to prevent malicious schemes and ensure valid YouTube URLs
-fn validate_youtube_url(url: &str) -> Result<(), MediaForgeError> {
-    // Check for malicious schemes
+/// Rejects malicious schemes and characters that could be used for shell injection
+fn validate_url_safety(url: &str) -> Result<(), MediaForgeError> {
     if url.starts_with("file://") 
         || url.starts_with("javascript:")
         || url.starts_with("data:")
@@ -64,6 +60,13 @@ fn validate_youtube_url(url: &str) -> Result<(), MediaForgeError> {
         ));
     }
     
+    Ok(())
+}
+
+/// Validates YouTube URL to prevent malicious schemes and ensure valid YouTube URLs
+fn validate_youtube_url(url: &str) -> Result<(), MediaForgeError> {
+    validate_url_safety(url)?;
+    
     // Valid YouTube URL patterns
     let valid_patterns = vec![
         r"^https?://(www\.)?youtube\.com/watch\?v=[\w-]{11}(&.*)?$",
@@ -71,6 +74,8 @@ fn validate_youtube_url(url: &str) -> Result<(), MediaForgeError> {
         r"^https?://(www\.)?youtube\.com/playlist\?list=[\w-]+(&.*)?$",
         r"^https?://(music\.)?youtube\.com/watch\?v=[\w-]{11}(&.*)?$",
         r"^https?://(www\.)?youtube\.com/shorts/[\w-]{11}(\?.*)?$",
+        r"^https?://(www\.)?youtube\.com/live/[\w-]{11}(\?.*)?$",
+        r"^https?://(www\.)?youtube\.com/(@[\w.-]+|channel/[\w-]+|c/[\w-]+)/live/?$",
     ];
     
     for pattern in valid_patterns {
@@ -85,95 +90,195 @@ fn validate_youtube_url(url: &str) -> Result<(), MediaForgeError> {
     ))
 }
 
-/// Sanitizes file paths to prevent path traversal and ensure paths are within allowed directories
-fn sanitize_path(path: &str) -> Result<PathBuf, MediaForgeError> {
-    // Expand tilde to home directory
-    let expanded_path = if path.starts_with("~/") {
-        let home = std::env::var("HOME")
-            .or_else(|_| std::env::var("USERPROFILE"))  // Windows support
-            .unwrap_or_else(|_| "/home".to_string());
-        path.replacen("~", &home, 1)
+/// Music sites accepted without generic mode; their downloads get album folders,
+/// track numbers and embedded tags
+const MUSIC_SITE_PATTERNS: [&str; 2] = [
+    r"^https?://((www|m|on)\.)?soundcloud\.com/[\w-]+(/[\w-]+)*/?(\?\S*)?$",
+    r"^https?://([\w-]+\.)?bandcamp\.com(/[\w-]+)*/?(\?\S*)?$",
+];
+
+/// Whether the URL points to one of the supported music sites
+fn is_music_site_url(url: &str) -> bool {
+    MUSIC_SITE_PATTERNS
+        .iter()
+        .any(|pattern| Regex::new(pattern).unwrap().is_match(url))
+}
+
+/// yt-dlp arguments for music site downloads. Playlist tracks go in a folder named
+/// after the album and are numbered by their album track number, falling back to
+/// their playlist position, which is also written as the track number tag. A custom
+/// `template` replaces that naming; the tagging is kept.
+fn music_download_args(output_path: &Path, format_ext: &str, playlist: bool, template: Option<&Path>) -> Vec<String> {
+    let template = if let Some(template) = template {
+        template.to_path_buf()
+    } else if playlist {
+        output_path.join(format!(
+            "%(album,playlist_title)s/%(track_number,playlist_index)02d - %(artist,uploader)s - %(track,title)s.{}",
+            format_ext
+        ))
     } else {
-        path.to_string()
+        output_path.join(format!("%(artist,uploader)s - %(track,title)s.{}", format_ext))
     };
-    
-    let path_buf = PathBuf::from(&expanded_path);
-    
-    // Prevent path traversal attacks
-    for component in path_buf.components() {
-        if matches!(component, std::path::Component::ParentDir) {
-            return Err(MediaForgeError::InvalidSettings(
-                "Path traversal detected: '..' not allowed in paths".into()
-            ));
+
+    let mut args = vec!["-o".to_string(), template.to_string_lossy().to_string()];
+    if playlist {
+        args.push("--parse-metadata".to_string());
+        args.push("%(track_number,playlist_index)s:%(track_number)s".to_string());
+    }
+    args.push("--embed-metadata".to_string());
+    args
+}
+
+/// Fields accepted in custom output templates
+const TEMPLATE_FIELDS: [&str; 28] = [
+    "title", "id", "ext", "uploader", "uploader_id", "channel", "channel_id", "upload_date",
+    "release_date", "timestamp", "duration", "duration_string", "playlist", "playlist_title",
+    "playlist_id", "playlist_index", "autonumber", "album", "artist", "track", "track_number",
+    "resolution", "height", "width", "fps", "format_id", "extractor", "webpage_url_domain",
+];
+
+const MAX_TEMPLATE_LEN: usize = 255;
+
+/// Checks a custom output template such as `%(uploader)s/%(title)s [%(id)s].%(ext)s`.
+/// Templates are relative to the download folder, may only use the fields in
+/// `TEMPLATE_FIELDS` (with `,` fallbacks and a width for numbers), and can't contain
+/// `..` segments or characters that are unsafe in file names or shells.
+fn validate_output_template(template: &str) -> Result<(), MediaForgeError> {
+    let invalid = |reason: String| {
+        Err(MediaForgeError::InvalidSettings(format!("Invalid output template '{}': {}", template, reason)))
+    };
+
+    if template.trim().is_empty() || template.len() > MAX_TEMPLATE_LEN {
+        return invalid(format!("must be 1 to {} characters", MAX_TEMPLATE_LEN));
+    }
+    if template.starts_with('/') || template.starts_with('~') {
+        return invalid("must be relative to the download folder".to_string());
+    }
+    if let Some(c) = template.chars().find(|c| c.is_control() || "\\:*?\"<>|`$;&".contains(*c)) {
+        return invalid(format!("'{}' is not allowed", c.escape_default()));
+    }
+    if template.split('/').any(|segment| segment.trim().is_empty() || segment == "." || segment == "..") {
+        return invalid("folders can't be empty, '.' or '..'".to_string());
+    }
+
+    let placeholder = Regex::new(r"%\(([a-z_]+(?:,[a-z_]+)*)\)(?:0?[1-9][0-9]?)?[sd]").unwrap();
+    for caps in placeholder.captures_iter(template) {
+        if let Some(field) = caps[1].split(',').find(|field| !TEMPLATE_FIELDS.contains(field)) {
+            return invalid(format!("unknown field '{}'", field));
         }
     }
-    
-    // Check for other dangerous path components
-    if expanded_path.contains("//") || expanded_path.contains("\\\\") {
-        return Err(MediaForgeError::InvalidSettings(
-            "Invalid path: double separators not allowed".into()
-        ));
+    // Anything left that starts with % is a placeholder form that isn't supported
+    if placeholder.replace_all(template, "").replace("%%", "").contains('%') {
+        return invalid("placeholders must look like %(title)s or %(playlist_index)03d".to_string());
     }
+    Ok(())
+}
+
+/// Full output template for a download saved as one file. `%(ext)s` becomes the
+/// requested format's extension, which is appended when the template has none.
+fn single_output_template(output_path: &Path, request: &DownloadRequest) -> PathBuf {
+    let ext = media_extension(&request.format);
+    match request.output_template.as_deref() {
+        Some(template) if template.contains("%(ext)s") => output_path.join(template.replace("%(ext)s", ext)),
+        Some(template) => output_path.join(format!("{}.{}", template, ext)),
+        None => output_path.join(format!("%(title)s.{}", ext)),
+    }
+}
+
+/// Whether the download keeps the best video and audio as separate, unmerged files
+fn keeps_separate_streams(request: &DownloadRequest) -> bool {
+    request.merge == Some(false)
+}
+
+/// Output template for unmerged video and audio. Both can have the same extension, so
+/// the format id tells them apart, as in yt-dlp's own `Title.f137.mp4` names.
+fn separate_streams_template(output_path: &Path, request: &DownloadRequest) -> PathBuf {
+    match request.output_template.as_deref() {
+        Some(template) if template.contains("%(ext)s") => {
+            output_path.join(template.replace("%(ext)s", "f%(format_id)s.%(ext)s"))
+        }
+        Some(template) => output_path.join(format!("{}.f%(format_id)s.%(ext)s", template)),
+        None => output_path.join("%(title)s.f%(format_id)s.%(ext)s"),
+    }
+}
+
+/// Validates a download URL. YouTube and the supported music sites are accepted by
+/// default; generic mode keeps the safety checks but accepts any http(s) host,
+/// leaving it to yt-dlp to find an extractor for the site.
+fn validate_url(url: &str, allow_generic: bool) -> Result<(), MediaForgeError> {
+    validate_url_safety(url)?;
     
-    // Ensure path is absolute or can be made absolute
-    let canonical_path = if path_buf.is_absolute() {
-        path_buf
-    } else {
-        std::env::current_dir()
-            .map_err(|e| MediaForgeError::FileSystemError(format!("Cannot get current directory: {}", e)))?
-            .join(path_buf)
-    };
-    
-    // Verify the parent directory exists or can be created
-    if let Some(parent) = canonical_path.parent() {
-        if !parent.exists() {
-            std::fs::create_dir_all(parent)
-                .map_err(|e| MediaForgeError::FileSystemError(format!("Cannot create directory: {}", e)))?;
+    if !allow_generic {
+        if is_music_site_url(url) {
+            return Ok(());
         }
+        return validate_youtube_url(url);
     }
     
-    // Ensure path is within reasonable bounds (not system directories)
-    let path_str = canonical_path.to_string_lossy();
-    if path_str.starts_with("/etc") 
-        || path_str.starts_with("/sys") 
-        || path_str.starts_with("/proc")
-        || path_str.starts_with("/boot")
-        || path_str.starts_with("/root")  // Unless we're root
-        || path_str.contains("/.ssh/")
-        || path_str.contains("/.gnupg/")
-    {
-        return Err(MediaForgeError::InvalidSettings(
-            "Access to system directories is not allowed".into()
+    let re = Regex::new(r"^https?://[A-Za-z0-9-]+(\.[A-Za-z0-9-]+)*(:\d{1,5})?([/?#]\S*)?$").unwrap();
+    if !re.is_match(url) {
+        return Err(MediaForgeError::InvalidUrl(
+            "URL must be an http or https link".into()
         ));
     }
     
-    Ok(canonical_path)
+    Ok(())
 }
 
 pub struct DownloadManager {
     tasks: Arc<DashMap<String, TaskProgress>>,
     task_handles: Arc<DashMap<String, TaskHandle>>,
+    queue: Arc<TaskQueue>,
+    /// Shared by all downloads so a throttling host pauses the whole queue
+    circuit_breaker: Arc<CircuitBreaker>,
+    batches: Arc<BatchTracker>,
+    history: Arc<DownloadHistory>,
+    /// Timeout for requests that don't set one; 0 disables it. Follows the settings.
+    default_timeout_secs: Arc<AtomicU64>,
+    /// Most unfinished tasks held at once. Follows the settings.
+    max_queue_size: Arc<AtomicUsize>,
+    tools: Arc<ToolPaths>,
+    updates: Arc<TaskUpdateEmitter>,
+    /// Set once the app quits, so tasks cancelled from then on count as interrupted
+    shutting_down: Arc<AtomicBool>,
 }
 
 impl DownloadManager {
-    pub fn new() -> Self {
+    pub fn new(config: &Config, tools: Arc<ToolPaths>) -> Self {
         Self {
             tasks: Arc::new(DashMap::new()),
             task_handles: Arc::new(DashMap::new()),
+            queue: TaskQueue::new(config.max_concurrent_downloads),
+            circuit_breaker: Arc::new(CircuitBreaker::for_network()),
+            batches: Arc::new(BatchTracker::new()),
+            history: Arc::new(DownloadHistory::new()),
+            default_timeout_secs: Arc::new(AtomicU64::new(config.download_timeout_secs)),
+            max_queue_size: Arc::new(AtomicUsize::new(config.max_queue_size)),
+            tools,
+            updates: Arc::new(TaskUpdateEmitter::default()),
+            shutting_down: Arc::new(AtomicBool::new(false)),
         }
     }
 
+    /// Picks up changed settings; the concurrency limit still waits for the next start
+    pub fn apply_config(&self, config: &Config) {
+        self.default_timeout_secs.store(config.download_timeout_secs, Ordering::Relaxed);
+        self.max_queue_size.store(config.max_queue_size, Ordering::Relaxed);
+    }
+
+    /// Refuses a request that would take the unfinished tasks past the queue limit
+    fn check_queue_capacity(&self, adding: usize) -> Result<(), MediaForgeError> {
+        let active = self.tasks.iter().filter(|task| !task.is_finished()).count();
+        crate::queue::check_capacity(active, adding, self.max_queue_size.load(Ordering::Relaxed))
+    }
+
     pub fn create_task(&self, name: String) -> String {
         let task_id = Uuid::new_v4().to_string();
         let task = TaskProgress {
             task_id: task_id.clone(),
             name,
             status: TaskStatus::Queued,
-            progress: 0.0,
-            speed: None,
-            eta: None,
-            error: None,
-            file_path: None,
+            ..Default::default()
         };
         self.tasks.insert(task_id.clone(), task);
         task_id
@@ -189,12 +294,127 @@ impl DownloadManager {
 
     pub fn update_task(&self, task_id: &str, update: impl FnOnce(&mut TaskProgress)) {
         if let Some(mut task) = self.tasks.get_mut(task_id) {
+            let previous = task.status.clone();
             update(&mut task);
+            task.record_status_change(&previous, unix_millis());
+        }
+    }
+
+    /// Returns whether the task was in the list
+    pub fn remove_task(&self, task_id: &str) -> bool {
+        self.tasks.remove(task_id).is_some()
+    }
+
+    /// Removes every completed, failed and cancelled task, returning their ids
+    pub fn clear_finished_tasks(&self) -> Vec<String> {
+        let mut removed = Vec::new();
+        self.tasks.retain(|task_id, task| {
+            if task.is_finished() {
+                removed.push(task_id.clone());
+            }
+            !task.is_finished()
+        });
+        removed
+    }
+
+    /// Totals for the tasks created by one `start_download` call
+    pub fn get_batch(&self, batch_id: &str) -> Option<BatchProgress> {
+        batch_progress(batch_id, self.get_all_tasks().iter())
+    }
+
+    /// Tasks in large batches leave notifying to the batch summary
+    fn notify_individually(&self, task: &TaskProgress) -> bool {
+        self.tasks.iter().filter(|t| t.batch_id == task.batch_id).count() <= BATCH_NOTIFICATION_THRESHOLD
+    }
+
+    fn emit_batch_update(&self, batch_id: &str, app_handle: &tauri::AppHandle) -> Option<BatchProgress> {
+        let batch = self.get_batch(batch_id)?;
+        let _ = app_handle.emit("batch-update", &batch);
+        Some(batch)
+    }
+
+    /// Sends the summary notification for a finished batch that was too large for
+    /// per-task notifications
+    fn notify_batch_complete(&self, batch: Option<BatchProgress>, app_handle: &tauri::AppHandle) {
+        let Some(batch) = batch.filter(|b| b.total > BATCH_NOTIFICATION_THRESHOLD) else {
+            return;
+        };
+        let target = self
+            .get_all_tasks()
+            .into_iter()
+            .filter(|t| t.batch_id == batch.batch_id && t.status == TaskStatus::Completed)
+            .find_map(|t| t.file_path)
+            .map(PathBuf::from);
+        notifications::send_batch_complete_notification(app_handle, "Downloads Complete", "Downloaded", &batch, target);
+    }
+
+    /// Called exactly once when a task ends, whatever its final state
+    async fn finish_task(&self, task_id: &str, url: &str, request: &DownloadRequest, app_handle: &tauri::AppHandle) {
+        if let Err(e) = self.record_history(task_id, url, request, app_handle).await {
+            warn!(task_id = task_id, error = %e, "Failed to record download history");
+        }
+
+        let Some(task) = self.get_task(task_id) else {
+            return;
+        };
+        let batch = self.emit_batch_update(&task.batch_id, app_handle);
+        if self.batches.finish(&task.batch_id) {
+            self.notify_batch_complete(batch, app_handle);
+        }
+    }
+
+    async fn record_history(
+        &self,
+        task_id: &str,
+        url: &str,
+        request: &DownloadRequest,
+        app_handle: &tauri::AppHandle,
+    ) -> Result<(), MediaForgeError> {
+        let Some(task) = self.get_task(task_id) else {
+            return Ok(());
+        };
+
+        let duration_ms = task.duration_ms();
+        let mut files: Vec<String> = task.file_path.into_iter().collect();
+        for file in task.output_files {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+        let mut sizes = Vec::new();
+        for file in &files {
+            if let Ok(metadata) = tokio::fs::metadata(file).await {
+                sizes.push(metadata.len());
+            }
         }
+
+        let format = match request.format {
+            _ if request.subtitles_only => "subtitles",
+            MediaFormat::Mp4 => "mp4",
+            MediaFormat::Mp3 => "mp3",
+        };
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        let entry = HistoryEntry {
+            task_id: task.task_id,
+            title: task.name,
+            url: url.to_string(),
+            output_path: files.into_iter().next(),
+            format: format.to_string(),
+            status: task.status,
+            timestamp,
+            size_bytes: (!sizes.is_empty()).then(|| sizes.iter().sum()),
+            duration_ms,
+        };
+        self.history.record(history::history_path(app_handle)?, entry).await
     }
 
-    pub fn remove_task(&self, task_id: &str) {
-        self.tasks.remove(task_id);
+    /// Finished downloads matching the query, newest first
+    pub async fn get_history(&self, query: HistoryQuery, app_handle: &tauri::AppHandle) -> Result<HistoryPage, MediaForgeError> {
+        self.history.query(history::history_path(app_handle)?, query).await
     }
 
     pub async fn start_download(
@@ -205,18 +425,34 @@ impl DownloadManager {
         // Validate download path before processing any URLs
         let _sanitized_path = sanitize_path(&request.download_path)?;
         
+        validate_download_options(&request)?;
+        
+        // Live recordings run as long as the stream does unless a timeout is given explicitly
+        let requested_timeout = request.timeout_secs.or(request.live.then_some(0));
+        let timeout_secs = validation::resolve_timeout(requested_timeout, self.default_timeout_secs.load(Ordering::Relaxed))?;
+        
+        // Validate every URL before creating any task so a batch is never left half-created
+        for url in request.urls.iter() {
+            validate_url(url, request.allow_generic)?;
+        }
+        self.check_queue_capacity(request.urls.len())?;
+        
         let mut task_ids = Vec::new();
+        let batch_id = Uuid::new_v4().to_string();
 
         for url in request.urls.iter() {
-            // Validate each URL before creating task
-            validate_youtube_url(url)?;
-            
             let task_id = self.create_task(format!("Downloading from {}", url));
             
-            // Set task to Downloading status BEFORE spawning to prevent race condition
+            // Tasks stay Queued until the queue admits them
+            let priority = request.priority.unwrap_or(0);
             self.update_task(&task_id, |task| {
-                task.status = TaskStatus::Downloading;
+                task.is_live = request.live;
+                task.priority = Some(priority);
+                task.batch_id = batch_id.clone();
             });
+            events::emit_task_created(&app_handle, self.get_task(&task_id));
+            self.queue.enqueue(&task_id, priority);
+            self.batches.add(&batch_id);
             
             task_ids.push(task_id.clone());
 
@@ -226,15 +462,45 @@ impl DownloadManager {
             let app_handle_clone = app_handle.clone();
             let app_handle_clone2 = app_handle.clone();
             let task_id_clone = task_id.clone();
+            let batch_id_clone = batch_id.clone();
             
             // Create cancellation token for this task
             let cancellation_token = CancellationToken::new();
             let cancellation_token_clone = cancellation_token.clone();
 
             let join_handle = tokio::spawn(async move {
-                // Run the download with timeout and cancellation support
+                // Wait for a download slot; higher-priority tasks are admitted first
+                let _slot = tokio::select! {
+                    slot = manager.queue.acquire(&task_id_clone) => slot,
+                    _ = cancellation_token_clone.cancelled() => {
+                        info!(
+                            task_id = task_id_clone,
+                            url = %url,
+                            "Queued task was cancelled before starting"
+                        );
+                        manager.update_task(&task_id_clone, |task| {
+                            task.mark_cancelled(manager.shutting_down.load(Ordering::Relaxed));
+                        });
+                        manager.task_handles.remove(&task_id_clone);
+                        manager.finish_task(&task_id_clone, &url, &req, &app_handle_clone2).await;
+                        return;
+                    }
+                };
+                
+                manager.update_task(&task_id_clone, |task| {
+                    task.status = TaskStatus::Downloading;
+                });
+                manager.updates.emit(&app_handle_clone2, manager.get_task(&task_id_clone));
+                manager.emit_batch_update(&batch_id_clone, &app_handle_clone2);
+                
+                // Run the download and its post-process command with timeout and cancellation support
+                let download = async {
+                    let outcome = manager.download_single_cancellable(&task_id_clone, &url, &req, app_handle_clone, cancellation_token_clone.clone()).await?;
+                    manager.post_process(&task_id_clone, &req, &app_handle_clone2, &cancellation_token_clone).await?;
+                    Ok::<_, MediaForgeError>(outcome)
+                };
                 let result = tokio::select! {
-                    result = manager.download_single_cancellable(&task_id_clone, &url, &req, app_handle_clone, cancellation_token_clone.clone()) => {
+                    result = download => {
                         result
                     }
                     _ = cancellation_token_clone.cancelled() => {
@@ -244,48 +510,59 @@ impl DownloadManager {
                             "Task was cancelled by user request"
                         );
                         manager.update_task(&task_id_clone, |task| {
-                            task.status = TaskStatus::Cancelled;
-                            task.error = Some("Task was cancelled by user".to_string());
+                            task.mark_cancelled(manager.shutting_down.load(Ordering::Relaxed));
                         });
                         // Clean up task handle on cancellation
                         manager.task_handles.remove(&task_id_clone);
+                        manager.finish_task(&task_id_clone, &url, &req, &app_handle_clone2).await;
                         return;
                     }
-                    _ = tokio::time::sleep(Duration::from_secs(3600)) => {
+                    _ = validation::sleep_for_timeout(timeout_secs) => {
+                        let timeout = timeout_secs.unwrap_or_default();
                         warn!(
                             task_id = task_id_clone,
                             url = %url,
-                            timeout_seconds = 3600,
+                            timeout_seconds = timeout,
                             "Download task timed out"
                         );
+                        let message = format!("Download timed out after {}", validation::format_timeout(timeout));
                         manager.update_task(&task_id_clone, |task| {
                             task.status = TaskStatus::Failed;
-                            task.error = Some("Download timed out after 1 hour".to_string());
+                            task.error = Some(message.clone());
                         });
+                        let cause = MediaForgeError::TemporaryError(message.clone());
+                        events::emit_task_failed(&app_handle_clone2, &task_id_clone, message, &cause);
                         // Clean up task handle on timeout
                         manager.task_handles.remove(&task_id_clone);
+                        manager.finish_task(&task_id_clone, &url, &req, &app_handle_clone2).await;
                         return;
                     }
                 };
                 
-                if let Err(e) = result {
-                    error!(
-                        task_id = task_id_clone,
-                        url = %url,
-                        error = %e,
-                        error_type = std::any::type_name_of_val(&e),
-                        "Download task failed with error"
-                    );
-                    manager.update_task(&task_id_clone, |task| {
-                        task.status = TaskStatus::Failed;
-                        task.error = Some(e.to_string());
-                    });
-                    // Clean up task handle on error
-                    manager.task_handles.remove(&task_id_clone);
+                match result {
+                    Err(e) => {
+                        error!(
+                            task_id = task_id_clone,
+                            url = %url,
+                            error = %e,
+                            error_type = std::any::type_name_of_val(&e),
+                            "Download task failed with error"
+                        );
+                        manager.update_task(&task_id_clone, |task| {
+                            task.status = TaskStatus::Failed;
+                            task.error = Some(e.to_string());
+                        });
+                        // Clean up task handle on error
+                        manager.task_handles.remove(&task_id_clone);
+                        crate::system::emit_dependency_missing(&e, &task_id_clone, &app_handle_clone2);
+                        events::emit_task_failed(&app_handle_clone2, &task_id_clone, e.to_string(), &e);
+                    }
+                    Ok(outcome) => manager.complete_task(&task_id_clone, outcome, &req, &app_handle_clone2),
                 }
                 
                 // Emit final task update - need a new clone since app_handle_clone was moved
-                let _ = app_handle_clone2.emit("task-update", manager.get_task(&task_id_clone));
+                manager.updates.emit(&app_handle_clone2, manager.get_task(&task_id_clone));
+                manager.finish_task(&task_id_clone, &url, &req, &app_handle_clone2).await;
             });
             
             // Store the task handle for cancellation
@@ -293,9 +570,63 @@ impl DownloadManager {
             self.task_handles.insert(task_id.clone(), task_handle);
         }
 
+        let batch = self.emit_batch_update(&batch_id, &app_handle);
+        if self.batches.seal(&batch_id) {
+            // Every task already finished while the batch was being created
+            self.notify_batch_complete(batch, &app_handle);
+        }
+
         Ok(task_ids)
     }
 
+    /// Marks a task as completed once its download and post-process command succeeded,
+    /// so that nothing it started outlives its handle
+    fn complete_task(&self, task_id: &str, outcome: DownloadOutcome, request: &DownloadRequest, app_handle: &tauri::AppHandle) {
+        self.update_task(task_id, |task| {
+            task.status = TaskStatus::Completed;
+            task.progress = 100.0;
+        });
+        self.task_handles.remove(task_id);
+
+        // Send notification unless the file was kept or the batch gets a summary instead
+        if outcome == DownloadOutcome::KeptExisting {
+            return;
+        }
+        let Some(task) = self.get_task(task_id).filter(|task| self.notify_individually(task)) else {
+            return;
+        };
+        if !request.split_chapters || task.output_files.is_empty() {
+            let output_path = sanitize_path(&request.download_path).unwrap_or_else(|_| PathBuf::from(&request.download_path));
+            notifications::send_download_complete_notification(app_handle, &task.name, task.file_path.as_deref(), &output_path);
+        } else {
+            notifications::send_chapters_complete_notification(app_handle, &task.name, &task.output_files);
+        }
+    }
+
+    /// Runs the request's post-process command once the download succeeded, before the
+    /// task completes. Its failure fails the task unless the request only wants a warning.
+    async fn post_process(
+        &self,
+        task_id: &str,
+        request: &DownloadRequest,
+        app_handle: &tauri::AppHandle,
+        cancellation_token: &CancellationToken,
+    ) -> Result<(), MediaForgeError> {
+        let Some(command) = &request.post_process_command else { return Ok(()) };
+        let Some(task) = self.get_task(task_id) else { return Ok(()) };
+        self.update_task(task_id, |task| task.status = TaskStatus::Processing);
+        self.updates.emit(app_handle, self.get_task(task_id));
+        hooks::post_process_task(
+            command,
+            request.post_process_ignore_failure,
+            &task,
+            cancellation_token,
+            MediaForgeError::DownloadError,
+            |warning| self.update_task(task_id, |task| task.append_warning(warning)),
+        )
+        .await
+    }
+
     async fn download_single_cancellable(
         &self,
         task_id: &str,
@@ -303,21 +634,43 @@ impl DownloadManager {
         request: &DownloadRequest,
         app_handle: tauri::AppHandle,
         cancellation_token: CancellationToken,
-    ) -> Result<(), MediaForgeError> {
+    ) -> Result<DownloadOutcome, MediaForgeError> {
         // Task status is already set to Downloading before spawn to prevent race condition
         
         // Re-validate URL and sanitize path (defensive programming)
-        validate_youtube_url(url)?;
+        validate_url(url, request.allow_generic)?;
         let output_path = sanitize_path(&request.download_path)?;
         
         // Validate disk space and permissions before starting
         crate::error::validation::validate_disk_space(&output_path, Some(100 * 1024 * 1024)).await?; // Assume 100MB minimum
         crate::error::validation::validate_write_permissions(&output_path).await?;
         
+        // yt-dlp skips or overwrites an existing file itself, but a free name for it has
+        // to be found before anything is fetched
+        let renamed_output = match request.conflict_policy {
+            ConflictPolicy::Rename => self.existing_output(url, request, &output_path).await.map(|existing| free_output_path(&existing)),
+            ConflictPolicy::Skip | ConflictPolicy::Overwrite => None,
+        };
+        
         // Use retry mechanism for network operations
         let retry_config = crate::error::RetryConfig::for_network();
         let download_result = crate::error::retry_async(retry_config, || {
-            self.download_single_attempt(task_id, url, request, app_handle.clone(), cancellation_token.clone())
+            let app_handle = app_handle.clone();
+            let cancellation_token = cancellation_token.clone();
+            let renamed_output = renamed_output.as_deref();
+            async move {
+                self.circuit_breaker.check()?;
+                let result = self
+                    .download_single_attempt(task_id, url, request, renamed_output, app_handle, cancellation_token)
+                    .await;
+                match &result {
+                    Ok(_) => self.circuit_breaker.record_success(),
+                    Err(error) => self.circuit_breaker.record_failure(error),
+                }
+                result
+            }
+        }, |attempt, max_attempts, previous_error| {
+            tasks::record_attempt(&self.tasks, &self.updates, &app_handle, task_id, attempt, max_attempts, previous_error);
         }).await;
         
         // Cleanup on failure
@@ -329,10 +682,7 @@ impl DownloadManager {
                 retry_attempts_exhausted = true,
                 "Download failed after all retry attempts"
             );
-            let format_ext = match request.format {
-                MediaFormat::Mp4 => "mp4", 
-                MediaFormat::Mp3 => "mp3",
-            };
+            let format_ext = media_extension(&request.format);
             let potential_file = output_path.join(format!("*.{}", format_ext));
             // Try to cleanup any partial files - use a glob pattern would be better but for now just log
             info!(
@@ -346,29 +696,96 @@ impl DownloadManager {
         download_result
     }
 
+    /// The file this download would write, if it already exists, for the rename policy.
+    /// Only single videos are checked; see `writes_single_file`.
+    async fn existing_output(&self, url: &str, request: &DownloadRequest, output_path: &Path) -> Option<PathBuf> {
+        if !writes_single_file(url, request) {
+            return None;
+        }
+
+        let template = single_output_template(output_path, request);
+        let mut cmd = TokioCommand::new(self.tools.ytdlp().await);
+        cmd.args(["--print", "filename", "--skip-download", "--no-playlist", "--no-warnings", "-o"])
+            .arg(&template)
+            .arg(url)
+            .stdin(Stdio::null())
+            .kill_on_drop(true);
+
+        let output = match tokio::time::timeout(Duration::from_secs(OUTPUT_NAME_TIMEOUT_SECS), cmd.output()).await {
+            Ok(Ok(output)) if output.status.success() => output,
+            // The download itself reports any real problem with the URL
+            _ => {
+                warn!(url = %url, "Could not determine the output file name; skipping the existing-file check");
+                return None;
+            }
+        };
+        let path = PathBuf::from(String::from_utf8_lossy(&output.stdout).lines().next()?.trim());
+        path.is_file().then_some(path)
+    }
+
     async fn download_single_attempt(
         &self,
         task_id: &str,
         url: &str,
         request: &DownloadRequest,
+        renamed_output: Option<&Path>,
         app_handle: tauri::AppHandle,
         cancellation_token: CancellationToken,
-    ) -> Result<(), MediaForgeError> {
+    ) -> Result<DownloadOutcome, MediaForgeError> {
         let output_path = sanitize_path(&request.download_path)?;
-        let format_ext = match request.format {
-            MediaFormat::Mp4 => "mp4",
-            MediaFormat::Mp3 => "mp3",
-        };
+        let format_ext = media_extension(&request.format);
         
         // Build yt-dlp command
-        let mut cmd = TokioCommand::new("yt-dlp");
+        let mut cmd = TokioCommand::new(self.tools.require_ytdlp().await?);
         
-        // Set output template with the correct extension
-        let output_template = output_path.join(format!("%(title)s.{}", format_ext));
-        cmd.arg("-o").arg(output_template.to_string_lossy().to_string());
+        if request.subtitles_only {
+            cmd.args(subtitle_args(&output_path, request.subtitle_langs.as_deref()));
+        } else if is_music_site_url(url) {
+            let playlist = matches!(request.download_type, DownloadType::Playlist);
+            let custom_template = request.output_template.is_some().then(|| single_output_template(&output_path, request));
+            cmd.args(music_download_args(&output_path, format_ext, playlist, custom_template.as_deref()));
+        } else if keeps_separate_streams(request) {
+            cmd.arg("-o").arg(separate_streams_template(&output_path, request).to_string_lossy().to_string());
+        } else if let Some(renamed) = renamed_output {
+            // A literal file name, so any % in it must not be read as a template field
+            cmd.arg("-o").arg(renamed.to_string_lossy().replace('%', "%%"));
+        } else {
+            let output_template = single_output_template(&output_path, request);
+            cmd.arg("-o").arg(output_template.to_string_lossy().to_string());
+        }
+
+        match request.conflict_policy {
+            ConflictPolicy::Skip => cmd.arg("--no-overwrites"),
+            ConflictPolicy::Overwrite => cmd.arg("--force-overwrites"),
+            // Already given a name that is free
+            ConflictPolicy::Rename => &mut cmd,
+        };
+
+        if request.split_chapters {
+            // Chapters go in a folder named after the video, numbered in playback order
+            let chapter_template = output_path.join(format!(
+                "%(title)s/%(section_number)03d - %(section_title)s.{}",
+                format_ext
+            ));
+            cmd.arg("--split-chapters");
+            cmd.arg("-o").arg(format!("chapter:{}", chapter_template.to_string_lossy()));
+        }
 
-        // Set format based on user selection
+        if request.embed_chapters {
+            cmd.arg("--embed-chapters");
+        }
+
+        // Set format based on user selection; subtitle-only downloads fetch no media
         match request.format {
+            _ if request.subtitles_only => {}
+            // A comma downloads each format on its own instead of merging them
+            MediaFormat::Mp4 if keeps_separate_streams(request) => {
+                let video = match &request.quality {
+                    Some(quality) => format!("bestvideo[height<={}]", quality),
+                    None => "bestvideo".to_string(),
+                };
+                cmd.arg("-f").arg(format!("{},bestaudio", video));
+            }
             MediaFormat::Mp4 => {
                 if let Some(quality) = &request.quality {
                     cmd.arg("-f")
@@ -392,6 +809,16 @@ impl DownloadManager {
                 .arg(format!("*{}-{}", trim.start_time, trim.end_time));
         }
 
+        if request.live {
+            // Record from the beginning of the stream and wait for scheduled streams to start
+            cmd.arg("--live-from-start");
+            cmd.arg("--wait-for-video").arg(LIVE_WAIT_RETRY_SECS.to_string());
+        }
+
+        if let Some(max_filesize) = &request.max_filesize {
+            cmd.arg("--max-filesize").arg(max_filesize);
+        }
+
         // Handle playlist downloads
         match request.download_type {
             DownloadType::Playlist => {
@@ -402,138 +829,203 @@ impl DownloadManager {
             }
         }
 
-        // Add progress output
+        // Add progress output, as delimited fields when yt-dlp can print them
         cmd.arg("--newline").arg("--progress");
+        let structured_progress = self.tools.ytdlp_has_progress_template().await;
+        let parse_progress = if structured_progress {
+            cmd.arg("--progress-template").arg(PROGRESS_TEMPLATE);
+            parse_progress_template
+        } else {
+            parse_ytdlp_progress
+        };
         
         cmd.arg(url);
 
-        // Execute command and capture output
-        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
-
-        let mut child = cmd.spawn().map_err(|e| {
-            MediaForgeError::YtDlpError(format!("Failed to spawn yt-dlp: {}", e))
-        })?;
-
-        let stdout = child.stdout.take().ok_or_else(|| {
-            MediaForgeError::YtDlpError("Failed to capture stdout".to_string())
-        })?;
-
-        let manager = self.clone();
-        let task_id_str = task_id.to_string();
-        let _task_id_clone = task_id_str.clone();
-        let app_handle_clone = app_handle.clone();
-        let cancellation_token_clone = cancellation_token.clone();
-
-        // Parse progress from stdout
-        let progress_handle = tokio::spawn(async move {
-            let reader = BufReader::new(stdout);
-            let mut lines = reader.lines();
-
-            loop {
-                tokio::select! {
-                    result = lines.next_line() => {
-                        match result {
-                            Ok(Some(line)) => {
-                                if let Some(progress) = parse_ytdlp_progress(&line) {
-                                    manager.update_task(&task_id_str, |task| {
-                                        task.progress = progress.percentage;
-                                        task.speed = progress.speed;
-                                        task.eta = progress.eta;
-                                    });
-
-                                    // Emit event to frontend
-                                    let _ = app_handle_clone.emit("task-update", manager.get_task(&task_id_str));
-                                }
-
-                                // Extract filename - look for the final merged/converted file
-                                if line.contains("[download] Destination:") || line.contains("[Merger]") || line.contains("[ExtractAudio]") {
-                                    if let Some(file_path) = line.split("Destination:").nth(1)
-                                        .or_else(|| line.split("Merging formats into").nth(1))
-                                        .or_else(|| line.split("to:").nth(1)) {
-                                        let file_path = file_path.trim().trim_matches('"').to_string();
-                                        manager.update_task(&task_id_str, |task| {
-                                            task.file_path = Some(file_path.clone());
-                                            task.name = file_path;
-                                        });
-                                    }
-                                }
-                            }
-                            Ok(None) => break, // EOF
-                            Err(_) => break,   // Error reading
+        // Set as soon as yt-dlp reports the video can't be downloaded at all
+        let unavailable_reason: Mutex<Option<String>> = Mutex::new(None);
+        // Last lines of stderr, used to explain failures
+        let mut stderr_tail: VecDeque<String> = VecDeque::new();
+        // Files yt-dlp skipped for exceeding --max-filesize
+        let mut skipped = 0;
+        // Set when yt-dlp found the file on disk and kept it instead of downloading
+        let mut already_downloaded = false;
+
+        let on_stdout_line = |line: &str| {
+            self.check_unavailable_line(task_id, line, &unavailable_reason, &app_handle);
+            self.check_live_line(task_id, line);
+            if detect_max_filesize_skip(line) {
+                skipped += 1;
+            }
+            if detect_already_downloaded(line) {
+                already_downloaded = true;
+            }
+
+            if let Some(progress) = parse_progress(line) {
+                self.update_task(task_id, |task| {
+                    if task.is_live {
+                        // Live streams have no total; report bytes and elapsed time instead
+                        if progress.downloaded_bytes.is_some() {
+                            task.downloaded_bytes = progress.downloaded_bytes;
                         }
+                        if progress.elapsed_seconds.is_some() {
+                            task.elapsed_seconds = progress.elapsed_seconds;
+                        }
+                    } else {
+                        task.progress = progress.percentage;
                     }
-                    _ = cancellation_token_clone.cancelled() => {
-                        info!(
-                            task_id = task_id_str,
-                            "Progress parsing cancelled for task"
-                        );
-                        break;
+                    task.speed_bytes_per_sec = progress.speed.as_deref().and_then(parse_speed_bytes);
+                    task.eta_seconds = progress.eta.as_deref().and_then(parse_eta_seconds);
+                    task.speed = progress.speed;
+                    task.eta = progress.eta;
+                    if progress.total_bytes.is_some() {
+                        task.total_bytes = progress.total_bytes;
+                        task.downloaded_bytes = progress.downloaded_bytes;
                     }
+                });
+
+                // Emit event to frontend
+                self.updates.emit(&app_handle, self.get_task(task_id));
+            }
+
+            // Each subtitle file is reported as it is written; with no media
+            // download, these are the task's outputs
+            if request.subtitles_only {
+                if let Some(subtitle_path) = parse_subtitle_path(line) {
+                    // Drop the language suffix along with the extension
+                    let without_ext = Path::new(&subtitle_path).with_extension("");
+                    let title = title_from_output_path(&without_ext.to_string_lossy());
+                    self.update_task(task_id, |task| {
+                        if task.file_path.is_none() {
+                            task.file_path = Some(subtitle_path.clone());
+                            task.name = title.unwrap_or_else(|| task.name.clone());
+                        }
+                        if !task.output_files.contains(&subtitle_path) {
+                            task.output_files.push(subtitle_path);
+                        }
+                    });
+                    self.updates.emit(&app_handle, self.get_task(task_id));
                 }
+            } else if let Some(chapter_path) = parse_chapter_path(line) {
+                self.update_task(task_id, |task| {
+                    if !task.output_files.contains(&chapter_path) {
+                        task.output_files.push(chapter_path);
+                    }
+                });
+                self.updates.emit(&app_handle, self.get_task(task_id));
+            } else if let Some(file_path) = parse_output_path(line) {
+                // Track the output file; later post-processing lines
+                // (merge/extract) replace the intermediate download path.
+                // Unmerged video and audio are both outputs, video first.
+                let title = title_from_output_path(&file_path);
+                self.update_task(task_id, |task| {
+                    if keeps_separate_streams(request) {
+                        if !task.output_files.contains(&file_path) {
+                            task.output_files.push(file_path.clone());
+                        }
+                        task.file_path.get_or_insert(file_path);
+                    } else {
+                        task.file_path = Some(file_path);
+                    }
+                    if let Some(title) = title {
+                        task.name = title;
+                    }
+                });
             }
-        });
+        };
 
-        // Wait for process completion or cancellation
-        let status = tokio::select! {
-            status = child.wait() => {
-                status.map_err(|e| {
-                    MediaForgeError::YtDlpError(format!("Failed to wait for yt-dlp: {}", e))
-                })?
+        // Watch stderr for unavailable/private video markers too
+        let on_stderr_line = |line: &str| {
+            self.check_unavailable_line(task_id, line, &unavailable_reason, &app_handle);
+            self.check_live_line(task_id, line);
+
+            if stderr_tail.len() == STDERR_TAIL_LINES {
+                stderr_tail.pop_front();
             }
-            _ = cancellation_token.cancelled() => {
+            stderr_tail.push_back(line.to_string());
+        };
+
+        let outcome = run_cancellable_command(cmd, &cancellation_token, on_stdout_line, on_stderr_line)
+            .await
+            .map_err(|e| MediaForgeError::from_spawn_error("yt-dlp", e, MediaForgeError::YtDlpError))?;
+        let status = match outcome {
+            CommandOutcome::Exited(status) => status,
+            CommandOutcome::Cancelled => {
                 info!(
                     task_id = task_id,
-                    "Terminating yt-dlp process for cancelled task"
+                    "Terminated yt-dlp process for cancelled task"
                 );
-                // Kill the child process
-                if let Err(e) = child.kill().await {
-                    error!(
-                        task_id = task_id,
-                        error = %e,
-                        "Failed to terminate yt-dlp process"
-                    );
-                }
-                // Wait briefly for cleanup
-                let _ = tokio::time::timeout(Duration::from_secs(5), child.wait()).await;
-                
-                // Cancel progress parsing
-                progress_handle.abort();
-                
                 return Err(MediaForgeError::YtDlpError("Download was cancelled".to_string()));
             }
         };
 
-        // Cancel progress parsing since process completed
-        progress_handle.abort();
+        // yt-dlp succeeds even when none of the requested subtitle languages exist
+        let no_subtitles = request.subtitles_only
+            && self.get_task(task_id).is_some_and(|task| task.output_files.is_empty());
+        // yt-dlp also succeeds when it skipped every file for exceeding the size limit
+        let limit = request.max_filesize.as_deref().unwrap_or_default();
+        let nothing_downloaded = self
+            .get_task(task_id)
+            .is_some_and(|task| task.file_path.is_none() && task.output_files.is_empty());
+        if status.success() && skipped > 0 && nothing_downloaded {
+            return Err(MediaForgeError::DownloadError(format!(
+                "Skipped: exceeds max filesize ({})",
+                limit
+            )));
+        }
+        
+        if status.success() && no_subtitles {
+            return Err(MediaForgeError::YtDlpError(format!(
+                "No subtitles available in the requested languages ({})",
+                request.subtitle_langs.as_deref().map(|l| l.join(", ")).unwrap_or_else(|| DEFAULT_SUBTITLE_LANG.to_string())
+            )));
+        }
+
+        if status.success() && request.verify {
+            self.verify_downloaded_files(task_id).await?;
+        }
 
         if status.success() {
+            // Playlists skip the files they already have and still download the rest
+            let kept_existing = already_downloaded && writes_single_file(url, request);
+            if kept_existing {
+                info!(task_id = task_id, "Output already exists, kept it instead of downloading");
+            }
             self.update_task(task_id, |task| {
-                task.status = TaskStatus::Completed;
                 task.progress = 100.0;
+                if skipped > 0 {
+                    task.warning = Some(format!("Skipped {} file(s) exceeding max filesize ({})", skipped, limit));
+                }
+                if kept_existing {
+                    task.warning = Some("Already downloaded; kept the existing file".to_string());
+                }
             });
             
-            // Clean up task handle since task completed
-            self.task_handles.remove(task_id);
-            
-            // Send notification
-            if let Some(task) = self.get_task(task_id) {
-                notifications::send_download_complete_notification(&app_handle, &task.name);
-            }
-            
-            let _ = app_handle.emit("task-update", self.get_task(task_id));
-            Ok(())
+            self.updates.emit(&app_handle, self.get_task(task_id));
+            Ok(if kept_existing { DownloadOutcome::KeptExisting } else { DownloadOutcome::Downloaded })
         } else {
-            // Clean up task handle on failure too  
-            self.task_handles.remove(task_id);
+            // Unavailable videos will never succeed, so skip classification and retries
+            if let Some(reason) = unavailable_reason.into_inner().unwrap_or_else(|e| e.into_inner()) {
+                error!(
+                    task_id = task_id,
+                    reason = %reason,
+                    "Video is unavailable for download"
+                );
+                return Err(MediaForgeError::YtDlpError(reason));
+            }
             
             // Enhanced error classification based on exit code and stderr
-            let error_message = format!("Download failed with exit code: {:?}", status.code());
+            let stderr_summary = summarize_stderr(&stderr_tail);
+            let error_message = match &stderr_summary {
+                Some(details) => format!("Download failed with exit code {:?}: {}", status.code(), details),
+                None => format!("Download failed with exit code: {:?}", status.code()),
+            };
             let error = Self::classify_ytdlp_error(&error_message, status.code());
             
             error!(
                 task_id = task_id,
                 error = %error,
                 exit_code = ?status.code(),
+                stderr = ?stderr_summary,
                 retryable = error.is_retryable(),
                 "yt-dlp process failed"
             );
@@ -542,36 +1034,121 @@ impl DownloadManager {
         }
     }
 
-    /// Classifies yt-dlp errors to determine if they're retryable
-    pub fn classify_ytdlp_error(message: &str, exit_code: Option<i32>) -> MediaForgeError {
-        let msg_lower = message.to_lowercase();
-        
-        // Network-related errors (retryable)
-        if msg_lower.contains("network") || 
-           msg_lower.contains("connection") ||
-           msg_lower.contains("timeout") ||
-           msg_lower.contains("temporary failure") ||
-           msg_lower.contains("503") ||  // Service unavailable
-           msg_lower.contains("502") ||  // Bad gateway  
-           msg_lower.contains("504") ||  // Gateway timeout
-           msg_lower.contains("429") ||  // Too many requests
-           exit_code == Some(1)          // Generic network failure
-        {
-            MediaForgeError::NetworkError(message.to_string())
+    /// Records and reports an unavailable-video marker found in yt-dlp output
+    fn check_unavailable_line(
+        &self,
+        task_id: &str,
+        line: &str,
+        unavailable_reason: &Mutex<Option<String>>,
+        app_handle: &tauri::AppHandle,
+    ) {
+        let Some(reason) = detect_unavailable_video(line) else {
+            return;
+        };
+
+        let mut slot = unavailable_reason.lock().unwrap();
+        if slot.is_some() {
+            return;
         }
-        // Permission/authentication errors (not retryable)
-        else if msg_lower.contains("private video") ||
-                msg_lower.contains("not available") ||
-                msg_lower.contains("geo-blocked") ||
-                exit_code == Some(2)  // Authentication/permission error
-        {
-            MediaForgeError::YtDlpError(message.to_string())
+        *slot = Some(reason.to_string());
+        drop(slot);
+
+        warn!(
+            task_id = task_id,
+            reason = reason,
+            "yt-dlp reported the video as unavailable"
+        );
+        self.update_task(task_id, |task| {
+            task.error = Some(reason.to_string());
+        });
+        self.updates.emit(app_handle, self.get_task(task_id));
+    }
+
+    /// Marks the task as a live recording when yt-dlp reports a live stream
+    fn check_live_line(&self, task_id: &str, line: &str) {
+        if !detect_live_stream(line) {
+            return;
         }
-        // Disk space errors
-        else if msg_lower.contains("no space left") ||
-                msg_lower.contains("disk full")
-        {
-            MediaForgeError::DiskSpaceError(message.to_string())
+        if self.get_task(task_id).is_some_and(|task| task.is_live) {
+            return;
+        }
+
+        info!(task_id = task_id, "yt-dlp reported a live stream");
+        self.update_task(task_id, |task| {
+            task.is_live = true;
+        });
+    }
+
+    /// Probes every file the task wrote, deleting them all if any is broken, since
+    /// yt-dlp can exit successfully after writing a truncated file
+    async fn verify_downloaded_files(&self, task_id: &str) -> Result<(), MediaForgeError> {
+        let Some(task) = self.get_task(task_id) else {
+            return Ok(());
+        };
+        let mut files = task.output_files.clone();
+        if let Some(file_path) = task.file_path.filter(|path| !files.contains(path)) {
+            files.push(file_path);
+        }
+
+        // Subtitles and thumbnails written alongside have no duration to probe
+        for file in files.iter().filter(|file| is_media_output(Path::new(file))) {
+            if let Err(reason) = verify_media_file(Path::new(file)).await {
+                warn!(task_id = task_id, file = %file, reason = %reason, "Downloaded file failed verification");
+                for file in &files {
+                    if let Err(e) = tokio::fs::remove_file(file).await {
+                        warn!(file = %file, error = %e, "Failed to delete a file that failed verification");
+                    }
+                }
+                return Err(MediaForgeError::DownloadError(format!("Verification failed for {}: {}", file, reason)));
+            }
+        }
+        info!(task_id = task_id, files = files.len(), "Downloaded files verified");
+        Ok(())
+    }
+
+    /// Classifies yt-dlp errors to determine if they're retryable.
+    ///
+    /// The message (which includes captured stderr) is checked before the exit code,
+    /// since yt-dlp exits with 1 for most failures regardless of cause.
+    pub fn classify_ytdlp_error(message: &str, exit_code: Option<i32>) -> MediaForgeError {
+        let msg_lower = message.to_lowercase();
+        let http_status = http_error_status(message);
+        
+        // The HTTP status decides first, so a reason phrase like "Service Unavailable"
+        // isn't mistaken for a missing video
+        if matches!(http_status, Some(429 | 500 | 502 | 503 | 504)) {
+            // Too many requests, server, gateway and service errors (retryable)
+            MediaForgeError::NetworkError(message.to_string())
+        }
+        else if matches!(http_status, Some(404 | 410)) {
+            MediaForgeError::YtDlpError(message.to_string())
+        }
+        // Network-related errors (retryable)
+        else if msg_lower.contains("network") || 
+           msg_lower.contains("connection") ||
+           msg_lower.contains("timeout") ||
+           msg_lower.contains("timed out") ||
+           msg_lower.contains("temporary failure")
+        {
+            MediaForgeError::NetworkError(message.to_string())
+        }
+        // Permission/authentication and invalid request errors (not retryable)
+        else if msg_lower.contains("private video") ||
+                msg_lower.contains("video unavailable") ||
+                msg_lower.contains("this video is unavailable") ||
+                msg_lower.contains("this video is not available") ||
+                msg_lower.contains("not available in your country") ||
+                msg_lower.contains("geo-blocked") ||
+                msg_lower.contains("unsupported url") ||
+                exit_code == Some(2)  // Authentication/permission error
+        {
+            MediaForgeError::YtDlpError(message.to_string())
+        }
+        // Disk space errors
+        else if msg_lower.contains("no space left") ||
+                msg_lower.contains("disk full")
+        {
+            MediaForgeError::DiskSpaceError(message.to_string())
         }
         // Generic retryable errors
         else if exit_code == Some(1) || msg_lower.contains("interrupted")
@@ -584,6 +1161,32 @@ impl DownloadManager {
         }
     }
 
+    /// Changes a task's queue priority. Queued tasks are reordered immediately;
+    /// for tasks that already started only the recorded priority changes.
+    pub fn set_task_priority(
+        &self,
+        task_id: &str,
+        priority: i32,
+        app_handle: &tauri::AppHandle,
+    ) -> Result<(), MediaForgeError> {
+        if self.get_task(task_id).is_none() {
+            return Err(MediaForgeError::TaskNotFound(task_id.to_string()));
+        }
+
+        let reordered = self.queue.set_priority(task_id, priority);
+        info!(
+            task_id = task_id,
+            priority = priority,
+            reordered = reordered,
+            "Task priority changed"
+        );
+        self.update_task(task_id, |task| {
+            task.priority = Some(priority);
+        });
+        self.updates.emit(app_handle, self.get_task(task_id));
+        Ok(())
+    }
+
     pub fn pause_task(&self, task_id: &str) -> Result<(), MediaForgeError> {
         self.update_task(task_id, |task| {
             task.status = TaskStatus::Paused;
@@ -634,6 +1237,13 @@ impl DownloadManager {
         
         Ok(())
     }
+
+    /// Stops every queued and running download when the app quits, along with its yt-dlp
+    /// process or post-process command. Returns how many tasks were interrupted.
+    pub async fn shutdown(&self, timeout: Duration) -> usize {
+        self.shutting_down.store(true, Ordering::Relaxed);
+        tasks::interrupt_all(&self.task_handles, &self.tasks, timeout, "Downloads").await
+    }
 }
 
 impl Clone for DownloadManager {
@@ -641,6 +1251,15 @@ impl Clone for DownloadManager {
         Self {
             tasks: Arc::clone(&self.tasks),
             task_handles: Arc::clone(&self.task_handles),
+            queue: Arc::clone(&self.queue),
+            circuit_breaker: Arc::clone(&self.circuit_breaker),
+            batches: Arc::clone(&self.batches),
+            history: Arc::clone(&self.history),
+            default_timeout_secs: Arc::clone(&self.default_timeout_secs),
+            max_queue_size: Arc::clone(&self.max_queue_size),
+            tools: Arc::clone(&self.tools),
+            updates: Arc::clone(&self.updates),
+            shutting_down: Arc::clone(&self.shutting_down),
         }
     }
 }
@@ -649,21 +1268,95 @@ struct ProgressInfo {
     percentage: f32,
     speed: Option<String>,
     eta: Option<String>,
+    total_bytes: Option<u64>,
+    downloaded_bytes: Option<u64>,
+    elapsed_seconds: Option<u64>,
 }
 
+/// Marks the lines printed with `PROGRESS_TEMPLATE`
+const PROGRESS_PREFIX: &str = "[progress]";
+
+/// Progress fields yt-dlp prints with `--progress-template`, unformatted so they read
+/// the same in every version and locale. Unknown values print as `NA`.
+const PROGRESS_TEMPLATE: &str = "download:[progress] %(progress.status)s|%(progress.downloaded_bytes)s|\
+%(progress.total_bytes)s|%(progress.total_bytes_estimate)s|%(progress.speed)s|%(progress.eta)s|%(progress.elapsed)s";
+
+/// Parses a line printed with `PROGRESS_TEMPLATE`
+fn parse_progress_template(line: &str) -> Option<ProgressInfo> {
+    let fields: Vec<&str> = line.trim().strip_prefix(PROGRESS_PREFIX)?.trim().split('|').collect();
+    let [status, downloaded, total, estimate, speed, eta, elapsed] = fields[..] else {
+        return None;
+    };
+    // Counts are integers but speed and times are floats, and either may be NA
+    let number = |field: &str| field.parse::<f64>().ok().filter(|n| n.is_finite() && *n >= 0.0);
+
+    let downloaded_bytes = number(downloaded).map(|n| n as u64);
+    let total_bytes = number(total).or_else(|| number(estimate)).map(|n| n as u64).filter(|&n| n > 0);
+    let percentage = match (downloaded_bytes, total_bytes) {
+        _ if status == "finished" => 100.0,
+        (Some(downloaded), Some(total)) => (downloaded as f64 / total as f64 * 100.0).min(100.0) as f32,
+        _ => 0.0,
+    };
+
+    Some(ProgressInfo {
+        percentage,
+        speed: number(speed).map(format_speed),
+        eta: number(eta).map(|secs| crate::converter::format_eta(secs as u64)),
+        total_bytes,
+        downloaded_bytes,
+        elapsed_seconds: number(elapsed).map(|secs| secs as u64),
+    })
+}
+
+/// Formats bytes per second the way yt-dlp does, e.g. `1.23MiB/s`
+fn format_speed(bytes_per_sec: f64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes_per_sec;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < units.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.2}{}/s", value, units[unit])
+}
+
+/// Parses the human-readable progress lines of yt-dlp releases without `--progress-template`
 fn parse_ytdlp_progress(line: &str) -> Option<ProgressInfo> {
     // Parse yt-dlp progress lines
     // Format: [download]   45.2% of 123.45MiB at 1.23MiB/s ETA 00:45
+    // Estimated sizes are prefixed with a tilde: "of ~123.45MiB" or "of ~ 123.45MiB"
+    // Live streams have no total: [download]   12.34MiB at  1.23MiB/s (00:01:23)
     if !line.contains("[download]") {
         return None;
     }
 
-    let percentage = line
-        .split_whitespace()
-        .find(|s| s.ends_with('%'))
+    let percentage_token = line.split_whitespace().find(|s| s.ends_with('%'));
+    let percentage = percentage_token
         .and_then(|s| s.trim_end_matches('%').parse::<f32>().ok())
         .unwrap_or(0.0);
 
+    let total_bytes = line
+        .split(" of ")
+        .nth(1)
+        .map(|s| s.trim_start().trim_start_matches('~').trim_start())
+        .and_then(|s| s.split_whitespace().next())
+        .and_then(parse_size_bytes);
+
+    let downloaded_bytes = match total_bytes {
+        Some(total) => Some((total as f64 * percentage as f64 / 100.0).round() as u64),
+        // Without a percentage the first value is the amount written so far
+        None if percentage_token.is_none() => line
+            .split_whitespace()
+            .nth(1)
+            .and_then(parse_size_bytes),
+        None => None,
+    };
+
+    let elapsed_seconds = line
+        .split_whitespace()
+        .find_map(|s| s.strip_prefix('(')?.strip_suffix(')'))
+        .and_then(parse_eta_seconds);
+
     let speed = line
         .split("at")
         .nth(1)
@@ -680,9 +1373,351 @@ fn parse_ytdlp_progress(line: &str) -> Option<ProgressInfo> {
         percentage,
         speed,
         eta,
+        total_bytes,
+        downloaded_bytes,
+        elapsed_seconds,
     })
 }
 
+/// Longest `verify_media_file` waits for ffprobe
+const VERIFY_TIMEOUT_SECS: u64 = 60;
+
+/// Whether `path` is a video or audio file, as opposed to the subtitles or thumbnails
+/// yt-dlp may write next to it
+fn is_media_output(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(ConversionType::for_input_extension)
+        .is_some_and(|kind| matches!(kind, ConversionType::Video | ConversionType::Audio))
+}
+
+/// Checks that ffprobe reads `path` as media with a non-zero duration
+async fn verify_media_file(path: &Path) -> Result<(), String> {
+    if !path.is_file() {
+        return Err("the file is missing".to_string());
+    }
+    let probe = crate::converter::probe_duration_secs(path);
+    match tokio::time::timeout(Duration::from_secs(VERIFY_TIMEOUT_SECS), probe).await {
+        Err(_) => Err(format!("ffprobe didn't finish within {} seconds", VERIFY_TIMEOUT_SECS)),
+        Ok(Err(e)) => Err(e.to_string()),
+        Ok(Ok(duration)) if duration <= 0.0 => Err("it has no duration".to_string()),
+        Ok(Ok(_)) => Ok(()),
+    }
+}
+
+/// Interval yt-dlp uses to re-check a scheduled live stream that has not started
+const LIVE_WAIT_RETRY_SECS: u64 = 60;
+
+/// How long to wait for yt-dlp to report a download's file name before giving up on
+/// the already-downloaded check
+const OUTPUT_NAME_TIMEOUT_SECS: u64 = 60;
+
+/// Number of stderr lines kept per download attempt for error reporting
+const STDERR_TAIL_LINES: usize = 20;
+
+/// Condenses captured stderr into an error detail: the `ERROR:` lines if yt-dlp printed any,
+/// otherwise the whole captured tail
+fn summarize_stderr(lines: &VecDeque<String>) -> Option<String> {
+    let errors: Vec<&str> = lines
+        .iter()
+        .map(|l| l.trim())
+        .filter(|l| l.starts_with("ERROR:"))
+        .collect();
+
+    let summary = if errors.is_empty() {
+        lines.iter().map(|l| l.trim()).filter(|l| !l.is_empty()).collect::<Vec<_>>().join("\n")
+    } else {
+        errors.join("\n")
+    };
+
+    if summary.is_empty() {
+        None
+    } else {
+        Some(summary)
+    }
+}
+
+/// Maps yt-dlp messages for videos that can never be downloaded to a user-facing reason
+fn detect_unavailable_video(line: &str) -> Option<&'static str> {
+    // Only error lines count; titles and paths can legitimately contain these phrases
+    if !line.trim_start().starts_with("ERROR:") {
+        return None;
+    }
+    let line_lower = line.to_lowercase();
+    
+    if line_lower.contains("private video") {
+        Some("This video is private")
+    } else if line_lower.contains("members-only") || line_lower.contains("members only") {
+        Some("This video is available to channel members only")
+    } else if line_lower.contains("live event will begin") {
+        Some("This live event has not started yet; enable live recording to wait for it")
+    } else if line_lower.contains("video unavailable")
+        || line_lower.contains("this video is not available")
+        || line_lower.contains("this video has been removed")
+    {
+        Some("This video is unavailable")
+    } else {
+        None
+    }
+}
+
+/// Checks a byte size such as `500M`, `1.5G` or `800k` in the form yt-dlp accepts
+fn validate_size_limit(field: &str, value: &str) -> Result<(), MediaForgeError> {
+    let re = Regex::new(r"^[1-9][0-9]{0,6}(\.[0-9]{1,2})?[kKmMgGtT]?$").unwrap();
+    if re.is_match(value) {
+        Ok(())
+    } else {
+        Err(MediaForgeError::InvalidSettings(format!(
+            "Invalid {} '{}': expected a size such as 500M or 1.5G",
+            field, value
+        )))
+    }
+}
+
+/// Recognizes yt-dlp's notice that a file was skipped for exceeding `--max-filesize`
+fn detect_max_filesize_skip(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    (trimmed.starts_with("[download]") || trimmed.starts_with("[info]"))
+        && trimmed.contains("larger than max-filesize")
+}
+
+/// Recognizes yt-dlp status messages saying the URL is a live stream or live event
+fn detect_live_stream(line: &str) -> bool {
+    // Download lines carry titles and paths, which can mention anything
+    let trimmed = line.trim_start();
+    let is_status_line = trimmed.starts_with('[') || trimmed.starts_with("ERROR:") || trimmed.starts_with("WARNING:");
+    if !is_status_line || trimmed.starts_with("[download]") {
+        return false;
+    }
+    let line_lower = trimmed.to_lowercase();
+    
+    line_lower.contains("is a live event")
+        || line_lower.contains("live event will begin")
+        || line_lower.contains("is live")
+        || line_lower.contains("live stream")
+        || line_lower.contains("livestream")
+}
+
+/// Parses a yt-dlp size such as `123.45MiB`, `~1.2GiB` or `512KB` into bytes
+fn parse_size_bytes(value: &str) -> Option<u64> {
+    let value = value.trim().trim_start_matches('~');
+    let split = value.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let multiplier: f64 = match unit.trim() {
+        "B" => 1.0,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        "TiB" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        "KB" | "kB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        _ => return None,
+    };
+    Some((number * multiplier).round() as u64)
+}
+
+/// Parses a yt-dlp speed such as `1.23MiB/s` into bytes per second.
+/// Returns `None` for placeholders like `Unknown B/s` printed while buffering.
+fn parse_speed_bytes(speed: &str) -> Option<u64> {
+    parse_size_bytes(speed.trim().strip_suffix("/s")?)
+}
+
+/// Parses a yt-dlp ETA (`SS`, `MM:SS` or `HH:MM:SS`) into seconds.
+/// Returns `None` for placeholders like `Unknown` or `--:--`.
+fn parse_eta_seconds(eta: &str) -> Option<u64> {
+    eta.trim()
+        .split(':')
+        .try_fold(0u64, |total, part| part.parse::<u64>().ok().map(|n| total * 60 + n))
+}
+
+/// Extracts the output file path from a yt-dlp status line.
+///
+/// Recognizes `[download] Destination: <path>`, `[download] <path> has already been downloaded`,
+/// `[Merger] Merging formats into "<path>"` and post-processor `Destination:` lines such as
+/// `[ExtractAudio] Destination: <path>`.
+fn parse_output_path(line: &str) -> Option<String> {
+    let line = line.trim();
+    if !line.starts_with('[') {
+        return None;
+    }
+
+    let path = if let Some(rest) = line.split_once("Merging formats into ").map(|(_, r)| r) {
+        rest
+    } else if let Some(rest) = line.split_once("Destination: ").map(|(_, r)| r) {
+        rest
+    } else if line.starts_with("[download] ") && line.ends_with(" has already been downloaded") {
+        line.trim_start_matches("[download] ")
+            .trim_end_matches(" has already been downloaded")
+    } else {
+        return None;
+    };
+
+    let path = path.trim().trim_matches('"');
+    if path.is_empty() {
+        None
+    } else {
+        Some(path.to_string())
+    }
+}
+
+/// Checks subtitle language codes, which are passed to yt-dlp as one comma-separated list
+fn validate_subtitle_langs(langs: &[String]) -> Result<(), MediaForgeError> {
+    let re = Regex::new(r"^[A-Za-z0-9*.-]{1,20}$").unwrap();
+    match langs.iter().find(|lang| !re.is_match(lang)) {
+        Some(lang) => Err(MediaForgeError::InvalidSettings(format!(
+            "Invalid subtitle language: '{}'",
+            lang
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// yt-dlp arguments that write a video's subtitles, including auto-generated
+/// captions, without downloading the video. Files are named `<title>.<lang>.<ext>`.
+fn subtitle_args(output_path: &Path, langs: Option<&[String]>) -> Vec<String> {
+    let langs = match langs {
+        Some(langs) if !langs.is_empty() => langs.join(","),
+        _ => DEFAULT_SUBTITLE_LANG.to_string(),
+    };
+    vec![
+        "-o".to_string(),
+        output_path.join("%(title)s.%(ext)s").to_string_lossy().to_string(),
+        "--skip-download".to_string(),
+        "--write-subs".to_string(),
+        "--write-auto-subs".to_string(),
+        "--sub-langs".to_string(),
+        langs,
+        "--sub-format".to_string(),
+        "srt/vtt/best".to_string(),
+    ]
+}
+
+/// Extracts a subtitle file path from yt-dlp's output, e.g.
+/// `[info] Writing video subtitles to: /path/Title.en.vtt`
+fn parse_subtitle_path(line: &str) -> Option<String> {
+    let path = line.trim().strip_prefix("[info] Writing video subtitles to: ")?;
+    let path = path.trim().trim_matches('"');
+    (!path.is_empty()).then(|| path.to_string())
+}
+
+/// Extracts a chapter file path from yt-dlp's split-chapters output, e.g.
+/// `[SplitChapters] Chapter 001; Destination: /path/Title/001 - Intro.mp4`
+fn parse_chapter_path(line: &str) -> Option<String> {
+    let rest = line.trim().strip_prefix("[SplitChapters] Chapter ")?;
+    let (_, path) = rest.split_once("; Destination: ")?;
+    let path = path.trim().trim_matches('"');
+    (!path.is_empty()).then(|| path.to_string())
+}
+
+fn media_extension(format: &MediaFormat) -> &'static str {
+    match format {
+        MediaFormat::Mp4 => "mp4",
+        MediaFormat::Mp3 => "mp3",
+    }
+}
+
+/// Checks the download options that can't be combined, before any task is created
+fn validate_download_options(request: &DownloadRequest) -> Result<(), MediaForgeError> {
+    if request.subtitles_only {
+        if request.trim.is_some() || request.split_chapters || request.embed_chapters || request.live || request.verify {
+            return Err(MediaForgeError::InvalidSettings(
+                "Subtitle-only downloads cannot be combined with trimming, chapter splitting or embedding, live recording or verification"
+                    .to_string()
+            ));
+        }
+        validate_subtitle_langs(request.subtitle_langs.as_deref().unwrap_or_default())?;
+    }
+    
+    if let Some(max_filesize) = &request.max_filesize {
+        validate_size_limit("max filesize", max_filesize)?;
+    }
+    
+    if let Some(template) = &request.output_template {
+        validate_output_template(template)?;
+    }
+    
+    if let Some(command) = &request.post_process_command {
+        hooks::validate_post_process_command(command)?;
+    }
+    
+    if request.embed_chapters && !supports_chapters(&request.format) {
+        return Err(MediaForgeError::InvalidSettings(format!(
+            "Chapter markers can't be embedded in .{} files; download mp4 instead",
+            media_extension(&request.format)
+        )));
+    }
+    
+    // Chapters are cut from the whole video, so they can't be combined with a trimmed section
+    if request.split_chapters && request.trim.is_some() {
+        return Err(MediaForgeError::InvalidSettings(
+            "Splitting by chapters cannot be combined with trimming".to_string()
+        ));
+    }
+    
+    if keeps_separate_streams(request)
+        && (request.format != MediaFormat::Mp4 || request.split_chapters || request.subtitles_only || request.live)
+    {
+        return Err(MediaForgeError::InvalidSettings(
+            "Separate video and audio files need an mp4 download without chapters, subtitles-only or live recording".to_string()
+        ));
+    }
+    
+    Ok(())
+}
+
+/// Whether yt-dlp can embed chapter markers in downloads of `format`. mp4 holds
+/// them (as do the m4a and mkv files yt-dlp may write along the way); mp3 has no
+/// chapter support yt-dlp can write.
+fn supports_chapters(format: &MediaFormat) -> bool {
+    match format {
+        MediaFormat::Mp4 => true,
+        MediaFormat::Mp3 => false,
+    }
+}
+
+/// Whether the download writes one file that a conflict policy applies to as a whole.
+/// Other modes write several files, and playlists skip the files they already have.
+fn writes_single_file(url: &str, request: &DownloadRequest) -> bool {
+    !request.subtitles_only
+        && !request.split_chapters
+        && !keeps_separate_streams(request)
+        && !request.live
+        && !matches!(request.download_type, DownloadType::Playlist)
+        && !is_music_site_url(url)
+}
+
+/// Recognizes yt-dlp keeping a file that is already on disk instead of downloading it
+fn detect_already_downloaded(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with("[download] ") && line.ends_with(" has already been downloaded")
+}
+
+/// First free name of the form `Title (1).mp4`, `Title (2).mp4`, ... next to `path`
+pub(crate) fn free_output_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    (1..)
+        .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| !candidate.exists())
+        .expect("some numbered name is free")
+}
+
+/// Derives a human-readable title from an output path produced by the `%(title)s` template,
+/// dropping yt-dlp's intermediate format suffix (e.g. `Title.f137.mp4` -> `Title`)
+fn title_from_output_path(path: &str) -> Option<String> {
+    let stem = std::path::Path::new(path).file_stem()?.to_str()?;
+    let format_suffix = Regex::new(r"\.f\d[\w-]*$").unwrap();
+    let title = format_suffix.replace(stem, "").trim().to_string();
+    if title.is_empty() {
+        None
+    } else {
+        Some(title)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -696,6 +1731,9 @@ mod tests {
         assert!(validate_youtube_url("https://www.youtube.com/playlist?list=PLrAXtmRdnEQy6nuLvTYpTNjVjYGD1UBx").is_ok());
         assert!(validate_youtube_url("https://music.youtube.com/watch?v=dQw4w9WgXcQ").is_ok());
         assert!(validate_youtube_url("https://www.youtube.com/shorts/dQw4w9WgXcQ").is_ok());
+        assert!(validate_youtube_url("https://www.youtube.com/live/dQw4w9WgXcQ").is_ok());
+        assert!(validate_youtube_url("https://www.youtube.com/@NASA/live").is_ok());
+        assert!(validate_youtube_url("https://www.youtube.com/channel/UCLA_DiR1FfKNvjuUpBHmylQ/live").is_ok());
     }
 
     #[test]
@@ -712,6 +1750,98 @@ mod tests {
         assert!(validate_youtube_url("https://youtube.com/watch?v=abc$(whoami)").is_err());
     }
 
+    #[test]
+    fn test_validate_url_generic() {
+        assert!(validate_url("https://vimeo.com/76979871", true).is_ok());
+        assert!(validate_url("https://soundcloud.com/artist/track?in=artist/sets/x", true).is_ok());
+        assert!(validate_url("http://localhost:8080/video.mp4", true).is_ok());
+        assert!(validate_url("https://www.twitch.tv/videos/123456", true).is_ok());
+        // Generic mode is opt-in
+        assert!(validate_url("https://vimeo.com/76979871", false).is_err());
+
+        // Safety checks still apply
+        assert!(validate_url("file:///etc/passwd", true).is_err());
+        assert!(validate_url("ftp://example.com/file", true).is_err());
+        assert!(validate_url("https://vimeo.com/1; rm -rf /", true).is_err());
+        assert!(validate_url("https://vimeo.com/$(whoami)", true).is_err());
+        assert!(validate_url("ssh://example.com", true).is_err());
+        assert!(validate_url("https://user@example.com/video", true).is_err());
+        assert!(validate_url("https://example.com/video name", true).is_err());
+    }
+
+    #[test]
+    fn test_max_filesize() {
+        for size in ["500M", "1.5G", "800k", "1048576"] {
+            assert!(validate_size_limit("max filesize", size).is_ok(), "{:?} should be accepted", size);
+        }
+        for size in ["", "0M", "500 M", "500MB", "-1G", "5M --exec x", "５00M"] {
+            assert!(validate_size_limit("max filesize", size).is_err(), "{:?} should be rejected", size);
+        }
+
+        assert!(detect_max_filesize_skip(
+            "[download] File is larger than max-filesize (1073741824 bytes > 524288000 bytes). Aborting."
+        ));
+        // Titles can say anything
+        assert!(!detect_max_filesize_skip("Video larger than max-filesize [abc].mp4"));
+    }
+
+    #[test]
+    fn test_subtitle_args() {
+        assert_eq!(
+            subtitle_args(Path::new("/subs"), None),
+            vec![
+                "-o", "/subs/%(title)s.%(ext)s", "--skip-download", "--write-subs", "--write-auto-subs",
+                "--sub-langs", "en", "--sub-format", "srt/vtt/best",
+            ]
+        );
+        let langs = vec!["de".to_string(), "es.*".to_string()];
+        assert_eq!(subtitle_args(Path::new("/subs"), Some(&langs))[6], "de,es.*");
+
+        assert!(validate_subtitle_langs(&langs).is_ok());
+        assert!(validate_subtitle_langs(&["en,fr".to_string()]).is_err());
+        assert!(validate_subtitle_langs(&["en; rm".to_string()]).is_err());
+        assert!(validate_subtitle_langs(&[String::new()]).is_err());
+    }
+
+    #[test]
+    fn test_parse_subtitle_path() {
+        assert_eq!(
+            parse_subtitle_path("[info] Writing video subtitles to: /subs/Talk.en.vtt").as_deref(),
+            Some("/subs/Talk.en.vtt")
+        );
+        assert_eq!(parse_subtitle_path("[download] Destination: /subs/Talk.en.vtt"), None);
+    }
+
+    #[test]
+    fn test_music_site_urls() {
+        assert!(validate_url("https://soundcloud.com/artist/sets/album-name", false).is_ok());
+        assert!(validate_url("https://soundcloud.com/artist/track-name", false).is_ok());
+        assert!(validate_url("https://artist.bandcamp.com/album/album-name", false).is_ok());
+        assert!(validate_url("https://artist.bandcamp.com/track/track-name", false).is_ok());
+        assert!(validate_url("https://soundcloud.com.evil.com/artist", false).is_err());
+        assert!(validate_url("https://bandcamp.com.evil.com/album/x", false).is_err());
+        assert!(validate_url("https://soundcloud.com/artist/track$(whoami)", false).is_err());
+    }
+
+    #[test]
+    fn test_music_download_args_number_playlist_tracks() {
+        let args = music_download_args(Path::new("/music"), "mp3", true, None);
+        assert_eq!(
+            args,
+            vec![
+                "-o",
+                "/music/%(album,playlist_title)s/%(track_number,playlist_index)02d - %(artist,uploader)s - %(track,title)s.mp3",
+                "--parse-metadata",
+                "%(track_number,playlist_index)s:%(track_number)s",
+                "--embed-metadata",
+            ]
+        );
+
+        let single = music_download_args(Path::new("/music"), "mp3", false, None);
+        assert_eq!(single[1], "/music/%(artist,uploader)s - %(track,title)s.mp3");
+        assert_eq!(single[2], "--embed-metadata");
+    }
+
     #[test]
     fn test_validate_youtube_url_invalid_domains() {
         // Invalid domains should be rejected
@@ -743,6 +1873,358 @@ mod tests {
         assert!(sanitize_path("/boot/grub").is_err());
     }
 
+    #[test]
+    fn test_parse_output_path() {
+        assert_eq!(
+            parse_output_path("[download] Destination: /tmp/My Video.f137.mp4"),
+            Some("/tmp/My Video.f137.mp4".to_string())
+        );
+        assert_eq!(
+            parse_output_path("[Merger] Merging formats into \"/tmp/My Video.mp4\""),
+            Some("/tmp/My Video.mp4".to_string())
+        );
+        assert_eq!(
+            parse_output_path("[ExtractAudio] Destination: /tmp/Song: Live.mp3"),
+            Some("/tmp/Song: Live.mp3".to_string())
+        );
+        assert_eq!(
+            parse_output_path("[download] /tmp/My Video.mp4 has already been downloaded"),
+            Some("/tmp/My Video.mp4".to_string())
+        );
+        assert_eq!(parse_output_path("[download]  45.2% of 10.00MiB at 1.00MiB/s ETA 00:05"), None);
+        assert_eq!(parse_output_path("[youtube] dQw4w9WgXcQ: Downloading webpage"), None);
+    }
+
+    #[test]
+    fn test_summarize_stderr_prefers_error_lines() {
+        let lines: VecDeque<String> = [
+            "WARNING: [youtube] Falling back to generic n function search",
+            "ERROR: [youtube] abc: Unsupported URL",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        assert_eq!(summarize_stderr(&lines), Some("ERROR: [youtube] abc: Unsupported URL".to_string()));
+
+        let warnings: VecDeque<String> = ["WARNING: something odd", ""].iter().map(|l| l.to_string()).collect();
+        assert_eq!(summarize_stderr(&warnings), Some("WARNING: something odd".to_string()));
+        assert_eq!(summarize_stderr(&VecDeque::new()), None);
+    }
+
+    #[test]
+    fn test_classify_ytdlp_error_uses_stderr_text() {
+        let unsupported = DownloadManager::classify_ytdlp_error(
+            "Download failed with exit code Some(1): ERROR: Unsupported URL: https://example.com",
+            Some(1),
+        );
+        assert!(!unsupported.is_retryable());
+
+        let reset = DownloadManager::classify_ytdlp_error(
+            "Download failed with exit code Some(1): ERROR: Unable to download webpage: <urlopen error [Errno 104] Connection reset by peer>",
+            Some(1),
+        );
+        assert!(reset.is_retryable());
+
+        let unknown = DownloadManager::classify_ytdlp_error("Download failed with exit code: Some(1)", Some(1));
+        assert!(unknown.is_retryable());
+
+        let throttled = DownloadManager::classify_ytdlp_error("ERROR: unable to download video data: HTTP Error 429: Too Many Requests", None);
+        assert!(matches!(throttled, MediaForgeError::NetworkError(_)));
+        let missing = DownloadManager::classify_ytdlp_error("ERROR: unable to download video data: HTTP Error 404: Not Found", None);
+        assert!(matches!(missing, MediaForgeError::YtDlpError(_)));
+        // Status-like digits elsewhere in the line are not HTTP errors
+        let title = DownloadManager::classify_ytdlp_error("ERROR: [youtube] x4290502: Top 504 songs failed to merge", None);
+        assert!(!title.is_retryable());
+
+        // "Unavailable" in a server error's reason phrase doesn't make it permanent
+        let service = DownloadManager::classify_ytdlp_error("ERROR: unable to download video data: HTTP Error 503: Service Unavailable", Some(1));
+        assert!(matches!(service, MediaForgeError::NetworkError(_)));
+        let busy = DownloadManager::classify_ytdlp_error("ERROR: unable to write data: Resource temporarily unavailable", Some(1));
+        assert!(busy.is_retryable());
+        let removed = DownloadManager::classify_ytdlp_error("ERROR: [youtube] dQw4w9WgXcQ: Video unavailable. This video has been removed", Some(1));
+        assert!(!removed.is_retryable());
+        let blocked = DownloadManager::classify_ytdlp_error("ERROR: [youtube] dQw4w9WgXcQ: This video is not available in your country", Some(1));
+        assert!(!blocked.is_retryable());
+    }
+
+    #[test]
+    fn test_detect_unavailable_video() {
+        let private = "ERROR: [youtube] dQw4w9WgXcQ: Private video. Sign in if you've been granted access to this video";
+        assert_eq!(detect_unavailable_video(private), Some("This video is private"));
+        assert_eq!(
+            detect_unavailable_video("ERROR: [youtube] dQw4w9WgXcQ: Video unavailable"),
+            Some("This video is unavailable")
+        );
+        assert_eq!(
+            detect_unavailable_video("ERROR: [youtube] dQw4w9WgXcQ: Join this channel to get access to members-only content like this video"),
+            Some("This video is available to channel members only")
+        );
+        assert_eq!(detect_unavailable_video("[download]  45.2% of 10.00MiB"), None);
+        assert_eq!(detect_unavailable_video("[download] Destination: /tmp/Private Video Tour.mp4"), None);
+
+        // Reasons must classify as non-retryable
+        for reason in ["This video is private", "This video is unavailable", "This video is available to channel members only"] {
+            assert!(!MediaForgeError::YtDlpError(reason.to_string()).is_retryable());
+        }
+    }
+
+    #[test]
+    fn test_parse_speed_bytes() {
+        assert_eq!(parse_speed_bytes("1.00MiB/s"), Some(1024 * 1024));
+        assert_eq!(parse_speed_bytes("512.00KiB/s"), Some(512 * 1024));
+        assert_eq!(parse_speed_bytes("800B/s"), Some(800));
+        assert_eq!(parse_speed_bytes("Unknown"), None);
+        assert_eq!(parse_speed_bytes("Unknown B/s"), None);
+        assert_eq!(parse_speed_bytes("--"), None);
+    }
+
+    #[test]
+    fn test_parse_ytdlp_progress_sizes() {
+        let progress = parse_ytdlp_progress("[download]  50.0% of 100.00MiB at 1.00MiB/s ETA 00:50").unwrap();
+        assert_eq!(progress.total_bytes, Some(100 * 1024 * 1024));
+        assert_eq!(progress.downloaded_bytes, Some(50 * 1024 * 1024));
+
+        let estimated = parse_ytdlp_progress("[download]  25.0% of ~ 40.00MiB at 1.00MiB/s ETA 00:30").unwrap();
+        assert_eq!(estimated.total_bytes, Some(40 * 1024 * 1024));
+        assert_eq!(estimated.downloaded_bytes, Some(10 * 1024 * 1024));
+
+        let tilde = parse_ytdlp_progress("[download]  10.0% of ~10.00KiB at 1.00KiB/s ETA 00:09").unwrap();
+        assert_eq!(tilde.total_bytes, Some(10 * 1024));
+
+        let unknown = parse_ytdlp_progress("[download] Destination: /tmp/video.mp4").unwrap();
+        assert_eq!(unknown.total_bytes, None);
+        assert_eq!(unknown.downloaded_bytes, None);
+    }
+
+    #[test]
+    fn test_parse_progress_template() {
+        let progress = parse_progress_template("[progress] downloading|52428800|104857600|NA|1048576.5|50|12.3").unwrap();
+        assert_eq!(progress.percentage, 50.0);
+        assert_eq!(progress.downloaded_bytes, Some(50 * 1024 * 1024));
+        assert_eq!(progress.total_bytes, Some(100 * 1024 * 1024));
+        assert_eq!(progress.speed.as_deref(), Some("1.00MiB/s"));
+        assert_eq!(progress.speed.as_deref().and_then(parse_speed_bytes), Some(1024 * 1024));
+        assert_eq!(progress.eta.as_deref(), Some("00:50"));
+        assert_eq!(progress.elapsed_seconds, Some(12));
+
+        // Fragmented downloads only have an estimated total
+        let estimated = parse_progress_template("[progress] downloading|1000|NA|4000.0|NA|NA|3.0").unwrap();
+        assert_eq!(estimated.total_bytes, Some(4000));
+        assert_eq!(estimated.percentage, 25.0);
+        assert_eq!(estimated.speed, None);
+        assert_eq!(estimated.eta, None);
+
+        // Live streams have no total at all
+        let live = parse_progress_template("[progress] downloading|12582912|NA|NA|1024|NA|83.9").unwrap();
+        assert_eq!(live.total_bytes, None);
+        assert_eq!(live.percentage, 0.0);
+        assert_eq!(live.downloaded_bytes, Some(12 * 1024 * 1024));
+        assert_eq!(live.elapsed_seconds, Some(83));
+        assert_eq!(live.speed.as_deref(), Some("1.00KiB/s"));
+
+        let finished = parse_progress_template("[progress] finished|2048|NA|NA|NA|NA|1.0").unwrap();
+        assert_eq!(finished.percentage, 100.0);
+
+        assert!(parse_progress_template("[download]  50.0% of 100.00MiB at 1.00MiB/s ETA 00:50").is_none());
+        assert!(parse_progress_template("[progress] downloading|1|2").is_none());
+    }
+
+    #[test]
+    fn test_parse_ytdlp_progress_live() {
+        let live = parse_ytdlp_progress("[download]   12.00MiB at  1.00MiB/s (00:01:23)").unwrap();
+        assert_eq!(live.total_bytes, None);
+        assert_eq!(live.downloaded_bytes, Some(12 * 1024 * 1024));
+        assert_eq!(live.elapsed_seconds, Some(83));
+
+        let fragments = parse_ytdlp_progress("[download]    2.50MiB at  512.00KiB/s (00:00:05) (frag 3/3)").unwrap();
+        assert_eq!(fragments.downloaded_bytes, Some(2560 * 1024));
+        assert_eq!(fragments.elapsed_seconds, Some(5));
+    }
+
+    #[test]
+    fn test_verify_media_file_missing() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let missing = std::env::temp_dir().join(format!("mediaforge-missing-{}.mp4", Uuid::new_v4()));
+        let result = rt.block_on(verify_media_file(&missing));
+        assert_eq!(result, Err("the file is missing".to_string()));
+    }
+
+    #[test]
+    fn test_detect_live_stream() {
+        assert!(detect_live_stream("ERROR: [youtube] dQw4w9WgXcQ: This live event will begin in 3 hours."));
+        assert!(detect_live_stream("[youtube] dQw4w9WgXcQ: This video is a live event"));
+        assert!(detect_live_stream("WARNING: [youtube] dQw4w9WgXcQ: Livestream has not ended, recording from start"));
+        assert!(!detect_live_stream("[download] Destination: /tmp/Best livestream moments.mp4"));
+        assert!(!detect_live_stream("Merging a live stream title into something"));
+        assert_eq!(
+            detect_unavailable_video("ERROR: [youtube] dQw4w9WgXcQ: This live event will begin in 3 hours."),
+            Some("This live event has not started yet; enable live recording to wait for it")
+        );
+    }
+
+    #[test]
+    fn test_parse_chapter_path() {
+        assert_eq!(
+            parse_chapter_path("[SplitChapters] Chapter 003; Destination: /tmp/Talk/003 - Q&A.mp4"),
+            Some("/tmp/Talk/003 - Q&A.mp4".to_string())
+        );
+        assert_eq!(parse_chapter_path("[SplitChapters] Splitting video by chapters; 5 chapters found"), None);
+        assert_eq!(parse_chapter_path("[download] Destination: /tmp/Talk.mp4"), None);
+    }
+
+    #[test]
+    fn test_parse_eta_seconds() {
+        assert_eq!(parse_eta_seconds("00:45"), Some(45));
+        assert_eq!(parse_eta_seconds("02:05"), Some(125));
+        assert_eq!(parse_eta_seconds("01:02:03"), Some(3723));
+        assert_eq!(parse_eta_seconds("Unknown"), None);
+        assert_eq!(parse_eta_seconds("--:--"), None);
+    }
+
+    #[test]
+    fn test_validate_output_template() {
+        for template in [
+            "%(title)s",
+            "%(uploader)s/%(title)s [%(id)s].%(ext)s",
+            "%(playlist_title,playlist)s/%(playlist_index)03d - %(title)s",
+            "100%% %(artist,uploader)s - %(track,title)s",
+        ] {
+            assert!(validate_output_template(template).is_ok(), "{} should be accepted", template);
+        }
+        for template in [
+            "",
+            "/etc/%(title)s",
+            "~/%(title)s",
+            "../%(title)s",
+            "%(uploader)s/../../%(title)s",
+            "a//%(title)s",
+            "%(title)s; rm -rf ~",
+            "$(whoami)/%(title)s",
+            "%(title)s|%(id)s",
+            "%(filepath)s",
+            "%(title)r",
+            "%(upload_date>%Y)s",
+            "50% %(title)s",
+            "%(title)s\n",
+            &"a".repeat(256),
+        ] {
+            assert!(validate_output_template(template).is_err(), "{:?} should be rejected", template);
+        }
+    }
+
+    #[test]
+    fn test_single_output_template() {
+        let request = |format: &str, template: Option<&str>| -> DownloadRequest {
+            serde_json::from_value(serde_json::json!({
+                "urls": ["https://www.youtube.com/watch?v=dQw4w9WgXcQ"],
+                "download_type": "Single",
+                "format": format,
+                "download_path": "/music",
+                "output_template": template,
+            }))
+            .unwrap()
+        };
+        let dir = Path::new("/music");
+        assert_eq!(single_output_template(dir, &request("mp4", None)), dir.join("%(title)s.mp4"));
+        assert_eq!(
+            single_output_template(dir, &request("mp3", Some("%(uploader)s/%(title)s [%(id)s].%(ext)s"))),
+            dir.join("%(uploader)s/%(title)s [%(id)s].mp3")
+        );
+        assert_eq!(
+            single_output_template(dir, &request("mp4", Some("%(id)s"))),
+            dir.join("%(id)s.mp4")
+        );
+    }
+
+    #[test]
+    fn test_chapter_options() {
+        assert!(supports_chapters(&MediaFormat::Mp4));
+        assert!(!supports_chapters(&MediaFormat::Mp3));
+
+        let request = |options: serde_json::Value| -> DownloadRequest {
+            let mut value = serde_json::json!({
+                "urls": ["https://www.youtube.com/watch?v=dQw4w9WgXcQ"],
+                "download_type": "Single",
+                "format": "mp4",
+                "download_path": "/videos",
+            });
+            value.as_object_mut().unwrap().extend(options.as_object().unwrap().clone());
+            serde_json::from_value(value).unwrap()
+        };
+        assert!(validate_download_options(&request(serde_json::json!({ "embed_chapters": true }))).is_ok());
+
+        let err = validate_download_options(&request(serde_json::json!({ "embed_chapters": true, "format": "mp3" })));
+        assert!(matches!(err, Err(MediaForgeError::InvalidSettings(message)) if message.contains(".mp3")));
+
+        let err = validate_download_options(&request(serde_json::json!({ "embed_chapters": true, "subtitles_only": true })));
+        assert!(matches!(err, Err(MediaForgeError::InvalidSettings(message)) if message.contains("chapter splitting or embedding")));
+    }
+
+    #[test]
+    fn test_separate_streams_template() {
+        let request = |template: Option<&str>| -> DownloadRequest {
+            serde_json::from_value(serde_json::json!({
+                "urls": ["https://www.youtube.com/watch?v=dQw4w9WgXcQ"],
+                "download_type": "Single",
+                "format": "mp4",
+                "download_path": "/videos",
+                "output_template": template,
+                "merge": false,
+            }))
+            .unwrap()
+        };
+        let dir = Path::new("/videos");
+        assert!(keeps_separate_streams(&request(None)));
+        assert_eq!(separate_streams_template(dir, &request(None)), dir.join("%(title)s.f%(format_id)s.%(ext)s"));
+        assert_eq!(
+            separate_streams_template(dir, &request(Some("%(uploader)s/%(title)s.%(ext)s"))),
+            dir.join("%(uploader)s/%(title)s.f%(format_id)s.%(ext)s")
+        );
+        assert_eq!(separate_streams_template(dir, &request(Some("%(id)s"))), dir.join("%(id)s.f%(format_id)s.%(ext)s"));
+
+        // The format id is dropped again from the task name
+        assert_eq!(title_from_output_path("/videos/Clip.f251.webm").as_deref(), Some("Clip"));
+    }
+
+    #[test]
+    fn test_free_output_path() {
+        let dir = std::env::temp_dir().join(format!("mediaforge-conflict-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let existing = dir.join("My Video.mp4");
+        std::fs::write(&existing, b"").unwrap();
+
+        assert_eq!(free_output_path(&existing), dir.join("My Video (1).mp4"));
+        std::fs::write(dir.join("My Video (1).mp4"), b"").unwrap();
+        assert_eq!(free_output_path(&existing), dir.join("My Video (2).mp4"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_is_media_output() {
+        assert!(is_media_output(Path::new("/videos/Talk.mp4")));
+        assert!(is_media_output(Path::new("/videos/Talk - 001 Intro.webm")));
+        assert!(is_media_output(Path::new("/music/Song.M4A")));
+        assert!(!is_media_output(Path::new("/videos/Talk.en.vtt")));
+        assert!(!is_media_output(Path::new("/videos/Talk.en.srt")));
+        assert!(!is_media_output(Path::new("/videos/Talk.webp")));
+        assert!(!is_media_output(Path::new("/videos/Talk")));
+    }
+
+    #[test]
+    fn test_detect_already_downloaded() {
+        assert!(detect_already_downloaded("[download] /videos/My Video.mp4 has already been downloaded"));
+        assert!(!detect_already_downloaded("[download] Destination: /videos/My Video.mp4"));
+        assert!(!detect_already_downloaded("[download] 100% of 10.00MiB"));
+    }
+
+    #[test]
+    fn test_title_from_output_path() {
+        assert_eq!(title_from_output_path("/tmp/My Video.f137.mp4"), Some("My Video".to_string()));
+        assert_eq!(title_from_output_path("/tmp/My Video.mp4"), Some("My Video".to_string()));
+        assert_eq!(title_from_output_path("/tmp/Song.f251-drc.webm"), Some("Song".to_string()));
+    }
+
     #[test]
     fn test_task_handle_creation() {
         use tokio_util::sync::CancellationToken;
@@ -774,7 +2256,7 @@ mod tests {
         
         let rt = tokio::runtime::Runtime::new().unwrap();
         rt.block_on(async {
-            let manager = DownloadManager::new();
+            let manager = DownloadManager::new(&Config::default(), Arc::default());
             
             // Create a task
             let task_id = manager.create_task("Test Task".to_string());
@@ -800,11 +2282,91 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_task_timestamps() {
+        let mut task = TaskProgress { status: TaskStatus::Downloading, ..Default::default() };
+        task.record_status_change(&TaskStatus::Queued, 1_000);
+        task.status = TaskStatus::Paused;
+        task.record_status_change(&TaskStatus::Downloading, 2_000);
+        task.status = TaskStatus::Downloading;
+        task.record_status_change(&TaskStatus::Paused, 3_000);
+        assert_eq!((task.started_at, task.finished_at), (Some(1_000), None));
+
+        // Cancelling stamps the end too
+        task.status = TaskStatus::Cancelled;
+        task.record_status_change(&TaskStatus::Downloading, 4_500);
+        assert_eq!(task.finished_at, Some(4_500));
+        assert_eq!(task.duration_ms(), Some(3_500));
+
+        task.status = TaskStatus::Queued;
+        task.record_status_change(&TaskStatus::Cancelled, 5_000);
+        assert_eq!((task.started_at, task.finished_at, task.duration_ms()), (None, None, None));
+
+        // The manager stamps every status change it applies
+        let manager = DownloadManager::new(&Config::default(), Arc::default());
+        let task_id = manager.create_task("Test Task".to_string());
+        manager.update_task(&task_id, |task| task.status = TaskStatus::Downloading);
+        manager.update_task(&task_id, |task| task.status = TaskStatus::Failed);
+        let task = manager.get_task(&task_id).unwrap();
+        assert!(task.started_at.is_some_and(|started| task.finished_at >= Some(started)));
+    }
+
+    #[test]
+    fn test_clear_finished_tasks() {
+        let manager = DownloadManager::new(&Config::default(), Arc::default());
+        let done = manager.create_task("Done".to_string());
+        manager.update_task(&done, |task| task.status = TaskStatus::Completed);
+        let failed = manager.create_task("Failed".to_string());
+        manager.update_task(&failed, |task| task.status = TaskStatus::Failed);
+        let queued = manager.create_task("Queued".to_string());
+
+        let mut removed = manager.clear_finished_tasks();
+        removed.sort();
+        let mut expected = vec![done, failed];
+        expected.sort();
+        assert_eq!(removed, expected);
+        assert!(manager.get_task(&queued).is_some());
+        assert!(manager.remove_task(&queued));
+        assert!(!manager.remove_task(&queued));
+    }
+
+    #[test]
+    fn test_shutdown_stops_running_children() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let manager = DownloadManager::new(&Config::default(), Arc::default());
+            let stops = manager.create_task("Stops on cancel".to_string());
+            let stuck = manager.create_task("Ignores cancel".to_string());
+
+            // The second worker never sees the cancellation, so shutdown has to abort it
+            for (task_id, listens) in [(&stops, true), (&stuck, false)] {
+                let token = CancellationToken::new();
+                let worker_token = if listens { token.clone() } else { CancellationToken::new() };
+                let join_handle = tokio::spawn(async move {
+                    let mut cmd = TokioCommand::new("sleep");
+                    cmd.arg("30");
+                    let _ = run_cancellable_command(cmd, &worker_token, |_| {}, |_| {}).await;
+                });
+                manager.task_handles.insert(task_id.clone(), TaskHandle::new(join_handle, token));
+            }
+
+            let started = std::time::Instant::now();
+            assert_eq!(manager.shutdown(Duration::from_secs(2)).await, 2);
+            assert!(started.elapsed() < Duration::from_secs(5));
+            assert!(manager.task_handles.is_empty());
+            for task_id in [&stops, &stuck] {
+                let task = manager.get_task(task_id).unwrap();
+                assert_eq!(task.status, TaskStatus::Interrupted);
+                assert_eq!(task.error.as_deref(), Some(INTERRUPTED_BY_EXIT));
+            }
+        });
+    }
+
     #[test]
     fn test_race_condition_prevention() {
         let rt = tokio::runtime::Runtime::new().unwrap();
         rt.block_on(async {
-            let manager = DownloadManager::new();
+            let manager = DownloadManager::new(&Config::default(), Arc::default());
             
             // Create a task
             let task_id = manager.create_task("Test Task".to_string());
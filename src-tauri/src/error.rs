@@ -1,4 +1,7 @@
+use regex::Regex;
 use serde::{Serialize, Deserialize};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 #[derive(Debug, Error, Serialize, Deserialize)]
@@ -45,6 +48,9 @@ pub enum MediaForgeError {
     
     #[error("Resource exhausted: {0}")]
     ResourceExhausted(String),
+    
+    #[error("Temporarily paused due to rate limiting: {0}")]
+    RateLimited(String),
 }
 
 impl From<std::io::Error> for MediaForgeError {
@@ -132,8 +138,63 @@ impl MediaForgeError {
             }
         }
     }
+
+    /// Classifies a failure to start `program`. A binary that isn't installed becomes
+    /// `MissingDependency` naming it; anything else is wrapped by `other`.
+    pub fn from_spawn_error(program: &str, err: std::io::Error, other: fn(String) -> Self) -> Self {
+        if err.kind() == std::io::ErrorKind::NotFound {
+            MediaForgeError::not_installed(program)
+        } else {
+            other(format!("Failed to run {}: {}", program, err))
+        }
+    }
+
+    /// `program` isn't installed, or can't be found where the app looks for it
+    pub fn not_installed(program: &str) -> Self {
+        MediaForgeError::MissingDependency(format!("{}{}", program, NOT_INSTALLED))
+    }
+
+    /// Name of the variant, e.g. "NetworkError", sent to the frontend to tell failures apart
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MediaForgeError::DownloadError(_) => "DownloadError",
+            MediaForgeError::ConversionError(_) => "ConversionError",
+            MediaForgeError::InvalidUrl(_) => "InvalidUrl",
+            MediaForgeError::FileSystemError(_) => "FileSystemError",
+            MediaForgeError::FFmpegError(_) => "FFmpegError",
+            MediaForgeError::YtDlpError(_) => "YtDlpError",
+            MediaForgeError::TaskNotFound(_) => "TaskNotFound",
+            MediaForgeError::InvalidSettings(_) => "InvalidSettings",
+            MediaForgeError::MissingDependency(_) => "MissingDependency",
+            MediaForgeError::NetworkError(_) => "NetworkError",
+            MediaForgeError::DiskSpaceError(_) => "DiskSpaceError",
+            MediaForgeError::PermissionError(_) => "PermissionError",
+            MediaForgeError::TemporaryError(_) => "TemporaryError",
+            MediaForgeError::ResourceExhausted(_) => "ResourceExhausted",
+            MediaForgeError::RateLimited(_) => "RateLimited",
+        }
+    }
+
+    /// The program a task couldn't start because it isn't installed, if that's why it failed
+    pub fn missing_program(&self) -> Option<&str> {
+        match self {
+            MediaForgeError::MissingDependency(msg) => msg.strip_suffix(NOT_INSTALLED),
+            _ => None,
+        }
+    }
+}
+
+/// The status of an `HTTP Error 429: Too Many Requests` line in yt-dlp's output. Only
+/// this form counts, so digits in a title or video id aren't taken for a status.
+pub fn http_error_status(message: &str) -> Option<u16> {
+    static HTTP_ERROR: OnceLock<Regex> = OnceLock::new();
+    let re = HTTP_ERROR.get_or_init(|| Regex::new(r"(?i)\bHTTP Error (\d{3})\b").unwrap());
+    re.captures(message)?[1].parse().ok()
 }
 
+/// Ends the message of a `MissingDependency` raised because a program couldn't be found
+const NOT_INSTALLED: &str = " is not installed or not on the PATH";
+
 /// Retry configuration for error recovery
 #[derive(Debug, Clone)]
 pub struct RetryConfig {
@@ -183,10 +244,13 @@ impl RetryConfig {
     }
 }
 
-/// Async retry utility with exponential backoff
+/// Async retry utility with exponential backoff. `on_attempt(attempt, max_attempts,
+/// previous_error)` runs before each try, so callers can show which try is running
+/// and why the last one failed.
 pub async fn retry_async<F, Fut, T>(
     config: RetryConfig,
     mut operation: F,
+    mut on_attempt: impl FnMut(u32, u32, Option<&MediaForgeError>),
 ) -> Result<T, MediaForgeError>
 where
     F: FnMut() -> Fut,
@@ -195,6 +259,7 @@ where
     let mut last_error = None;
     
     for attempt in 1..=config.max_attempts {
+        on_attempt(attempt, config.max_attempts, last_error.as_ref());
         match operation().await {
             Ok(result) => return Ok(result),
             Err(error) => {
@@ -222,10 +287,111 @@ where
     }))
 }
 
+/// Stops new attempts against a host that keeps failing.
+///
+/// Consecutive network or rate-limit failures are counted across all tasks; when
+/// `threshold` of them happen within `window`, every attempt fails fast with
+/// `RateLimited` until `cooldown` has passed. The first success resets the count.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    window: Duration,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    failures: u32,
+    first_failure: Option<Instant>,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, window: Duration, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            window,
+            cooldown,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+    
+    /// Enough failures to outlast one download's retries before tripping
+    pub fn for_network() -> Self {
+        Self::new(8, Duration::from_secs(180), Duration::from_secs(300))
+    }
+    
+    /// Errors that suggest the host is refusing or throttling us
+    fn counts(error: &MediaForgeError) -> bool {
+        match error {
+            MediaForgeError::NetworkError(_) => true,
+            MediaForgeError::YtDlpError(msg) => http_error_status(msg) == Some(429),
+            _ => false,
+        }
+    }
+    
+    /// Fails while the breaker is open
+    pub fn check(&self) -> Result<(), MediaForgeError> {
+        self.check_at(Instant::now())
+    }
+    
+    fn check_at(&self, now: Instant) -> Result<(), MediaForgeError> {
+        let state = self.state.lock().unwrap();
+        match state.open_until {
+            Some(until) if now < until => {
+                // Round up so the message never says "0 seconds"
+                let remaining = (until - now).as_secs().div_ceil(60).max(1) * 60;
+                Err(MediaForgeError::RateLimited(format!(
+                    "too many failed requests; downloads resume in {}",
+                    validation::format_timeout(remaining)
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+    
+    pub fn record_success(&self) {
+        *self.state.lock().unwrap() = BreakerState::default();
+    }
+    
+    pub fn record_failure(&self, error: &MediaForgeError) {
+        self.record_failure_at(error, Instant::now());
+    }
+    
+    fn record_failure_at(&self, error: &MediaForgeError, now: Instant) {
+        if !Self::counts(error) {
+            return;
+        }
+        
+        let mut state = self.state.lock().unwrap();
+        match state.first_failure {
+            Some(first) if now.duration_since(first) <= self.window => state.failures += 1,
+            _ => {
+                state.failures = 1;
+                state.first_failure = Some(now);
+            }
+        }
+        
+        if state.failures >= self.threshold {
+            log::warn!(
+                "{} network failures within {}s, pausing new attempts for {}s",
+                state.failures,
+                self.window.as_secs(),
+                self.cooldown.as_secs()
+            );
+            *state = BreakerState {
+                open_until: Some(now + self.cooldown),
+                ..BreakerState::default()
+            };
+        }
+    }
+}
+
 /// Disk space and resource validation utilities
 pub mod validation {
     use super::MediaForgeError;
-    use std::path::Path;
+    use std::path::{Component, Path, PathBuf, Prefix};
     
     /// Minimum free space required (500MB)
     const MIN_FREE_SPACE_BYTES: u64 = 500 * 1024 * 1024;
@@ -256,55 +422,39 @@ pub mod validation {
         }
     }
     
-    /// Gets available disk space for a path (cross-platform)
-    async fn get_available_space(path: &Path) -> Result<u64, std::io::Error> {
-        use std::process::Command;
-        
-        // Use `df` command on Unix-like systems (Linux, macOS)
-        let output = Command::new("df")
-            .arg("-B1") // Output in bytes
-            .arg(path)
-            .output()?;
-            
-        if !output.status.success() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Failed to get disk space information"
-            ));
-        }
-        
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        // Parse df output - format: filesystem blocks used available use% mounted_on
-        for line in stdout.lines().skip(1) { // Skip header
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 4 {
-                if let Ok(available) = parts[3].parse::<u64>() {
-                    return Ok(available);
-                }
-            }
+    /// Gets available disk space for a path (cross-platform). A path that doesn't exist
+    /// yet is measured at its nearest existing ancestor, where it would be created.
+    pub(super) async fn get_available_space(path: &Path) -> Result<u64, std::io::Error> {
+        let existing = path.ancestors().find(|a| a.exists()).unwrap_or(path);
+        // statvfs on Unix, GetDiskFreeSpaceExW on Windows; space available to this user
+        fs2::available_space(existing)
+    }
+    
+    /// Checks a folder chosen for output the way downloads and conversions will: a safe
+    /// path that isn't a file, writable, with room to spare. Returns the path they'll use.
+    pub async fn validate_output_directory(path: &str) -> Result<PathBuf, MediaForgeError> {
+        let dir = sanitize_path(path)?;
+        if dir.exists() && !dir.is_dir() {
+            return Err(MediaForgeError::FileSystemError(format!("Not a folder: {}", dir.display())));
         }
-        
-        Err(std::io::Error::new(
-            std::io::ErrorKind::InvalidData,
-            "Could not parse disk space information"
-        ))
+        validate_write_permissions(&dir).await?;
+        validate_disk_space(&dir, None).await?;
+        Ok(dir)
     }
     
-    /// Validates write permissions for a directory
+    /// Validates write permissions for a directory. A directory that doesn't exist yet
+    /// isn't created; the nearest existing one it would be created in is checked instead.
     pub async fn validate_write_permissions(path: &Path) -> Result<(), MediaForgeError> {
         use tokio::fs;
         
-        if !path.exists() {
-            // Try to create the directory
-            if let Err(e) = fs::create_dir_all(path).await {
-                return Err(MediaForgeError::PermissionError(
-                    format!("Cannot create directory {:?}: {}", path, e)
-                ));
-            }
-        }
+        let Some(dir) = path.ancestors().find(|dir| dir.is_dir()) else {
+            return Err(MediaForgeError::PermissionError(
+                format!("Cannot write to directory {:?}: no part of it exists", path)
+            ));
+        };
         
         // Try to create a test file
-        let test_file = path.join(".mediaforge_write_test");
+        let test_file = dir.join(".mediaforge_write_test");
         match fs::write(&test_file, b"test").await {
             Ok(()) => {
                 // Clean up test file
@@ -312,11 +462,181 @@ pub mod validation {
                 Ok(())
             }
             Err(e) => Err(MediaForgeError::PermissionError(
-                format!("Cannot write to directory {:?}: {}", path, e)
+                format!("Cannot write to directory {:?}: {}", dir, e)
             ))
         }
     }
     
+    /// Sanitizes file paths to prevent path traversal and ensure paths are within
+    /// allowed directories. Only checks the path: nothing is created, see `create_dir`.
+    pub fn sanitize_path(path: &str) -> Result<PathBuf, MediaForgeError> {
+        let canonical_path = resolve_path(path)?;
+        
+        // Ensure path is within reasonable bounds (not system directories)
+        let path_str = canonical_path.to_string_lossy();
+        if path_str.starts_with("/etc") 
+            || path_str.starts_with("/sys") 
+            || path_str.starts_with("/proc")
+            || path_str.starts_with("/boot")
+            || path_str.starts_with("/root")  // Unless we're root
+            || path_str.contains("/.ssh/")
+            || path_str.contains("/.gnupg/")
+        {
+            return Err(MediaForgeError::InvalidSettings(
+                "Access to system directories is not allowed".into()
+            ));
+        }
+        
+        Ok(canonical_path)
+    }
+    
+    /// Expands `~` and makes the path absolute, rejecting `..` and doubled separators.
+    /// Unlike `sanitize_path` this allows system directories, for settings that are
+    /// checked again when they are used.
+    pub fn resolve_path(path: &str) -> Result<PathBuf, MediaForgeError> {
+        let expanded_path = expand_home(path)?;
+        
+        let path_buf = PathBuf::from(&expanded_path);
+        
+        // Prevent path traversal attacks
+        for component in path_buf.components() {
+            if matches!(component, std::path::Component::ParentDir) {
+                return Err(MediaForgeError::InvalidSettings(
+                    "Path traversal detected: '..' not allowed in paths".into()
+                ));
+            }
+        }
+        
+        // Check for other dangerous path components
+        if has_double_separator(&expanded_path, &path_buf) {
+            return Err(MediaForgeError::InvalidSettings(
+                "Invalid path: double separators not allowed".into()
+            ));
+        }
+        
+        // Ensure path is absolute or can be made absolute
+        let canonical_path = if path_buf.is_absolute() {
+            path_buf
+        } else {
+            std::env::current_dir()
+                .map_err(|e| MediaForgeError::FileSystemError(format!("Cannot get current directory: {}", e)))?
+                .join(path_buf)
+        };
+        
+        Ok(canonical_path)
+    }
+    
+    /// Expands a leading `~` or `~/` to the user's home directory; `~name` is left alone
+    pub(super) fn expand_home(path: &str) -> Result<String, MediaForgeError> {
+        match path.strip_prefix('~') {
+            Some(rest) if rest.is_empty() || rest.starts_with(['/', std::path::MAIN_SEPARATOR]) => {
+                let home = dirs::home_dir().ok_or_else(|| {
+                    MediaForgeError::FileSystemError("Cannot find the home directory to expand '~'".into())
+                })?;
+                let home = home.to_string_lossy();
+                Ok(format!("{}{}", home.trim_end_matches(['/', std::path::MAIN_SEPARATOR]), rest))
+            }
+            _ => Ok(path.to_string()),
+        }
+    }
+    
+    /// Whether `path` repeats a separator, as malformed paths do. The leading `\\` of a
+    /// Windows UNC path (`\\server\share\downloads`) is allowed; a leading `//` is not.
+    fn has_double_separator(path: &str, path_buf: &Path) -> bool {
+        let is_unc = matches!(
+            path_buf.components().next(),
+            Some(Component::Prefix(prefix)) if matches!(prefix.kind(), Prefix::UNC(..) | Prefix::VerbatimUNC(..))
+        );
+        let rest = match path.strip_prefix("\\\\") {
+            Some(rest) if is_unc => rest,
+            _ => path,
+        };
+        rest.contains("//") || rest.contains("\\\\")
+    }
+    
+    /// An output file path checked by `sanitize_output_path`
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum OutputPath {
+        /// Nothing exists at the path yet
+        New(PathBuf),
+        /// A file is already there; the conflict policy decides what happens to it
+        Existing(PathBuf),
+    }
+    
+    impl OutputPath {
+        pub fn path(&self) -> &Path {
+            match self {
+                OutputPath::New(path) | OutputPath::Existing(path) => path,
+            }
+        }
+        
+        pub fn into_path(self) -> PathBuf {
+            match self {
+                OutputPath::New(path) | OutputPath::Existing(path) => path,
+            }
+        }
+    }
+    
+    /// The stricter `sanitize_path` for a file about to be written: an existing file
+    /// is reported instead of quietly overwritten, and a directory is refused
+    pub fn sanitize_output_path(path: &str) -> Result<OutputPath, MediaForgeError> {
+        let path = sanitize_path(path)?;
+        if path.is_dir() {
+            return Err(MediaForgeError::InvalidSettings(
+                format!("Output {:?} is a directory", path)
+            ));
+        }
+        if path.exists() {
+            Ok(OutputPath::Existing(path))
+        } else {
+            Ok(OutputPath::New(path))
+        }
+    }
+    
+    /// Creates a directory, and any missing parents, for output. Callers opt in to
+    /// this after validating the path.
+    pub fn create_dir(path: &Path) -> Result<(), MediaForgeError> {
+        std::fs::create_dir_all(path)
+            .map_err(|e| MediaForgeError::FileSystemError(format!("Cannot create directory: {}", e)))
+    }
+    
+    /// Upper bound for user-configured task timeouts (7 days)
+    pub const MAX_TIMEOUT_SECS: u64 = 7 * 24 * 60 * 60;
+    
+    /// Resolves a requested task timeout against the default for that task type.
+    /// `Some(0)`, or no request with a default of 0, disables the timeout and yields `None`.
+    pub fn resolve_timeout(timeout_secs: Option<u64>, default_secs: u64) -> Result<Option<u64>, MediaForgeError> {
+        match timeout_secs {
+            None => Ok(Some(default_secs).filter(|&secs| secs > 0)),
+            Some(0) => Ok(None),
+            Some(secs) if secs > MAX_TIMEOUT_SECS => Err(MediaForgeError::InvalidSettings(
+                format!("Timeout of {}s exceeds the maximum of {}s (7 days); use 0 to disable it", secs, MAX_TIMEOUT_SECS)
+            )),
+            Some(secs) => Ok(Some(secs)),
+        }
+    }
+    
+    /// Formats a timeout for user-facing messages, e.g. "1 hour" or "90 seconds"
+    pub fn format_timeout(secs: u64) -> String {
+        let plural = |n: u64, unit: &str| if n == 1 { format!("1 {}", unit) } else { format!("{} {}s", n, unit) };
+        
+        if secs % 3600 == 0 {
+            plural(secs / 3600, "hour")
+        } else if secs % 60 == 0 {
+            plural(secs / 60, "minute")
+        } else {
+            plural(secs, "second")
+        }
+    }
+    
+    /// Sleeps for the given timeout, or forever when the timeout is disabled
+    pub async fn sleep_for_timeout(timeout_secs: Option<u64>) {
+        match timeout_secs {
+            Some(secs) => tokio::time::sleep(std::time::Duration::from_secs(secs)).await,
+            None => std::future::pending::<()>().await,
+        }
+    }
+    
     /// Cleanup partial/temporary files on error
     pub async fn cleanup_on_error(file_path: &Path) -> Result<(), MediaForgeError> {
         use tokio::fs;
@@ -331,3 +651,221 @@ pub mod validation {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::validation::*;
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_from_spawn_error() {
+        let missing = std::io::Error::new(std::io::ErrorKind::NotFound, "No such file or directory");
+        let err = MediaForgeError::from_spawn_error("ffmpeg", missing, MediaForgeError::FFmpegError);
+        assert!(matches!(err, MediaForgeError::MissingDependency(_)));
+        assert_eq!(err.missing_program(), Some("ffmpeg"));
+        assert_eq!(err.to_string(), "System dependency missing: ffmpeg is not installed or not on the PATH");
+
+        let denied = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "Permission denied");
+        let err = MediaForgeError::from_spawn_error("yt-dlp", denied, MediaForgeError::YtDlpError);
+        assert!(matches!(err, MediaForgeError::YtDlpError(ref msg) if msg == "Failed to run yt-dlp: Permission denied"));
+        assert_eq!(err.missing_program(), None);
+
+        // Missing codecs are also MissingDependency, but there's no program to reinstall
+        assert_eq!(MediaForgeError::MissingDependency("HEIC support".to_string()).missing_program(), None);
+    }
+
+    #[test]
+    fn test_error_kind_matches_serialized_variant() {
+        let errors = [
+            MediaForgeError::NetworkError("reset".to_string()),
+            MediaForgeError::DiskSpaceError("full".to_string()),
+            MediaForgeError::RateLimited("slow down".to_string()),
+        ];
+        for err in errors {
+            let value = serde_json::to_value(&err).unwrap();
+            assert!(value.get(err.kind()).is_some(), "{} not in {}", err.kind(), value);
+        }
+    }
+
+    #[test]
+    fn test_sanitize_path_only_validates() {
+        let dir = std::env::temp_dir().join(format!("mediaforge-sanitize-{}", uuid::Uuid::new_v4()));
+        let nested = dir.join("a").join("b");
+
+        // Checking a path leaves the file system alone; creating the folder is separate
+        assert_eq!(sanitize_path(&nested.to_string_lossy()).unwrap(), nested);
+        assert!(!dir.exists());
+        create_dir(&nested).unwrap();
+        assert!(nested.is_dir());
+
+        let output = nested.join("clip.mp4");
+        assert_eq!(sanitize_output_path(&output.to_string_lossy()).unwrap(), OutputPath::New(output.clone()));
+        std::fs::write(&output, b"").unwrap();
+        let existing = sanitize_output_path(&output.to_string_lossy()).unwrap();
+        assert_eq!(existing, OutputPath::Existing(output.clone()));
+        assert_eq!(existing.into_path(), output);
+        assert!(sanitize_output_path(&nested.to_string_lossy()).is_err());
+        assert!(sanitize_output_path("/etc/passwd").is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_expand_home() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(Path::new(&expand_home("~/Downloads").unwrap()), home.join("Downloads"));
+        assert_eq!(Path::new(&expand_home("~").unwrap()), home);
+        assert_eq!(expand_home("~other/Downloads").unwrap(), "~other/Downloads");
+        assert_eq!(expand_home("/srv/~/media").unwrap(), "/srv/~/media");
+        #[cfg(windows)]
+        assert_eq!(Path::new(&expand_home(r"~\Videos").unwrap()), home.join("Videos"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_sanitize_path_unc() {
+        let share = sanitize_path(r"\\server\share\downloads").unwrap();
+        assert_eq!(share, Path::new(r"\\server\share\downloads"));
+        assert!(sanitize_path(r"\\?\UNC\server\share\downloads").is_ok());
+
+        assert!(sanitize_path(r"\\server\share\..\other").is_err());
+        assert!(sanitize_path(r"\\server\share\a\\b").is_err());
+        assert!(sanitize_path("//server/share/downloads").is_err());
+        assert!(sanitize_path(r"C:\Users\me\\Videos").is_err());
+    }
+
+    #[test]
+    fn test_http_error_status() {
+        assert_eq!(http_error_status("ERROR: HTTP Error 503: Service Unavailable"), Some(503));
+        assert_eq!(http_error_status("error: http error 502: Bad Gateway"), Some(502));
+        assert_eq!(http_error_status("Downloading 429 of 502 fragments"), None);
+    }
+
+    #[test]
+    fn test_circuit_breaker_trips_and_resets() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60), Duration::from_secs(300));
+        let start = Instant::now();
+        let network = MediaForgeError::NetworkError("HTTP Error 429: Too Many Requests".into());
+
+        breaker.record_failure_at(&network, start);
+        breaker.record_failure_at(&network, start + Duration::from_secs(10));
+        // Unrelated errors neither count nor reset
+        breaker.record_failure_at(&MediaForgeError::YtDlpError("Private video".into()), start);
+        assert!(breaker.check_at(start + Duration::from_secs(10)).is_ok());
+
+        breaker.record_failure_at(&network, start + Duration::from_secs(20));
+        let err = breaker.check_at(start + Duration::from_secs(21)).unwrap_err();
+        assert!(matches!(err, MediaForgeError::RateLimited(_)));
+        assert!(!err.is_retryable());
+        assert!(err.to_string().contains("5 minutes"));
+        assert!(breaker.check_at(start + Duration::from_secs(320)).is_ok());
+
+        // A success clears the count
+        breaker.record_failure_at(&network, start);
+        breaker.record_failure_at(&network, start);
+        breaker.record_success();
+        breaker.record_failure_at(&network, start);
+        assert!(breaker.check_at(start).is_ok());
+    }
+
+    #[test]
+    fn test_circuit_breaker_window() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60), Duration::from_secs(300));
+        let start = Instant::now();
+        let network = MediaForgeError::NetworkError("Connection reset".into());
+
+        breaker.record_failure_at(&network, start);
+        // Too far apart to count as a burst
+        breaker.record_failure_at(&network, start + Duration::from_secs(120));
+        assert!(breaker.check_at(start + Duration::from_secs(120)).is_ok());
+        breaker.record_failure_at(&network, start + Duration::from_secs(150));
+        assert!(breaker.check_at(start + Duration::from_secs(150)).is_err());
+    }
+
+    #[test]
+    fn test_circuit_breaker_counts_rate_limits_only() {
+        assert!(CircuitBreaker::counts(&MediaForgeError::YtDlpError("ERROR: HTTP Error 429: Too Many Requests".into())));
+        // A 429 in a video id or title isn't a rate limit
+        assert!(!CircuitBreaker::counts(&MediaForgeError::YtDlpError("ERROR: [youtube] x4290502: Video unavailable".into())));
+        assert!(!CircuitBreaker::counts(&MediaForgeError::YtDlpError("ERROR: HTTP Error 403: Forbidden".into())));
+    }
+
+    #[tokio::test]
+    async fn test_retry_async_reports_attempts() {
+        let config = RetryConfig { max_attempts: 3, base_delay: 0, max_delay: 0, exponential_backoff: false };
+        let mut attempts = Vec::new();
+        let result: Result<(), MediaForgeError> = retry_async(
+            config,
+            || async { Err(MediaForgeError::NetworkError("timed out".to_string())) },
+            |attempt, max, previous| attempts.push((attempt, max, previous.map(|e| e.to_string()))),
+        )
+        .await;
+        assert!(result.is_err());
+        let previous = Some("Network error (retryable): timed out".to_string());
+        assert_eq!(attempts, vec![(1, 3, None), (2, 3, previous.clone()), (3, 3, previous)]);
+    }
+
+    #[tokio::test]
+    async fn test_get_available_space() {
+        let available = get_available_space(&std::env::temp_dir()).await.unwrap();
+        assert!(available > 0);
+        // Below an exabyte
+        assert!(available < 1 << 60);
+
+        // Folders still to be created count against the disk they'll be created on
+        let missing = std::env::temp_dir().join(format!("mediaforge-space-{}", uuid::Uuid::new_v4())).join("nested");
+        assert!(get_available_space(&missing).await.unwrap() > 0);
+        assert!(get_available_space(Path::new("")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_output_directory() {
+        let dir = std::env::temp_dir().join(format!("mediaforge-output-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert_eq!(validate_output_directory(&dir.to_string_lossy()).await.unwrap(), dir);
+
+        let file = dir.join("clip.mp4");
+        std::fs::write(&file, b"").unwrap();
+        let err = validate_output_directory(&file.to_string_lossy()).await.unwrap_err();
+        assert!(matches!(err, MediaForgeError::FileSystemError(_)));
+        assert!(validate_output_directory("/etc").await.is_err());
+        assert!(validate_output_directory(&format!("{}/../other", dir.display())).await.is_err());
+
+        // A folder yet to be created is checked through its parent and left uncreated
+        let missing = dir.join("later/nested");
+        assert_eq!(validate_output_directory(&missing.to_string_lossy()).await.unwrap(), missing);
+        assert!(!dir.join("later").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_validate_disk_space() {
+        let huge = Some(1 << 60);
+        assert!(validate_disk_space(&std::env::temp_dir(), Some(1)).await.is_ok());
+        assert!(matches!(
+            validate_disk_space(&std::env::temp_dir(), huge).await,
+            Err(MediaForgeError::DiskSpaceError(_))
+        ));
+        // Space that can't be measured doesn't stop the operation
+        assert!(validate_disk_space(Path::new(""), huge).await.is_ok());
+    }
+
+    #[test]
+    fn test_resolve_timeout() {
+        assert_eq!(resolve_timeout(None, 3600).unwrap(), Some(3600));
+        assert_eq!(resolve_timeout(Some(0), 3600).unwrap(), None);
+        assert_eq!(resolve_timeout(None, 0).unwrap(), None);
+        assert_eq!(resolve_timeout(Some(8 * 3600), 3600).unwrap(), Some(8 * 3600));
+        assert!(resolve_timeout(Some(MAX_TIMEOUT_SECS + 1), 3600).is_err());
+    }
+
+    #[test]
+    fn test_format_timeout() {
+        assert_eq!(format_timeout(3600), "1 hour");
+        assert_eq!(format_timeout(7200), "2 hours");
+        assert_eq!(format_timeout(120), "2 minutes");
+        assert_eq!(format_timeout(90), "90 seconds");
+    }
+}
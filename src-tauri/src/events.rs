@@ -0,0 +1,223 @@
+use crate::error::MediaForgeError;
+use crate::types::{GlobalProgress, TaskFailed, TaskProgress, TaskRemoved, TaskStatus};
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Notify;
+
+/// Shortest gap between two progress-only `task-update` events for one task,
+/// about four a second
+pub const TASK_UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Shortest gap between two recomputations of overall progress for `global-progress`
+pub const GLOBAL_PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Woken whenever a task event is sent, to recompute overall progress
+static TASKS_CHANGED: Notify = Notify::const_new();
+
+/// Recomputes overall progress after tasks change, at most a few times a second, and
+/// sends `global-progress` whenever it changed, so the last one sent once everything
+/// finishes is the idle state. Nothing is recomputed while no task changes.
+pub fn spawn_global_progress(app: AppHandle, progress: impl Fn() -> GlobalProgress + Send + 'static) {
+    tauri::async_runtime::spawn(async move {
+        let mut last: Option<GlobalProgress> = None;
+        loop {
+            TASKS_CHANGED.notified().await;
+            let current = progress();
+            if last.as_ref() != Some(&current) {
+                let _ = app.emit("global-progress", &current);
+                last = Some(current);
+            }
+            // A change while waiting leaves a permit behind, so it is picked up next
+            tokio::time::sleep(GLOBAL_PROGRESS_INTERVAL).await;
+        }
+    });
+}
+
+fn send_task_update(app: &AppHandle, task: &TaskProgress) {
+    let _ = app.emit("task-update", task);
+    TASKS_CHANGED.notify_one();
+}
+
+/// Sends `task-created` with a new task's initial state
+pub fn emit_task_created(app: &AppHandle, task: Option<TaskProgress>) {
+    if let Some(task) = task {
+        let _ = app.emit("task-created", task);
+        TASKS_CHANGED.notify_one();
+    }
+}
+
+/// Sends `task-removed` for tasks taken off a manager's list
+pub fn emit_task_removed(app: &AppHandle, task_ids: Vec<String>) {
+    if !task_ids.is_empty() {
+        let _ = app.emit("task-removed", TaskRemoved { task_ids });
+        TASKS_CHANGED.notify_one();
+    }
+}
+
+/// Sends `task-failed` with how the failure was classified. `error` is the message
+/// shown on the task, which may differ from `cause`'s.
+pub fn emit_task_failed(app: &AppHandle, task_id: &str, error: String, cause: &MediaForgeError) {
+    let failed = TaskFailed {
+        task_id: task_id.to_string(),
+        error,
+        kind: cause.kind().to_string(),
+        retryable: cause.is_retryable(),
+    };
+    let _ = app.emit("task-failed", failed);
+}
+
+#[derive(Debug)]
+struct EmitState {
+    last_emit: Instant,
+    status: TaskStatus,
+    /// Newest update held back by the throttle; a flush is scheduled while this is set
+    pending: Option<TaskProgress>,
+}
+
+/// Coalesces `task-update` events so yt-dlp and ffmpeg progress lines don't flood
+/// the webview.
+///
+/// A task's first update, status changes and finished tasks are sent straight away.
+/// Other updates are sent at most once per interval; the newest one held back is
+/// sent when the interval is up, so the last value always gets through.
+#[derive(Debug)]
+pub struct TaskUpdateEmitter {
+    interval: Duration,
+    tasks: DashMap<String, EmitState>,
+}
+
+impl Default for TaskUpdateEmitter {
+    fn default() -> Self {
+        Self::new(TASK_UPDATE_INTERVAL)
+    }
+}
+
+impl TaskUpdateEmitter {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            tasks: DashMap::new(),
+        }
+    }
+
+    /// Sends the update now or holds it back, scheduling a flush for it
+    pub fn emit(self: &Arc<Self>, app: &AppHandle, task: Option<TaskProgress>) {
+        let Some(task) = task else { return };
+        let task_id = task.task_id.clone();
+        if let Some(delay) = self.offer(task, Instant::now(), |task| send_task_update(app, task)) {
+            let updates = self.clone();
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                tokio::time::sleep(delay).await;
+                updates.flush(&task_id, Instant::now(), |task| send_task_update(&app, task));
+            });
+        }
+    }
+
+    /// Passes the update to `send` if it is due, otherwise keeps it as the pending one.
+    /// Returns how long to wait before flushing when no flush is scheduled yet.
+    ///
+    /// `send` runs while the task's entry is locked so a flush can't overtake a newer update.
+    fn offer(&self, task: TaskProgress, now: Instant, send: impl FnOnce(&TaskProgress)) -> Option<Duration> {
+        match self.tasks.entry(task.task_id.clone()) {
+            Entry::Vacant(entry) => {
+                send(&task);
+                if !task.is_finished() {
+                    entry.insert(EmitState { last_emit: now, status: task.status.clone(), pending: None });
+                }
+                None
+            }
+            Entry::Occupied(mut entry) => {
+                if task.is_finished() {
+                    send(&task);
+                    entry.remove();
+                    return None;
+                }
+                let state = entry.get_mut();
+                let since_last = now.saturating_duration_since(state.last_emit);
+                if task.status != state.status || since_last >= self.interval {
+                    send(&task);
+                    state.last_emit = now;
+                    state.status = task.status.clone();
+                    state.pending = None;
+                    return None;
+                }
+                let scheduled = state.pending.replace(task).is_some();
+                (!scheduled).then(|| self.interval - since_last)
+            }
+        }
+    }
+
+    /// Sends the task's held-back update, if one is still waiting
+    fn flush(&self, task_id: &str, now: Instant, send: impl FnOnce(&TaskProgress)) {
+        if let Some(mut state) = self.tasks.get_mut(task_id) {
+            if let Some(task) = state.pending.take() {
+                send(&task);
+                state.last_emit = now;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(progress: f32, status: TaskStatus) -> TaskProgress {
+        TaskProgress {
+            task_id: "task".to_string(),
+            status,
+            progress,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_burst_is_coalesced() {
+        let emitter = TaskUpdateEmitter::default();
+        let start = Instant::now();
+        let mut sent: Vec<f32> = Vec::new();
+        let mut flushes = Vec::new();
+
+        // 1000 progress lines over one second
+        for i in 1..=1000 {
+            let now = start + Duration::from_millis(i);
+            let task = update(i as f32 / 10.0, TaskStatus::Downloading);
+            if let Some(delay) = emitter.offer(task, now, |t| sent.push(t.progress)) {
+                flushes.push(now + delay);
+            }
+        }
+        assert!(sent.len() <= 5, "{} updates sent", sent.len());
+        assert_eq!(sent[0], 0.1);
+
+        // The newest held-back value is the one flushed
+        let last_flush = *flushes.last().unwrap();
+        emitter.flush("task", last_flush, |t| sent.push(t.progress));
+        assert_eq!(*sent.last().unwrap(), 100.0);
+        assert!(sent.len() <= 6);
+
+        // Nothing is left to flush twice
+        emitter.flush("task", last_flush, |t| sent.push(t.progress));
+        assert!(sent.len() <= 6);
+    }
+
+    #[test]
+    fn test_status_changes_are_not_throttled() {
+        let emitter = TaskUpdateEmitter::default();
+        let now = Instant::now();
+        let mut sent = Vec::new();
+
+        emitter.offer(update(10.0, TaskStatus::Downloading), now, |t| sent.push(t.status.clone()));
+        emitter.offer(update(20.0, TaskStatus::Downloading), now, |t| sent.push(t.status.clone()));
+        emitter.offer(update(20.0, TaskStatus::Paused), now, |t| sent.push(t.status.clone()));
+        emitter.offer(update(100.0, TaskStatus::Completed), now, |t| sent.push(t.status.clone()));
+        assert_eq!(sent, vec![TaskStatus::Downloading, TaskStatus::Paused, TaskStatus::Completed]);
+
+        // A finished task's pending update is gone, so a late flush sends nothing
+        emitter.flush("task", now + TASK_UPDATE_INTERVAL, |t| sent.push(t.status.clone()));
+        assert_eq!(sent.len(), 3);
+    }
+}
@@ -0,0 +1,209 @@
+use crate::error::MediaForgeError;
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::Manager;
+
+/// File inside the app data dir holding finished downloads
+const HISTORY_FILE: &str = "history.json";
+
+/// Written to the file so later schema changes can tell old files apart. New fields
+/// must be optional (`#[serde(default)]`) so files from older versions keep loading.
+const HISTORY_VERSION: u32 = 1;
+
+/// Oldest entries are dropped beyond this
+const MAX_HISTORY_ENTRIES: usize = 10_000;
+
+pub const DEFAULT_HISTORY_PAGE_SIZE: usize = 50;
+pub const MAX_HISTORY_PAGE_SIZE: usize = 500;
+
+#[derive(Serialize, Deserialize)]
+struct HistoryFile<T> {
+    version: u32,
+    entries: T,
+}
+
+pub fn history_path(app: &tauri::AppHandle) -> Result<PathBuf, MediaForgeError> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(HISTORY_FILE))
+        .map_err(|e| MediaForgeError::FileSystemError(format!("Could not locate the app data folder: {}", e)))
+}
+
+fn json_error(e: serde_json::Error) -> MediaForgeError {
+    MediaForgeError::FileSystemError(format!("Invalid history data: {}", e))
+}
+
+/// Reads the history file; a missing file is an empty history. Entries that don't
+/// parse are skipped rather than losing the whole history.
+fn load_history(path: &Path) -> Result<Vec<HistoryEntry>, MediaForgeError> {
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let file: HistoryFile<Vec<Value>> = serde_json::from_slice(&data).map_err(json_error)?;
+    if file.version > HISTORY_VERSION {
+        log::warn!("History file is version {}, newer than {}; unknown fields are ignored", file.version, HISTORY_VERSION);
+    }
+
+    Ok(file
+        .entries
+        .into_iter()
+        .filter_map(|entry| match serde_json::from_value(entry) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                log::warn!("Skipping unreadable history entry: {}", e);
+                None
+            }
+        })
+        .collect())
+}
+
+/// Writes through a temporary file so a crash never leaves a truncated history
+fn save_history(path: &Path, entries: &[HistoryEntry]) -> Result<(), MediaForgeError> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let file = HistoryFile { version: HISTORY_VERSION, entries };
+    let temp_path = path.with_extension("json.tmp");
+    std::fs::write(&temp_path, serde_json::to_vec(&file).map_err(json_error)?)?;
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// Matching entries, newest first, cut to the requested page
+fn query_entries(entries: &[HistoryEntry], query: &HistoryQuery) -> HistoryPage {
+    let search = query.search.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_lowercase);
+    let mut matches: Vec<&HistoryEntry> = entries
+        .iter()
+        .filter(|entry| query.status.as_ref().map_or(true, |status| entry.status == *status))
+        .filter(|entry| query.since.map_or(true, |since| entry.timestamp >= since))
+        .filter(|entry| query.until.map_or(true, |until| entry.timestamp <= until))
+        .filter(|entry| search.as_ref().map_or(true, |search| entry.title.to_lowercase().contains(search)))
+        .collect();
+    matches.sort_by_key(|entry| std::cmp::Reverse(entry.timestamp));
+
+    let limit = query.limit.unwrap_or(DEFAULT_HISTORY_PAGE_SIZE).min(MAX_HISTORY_PAGE_SIZE);
+    HistoryPage {
+        total: matches.len(),
+        entries: matches.into_iter().skip(query.offset).take(limit).cloned().collect(),
+    }
+}
+
+/// Finished downloads, kept apart from the live task list. The lock serialises
+/// tasks finishing at the same time so no entry is lost; the file is read and
+/// written on the blocking pool, as it grows to thousands of entries.
+#[derive(Default)]
+pub struct DownloadHistory {
+    lock: Arc<Mutex<()>>,
+}
+
+impl DownloadHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn record(&self, path: PathBuf, entry: HistoryEntry) -> Result<(), MediaForgeError> {
+        self.with_lock(move || {
+            let mut entries = load_history(&path)?;
+            entries.push(entry);
+            if entries.len() > MAX_HISTORY_ENTRIES {
+                entries.drain(..entries.len() - MAX_HISTORY_ENTRIES);
+            }
+            save_history(&path, &entries)
+        })
+        .await
+    }
+
+    pub async fn query(&self, path: PathBuf, query: HistoryQuery) -> Result<HistoryPage, MediaForgeError> {
+        self.with_lock(move || Ok(query_entries(&load_history(&path)?, &query))).await
+    }
+
+    async fn with_lock<T: Send + 'static>(
+        &self,
+        f: impl FnOnce() -> Result<T, MediaForgeError> + Send + 'static,
+    ) -> Result<T, MediaForgeError> {
+        let lock = Arc::clone(&self.lock);
+        tokio::task::spawn_blocking(move || {
+            let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+            f()
+        })
+        .await
+        .map_err(|e| MediaForgeError::FileSystemError(format!("History access failed: {}", e)))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(title: &str, status: TaskStatus, timestamp: u64) -> HistoryEntry {
+        HistoryEntry {
+            task_id: format!("task-{}", timestamp),
+            title: title.to_string(),
+            url: "https://www.youtube.com/watch?v=dQw4w9WgXcQ".to_string(),
+            output_path: Some(format!("/tmp/{}.mp4", title)),
+            format: "mp4".to_string(),
+            status,
+            timestamp,
+            size_bytes: Some(1024),
+            duration_ms: None,
+        }
+    }
+
+    #[test]
+    fn test_query_entries() {
+        let entries = vec![
+            entry("Rust in 100 Seconds", TaskStatus::Completed, 100),
+            entry("Lofi beats", TaskStatus::Failed, 200),
+            entry("RUST async explained", TaskStatus::Completed, 300),
+            entry("Cooking pasta", TaskStatus::Completed, 400),
+        ];
+
+        let page = query_entries(&entries, &HistoryQuery { search: Some(" rust ".to_string()), ..Default::default() });
+        assert_eq!(page.total, 2);
+        // Newest first
+        assert_eq!(page.entries[0].timestamp, 300);
+
+        let page = query_entries(&entries, &HistoryQuery {
+            status: Some(TaskStatus::Completed),
+            since: Some(150),
+            until: Some(400),
+            ..Default::default()
+        });
+        assert_eq!(page.entries.iter().map(|e| e.timestamp).collect::<Vec<_>>(), vec![400, 300]);
+
+        let page = query_entries(&entries, &HistoryQuery { offset: 1, limit: Some(2), ..Default::default() });
+        assert_eq!(page.total, 4);
+        assert_eq!(page.entries.iter().map(|e| e.timestamp).collect::<Vec<_>>(), vec![300, 200]);
+    }
+
+    #[tokio::test]
+    async fn test_history_file_compatibility() {
+        let dir = std::env::temp_dir().join(format!("mediaforge-history-{}", uuid::Uuid::new_v4()));
+        let path = dir.join(HISTORY_FILE);
+        let history = DownloadHistory::new();
+
+        assert_eq!(history.query(path.clone(), HistoryQuery::default()).await.unwrap().total, 0);
+        history.record(path.clone(), entry("First", TaskStatus::Completed, 1)).await.unwrap();
+        history.record(path.clone(), entry("Second", TaskStatus::Failed, 2)).await.unwrap();
+        assert_eq!(history.query(path.clone(), HistoryQuery::default()).await.unwrap().total, 2);
+
+        // A newer file with extra fields and a broken entry still loads what it can
+        std::fs::write(&path, r#"{"version": 2, "added_later": true, "entries": [
+            {"task_id": "a", "title": "Kept", "url": "https://example.com", "format": "mp3",
+             "status": "Completed", "timestamp": 5, "rating": 4},
+            {"title": "No status or timestamp"}
+        ]}"#).unwrap();
+        let page = history.query(path.clone(), HistoryQuery::default()).await.unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.entries[0].title, "Kept");
+        assert_eq!(page.entries[0].output_path, None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,241 @@
+use crate::error::MediaForgeError;
+use crate::process::{run_cancellable_command, CommandOutcome, OutputTail};
+use crate::types::TaskProgress;
+use std::path::Path;
+use tokio::process::Command as TokioCommand;
+use tokio::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// Replaced by the output file's path wherever it appears in a post-process argument
+pub const FILE_PLACEHOLDER: &str = "{file}";
+
+/// Longest a post-process command may run for one file
+const POST_PROCESS_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+const MAX_ARGS: usize = 64;
+const MAX_ARG_LEN: usize = 4096;
+
+/// Lines of the command's error output kept for the task's error message
+const ERROR_OUTPUT_LINES: usize = 20;
+
+/// Characters a shell would interpret. The command never runs through one, but a
+/// request that contains them was almost certainly written for a shell.
+const SHELL_METACHARACTERS: &[char] = &[';', '|', '&', '$', '`', '<', '>', '(', ')', '{', '}', '*', '?', '!', '~', '%'];
+
+/// Programs that would hand their arguments to a shell after all
+const SHELLS: &[&str] = &["sh", "bash", "zsh", "dash", "ksh", "csh", "tcsh", "fish", "cmd", "powershell", "pwsh"];
+
+/// Checks a post-process command before any task starts. The first element is the
+/// program and the rest its arguments, each passed as-is with `{file}` substituted.
+pub fn validate_post_process_command(command: &[String]) -> Result<(), MediaForgeError> {
+    let invalid = |message: String| Err(MediaForgeError::InvalidSettings(message));
+
+    let Some(program) = command.first().filter(|p| !p.trim().is_empty()) else {
+        return invalid("Post-process command needs a program to run".to_string());
+    };
+    if command.len() > MAX_ARGS {
+        return invalid(format!("Post-process command can have at most {} arguments", MAX_ARGS));
+    }
+    for arg in command {
+        if arg.len() > MAX_ARG_LEN {
+            return invalid(format!("Post-process arguments can be at most {} characters", MAX_ARG_LEN));
+        }
+        let unexpanded = arg.replace(FILE_PLACEHOLDER, "");
+        if let Some(c) = unexpanded.chars().find(|c| SHELL_METACHARACTERS.contains(c) || c.is_control()) {
+            return invalid(format!(
+                "Post-process argument '{}' contains '{}', which isn't allowed",
+                arg,
+                c.escape_default()
+            ));
+        }
+    }
+
+    if program.contains(FILE_PLACEHOLDER) {
+        return invalid("Post-process command can't run the output file itself".to_string());
+    }
+    let path = Path::new(program);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_lowercase();
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+    // Windows runs batch files through cmd.exe
+    if SHELLS.contains(&stem.as_str()) || matches!(extension.as_str(), "bat" | "cmd") {
+        return invalid(format!("Post-process command can't run a shell ({})", program));
+    }
+    Ok(())
+}
+
+/// The command's arguments for one output file
+pub fn post_process_args(command: &[String], file: &str) -> Vec<String> {
+    command.iter().map(|arg| arg.replace(FILE_PLACEHOLDER, file)).collect()
+}
+
+/// Files a finished task produced, each of which the command runs on once
+fn task_outputs(task: &TaskProgress) -> Vec<&String> {
+    let mut files: Vec<&String> = task.file_path.iter().collect();
+    for file in &task.output_files {
+        if !files.contains(&file) {
+            files.push(file);
+        }
+    }
+    files
+}
+
+/// Runs the command on every output of a finished task, in order, stopping at the
+/// first failure. What it prints goes to the log under the task's id; a failure is
+/// wrapped by `other` with the end of its error output.
+async fn run_post_process(
+    command: &[String],
+    task: &TaskProgress,
+    cancellation_token: &CancellationToken,
+    other: fn(String) -> MediaForgeError,
+) -> Result<(), MediaForgeError> {
+    let outputs = task_outputs(task);
+    if outputs.is_empty() {
+        warn!(task_id = task.task_id, "Task has no output for the post-process command to run on");
+    }
+    for file in outputs {
+        run_for_file(command, file, &task.task_id, cancellation_token, other).await?;
+    }
+    Ok(())
+}
+
+/// Runs the command for a task that finished successfully. With `ignore_failure`, a
+/// failed command is handed to `add_warning` instead of failing the task.
+pub async fn post_process_task(
+    command: &[String],
+    ignore_failure: bool,
+    task: &TaskProgress,
+    cancellation_token: &CancellationToken,
+    other: fn(String) -> MediaForgeError,
+    add_warning: impl FnOnce(String),
+) -> Result<(), MediaForgeError> {
+    match run_post_process(command, task, cancellation_token, other).await {
+        Err(e) if ignore_failure => {
+            warn!(task_id = task.task_id, error = %e, "Post-process command failed; keeping the output");
+            add_warning(e.to_string());
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+async fn run_for_file(
+    command: &[String],
+    file: &str,
+    task_id: &str,
+    cancellation_token: &CancellationToken,
+    other: fn(String) -> MediaForgeError,
+) -> Result<(), MediaForgeError> {
+    let args = post_process_args(command, file);
+    let program = &args[0];
+    let mut cmd = TokioCommand::new(program);
+    cmd.args(&args[1..]);
+    info!(task_id, program = %program, file, "Running post-process command");
+
+    let mut error_output = OutputTail::new(ERROR_OUTPUT_LINES);
+    let run = run_cancellable_command(
+        cmd,
+        cancellation_token,
+        |line| info!(task_id, output = line, "Post-process output"),
+        |line| {
+            info!(task_id, output = line, "Post-process output");
+            error_output.push(line);
+        },
+    );
+    let outcome = tokio::time::timeout(POST_PROCESS_TIMEOUT, run)
+        .await
+        .map_err(|_| other(format!("Post-process command {} timed out on {}", program, file)))?
+        // A missing program is the request's mistake, not a dependency the app installs
+        .map_err(|e| other(format!("Failed to run post-process command {}: {}", program, e)))?;
+
+    match outcome {
+        CommandOutcome::Exited(status) if status.success() => {
+            info!(task_id, program = %program, file, "Post-process command finished");
+            Ok(())
+        }
+        CommandOutcome::Exited(status) => {
+            let output = error_output.join();
+            let mut message = format!("Post-process command {} failed on {} ({})", program, file, status);
+            if !output.is_empty() {
+                message = format!("{}: {}", message, output);
+            }
+            Err(other(message))
+        }
+        CommandOutcome::Cancelled => Err(other("Post-process command was cancelled".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_validate_post_process_command() {
+        for ok in [
+            &["rclone", "copy", "{file}", "remote:media"][..],
+            &["/usr/local/bin/tag-media", "--input={file}"],
+            &["C:\\Tools\\upload.exe", "{file}"],
+        ] {
+            assert!(validate_post_process_command(&command(ok)).is_ok(), "{:?}", ok);
+        }
+
+        for bad in [
+            &[][..],
+            &["  "],
+            &["rclone", "copy", "{file};", "rm -rf ~"],
+            &["notify", "$(whoami)"],
+            &["notify", "a|b"],
+            &["notify", "done\n"],
+            &["notify", "{files}"],
+            &["{file}"],
+            &["bash", "-c", "echo {file}"],
+            &["/bin/sh", "{file}"],
+            &["PowerShell.exe", "{file}"],
+            &["upload.bat", "{file}"],
+        ] {
+            let err = validate_post_process_command(&command(bad)).unwrap_err();
+            assert!(matches!(err, MediaForgeError::InvalidSettings(_)), "{:?}", bad);
+        }
+
+        let too_many = vec!["arg".to_string(); MAX_ARGS + 1];
+        assert!(validate_post_process_command(&too_many).is_err());
+    }
+
+    #[test]
+    fn test_post_process_args() {
+        let args = post_process_args(&command(&["cp", "{file}", "--name={file}.bak"]), "/media/a b.mp4");
+        assert_eq!(args, command(&["cp", "/media/a b.mp4", "--name=/media/a b.mp4.bak"]));
+    }
+
+    #[tokio::test]
+    async fn test_run_post_process() {
+        let token = CancellationToken::new();
+        let task = TaskProgress {
+            task_id: "task".to_string(),
+            file_path: Some("/tmp/out.mp4".to_string()),
+            output_files: vec!["/tmp/out.mp4".to_string(), "/tmp/out.srt".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(task_outputs(&task).len(), 2);
+
+        let ok = run_post_process(&command(&["true", "{file}"]), &task, &token, MediaForgeError::ConversionError).await;
+        assert!(ok.is_ok());
+
+        let err = run_post_process(&command(&["false", "{file}"]), &task, &token, MediaForgeError::ConversionError)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MediaForgeError::ConversionError(ref m) if m.contains("/tmp/out.mp4")), "{}", err);
+
+        let mut warning = None;
+        let ignored = post_process_task(&command(&["false", "{file}"]), true, &task, &token, MediaForgeError::ConversionError, |w| {
+            warning = Some(w)
+        })
+        .await;
+        assert!(ignored.is_ok());
+        assert!(warning.is_some_and(|w| w.contains("/tmp/out.mp4")));
+    }
+}
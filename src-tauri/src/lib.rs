@@ -1,17 +1,33 @@
+mod batch;
 mod commands;
+mod config;
 mod converter;
 mod downloader;
 mod error;
+mod events;
+mod history;
+mod hooks;
+mod logging;
 mod notifications;
+mod presets;
+mod process;
+mod queue;
 mod system;
+mod tasks;
 mod types;
 
 use commands::*;
 use converter::ConversionManager;
 use downloader::DownloadManager;
-use tracing::info;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{Manager, RunEvent};
+use tracing::{info, warn};
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
+/// Longest the app waits on quit for running tasks to stop before killing them
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Initialize structured logging with tracing
 fn init_tracing() -> Result<(), Box<dyn std::error::Error>> {
     let filter = if cfg!(debug_assertions) {
@@ -26,7 +42,8 @@ fn init_tracing() -> Result<(), Box<dyn std::error::Error>> {
             .unwrap()
     };
 
-    let registry = tracing_subscriber::registry();
+    // The file layer is filled in during setup, once the log folder is known
+    let registry = tracing_subscriber::registry().with(logging::file_layer_slot());
 
     if cfg!(debug_assertions) {
         // Development: Human-readable format with colors
@@ -59,11 +76,6 @@ fn init_tracing() -> Result<(), Box<dyn std::error::Error>> {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let app_state = AppState {
-        download_manager: DownloadManager::new(),
-        conversion_manager: ConversionManager::new(),
-    };
-
     // Initialize structured logging first
     init_tracing().expect("Failed to initialize tracing");
     
@@ -74,7 +86,45 @@ pub fn run() {
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_notification::init())
-        .setup(|_app| {
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .setup(|app| {
+            let seeded = presets::presets_dir(app.handle()).and_then(|dir| presets::seed_builtin_presets(&dir));
+            if let Err(e) = seeded {
+                warn!(error = %e, "Failed to create built-in conversion presets");
+            }
+
+            // Managers take their concurrency and timeout defaults from the settings
+            let config = match config::config_path(app.handle()) {
+                Ok(path) => config::load_config(&path),
+                Err(e) => {
+                    warn!(error = %e, "Failed to locate settings, using defaults");
+                    config::Config::default()
+                }
+            };
+            notifications::set_enabled(config.notifications);
+            match app.path().app_log_dir() {
+                Ok(dir) => {
+                    if let Err(e) = logging::enable_file_logging(&dir) {
+                        warn!(error = %e, "Failed to start file logging");
+                    }
+                }
+                Err(e) => warn!(error = %e, "Failed to locate the log folder"),
+            }
+            match app.path().app_data_dir() {
+                Ok(dir) => system::set_local_bin_dir(dir.join("bin")),
+                Err(e) => warn!(error = %e, "Failed to locate the app data folder for downloaded tools"),
+            }
+            let tools = Arc::new(system::ToolPaths::default());
+            app.manage(AppState {
+                download_manager: DownloadManager::new(&config, tools.clone()),
+                conversion_manager: ConversionManager::new(&config, tools.clone()),
+                tools,
+            });
+            let handle = app.handle().clone();
+            events::spawn_global_progress(app.handle().clone(), move || {
+                batch::global_progress(handle.state::<AppState>().all_tasks().iter())
+            });
+            
             info!(
                 app_name = "MediaForge",
                 version = "1.0.0",
@@ -83,21 +133,60 @@ pub fn run() {
             );
             Ok(())
         })
-        .manage(app_state)
         .invoke_handler(tauri::generate_handler![
             check_dependencies,
+            refresh_dependencies,
             install_ytdlp_command,
+            install_ffmpeg,
             start_download,
             get_download_tasks,
             get_task_progress,
             pause_download,
             cancel_download,
+            set_task_priority,
             remove_task,
+            clear_finished_tasks,
+            get_history,
+            get_config,
+            set_config,
             start_conversion,
+            estimate_output_size,
+            estimate_conversion,
+            save_preset,
+            list_presets,
+            delete_preset,
+            convert_folder,
+            list_supported_input_formats,
+            validate_files,
+            probe_media,
+            extract_streams,
+            get_batch,
+            get_stats,
+            get_global_progress,
+            get_active_task_count,
             get_conversion_tasks,
             cancel_conversion,
+            validate_output_directory,
             open_folder,
+            open_file,
+            reveal_file,
+            copy_to_clipboard,
+            get_log_directory,
+            export_logs,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let RunEvent::Exit = event {
+                // Stop yt-dlp/ffmpeg children so they don't outlive the app writing partial files
+                let state = app.state::<AppState>();
+                let (downloads, conversions) = tauri::async_runtime::block_on(async {
+                    tokio::join!(
+                        state.download_manager.shutdown(SHUTDOWN_TIMEOUT),
+                        state.conversion_manager.shutdown(SHUTDOWN_TIMEOUT),
+                    )
+                });
+                info!(downloads, conversions, "Interrupted running tasks on exit");
+            }
+        });
 }
@@ -0,0 +1,286 @@
+use crate::error::MediaForgeError;
+use crate::types::SystemInfo;
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+use std::io::{self, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::{self, MakeWriter};
+use tracing_subscriber::{reload, Layer, Registry};
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Log files are named `mediaforge.<date>.log`
+const LOG_FILE_PREFIX: &str = "mediaforge";
+
+/// Daily log files kept before the oldest is deleted
+pub const MAX_LOG_FILES: usize = 14;
+
+/// Total size the log folder is trimmed to at startup, oldest files first
+const MAX_LOG_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Days of logs exported unless the request says otherwise
+pub const DEFAULT_EXPORT_DAYS: u32 = 7;
+
+type FileLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Swaps the file layer in once the app knows where its log folder is
+static FILE_LAYER: OnceLock<reload::Handle<Option<FileLayer>, Registry>> = OnceLock::new();
+
+/// An empty slot for the file layer, filled by `enable_file_logging`. Must be the
+/// first layer on the registry.
+pub fn file_layer_slot() -> reload::Layer<Option<FileLayer>, Registry> {
+    let (layer, handle) = reload::Layer::new(None);
+    let _ = FILE_LAYER.set(handle);
+    layer
+}
+
+/// Starts writing JSON logs to a file in `dir` that rotates daily, alongside the console
+pub fn enable_file_logging(dir: &Path) -> Result<(), MediaForgeError> {
+    std::fs::create_dir_all(dir)?;
+    let removed = prune_logs(dir, MAX_LOG_BYTES)?;
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(dir)
+        .map_err(|e| MediaForgeError::FileSystemError(format!("Cannot create log file: {}", e)))?;
+    let layer = fmt::layer()
+        .json()
+        .with_ansi(false)
+        .with_target(true)
+        .with_writer(RedactingMakeWriter(appender))
+        .boxed();
+    let handle = FILE_LAYER
+        .get()
+        .ok_or_else(|| MediaForgeError::FileSystemError("Logging was not initialized".to_string()))?;
+    handle
+        .reload(Some(layer))
+        .map_err(|e| MediaForgeError::FileSystemError(format!("Cannot enable file logging: {}", e)))?;
+    tracing::info!(log_dir = %dir.display(), removed_files = removed, "File logging enabled");
+    Ok(())
+}
+
+/// Hides query values and fragments of URLs, where private playlist and share tokens
+/// live, keeping the parameter names for context
+pub fn redact_urls(text: &str) -> Cow<'_, str> {
+    static URL: OnceLock<Regex> = OnceLock::new();
+    let url = URL.get_or_init(|| Regex::new(r#"(https?://[^\s"'<>\\?#]+)(\?[^\s"'<>\\#]*)?(#[^\s"'<>\\]*)?"#).unwrap());
+    url.replace_all(text, |caps: &Captures| {
+        let mut redacted = caps[1].to_string();
+        if let Some(query) = caps.get(2) {
+            let keys: Vec<String> = query.as_str()[1..]
+                .split('&')
+                .filter(|pair| !pair.is_empty())
+                .map(|pair| match pair.split_once('=') {
+                    Some((key, _)) => format!("{}=*", key),
+                    None => "*".to_string(),
+                })
+                .collect();
+            redacted.push('?');
+            redacted.push_str(&keys.join("&"));
+        }
+        if caps.get(3).is_some() {
+            redacted.push_str("#*");
+        }
+        redacted
+    })
+}
+
+/// Writer that redacts URLs in each formatted event before it reaches the file
+struct RedactingWriter<W> {
+    inner: W,
+}
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // The formatter writes a whole event at once, so URLs are never split
+        let text = String::from_utf8_lossy(buf);
+        self.inner.write_all(redact_urls(&text).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+struct RedactingMakeWriter<M>(M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for RedactingMakeWriter<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter { inner: self.0.make_writer() }
+    }
+}
+
+/// Log files in `dir` with their size and modification time, newest first
+fn log_files(dir: &Path) -> io::Result<Vec<(PathBuf, u64, SystemTime)>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let is_log = entry.file_name().to_str().is_some_and(|name| name.starts_with(LOG_FILE_PREFIX));
+        if is_log && metadata.is_file() {
+            files.push((entry.path(), metadata.len(), metadata.modified()?));
+        }
+    }
+    files.sort_by_key(|&(_, _, modified)| std::cmp::Reverse(modified));
+    Ok(files)
+}
+
+/// Deletes the oldest log files until the rest fit in `max_bytes`; the newest is always
+/// kept. Returns how many were deleted.
+fn prune_logs(dir: &Path, max_bytes: u64) -> Result<usize, MediaForgeError> {
+    let mut total = 0;
+    let mut removed = 0;
+    for (index, (path, size, _)) in log_files(dir)?.into_iter().enumerate() {
+        total += size;
+        if index > 0 && total > max_bytes {
+            std::fs::remove_file(&path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Replaces the home folder in the dependency paths so the snapshot doesn't carry the user name
+fn redacted_system_info(info: &SystemInfo, home: Option<&Path>) -> SystemInfo {
+    let redact = |path: &Option<String>| {
+        path.as_ref().map(|path| match home.and_then(|home| Path::new(path).strip_prefix(home).ok()) {
+            Some(rest) => Path::new("~").join(rest).to_string_lossy().into_owned(),
+            None => path.clone(),
+        })
+    };
+    SystemInfo {
+        ytdlp_path: redact(&info.ytdlp_path),
+        ffmpeg_path: redact(&info.ffmpeg_path),
+        imagemagick_path: redact(&info.imagemagick_path),
+        ..info.clone()
+    }
+}
+
+/// Zips the log files changed in the last `days` days together with a redacted
+/// `system-info.json`, for attaching to a bug report. Returns the number of log files.
+pub fn export_logs(
+    log_dir: &Path,
+    destination: &Path,
+    days: u32,
+    system_info: &SystemInfo,
+) -> Result<usize, MediaForgeError> {
+    let since = SystemTime::now() - Duration::from_secs(u64::from(days) * 24 * 60 * 60);
+    let mut entries = Vec::new();
+    // A missing folder just means nothing was logged yet
+    let files = if log_dir.exists() { log_files(log_dir)? } else { Vec::new() };
+    for (path, _, _) in files.into_iter().filter(|(_, _, modified)| *modified >= since) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        entries.push((name, std::fs::read(&path)?));
+    }
+    let log_count = entries.len();
+
+    let info = redacted_system_info(system_info, dirs::home_dir().as_deref());
+    let info_json = serde_json::to_vec_pretty(&info)
+        .map_err(|e| MediaForgeError::FileSystemError(format!("Cannot write system info: {}", e)))?;
+    entries.push(("system-info.json".to_string(), info_json));
+
+    let file = std::fs::File::create(destination)?;
+    write_zip(io::BufWriter::new(file), &entries)?;
+    Ok(log_count)
+}
+
+/// Writes a deflate-compressed zip archive of `(name, contents)` entries
+fn write_zip(out: impl Write + Seek, entries: &[(String, Vec<u8>)]) -> Result<(), MediaForgeError> {
+    let zip_error = |e: ZipError| MediaForgeError::FileSystemError(format!("Cannot write the log archive: {}", e));
+    let modified = zip::DateTime::try_from(chrono::Local::now().naive_local()).unwrap_or_default();
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(modified);
+
+    let mut zip = ZipWriter::new(out);
+    for (name, contents) in entries {
+        zip.start_file(name.as_str(), options).map_err(zip_error)?;
+        zip.write_all(contents)?;
+    }
+    zip.finish().map_err(zip_error)?.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_redact_urls() {
+        let line = r#"{"url":"https://www.youtube.com/playlist?list=PLsecret&si=token123#t=10","task_id":"a"}"#;
+        assert_eq!(
+            redact_urls(line),
+            r#"{"url":"https://www.youtube.com/playlist?list=*&si=*#*","task_id":"a"}"#
+        );
+        assert_eq!(redact_urls("fetching https://example.com/video.mp4 now"), "fetching https://example.com/video.mp4 now");
+        assert!(matches!(redact_urls("no urls here"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_prune_logs_keeps_newest() {
+        let dir = std::env::temp_dir().join(format!("mediaforge-logs-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for day in 1..=3 {
+            let path = dir.join(format!("mediaforge.2026-01-0{}.log", day));
+            std::fs::write(&path, vec![b'x'; 100]).unwrap();
+            let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000 + day * 86_400);
+            std::fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        }
+        std::fs::write(dir.join("other.txt"), vec![b'x'; 1000]).unwrap();
+
+        assert_eq!(prune_logs(&dir, 250).unwrap(), 1);
+        assert!(!dir.join("mediaforge.2026-01-01.log").exists());
+        assert!(dir.join("mediaforge.2026-01-02.log").exists());
+        // The newest file stays even when it alone is over the limit
+        assert_eq!(prune_logs(&dir, 10).unwrap(), 1);
+        assert!(dir.join("mediaforge.2026-01-03.log").exists());
+        assert!(dir.join("other.txt").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_write_zip() {
+        let entries = vec![
+            ("mediaforge.2026-01-01.log".to_string(), b"line one\nline two\n".repeat(50)),
+            ("system-info.json".to_string(), b"{}".to_vec()),
+        ];
+        let mut zip = io::Cursor::new(Vec::new());
+        write_zip(&mut zip, &entries).unwrap();
+
+        let mut archive = zip::ZipArchive::new(zip).unwrap();
+        assert_eq!(archive.len(), 2);
+        for (name, expected) in &entries {
+            let mut file = archive.by_name(name).unwrap();
+            assert_eq!(file.compression(), CompressionMethod::Deflated);
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents).unwrap();
+            assert_eq!(&contents, expected);
+        }
+    }
+
+    #[test]
+    fn test_redacted_system_info() {
+        let info = SystemInfo {
+            has_ytdlp: true,
+            has_ffmpeg: true,
+            has_imagemagick: false,
+            ytdlp_path: Some("/home/alex/.local/bin/yt-dlp".to_string()),
+            ffmpeg_path: Some("/usr/bin/ffmpeg".to_string()),
+            imagemagick_path: None,
+        };
+        let redacted = redacted_system_info(&info, Some(Path::new("/home/alex")));
+        assert_eq!(redacted.ytdlp_path.as_deref(), Some("~/.local/bin/yt-dlp"));
+        assert_eq!(redacted.ffmpeg_path.as_deref(), Some("/usr/bin/ffmpeg"));
+        assert!(redacted.has_ytdlp);
+    }
+}
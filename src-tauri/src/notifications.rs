@@ -1,14 +1,147 @@
+use crate::types::BatchProgress;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use tauri::AppHandle;
 use tauri_plugin_notification::NotificationExt;
 
-pub fn send_download_complete_notification(app: &AppHandle, filename: &str) {
-    let _ = app
-        .notification()
-        .builder()
-        .title("Download Complete")
-        .body(format!("Successfully downloaded: {}", filename))
-        .icon("icons/icon.png")
-        .show();
+/// Mirrors the `notifications` setting
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turns completion notifications on or off for the rest of the session
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Shows a completion notification. On Linux, clicking it opens `target` in the file
+/// manager and a file target also gets an "Open file" button that opens it directly.
+///
+/// The notification plugin only delivers actions on mobile, so on Linux the
+/// notification is sent through notify-rust directly and the click is awaited
+/// on a blocking thread. macOS and Windows show a plain notification, as neither
+/// backend reports the click back to the app.
+fn show_clickable_notification(app: &AppHandle, title: &str, body: String, target: PathBuf) {
+    if !enabled() {
+        return;
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let _ = app;
+        let mut notification = notify_rust::Notification::new();
+        notification
+            .summary(title)
+            .body(&body)
+            .icon("icons/icon.png")
+            // "default" is the action the notification server invokes when the body is clicked
+            .action("default", "Open folder");
+        if target.is_file() {
+            notification.action("open-file", "Open file");
+        }
+
+        tauri::async_runtime::spawn_blocking(move || match notification.show() {
+            Ok(handle) => handle.wait_for_action(|action| match action {
+                "default" => {
+                    if let Err(e) = crate::system::open_in_file_manager(&target) {
+                        log::warn!("Failed to open output folder from notification: {}", e);
+                    }
+                }
+                "open-file" => {
+                    if let Err(e) = crate::system::open_with_default_app(&target) {
+                        log::warn!("Failed to open output file from notification: {}", e);
+                    }
+                }
+                _ => {}
+            }),
+            Err(e) => log::warn!("Failed to show notification: {}", e),
+        });
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = app
+            .notification()
+            .builder()
+            .title(title)
+            .body(body)
+            .icon("icons/icon.png")
+            .show();
+        let _ = target;
+    }
+}
+
+/// Shows a download completion notification. When the downloader could not capture
+/// the output file path, clicking it on Linux opens the download directory instead.
+pub fn send_download_complete_notification(
+    app: &AppHandle,
+    filename: &str,
+    file_path: Option<&str>,
+    download_dir: &Path,
+) {
+    let target = file_path
+        .map(PathBuf::from)
+        .filter(|p| p.exists())
+        .unwrap_or_else(|| download_dir.to_path_buf());
+
+    show_clickable_notification(
+        app,
+        "Download Complete",
+        format!("Successfully downloaded: {}", filename),
+        target,
+    );
+}
+
+/// Shows a completion notification for a download split into chapter files.
+/// Clicking it on Linux opens the folder holding the chapters.
+pub fn send_chapters_complete_notification(app: &AppHandle, filename: &str, chapter_files: &[String]) {
+    let Some(first) = chapter_files.first() else {
+        return;
+    };
+
+    show_clickable_notification(
+        app,
+        "Download Complete",
+        format!("Split {} into {} chapters", filename, chapter_files.len()),
+        PathBuf::from(first),
+    );
+}
+
+/// Shows one summary notification for a finished batch in place of one per task.
+/// Clicking it on Linux opens the folder of `target`, typically the last completed output.
+pub fn send_batch_complete_notification(
+    app: &AppHandle,
+    title: &str,
+    verb: &str,
+    batch: &BatchProgress,
+    target: Option<PathBuf>,
+) {
+    if !enabled() {
+        return;
+    }
+
+    let mut body = format!("{} {} of {} files", verb, batch.completed, batch.total);
+    if batch.failed > 0 {
+        body.push_str(&format!(", {} failed", batch.failed));
+    }
+    if batch.cancelled > 0 {
+        body.push_str(&format!(", {} cancelled", batch.cancelled));
+    }
+
+    match target {
+        Some(target) => show_clickable_notification(app, title, body, target),
+        None => {
+            let _ = app
+                .notification()
+                .builder()
+                .title(title)
+                .body(body)
+                .icon("icons/icon.png")
+                .show();
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -22,14 +155,13 @@ pub fn send_download_failed_notification(app: &AppHandle, filename: &str, error:
         .show();
 }
 
-pub fn send_conversion_complete_notification(app: &AppHandle, filename: &str) {
-    let _ = app
-        .notification()
-        .builder()
-        .title("Conversion Complete")
-        .body(format!("Successfully converted: {}", filename))
-        .icon("icons/icon.png")
-        .show();
+pub fn send_conversion_complete_notification(app: &AppHandle, filename: &str, output_path: &Path) {
+    show_clickable_notification(
+        app,
+        "Conversion Complete",
+        format!("Successfully converted: {}", filename),
+        output_path.to_path_buf(),
+    );
 }
 
 #[allow(dead_code)]
@@ -0,0 +1,344 @@
+use crate::error::MediaForgeError;
+use crate::types::*;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+/// Folder inside the app data dir holding one JSON file per preset
+const PRESETS_DIR: &str = "presets";
+const MAX_PRESET_NAME_LEN: usize = 64;
+
+/// Folder the presets are stored in
+pub fn presets_dir(app: &tauri::AppHandle) -> Result<PathBuf, MediaForgeError> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(PRESETS_DIR))
+        .map_err(|e| MediaForgeError::FileSystemError(format!("Could not locate the app data folder: {}", e)))
+}
+
+/// File name for a preset. Names are restricted to characters that are safe on every
+/// platform instead of being rewritten, so two names never share a file.
+fn preset_file_name(name: &str) -> Result<String, MediaForgeError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_PRESET_NAME_LEN
+        && name.trim() == name
+        && !name.starts_with('.')
+        && !name.ends_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || " ._-+()".contains(c));
+
+    if !valid {
+        return Err(MediaForgeError::InvalidSettings(format!(
+            "Invalid preset name '{}': use up to {} letters, digits, spaces and . _ - + ( )",
+            name, MAX_PRESET_NAME_LEN
+        )));
+    }
+    Ok(format!("{}.json", name))
+}
+
+fn json_error(e: serde_json::Error) -> MediaForgeError {
+    MediaForgeError::FileSystemError(format!("Invalid preset data: {}", e))
+}
+
+/// Saves a preset, replacing any preset with the same name
+pub fn save_preset(dir: &Path, preset: &ConversionPreset) -> Result<(), MediaForgeError> {
+    let path = dir.join(preset_file_name(&preset.name)?);
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(path, serde_json::to_vec_pretty(preset).map_err(json_error)?)?;
+    Ok(())
+}
+
+pub fn load_preset(dir: &Path, name: &str) -> Result<ConversionPreset, MediaForgeError> {
+    let path = dir.join(preset_file_name(name)?);
+    let data = std::fs::read(&path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => MediaForgeError::InvalidSettings(format!("Unknown preset '{}'", name)),
+        _ => MediaForgeError::from(e),
+    })?;
+    serde_json::from_slice(&data).map_err(json_error)
+}
+
+/// All saved presets sorted by name; unreadable files are skipped
+pub fn list_presets(dir: &Path) -> Result<Vec<ConversionPreset>, MediaForgeError> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut presets = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        match std::fs::read(&path).map_err(MediaForgeError::from).and_then(|data| {
+            serde_json::from_slice::<ConversionPreset>(&data).map_err(json_error)
+        }) {
+            Ok(preset) => presets.push(preset),
+            Err(e) => log::warn!("Skipping preset {:?}: {}", path, e),
+        }
+    }
+
+    presets.sort_by_key(|p| p.name.to_lowercase());
+    Ok(presets)
+}
+
+pub fn delete_preset(dir: &Path, name: &str) -> Result<(), MediaForgeError> {
+    let path = dir.join(preset_file_name(name)?);
+    std::fs::remove_file(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => MediaForgeError::InvalidSettings(format!("Unknown preset '{}'", name)),
+        _ => MediaForgeError::from(e),
+    })
+}
+
+/// Writes the built-in presets the first time the app runs. Once the folder exists
+/// nothing is written, so deleted built-ins stay deleted.
+pub fn seed_builtin_presets(dir: &Path) -> Result<(), MediaForgeError> {
+    if dir.exists() {
+        return Ok(());
+    }
+    for preset in builtin_presets() {
+        save_preset(dir, &preset)?;
+    }
+    Ok(())
+}
+
+fn builtin_presets() -> Vec<ConversionPreset> {
+    let video = |name: &str, resolution: &str, bitrate: &str| ConversionPreset {
+        name: name.to_string(),
+        settings: PresetSettings {
+            conversion_type: ConversionType::Video,
+            output_format: Some("mp4".to_string()),
+            video_settings: Some(VideoSettings {
+                resolution: Some(resolution.to_string()),
+                bitrate: Some(bitrate.to_string()),
+                ..Default::default()
+            }),
+            audio_settings: None,
+            image_settings: None,
+        },
+    };
+    let audio = |name: &str, format: &str, settings: AudioSettings| ConversionPreset {
+        name: name.to_string(),
+        settings: PresetSettings {
+            conversion_type: ConversionType::Audio,
+            output_format: Some(format.to_string()),
+            video_settings: None,
+            audio_settings: Some(settings),
+            image_settings: None,
+        },
+    };
+
+    vec![
+        video("Web 1080p", "1920x1080", "5M"),
+        video("Web 720p", "1280x720", "2500k"),
+        audio("Podcast mono 64k", "mp3", AudioSettings {
+            bitrate: Some("64".to_string()),
+            sample_rate: Some("44100".to_string()),
+            channels: Some(1),
+            ..Default::default()
+        }),
+        audio("Lossless archive (FLAC)", "flac", AudioSettings {
+            compression_level: Some(8),
+            ..Default::default()
+        }),
+        ConversionPreset {
+            name: "Web image".to_string(),
+            settings: PresetSettings {
+                conversion_type: ConversionType::Image,
+                output_format: Some("webp".to_string()),
+                video_settings: None,
+                audio_settings: None,
+                image_settings: Some(ImageSettings {
+                    quality: Some(80),
+                    // Shrink larger images only
+                    resize: Some("1920x1920>".to_string()),
+                    strip_metadata: true,
+                    ..Default::default()
+                }),
+            },
+        },
+    ]
+}
+
+/// Overlays `top` onto `base` field by field. Nulls and `false` in `top` count as
+/// unset, so a request can switch a preset's flag on but not off.
+fn overlay(base: Value, top: Value) -> Value {
+    match (base, top) {
+        (Value::Object(mut base), Value::Object(top)) => {
+            for (key, value) in top {
+                let merged = match base.remove(&key) {
+                    Some(base_value) => overlay(base_value, value),
+                    None => value,
+                };
+                base.insert(key, merged);
+            }
+            Value::Object(base)
+        }
+        (base, Value::Null | Value::Bool(false)) => base,
+        (_, top) => top,
+    }
+}
+
+fn merge_settings<T: Serialize + DeserializeOwned>(
+    preset: Option<T>,
+    request: Option<T>,
+) -> Result<Option<T>, MediaForgeError> {
+    match (preset, request) {
+        (Some(preset), Some(request)) => {
+            let merged = overlay(
+                serde_json::to_value(preset).map_err(json_error)?,
+                serde_json::to_value(request).map_err(json_error)?,
+            );
+            serde_json::from_value(merged).map(Some).map_err(json_error)
+        }
+        (preset, request) => Ok(request.or(preset)),
+    }
+}
+
+/// Fills in the request's unset fields from a preset; explicit request values win.
+/// An `Auto` request takes a preset of any type for its inputs of that type.
+fn apply_preset(mut request: ConvertRequest, preset: ConversionPreset) -> Result<ConvertRequest, MediaForgeError> {
+    let settings = preset.settings;
+    if request.conversion_type == ConversionType::Auto {
+        let formats = request.auto_formats.get_or_insert_with(AutoFormats::default);
+        let format = match settings.conversion_type {
+            ConversionType::Image => Some(&mut formats.image),
+            ConversionType::Video => Some(&mut formats.video),
+            ConversionType::Audio => Some(&mut formats.audio),
+            ConversionType::Auto => None,
+        };
+        if let Some(format) = format.filter(|f| f.as_deref().map_or(true, str::is_empty)) {
+            *format = settings.output_format;
+        }
+    } else {
+        if settings.conversion_type != request.conversion_type {
+            return Err(MediaForgeError::InvalidSettings(format!(
+                "Preset '{}' is for {:?} conversions, not {:?}",
+                preset.name, settings.conversion_type, request.conversion_type
+            )));
+        }
+        if request.output_format.is_empty() {
+            request.output_format = settings.output_format.ok_or_else(|| {
+                MediaForgeError::InvalidSettings(format!("Preset '{}' has no output format; choose one", preset.name))
+            })?;
+        }
+    }
+    request.video_settings = merge_settings(settings.video_settings, request.video_settings)?;
+    request.audio_settings = merge_settings(settings.audio_settings, request.audio_settings)?;
+    request.image_settings = merge_settings(settings.image_settings, request.image_settings)?;
+    Ok(request)
+}
+
+/// Loads and applies the request's preset, if it names one
+pub fn resolve_preset(app: &tauri::AppHandle, request: ConvertRequest) -> Result<ConvertRequest, MediaForgeError> {
+    match request.preset.clone() {
+        Some(name) => apply_preset(request, load_preset(&presets_dir(app)?, &name)?),
+        None => Ok(request),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(conversion_type: ConversionType) -> ConvertRequest {
+        ConvertRequest {
+            input_files: vec![PathBuf::from("/tmp/talk.wav")],
+            conversion_type,
+            output_format: String::new(),
+            output_path: "/tmp".to_string(),
+            output_mode: OutputMode::SingleDirectory,
+            video_settings: None,
+            audio_settings: None,
+            image_settings: None,
+            timeout_secs: None,
+            filename_template: None,
+            conflict_policy: None,
+            verify_output: None,
+            preset: Some("Podcast mono 64k".to_string()),
+            auto_formats: None,
+            delete_source_on_success: false,
+            post_process_command: None,
+            post_process_ignore_failure: false,
+        }
+    }
+
+    #[test]
+    fn test_preset_file_name() {
+        assert_eq!(preset_file_name("Web 1080p H.264 (CRF 23)").unwrap(), "Web 1080p H.264 (CRF 23).json");
+        for name in ["", "..", ".hidden", "trailing.", " padded", "a/b", "a\\b", "c:d", "x*y", &"n".repeat(65)] {
+            assert!(preset_file_name(name).is_err(), "{:?} should be rejected", name);
+        }
+    }
+
+    #[test]
+    fn test_apply_preset_request_values_win() {
+        let preset = builtin_presets().into_iter().find(|p| p.name == "Podcast mono 64k").unwrap();
+        let mut req = request(ConversionType::Audio);
+        req.audio_settings = Some(AudioSettings {
+            bitrate: Some("96".to_string()),
+            strip_metadata: true,
+            ..Default::default()
+        });
+
+        let merged = apply_preset(req, preset.clone()).unwrap();
+        let audio = merged.audio_settings.unwrap();
+        assert_eq!(merged.output_format, "mp3");
+        assert_eq!(audio.bitrate.as_deref(), Some("96"));
+        assert_eq!(audio.channels, Some(1));
+        assert_eq!(audio.sample_rate.as_deref(), Some("44100"));
+        assert!(audio.strip_metadata);
+
+        // An explicit format is kept, and presets only apply to their own media type
+        let flac = ConvertRequest { output_format: "flac".to_string(), ..request(ConversionType::Audio) };
+        assert_eq!(apply_preset(flac, preset.clone()).unwrap().output_format, "flac");
+        assert!(apply_preset(request(ConversionType::Video), preset.clone()).is_err());
+    }
+
+    #[test]
+    fn test_apply_preset_to_auto_request() {
+        let preset = builtin_presets().into_iter().find(|p| p.name == "Podcast mono 64k").unwrap();
+
+        // The preset covers the request's audio inputs and leaves the rest alone
+        let auto = ConvertRequest {
+            auto_formats: Some(AutoFormats { image: Some("webp".to_string()), ..Default::default() }),
+            ..request(ConversionType::Auto)
+        };
+        let merged = apply_preset(auto, preset.clone()).unwrap();
+        let formats = merged.auto_formats.unwrap();
+        assert_eq!(formats.audio.as_deref(), Some("mp3"));
+        assert_eq!(formats.image.as_deref(), Some("webp"));
+        assert_eq!(formats.video, None);
+        assert_eq!(merged.audio_settings.unwrap().channels, Some(1));
+
+        // A format chosen for the type wins over the preset's
+        let auto = ConvertRequest {
+            auto_formats: Some(AutoFormats { audio: Some("flac".to_string()), ..Default::default() }),
+            ..request(ConversionType::Auto)
+        };
+        let merged = apply_preset(auto, preset).unwrap();
+        assert_eq!(merged.auto_formats.unwrap().audio.as_deref(), Some("flac"));
+    }
+
+    #[test]
+    fn test_preset_store() {
+        let dir = std::env::temp_dir().join(format!("mediaforge-presets-{}", uuid::Uuid::new_v4()));
+
+        seed_builtin_presets(&dir).unwrap();
+        let names: Vec<String> = list_presets(&dir).unwrap().into_iter().map(|p| p.name).collect();
+        assert_eq!(names.len(), builtin_presets().len());
+        assert!(names.contains(&"Web 1080p".to_string()));
+
+        // Deleted built-ins aren't seeded again
+        delete_preset(&dir, "Web 1080p").unwrap();
+        seed_builtin_presets(&dir).unwrap();
+        assert!(load_preset(&dir, "Web 1080p").is_err());
+        assert!(delete_preset(&dir, "Web 1080p").is_err());
+
+        let mut preset = load_preset(&dir, "Web 720p").unwrap();
+        preset.name = "Mine".to_string();
+        save_preset(&dir, &preset).unwrap();
+        assert_eq!(load_preset(&dir, "Mine").unwrap().settings.output_format.as_deref(), Some("mp4"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::VecDeque;
+use std::io;
+use std::process::{ExitStatus, Stdio};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader};
+use tokio::process::Command as TokioCommand;
+use tokio::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// How long a killed process gets to exit, and how long output is still read after
+/// the process exits (a child it started may hold the pipes open)
+const WIND_DOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How a command run by `run_cancellable_command` ended
+#[derive(Debug)]
+pub enum CommandOutcome {
+    /// The process ran to the end, successfully or not
+    Exited(ExitStatus),
+    /// The token was cancelled and the process killed
+    Cancelled,
+}
+
+/// The last lines a process wrote, kept to explain a failure without holding on to
+/// hours of progress output
+pub struct OutputTail {
+    lines: VecDeque<String>,
+    max_lines: usize,
+}
+
+impl OutputTail {
+    pub fn new(max_lines: usize) -> Self {
+        Self { lines: VecDeque::new(), max_lines }
+    }
+
+    pub fn push(&mut self, line: &str) {
+        if self.lines.len() == self.max_lines {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line.trim().to_string());
+    }
+
+    pub fn join(&self) -> String {
+        self.lines.iter().map(String::as_str).collect::<Vec<_>>().join("\n")
+    }
+}
+
+/// Reads up to the next `\n` or `\r` into `line`, returning false at end of output.
+/// Progress bars (ImageMagick's `-monitor`) redraw their line with `\r`, so both end
+/// a line; empty lines are skipped. Bytes stay in `line` until it is handled, which
+/// makes this safe to cancel in `select!`.
+async fn next_line<R: AsyncBufRead + Unpin>(reader: &mut R, line: &mut Vec<u8>) -> io::Result<bool> {
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            return Ok(!line.is_empty());
+        }
+
+        match available.iter().position(|b| matches!(b, b'\n' | b'\r')) {
+            Some(end) => {
+                line.extend_from_slice(&available[..end]);
+                reader.consume(end + 1);
+                if !line.is_empty() {
+                    return Ok(true);
+                }
+            }
+            None => {
+                let len = available.len();
+                line.extend_from_slice(available);
+                reader.consume(len);
+            }
+        }
+    }
+}
+
+/// Runs `cmd`, passing each line of stdout and stderr to the callbacks as it arrives.
+/// Both pipes are always drained, so a chatty process never blocks on a full pipe.
+/// When `cancellation_token` fires the process is killed and `Cancelled` returned;
+/// it is also killed if the returned future is dropped.
+pub async fn run_cancellable_command(
+    mut cmd: TokioCommand,
+    cancellation_token: &CancellationToken,
+    mut on_stdout_line: impl FnMut(&str),
+    mut on_stderr_line: impl FnMut(&str),
+) -> io::Result<CommandOutcome> {
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let mut child = cmd.spawn()?;
+    let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+    let mut stderr = BufReader::new(child.stderr.take().expect("stderr is piped"));
+
+    let (mut stdout_line, mut stderr_line) = (Vec::new(), Vec::new());
+    let (mut stdout_open, mut stderr_open) = (true, true);
+    let mut status = None;
+
+    while status.is_none() || stdout_open || stderr_open {
+        tokio::select! {
+            more = next_line(&mut stdout, &mut stdout_line), if stdout_open => {
+                if matches!(more, Ok(true)) {
+                    on_stdout_line(&String::from_utf8_lossy(&stdout_line));
+                    stdout_line.clear();
+                } else {
+                    stdout_open = false;
+                }
+            }
+            more = next_line(&mut stderr, &mut stderr_line), if stderr_open => {
+                if matches!(more, Ok(true)) {
+                    on_stderr_line(&String::from_utf8_lossy(&stderr_line));
+                    stderr_line.clear();
+                } else {
+                    stderr_open = false;
+                }
+            }
+            exited = child.wait(), if status.is_none() => status = Some(exited?),
+            _ = tokio::time::sleep(WIND_DOWN_TIMEOUT), if status.is_some() => break,
+            _ = cancellation_token.cancelled() => {
+                if let Err(e) = child.kill().await {
+                    log::error!("Failed to kill {:?}: {}", cmd.as_std().get_program(), e);
+                }
+                let _ = tokio::time::timeout(WIND_DOWN_TIMEOUT, child.wait()).await;
+                return Ok(CommandOutcome::Cancelled);
+            }
+        }
+    }
+
+    Ok(CommandOutcome::Exited(status.expect("loop ends once the process exits")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_next_line_splits_on_carriage_returns() {
+        let mut reader: &[u8] = b"load: 10%\rload: 50%\r\nsave: 100%\n\nlast";
+        let mut line = Vec::new();
+        let mut lines = Vec::new();
+        while next_line(&mut reader, &mut line).await.unwrap() {
+            lines.push(String::from_utf8(std::mem::take(&mut line)).unwrap());
+        }
+        assert_eq!(lines, ["load: 10%", "load: 50%", "save: 100%", "last"]);
+    }
+
+    #[test]
+    fn test_output_tail() {
+        let mut tail = OutputTail::new(2);
+        for line in ["banner", "  Invalid data found  ", "Conversion failed!"] {
+            tail.push(line);
+        }
+        assert_eq!(tail.join(), "Invalid data found\nConversion failed!");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_cancellable_command() {
+        let token = CancellationToken::new();
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let mut cmd = TokioCommand::new("sh");
+        cmd.args(["-c", "echo one; echo two >&2; echo three; exit 3"]);
+        let outcome = run_cancellable_command(cmd, &token, |l| out.push(l.to_string()), |l| err.push(l.to_string()))
+            .await
+            .unwrap();
+        assert!(matches!(outcome, CommandOutcome::Exited(status) if status.code() == Some(3)));
+        assert_eq!((out, err), (vec!["one".to_string(), "three".to_string()], vec!["two".to_string()]));
+
+        // Cancelling kills a process that would otherwise run for an hour
+        let mut cmd = TokioCommand::new("sleep");
+        cmd.arg("3600");
+        let canceller = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            canceller.cancel();
+        });
+        let started = std::time::Instant::now();
+        let outcome = run_cancellable_command(cmd, &token, |_| {}, |_| {}).await.unwrap();
+        assert!(matches!(outcome, CommandOutcome::Cancelled));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,224 @@
+use crate::error::MediaForgeError;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+
+/// Number of downloads allowed to run at the same time unless the settings say otherwise
+pub const MAX_CONCURRENT_DOWNLOADS: usize = 3;
+
+/// Number of unfinished tasks a manager holds unless the settings say otherwise
+pub const MAX_QUEUE_SIZE: usize = 500;
+
+/// Refuses `adding` more tasks when they and the `active` unfinished ones would exceed
+/// `max`, so a huge import can't create a task (and a future) per entry
+pub fn check_capacity(active: usize, adding: usize, max: usize) -> Result<(), MediaForgeError> {
+    if active.saturating_add(adding) > max {
+        return Err(MediaForgeError::ResourceExhausted(format!(
+            "Adding {} tasks would exceed the queue limit of {} ({} already queued or running)",
+            adding, max, active
+        )));
+    }
+    Ok(())
+}
+
+/// A task waiting for a slot
+struct WaitingTask {
+    task_id: String,
+    priority: i32,
+    /// Submission order, used to keep equal priorities first-in first-out
+    seq: u64,
+}
+
+#[derive(Default)]
+struct QueueState {
+    waiting: Vec<WaitingTask>,
+    next_seq: u64,
+}
+
+impl QueueState {
+    /// The waiting task that should be admitted next: highest priority, then oldest
+    fn next(&self) -> Option<&WaitingTask> {
+        self.waiting
+            .iter()
+            .max_by(|a, b| a.priority.cmp(&b.priority).then(b.seq.cmp(&a.seq)))
+    }
+}
+
+/// Admits tasks to a fixed number of concurrent slots in priority order.
+///
+/// Tasks are registered with `enqueue` when they are created, so admission follows
+/// submission order unless a priority is changed with `set_priority`.
+pub struct TaskQueue {
+    semaphore: Arc<Semaphore>,
+    state: Mutex<QueueState>,
+    changed: Notify,
+}
+
+/// A held slot; dropping it lets the next waiting task start
+pub struct QueueSlot {
+    permit: Option<OwnedSemaphorePermit>,
+    queue: Arc<TaskQueue>,
+}
+
+impl Drop for QueueSlot {
+    fn drop(&mut self) {
+        // Release the permit before waking waiters so they can take it
+        self.permit.take();
+        self.queue.changed.notify_waiters();
+    }
+}
+
+/// Removes a task from the waiting list if `acquire` is dropped before it gets a slot
+struct WaitingGuard<'a> {
+    queue: &'a TaskQueue,
+    task_id: &'a str,
+}
+
+impl Drop for WaitingGuard<'_> {
+    fn drop(&mut self) {
+        self.queue.remove(self.task_id);
+    }
+}
+
+impl TaskQueue {
+    pub fn new(max_concurrent: usize) -> Arc<Self> {
+        Arc::new(Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrent)),
+            state: Mutex::new(QueueState::default()),
+            changed: Notify::new(),
+        })
+    }
+
+    /// Registers a task as waiting for a slot
+    pub fn enqueue(&self, task_id: &str, priority: i32) {
+        let mut state = self.state.lock().unwrap();
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        state.waiting.push(WaitingTask {
+            task_id: task_id.to_string(),
+            priority,
+            seq,
+        });
+    }
+
+    /// Waits until the task is next in line and a slot is free.
+    /// The task must have been registered with `enqueue` first.
+    pub async fn acquire(self: &Arc<Self>, task_id: &str) -> QueueSlot {
+        let guard = WaitingGuard { queue: self, task_id };
+
+        loop {
+            // Register for wakeups before checking so a release in between isn't missed
+            let changed = self.changed.notified();
+
+            let is_next = self
+                .state
+                .lock()
+                .unwrap()
+                .next()
+                .is_some_and(|next| next.task_id == task_id);
+
+            if is_next {
+                if let Ok(permit) = Arc::clone(&self.semaphore).try_acquire_owned() {
+                    drop(guard);
+                    // Another slot may still be free for the task behind this one
+                    self.changed.notify_waiters();
+                    return QueueSlot {
+                        permit: Some(permit),
+                        queue: Arc::clone(self),
+                    };
+                }
+            }
+
+            changed.await;
+        }
+    }
+
+    /// Changes the priority of a waiting task. Returns false if the task is not
+    /// waiting (already running or finished).
+    pub fn set_priority(&self, task_id: &str, priority: i32) -> bool {
+        let found = {
+            let mut state = self.state.lock().unwrap();
+            match state.waiting.iter_mut().find(|t| t.task_id == task_id) {
+                Some(task) => {
+                    task.priority = priority;
+                    true
+                }
+                None => false,
+            }
+        };
+
+        if found {
+            self.changed.notify_waiters();
+        }
+        found
+    }
+
+    /// Drops a task from the waiting list
+    pub fn remove(&self, task_id: &str) {
+        self.state.lock().unwrap().waiting.retain(|t| t.task_id != task_id);
+        self.changed.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn test_check_capacity() {
+        assert!(check_capacity(0, 500, 500).is_ok());
+        assert!(check_capacity(499, 1, 500).is_ok());
+        assert!(matches!(check_capacity(499, 2, 500), Err(MediaForgeError::ResourceExhausted(_))));
+        assert!(check_capacity(0, usize::MAX, 500).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_admits_in_priority_order() {
+        let queue = TaskQueue::new(1);
+        let blocker = {
+            queue.enqueue("running", 0);
+            queue.acquire("running").await
+        };
+
+        for (id, priority) in [("low", 0), ("normal", 0), ("urgent", 5)] {
+            queue.enqueue(id, priority);
+        }
+        queue.set_priority("normal", 1);
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        for id in ["low", "normal", "urgent"] {
+            let queue = Arc::clone(&queue);
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let _slot = queue.acquire(id).await;
+                tx.send(id).unwrap();
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            });
+        }
+
+        drop(blocker);
+        let mut order = Vec::new();
+        for _ in 0..3 {
+            order.push(rx.recv().await.unwrap());
+        }
+        assert_eq!(order, vec!["urgent", "normal", "low"]);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_waiter_is_removed() {
+        let queue = TaskQueue::new(1);
+        queue.enqueue("running", 0);
+        let slot = queue.acquire("running").await;
+
+        queue.enqueue("cancelled", 10);
+        queue.enqueue("next", 0);
+        let waiting = tokio::time::timeout(Duration::from_millis(20), queue.acquire("cancelled")).await;
+        assert!(waiting.is_err());
+        assert!(!queue.set_priority("cancelled", 1));
+
+        drop(slot);
+        let next = tokio::time::timeout(Duration::from_secs(1), queue.acquire("next")).await;
+        assert!(next.is_ok());
+    }
+}
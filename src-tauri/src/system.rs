@@ -1,65 +1,999 @@
-use crate::types::SystemInfo;
+use crate::error::MediaForgeError;
+use crate::process::{run_cancellable_command, CommandOutcome, OutputTail};
+use crate::types::{ClipboardMode, DependencyMissing, InstallProgress, InstallResult, InstallStrategy, SystemInfo, YtdlpInstallResult};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+use tokio_util::sync::CancellationToken;
 
-pub fn check_system_dependencies() -> SystemInfo {
-    let has_ytdlp = check_command_exists("yt-dlp");
-    let has_ffmpeg = check_command_exists("ffmpeg");
-    
-    let ytdlp_path = if has_ytdlp {
-        get_command_path("yt-dlp")
-    } else {
-        None
-    };
+/// How long a dependency check is reused before the programs are looked for again
+const DEPENDENCY_CHECK_TTL: Duration = Duration::from_secs(300);
+
+async fn check_system_dependencies(tools: &ToolPaths) -> SystemInfo {
+    // Looks where the downloader does, including the app's own copy and pip's user folder
+    let ytdlp_path = tools.find_ytdlp().await.map(|path| path.to_string_lossy().into_owned());
+    let has_ytdlp = ytdlp_path.is_some();
+    let ffmpeg_path = get_command_path("ffmpeg").await;
+    let has_ffmpeg = ffmpeg_path.is_some();
     
-    let ffmpeg_path = if has_ffmpeg {
-        get_command_path("ffmpeg")
-    } else {
-        None
+    let magick_path = get_command_path("magick").await;
+    let convert_path = match magick_path {
+        Some(_) => None,
+        None => get_command_path("convert").await,
     };
+    let imagemagick = ImageMagick::select(magick_path.is_some(), convert_path.is_some());
+    let imagemagick_path = imagemagick.and(magick_path.or(convert_path));
     
     SystemInfo {
         has_ytdlp,
         has_ffmpeg,
+        has_imagemagick: imagemagick.is_some(),
         ytdlp_path,
         ffmpeg_path,
+        imagemagick_path,
     }
 }
 
-fn check_command_exists(command: &str) -> bool {
-    Command::new("which")
-        .arg(command)
-        .output()
-        .map(|output| output.status.success())
-        .unwrap_or(false)
+/// How long the D-Bus file manager gets to answer a request to show a file
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+const REVEAL_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Shows `path` selected in the platform file manager. On Linux this asks the file
+/// manager over D-Bus and, when none answers in time, opens the containing folder
+/// instead.
+pub async fn reveal_in_file_manager(path: &Path) -> Result<(), String> {
+    let path = crate::error::validation::sanitize_path(&path.to_string_lossy()).map_err(|e| e.to_string())?;
+    if !path.exists() {
+        return Err(format!("Not found: {}", path.display()));
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let request = tokio::process::Command::new("dbus-send")
+            .args([
+                "--session",
+                "--print-reply",
+                "--dest=org.freedesktop.FileManager1",
+                "/org/freedesktop/FileManager1",
+                "org.freedesktop.FileManager1.ShowItems",
+            ])
+            .arg(format!("array:string:{}", file_uri(&path)))
+            .arg("string:")
+            .kill_on_drop(true)
+            .output();
+        let shown = tokio::time::timeout(REVEAL_TIMEOUT, request)
+            .await
+            .is_ok_and(|output| output.is_ok_and(|output| output.status.success()));
+        if !shown {
+            return open_in_file_manager(&path);
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        Command::new("open")
+            .arg("-R")
+            .arg(&path)
+            .spawn()
+            .map_err(|e| format!("Failed to reveal file: {}", e))?;
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        // explorer wants the path quoted after the comma, which normal argument
+        // quoting (around the whole argument) doesn't produce
+        Command::new("explorer")
+            .raw_arg(format!("/select,\"{}\"", path.display()))
+            .spawn()
+            .map_err(|e| format!("Failed to reveal file: {}", e))?;
+    }
+
+    Ok(())
 }
 
-fn get_command_path(command: &str) -> Option<String> {
-    Command::new("which")
+/// A `file://` URI for an absolute path. Everything but unreserved characters and `/`
+/// is percent-encoded, which also keeps commas away from `dbus-send`'s array syntax.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+/// The ImageMagick command line that is installed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageMagick {
+    /// ImageMagick 7: `magick`, with the other tools as subcommands (`magick identify`)
+    Magick,
+    /// ImageMagick 6: `convert`, with the other tools as programs of their own (`identify`)
+    Legacy,
+}
+
+/// Set once ImageMagick has been found; a missing install is looked for again next time
+static IMAGEMAGICK: OnceLock<ImageMagick> = OnceLock::new();
+
+impl ImageMagick {
+    /// Prefers ImageMagick 7. On Windows `convert` is the system's disk conversion
+    /// tool, so only `magick` counts there.
+    pub fn select(has_magick: bool, has_convert: bool) -> Option<Self> {
+        if has_magick {
+            Some(ImageMagick::Magick)
+        } else if has_convert && !cfg!(windows) {
+            Some(ImageMagick::Legacy)
+        } else {
+            None
+        }
+    }
+
+    /// Finds the installed ImageMagick, if any
+    pub async fn detect() -> Option<Self> {
+        if let Some(found) = IMAGEMAGICK.get() {
+            return Some(*found);
+        }
+        let has_magick = get_command_path("magick").await.is_some();
+        let has_convert = !has_magick && get_command_path("convert").await.is_some();
+        let found = Self::select(has_magick, has_convert)?;
+        Some(*IMAGEMAGICK.get_or_init(|| found))
+    }
+
+    /// The program that converts images
+    pub fn program(self) -> &'static str {
+        match self {
+            ImageMagick::Magick => "magick",
+            ImageMagick::Legacy => "convert",
+        }
+    }
+
+    /// A command running the conversion program; arguments are the same for both versions
+    pub fn command(self) -> tokio::process::Command {
+        tokio::process::Command::new(self.program())
+    }
+
+    /// A command running `tool`, e.g. "identify"
+    pub fn tool(self, tool: &str) -> tokio::process::Command {
+        match self {
+            ImageMagick::Magick => {
+                let mut cmd = tokio::process::Command::new("magick");
+                cmd.arg(tool);
+                cmd
+            }
+            ImageMagick::Legacy => tokio::process::Command::new(tool),
+        }
+    }
+}
+
+/// `where` on Windows, which has no `which`
+const COMMAND_FINDER: &str = if cfg!(windows) { "where" } else { "which" };
+
+async fn get_command_path(command: &str) -> Option<String> {
+    tokio::process::Command::new(COMMAND_FINDER)
         .arg(command)
         .output()
+        .await
         .ok()
         .and_then(|output| {
             if output.status.success() {
+                // `where` lists every match; the first is the one that runs
                 String::from_utf8(output.stdout)
                     .ok()
-                    .map(|s| s.trim().to_string())
+                    .and_then(|s| s.lines().next().map(|line| line.trim().to_string()))
             } else {
                 None
             }
         })
 }
 
-pub fn install_ytdlp() -> Result<String, String> {
-    // Try to install yt-dlp using pip
-    let output = Command::new("pip3")
-        .args(["install", "--user", "yt-dlp"])
-        .output()
-        .map_err(|e| format!("Failed to run pip3: {}", e))?;
+/// Where yt-dlp's release binaries are downloaded from
+const YTDLP_RELEASE_URL: &str = "https://github.com/yt-dlp/yt-dlp/releases/latest/download";
+
+/// Checksums yt-dlp publishes next to each release's binaries
+const YTDLP_CHECKSUMS: &str = "SHA2-256SUMS";
+
+/// Longest one request for the yt-dlp binary or its checksums may take
+const YTDLP_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// The app's own folder for programs it downloads, under its data folder
+static LOCAL_BIN_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Sets the folder `install_ytdlp` puts the standalone yt-dlp in; called once at startup
+pub fn set_local_bin_dir(dir: PathBuf) {
+    let _ = LOCAL_BIN_DIR.set(dir);
+}
+
+fn local_ytdlp() -> Option<PathBuf> {
+    Some(LOCAL_BIN_DIR.get()?.join(if cfg!(windows) { "yt-dlp.exe" } else { "yt-dlp" }))
+}
+
+/// Folders `pip install --user` (and pipx) put scripts in, which apps started from the
+/// desktop often don't have on their PATH. Python versions are listed newest first.
+fn user_script_dirs(os: &str, home: &Path, data_dir: Option<&Path>) -> Vec<PathBuf> {
+    match os {
+        "macos" => {
+            let mut dirs = python_version_dirs(&home.join("Library").join("Python"));
+            dirs.iter_mut().for_each(|dir| dir.push("bin"));
+            dirs.push(home.join(".local").join("bin"));
+            dirs
+        }
+        "windows" => {
+            let mut dirs = data_dir.map(|dir| python_version_dirs(&dir.join("Python"))).unwrap_or_default();
+            dirs.iter_mut().for_each(|dir| dir.push("Scripts"));
+            dirs
+        }
+        _ => vec![home.join(".local").join("bin")],
+    }
+}
+
+/// The per-version folders in `dir` ("3.12" on macOS, "Python312" on Windows), newest first
+fn python_version_dirs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    // Only Python 3 installs per-user scripts here, so the digits order the versions
+    let version = |path: &PathBuf| -> u32 {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        name.chars().filter(char::is_ascii_digit).collect::<String>().parse().unwrap_or(0)
+    };
+    let mut dirs: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).filter(|path| path.is_dir()).collect();
+    dirs.sort_by_key(|path| std::cmp::Reverse(version(path)));
+    dirs
+}
+
+/// Finds yt-dlp: the app's own copy when it had to download one, then the one on the
+/// PATH, then pip's user script folders
+async fn resolve_ytdlp() -> Option<PathBuf> {
+    if let Some(local) = local_ytdlp().filter(|path| path.is_file()) {
+        return Some(local);
+    }
+    if let Some(path) = get_command_path("yt-dlp").await {
+        return Some(PathBuf::from(path));
+    }
+    let home = dirs::home_dir()?;
+    let program = if cfg!(windows) { "yt-dlp.exe" } else { "yt-dlp" };
+    user_script_dirs(std::env::consts::OS, &home, dirs::data_dir().as_deref())
+        .into_iter()
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
+/// The first yt-dlp release with `--progress-template`
+const PROGRESS_TEMPLATE_SINCE: &str = "2021.10.09";
+
+/// Whether a yt-dlp reporting `version` accepts `--progress-template`. Releases are
+/// numbered by date; anything else (a source build) is assumed to be recent.
+fn supports_progress_template(version: &str) -> bool {
+    match version.get(..PROGRESS_TEMPLATE_SINCE.len()) {
+        Some(date) if date.chars().all(|c| c.is_ascii_digit() || c == '.') => date >= PROGRESS_TEMPLATE_SINCE,
+        _ => true,
+    }
+}
+
+/// Where the external programs were found, so they aren't looked for on every download
+#[derive(Debug, Default)]
+pub struct ToolPaths {
+    ytdlp: Mutex<Option<PathBuf>>,
+    /// Whether that yt-dlp supports `--progress-template`, once its version is known
+    ytdlp_progress_template: Mutex<Option<bool>>,
+    /// The last dependency check and when it ran
+    system_info: RwLock<Option<(SystemInfo, Instant)>>,
+}
+
+impl ToolPaths {
+    /// The absolute path of yt-dlp, looked for again when it isn't known or has gone
+    pub async fn find_ytdlp(&self) -> Option<PathBuf> {
+        let cached = self.ytdlp.lock().unwrap().clone();
+        if let Some(path) = cached.filter(|path| path.is_file()) {
+            return Some(path);
+        }
+        let found = resolve_ytdlp().await;
+        *self.ytdlp.lock().unwrap() = found.clone();
+        *self.ytdlp_progress_template.lock().unwrap() = None;
+        found
+    }
+
+    /// Which dependencies are installed, from the last check unless it is out of date.
+    /// Only a check that found everything is reused, so a tool installed since is seen.
+    pub async fn system_info(&self) -> SystemInfo {
+        if let Some((info, checked)) = self.system_info.read().unwrap().as_ref() {
+            if checked.elapsed() < DEPENDENCY_CHECK_TTL {
+                return info.clone();
+            }
+        }
+        self.refresh().await
+    }
+
+    /// Looks for every dependency again, e.g. after the user installed one
+    pub async fn refresh(&self) -> SystemInfo {
+        let info = check_system_dependencies(self).await;
+        let found_all = info.has_ytdlp && info.has_ffmpeg && info.has_imagemagick;
+        *self.system_info.write().unwrap() = found_all.then(|| (info.clone(), Instant::now()));
+        info
+    }
+
+    /// The yt-dlp to run, or `MissingDependency` without trying when the last check
+    /// found none
+    pub async fn require_ytdlp(&self) -> Result<PathBuf, MediaForgeError> {
+        if !self.system_info().await.has_ytdlp {
+            return Err(MediaForgeError::not_installed("yt-dlp"));
+        }
+        Ok(self.ytdlp().await)
+    }
+
+    /// `MissingDependency` when the last check found no ffmpeg
+    pub async fn require_ffmpeg(&self) -> Result<(), MediaForgeError> {
+        if !self.system_info().await.has_ffmpeg {
+            return Err(MediaForgeError::not_installed("ffmpeg"));
+        }
+        Ok(())
+    }
+
+    /// Whether yt-dlp can print progress with `--progress-template`; older releases
+    /// only print the human-readable `[download]` lines
+    pub async fn ytdlp_has_progress_template(&self) -> bool {
+        let program = self.ytdlp().await;
+        if let Some(supported) = *self.ytdlp_progress_template.lock().unwrap() {
+            return supported;
+        }
+        match ytdlp_version(&program).await {
+            Some(version) => {
+                let supported = supports_progress_template(&version);
+                *self.ytdlp_progress_template.lock().unwrap() = Some(supported);
+                supported
+            }
+            None => true,
+        }
+    }
+
+    /// The yt-dlp to run. When it can't be found this is plain "yt-dlp", so running it
+    /// fails as a missing dependency.
+    pub async fn ytdlp(&self) -> PathBuf {
+        self.find_ytdlp().await.unwrap_or_else(|| PathBuf::from("yt-dlp"))
+    }
+}
+
+/// The version `program` reports, or None when it doesn't run
+async fn ytdlp_version(program: &Path) -> Option<String> {
+    let output = tokio::process::Command::new(program).arg("--version").output().await.ok()?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !version.is_empty()).then_some(version)
+}
+
+/// Programs the install plans look for on the PATH
+const INSTALLER_PROGRAMS: [&str; 5] = ["brew", "winget", "pip3", "pipx", "pkexec"];
+
+/// Which of the installers and Linux package managers are on the PATH, looked up
+/// concurrently without blocking the runtime
+async fn installed_installers() -> HashSet<&'static str> {
+    let programs = INSTALLER_PROGRAMS.into_iter().chain(LINUX_PACKAGE_MANAGERS.iter().map(|(manager, _)| *manager));
+    let found = futures::future::join_all(
+        programs.map(|program| async move { get_command_path(program).await.map(|_| program) }),
+    )
+    .await;
+    found.into_iter().flatten().collect()
+}
+
+/// Package manager installs of yt-dlp to try in order, before downloading the
+/// standalone binary. Linux package managers are only tried through `pkexec`.
+fn ytdlp_install_commands(os: &str, has: impl Fn(&str) -> bool) -> Vec<(InstallStrategy, Vec<String>)> {
+    let command = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+    let mut commands = Vec::new();
+    if os == "macos" && has("brew") {
+        commands.push((InstallStrategy::Homebrew, command(&["brew", "install", "yt-dlp"])));
+    }
+    if os == "windows" && has("winget") {
+        commands.push((
+            InstallStrategy::Winget,
+            command(&[
+                "winget", "install", "--id", "yt-dlp.yt-dlp", "-e",
+                "--accept-source-agreements", "--accept-package-agreements",
+            ]),
+        ));
+    }
+    if has("pip3") {
+        commands.push((InstallStrategy::Pip, command(&["pip3", "install", "--user", "--upgrade", "yt-dlp"])));
+    }
+    if has("pipx") {
+        commands.push((InstallStrategy::Pipx, command(&["pipx", "install", "yt-dlp"])));
+    }
+    if os == "linux" && has("pkexec") {
+        if let Some(install) = linux_install_command(&has, "yt-dlp") {
+            commands.push((InstallStrategy::PackageManager, command(&[&["pkexec"], &install[..]].concat())));
+        }
+    }
+    commands
+}
+
+/// The standalone yt-dlp build for `os` and `arch` (as in `std::env::consts`)
+fn ytdlp_release_asset(os: &str, arch: &str) -> Option<&'static str> {
+    match (os, arch) {
+        ("linux", "x86_64") => Some("yt-dlp_linux"),
+        ("linux", "aarch64") => Some("yt-dlp_linux_aarch64"),
+        ("macos", _) => Some("yt-dlp_macos"),
+        ("windows", "x86_64") => Some("yt-dlp.exe"),
+        ("windows", "x86") => Some("yt-dlp_x86.exe"),
+        _ => None,
+    }
+}
+
+/// Why an installer failed, in a line. pip refusing a system-managed Python (PEP 668)
+/// gets a plain explanation instead of its long notice.
+fn describe_install_failure(stderr: &str) -> String {
+    if stderr.contains("externally-managed-environment") {
+        return "this Python is managed by the system (PEP 668), so pip won't install into it".to_string();
+    }
+    stderr
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty())
+        .unwrap_or("exited with an error")
+        .to_string()
+}
+
+/// The hash a `SHA2-256SUMS` file lists for `asset`, lowercase
+fn expected_sha256(sums: &str, asset: &str) -> Option<String> {
+    sums.lines().find_map(|line| {
+        let (hash, name) = line.trim().split_once(char::is_whitespace)?;
+        // `*` marks a binary-mode entry in sha256sum's output
+        (name.trim().trim_start_matches('*') == asset).then(|| hash.to_lowercase())
+    })
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+async fn fetch_release_file(client: &reqwest::Client, name: &str) -> Result<Vec<u8>, String> {
+    let url = format!("{}/{}", YTDLP_RELEASE_URL, name);
+    let failed = |e: reqwest::Error| format!("could not download {}: {}", url, e);
+    let response = client.get(&url).send().await.and_then(|r| r.error_for_status()).map_err(failed)?;
+    Ok(response.bytes().await.map_err(failed)?.to_vec())
+}
+
+/// Downloads the standalone yt-dlp to `target`, replacing it only once complete and
+/// its hash matches the one the release publishes
+async fn download_ytdlp_binary(target: &Path) -> Result<(), String> {
+    let asset = ytdlp_release_asset(std::env::consts::OS, std::env::consts::ARCH)
+        .ok_or_else(|| "there is no standalone build for this platform".to_string())?;
+    let client = reqwest::Client::builder()
+        .timeout(YTDLP_DOWNLOAD_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let sums = fetch_release_file(&client, YTDLP_CHECKSUMS).await?;
+    let expected = expected_sha256(&String::from_utf8_lossy(&sums), asset)
+        .ok_or_else(|| format!("{} doesn't list {}", YTDLP_CHECKSUMS, asset))?;
+    let bytes = fetch_release_file(&client, asset).await?;
+    let actual = sha256_hex(&bytes);
+    if actual != expected {
+        return Err(format!("{} failed its checksum (expected {}, got {})", asset, expected, actual));
+    }
+
+    if let Some(dir) = target.parent() {
+        tokio::fs::create_dir_all(dir).await.map_err(|e| e.to_string())?;
+    }
+    let partial = target.with_extension("part");
+    tokio::fs::write(&partial, &bytes).await.map_err(|e| e.to_string())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        tokio::fs::set_permissions(&partial, std::fs::Permissions::from_mode(0o755))
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    tokio::fs::rename(&partial, target).await.map_err(|e| e.to_string())
+}
+
+/// Installs yt-dlp with the first method that works: Homebrew or winget, pip, pipx,
+/// the Linux package manager, and finally the standalone binary in the app's folder.
+/// The first installer that succeeds ends the search, so yt-dlp is never installed
+/// twice. An install only counts once `yt-dlp --version` runs; the dependency check
+/// is then redone.
+pub async fn install_ytdlp(tools: &ToolPaths) -> Result<YtdlpInstallResult, String> {
+    let mut failures = Vec::new();
+    let installers = installed_installers().await;
+    for (strategy, args) in ytdlp_install_commands(std::env::consts::OS, |program| installers.contains(program)) {
+        let command = args.join(" ");
+        log::info!("Installing yt-dlp with: {}", command);
+        match tokio::process::Command::new(&args[0]).args(&args[1..]).output().await {
+            Ok(output) if output.status.success() => {
+                let program = tools.ytdlp().await;
+                match ytdlp_version(&program).await {
+                    Some(version) => {
+                        let path = Some(program.to_string_lossy().into_owned());
+                        tools.refresh().await;
+                        return Ok(YtdlpInstallResult { strategy, version, path });
+                    }
+                    None => {
+                        return Err(format!(
+                            "Installed yt-dlp with {}, but it couldn't be found; check that its folder is on the PATH",
+                            command
+                        ))
+                    }
+                }
+            }
+            Ok(output) => failures.push(format!(
+                "{}: {}",
+                command,
+                describe_install_failure(&String::from_utf8_lossy(&output.stderr))
+            )),
+            Err(e) => failures.push(format!("{}: {}", command, e)),
+        }
+    }
+
+    if let Some(target) = local_ytdlp() {
+        match download_ytdlp_binary(&target).await {
+            Ok(()) => match ytdlp_version(&target).await {
+                Some(version) => {
+                    tools.refresh().await;
+                    return Ok(YtdlpInstallResult {
+                        strategy: InstallStrategy::StandaloneBinary,
+                        version,
+                        path: Some(target.to_string_lossy().into_owned()),
+                    });
+                }
+                None => failures.push("standalone binary: downloaded, but it doesn't run".to_string()),
+            },
+            Err(e) => failures.push(format!("standalone binary: {}", e)),
+        }
+    }
+
+    Err(format!("Could not install yt-dlp:\n{}", failures.join("\n")))
+}
+
+/// Linux package managers and their unattended install arguments, in the order
+/// they're looked for
+const LINUX_PACKAGE_MANAGERS: [(&str, &[&str]); 4] = [
+    ("apt-get", &["install", "-y"]),
+    ("dnf", &["install", "-y"]),
+    ("pacman", &["-S", "--noconfirm"]),
+    ("zypper", &["--non-interactive", "install"]),
+];
+
+/// The command installing `package` with the first Linux package manager found; it needs root
+fn linux_install_command(has: impl Fn(&str) -> bool, package: &str) -> Option<Vec<&str>> {
+    let (manager, args) = LINUX_PACKAGE_MANAGERS.iter().find(|(manager, _)| has(manager))?;
+    Some([&[*manager][..], args, &[package]].concat())
+}
+
+/// How ffmpeg gets installed on this machine
+#[derive(Debug, PartialEq)]
+pub enum FfmpegInstall {
+    /// A command that installs it without a terminal
+    Run(Vec<String>),
+    /// What the user has to do themselves
+    Manual(String),
+}
+
+/// Picks the ffmpeg install for `os` (as in `std::env::consts::OS`), given which
+/// programs are on the PATH. Linux package managers need root, which `pkexec` asks for
+/// with a graphical prompt; without it the user gets the `sudo` command to run.
+pub fn ffmpeg_install_plan(os: &str, has: impl Fn(&str) -> bool) -> FfmpegInstall {
+    let run = |args: &[&str]| FfmpegInstall::Run(args.iter().map(|a| a.to_string()).collect());
+    match os {
+        "macos" if has("brew") => run(&["brew", "install", "ffmpeg"]),
+        "macos" => FfmpegInstall::Manual("Install Homebrew from https://brew.sh, then run: brew install ffmpeg".to_string()),
+        "windows" if has("winget") => run(&[
+            "winget", "install", "--id", "Gyan.FFmpeg", "-e",
+            "--accept-source-agreements", "--accept-package-agreements",
+        ]),
+        "windows" => FfmpegInstall::Manual(
+            "Run: winget install Gyan.FFmpeg, or download a build from https://ffmpeg.org/download.html".to_string(),
+        ),
+        "linux" => match linux_install_command(&has, "ffmpeg") {
+            Some(command) => {
+                if has("pkexec") {
+                    run(&[&["pkexec"], &command[..]].concat())
+                } else {
+                    FfmpegInstall::Manual(format!("Run: sudo {}", command.join(" ")))
+                }
+            }
+            None => FfmpegInstall::Manual(
+                "Install ffmpeg with your distribution's package manager, or download a static build \
+                 from https://johnvansickle.com/ffmpeg/".to_string(),
+            ),
+        },
+        _ => FfmpegInstall::Manual("Download ffmpeg from https://ffmpeg.org/download.html".to_string()),
+    }
+}
+
+/// Sends a `dependency-missing` event when `err` says the task couldn't start a
+/// program because it isn't installed
+pub fn emit_dependency_missing(err: &MediaForgeError, task_id: &str, app: &tauri::AppHandle) {
+    if let Some(program) = err.missing_program() {
+        let _ = app.emit(
+            "dependency-missing",
+            DependencyMissing { dependency: program.to_string(), task_id: task_id.to_string() },
+        );
+    }
+}
+
+/// Installs ffmpeg with the platform's package manager, sending each line it prints
+/// as an `install-progress` event, then checks the dependencies again
+pub async fn install_ffmpeg(app: &tauri::AppHandle, tools: &ToolPaths) -> InstallResult {
+    let installers = installed_installers().await;
+    let plan = ffmpeg_install_plan(std::env::consts::OS, |program| installers.contains(program));
+    let (command, outcome) = match plan {
+        FfmpegInstall::Manual(instructions) => (None, Err(instructions)),
+        FfmpegInstall::Run(args) => {
+            let mut cmd = tokio::process::Command::new(&args[0]);
+            cmd.args(&args[1..]);
+            let progress = |line: &str| {
+                let line = line.to_string();
+                let _ = app.emit("install-progress", InstallProgress { dependency: "ffmpeg".to_string(), line });
+            };
+            let mut error_output = OutputTail::new(10);
+            let on_stderr = |line: &str| {
+                error_output.push(line);
+                progress(line);
+            };
+            let outcome = match run_cancellable_command(cmd, &CancellationToken::new(), progress, on_stderr).await {
+                Ok(CommandOutcome::Exited(status)) if status.success() => Ok(()),
+                Ok(_) => Err(format!("{} failed: {}", args[0], error_output.join())),
+                Err(e) => Err(format!("Could not run {}: {}", args[0], e)),
+            };
+            (Some(args.join(" ")), outcome)
+        }
+    };
+
+    let system_info = tools.refresh().await;
+    let message = match &outcome {
+        Ok(()) if !system_info.has_ffmpeg => {
+            Some("ffmpeg was installed but isn't on the PATH yet; restart MediaForge".to_string())
+        }
+        Ok(()) => None,
+        Err(message) => Some(message.clone()),
+    };
+    InstallResult {
+        installed: outcome.is_ok(),
+        command,
+        message,
+        system_info,
+    }
+}
+
+/// Opens the folder containing `path` (or `path` itself when it is a directory)
+/// in the platform file manager.
+pub fn open_in_file_manager(path: &Path) -> Result<(), String> {
+    // Extract directory path if a file path is provided
+    let folder = if path.is_file() {
+        path.parent().unwrap_or(path)
+    } else {
+        path
+    };
     
-    if output.status.success() {
-        Ok("yt-dlp installed successfully".to_string())
+    // Open folder based on OS
+    #[cfg(target_os = "linux")]
+    {
+        Command::new("xdg-open")
+            .arg(folder)
+            .spawn()
+            .map_err(|e| format!("Failed to open folder: {}", e))?;
+    }
+    
+    #[cfg(target_os = "macos")]
+    {
+        Command::new("open")
+            .arg(folder)
+            .spawn()
+            .map_err(|e| format!("Failed to open folder: {}", e))?;
+    }
+    
+    #[cfg(target_os = "windows")]
+    {
+        Command::new("explorer")
+            .arg(folder)
+            .spawn()
+            .map_err(|e| format!("Failed to open folder: {}", e))?;
+    }
+    
+    Ok(())
+}
+
+/// Opens `path` with the platform's default application, e.g. to play a converted
+/// video. Only existing files outside system folders are opened.
+pub fn open_with_default_app(path: &Path) -> Result<(), String> {
+    let file = crate::error::validation::sanitize_path(&path.to_string_lossy()).map_err(|e| e.to_string())?;
+    if !file.is_file() {
+        return Err(format!("Not a file: {}", file.display()));
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        Command::new("xdg-open")
+            .arg(&file)
+            .spawn()
+            .map_err(|e| format!("Failed to open file: {}", e))?;
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        Command::new("open")
+            .arg(&file)
+            .spawn()
+            .map_err(|e| format!("Failed to open file: {}", e))?;
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        // Explorer opens a file with its associated app. Going through `cmd /C start`
+        // would let cmd interpret `&`, `^` and the like in the path.
+        Command::new("explorer")
+            .arg(&file)
+            .spawn()
+            .map_err(|e| format!("Failed to open file: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// The Linux tool that can put a URI list on the clipboard and its arguments: `wl-copy`
+/// under Wayland, otherwise `xclip`
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn linux_uri_clipboard_command(wayland: bool, has: impl Fn(&str) -> bool) -> Option<Vec<&'static str>> {
+    if wayland && has("wl-copy") {
+        Some(vec!["wl-copy", "--type", "text/uri-list"])
+    } else if has("xclip") {
+        Some(vec!["xclip", "-selection", "clipboard", "-t", "text/uri-list"])
     } else {
-        let error = String::from_utf8_lossy(&output.stderr);
-        Err(format!("Failed to install yt-dlp: {}", error))
+        None
+    }
+}
+
+/// Runs `program` with `text` on its stdin
+#[cfg(target_os = "linux")]
+async fn pipe_to(program: &str, args: &[&str], text: &str) -> Result<(), String> {
+    use std::process::Stdio;
+    use tokio::io::AsyncWriteExt;
+
+    // Clipboard tools keep running to serve the clipboard, so their output isn't waited on
+    let mut child = tokio::process::Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    // Dropping stdin closes it, which tells the tool the text is complete
+    let written = match child.stdin.take() {
+        Some(mut stdin) => stdin.write_all(text.as_bytes()).await,
+        None => Ok(()),
+    };
+    let status = child.wait().await.map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    match written {
+        Err(e) => Err(format!("Failed to copy with {}: {}", program, e)),
+        _ if !status.success() => Err(format!("{} failed to copy to the clipboard", program)),
+        _ => Ok(()),
+    }
+}
+
+/// Puts `file` on the clipboard: its path as text, or for `Uri` the file itself so a
+/// file manager can paste it
+pub async fn copy_file_to_clipboard(app: &tauri::AppHandle, file: &Path, mode: ClipboardMode) -> Result<(), String> {
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+
+    if mode == ClipboardMode::Path {
+        return app
+            .clipboard()
+            .write_text(file.to_string_lossy().into_owned())
+            .map_err(|e| format!("Failed to copy to the clipboard: {}", e));
+    }
+
+    // The clipboard plugin only writes text, so a file goes through each platform's own tool
+    #[cfg(target_os = "linux")]
+    {
+        let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+        let mut installed = Vec::new();
+        for tool in ["wl-copy", "xclip"] {
+            if get_command_path(tool).await.is_some() {
+                installed.push(tool);
+            }
+        }
+        let Some(args) = linux_uri_clipboard_command(wayland, |tool| installed.contains(&tool)) else {
+            return Err("Install wl-clipboard or xclip to copy files to the clipboard".to_string());
+        };
+        // A URI list ends every line with CRLF
+        pipe_to(args[0], &args[1..], &format!("{}\r\n", file_uri(file))).await?;
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        // The path goes through the environment so it needs no AppleScript quoting
+        let status = tokio::process::Command::new("osascript")
+            .args(["-e", "set the clipboard to POSIX file (system attribute \"MEDIAFORGE_CLIPBOARD\")"])
+            .env("MEDIAFORGE_CLIPBOARD", file)
+            .status()
+            .await
+            .map_err(|e| format!("Failed to run osascript: {}", e))?;
+        if !status.success() {
+            return Err("osascript failed to copy to the clipboard".to_string());
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        // `-LiteralPath` copies the file itself, as Explorer's Copy does. The path goes
+        // through the environment so it needs no PowerShell quoting.
+        let status = tokio::process::Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", "Set-Clipboard -LiteralPath $env:MEDIAFORGE_CLIPBOARD"])
+            .env("MEDIAFORGE_CLIPBOARD", file)
+            .status()
+            .await
+            .map_err(|e| format!("Failed to run PowerShell: {}", e))?;
+        if !status.success() {
+            return Err("PowerShell failed to copy to the clipboard".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linux_uri_clipboard_command() {
+        let only = |tools: &'static [&'static str]| move |name: &str| tools.contains(&name);
+        assert_eq!(
+            linux_uri_clipboard_command(true, only(&["wl-copy", "xclip"])),
+            Some(vec!["wl-copy", "--type", "text/uri-list"])
+        );
+        assert_eq!(
+            linux_uri_clipboard_command(false, only(&["wl-copy", "xclip"])),
+            Some(vec!["xclip", "-selection", "clipboard", "-t", "text/uri-list"])
+        );
+        assert_eq!(linux_uri_clipboard_command(true, only(&["xsel"])), None);
+    }
+
+    #[test]
+    fn test_ytdlp_checksum() {
+        let sums = "0d8e0f1a  yt-dlp\n9F86D081884C7D659A2FEAA0C55AD015A3BF4F1B2B0B822CD15D6C15B0F00A08 *yt-dlp.exe\n";
+        assert_eq!(expected_sha256(sums, "yt-dlp").as_deref(), Some("0d8e0f1a"));
+        assert_eq!(
+            expected_sha256(sums, "yt-dlp.exe").as_deref(),
+            Some("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08")
+        );
+        assert_eq!(expected_sha256(sums, "yt-dlp_macos"), None);
+        assert_eq!(sha256_hex(b"test"), "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08");
+    }
+
+    #[test]
+    fn test_supports_progress_template() {
+        assert!(supports_progress_template("2021.10.09"));
+        assert!(supports_progress_template("2024.08.06"));
+        assert!(supports_progress_template("2024.08.06.232843"));
+        assert!(!supports_progress_template("2021.09.25"));
+        assert!(!supports_progress_template("2021.06.01"));
+        assert!(supports_progress_template("abc1234"));
+    }
+
+    #[test]
+    fn test_user_script_dirs() {
+        let home = std::env::temp_dir().join(format!("mediaforge-home-{}", uuid::Uuid::new_v4()));
+        for version in ["3.9", "3.12", "3.11"] {
+            std::fs::create_dir_all(home.join("Library").join("Python").join(version).join("bin")).unwrap();
+        }
+        let appdata = home.join("AppData");
+        for version in ["Python39", "Python312"] {
+            std::fs::create_dir_all(appdata.join("Python").join(version).join("Scripts")).unwrap();
+        }
+
+        let python = |version: &str| home.join("Library").join("Python").join(version).join("bin");
+        assert_eq!(
+            user_script_dirs("macos", &home, None),
+            vec![python("3.12"), python("3.11"), python("3.9"), home.join(".local").join("bin")]
+        );
+        let scripts = |version: &str| appdata.join("Python").join(version).join("Scripts");
+        assert_eq!(
+            user_script_dirs("windows", &home, Some(&appdata)),
+            vec![scripts("Python312"), scripts("Python39")]
+        );
+        assert!(user_script_dirs("windows", &home, None).is_empty());
+        assert_eq!(user_script_dirs("linux", &home, None), vec![home.join(".local").join("bin")]);
+
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    #[test]
+    fn test_imagemagick_select() {
+        assert_eq!(ImageMagick::select(true, true), Some(ImageMagick::Magick));
+        assert_eq!(ImageMagick::select(true, false), Some(ImageMagick::Magick));
+        assert_eq!(ImageMagick::select(false, false), None);
+        let legacy = ImageMagick::select(false, true);
+        assert_eq!(legacy, if cfg!(windows) { None } else { Some(ImageMagick::Legacy) });
+
+        let args = |cmd: tokio::process::Command| {
+            let cmd = cmd.as_std();
+            let mut args = vec![cmd.get_program().to_string_lossy().into_owned()];
+            args.extend(cmd.get_args().map(|a| a.to_string_lossy().into_owned()));
+            args
+        };
+        assert_eq!(args(ImageMagick::Magick.tool("identify")), ["magick", "identify"]);
+        assert_eq!(args(ImageMagick::Legacy.tool("identify")), ["identify"]);
+        assert_eq!(args(ImageMagick::Legacy.command()), ["convert"]);
+    }
+
+    #[test]
+    fn test_ffmpeg_install_plan() {
+        let run = |args: &[&str]| FfmpegInstall::Run(args.iter().map(|a| a.to_string()).collect());
+        let only = |programs: &'static [&'static str]| move |program: &str| programs.contains(&program);
+
+        assert_eq!(ffmpeg_install_plan("macos", only(&["brew"])), run(&["brew", "install", "ffmpeg"]));
+        assert!(matches!(ffmpeg_install_plan("macos", only(&[])), FfmpegInstall::Manual(m) if m.contains("brew.sh")));
+        assert!(matches!(
+            ffmpeg_install_plan("windows", only(&["winget"])),
+            FfmpegInstall::Run(args) if args[..4] == ["winget", "install", "--id", "Gyan.FFmpeg"]
+        ));
+
+        // The first package manager found is used, through pkexec when there is one
+        assert_eq!(
+            ffmpeg_install_plan("linux", only(&["dnf", "pacman", "pkexec"])),
+            run(&["pkexec", "dnf", "install", "-y", "ffmpeg"])
+        );
+        assert_eq!(
+            ffmpeg_install_plan("linux", only(&["apt-get"])),
+            FfmpegInstall::Manual("Run: sudo apt-get install -y ffmpeg".to_string())
+        );
+        assert!(matches!(ffmpeg_install_plan("linux", only(&["pkexec"])), FfmpegInstall::Manual(m) if m.contains("static build")));
+        assert!(matches!(ffmpeg_install_plan("freebsd", only(&[])), FfmpegInstall::Manual(_)));
+    }
+
+    #[test]
+    fn test_ytdlp_install_commands() {
+        let only = |programs: &'static [&'static str]| move |program: &str| programs.contains(&program);
+        let strategies = |os, programs| {
+            ytdlp_install_commands(os, only(programs)).into_iter().map(|(strategy, _)| strategy).collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            strategies("macos", &["brew", "pip3", "pipx"]),
+            [InstallStrategy::Homebrew, InstallStrategy::Pip, InstallStrategy::Pipx]
+        );
+        assert_eq!(strategies("windows", &["winget", "brew"]), [InstallStrategy::Winget]);
+        // Package managers need pkexec for root
+        assert_eq!(strategies("linux", &["pip3", "apt-get"]), [InstallStrategy::Pip]);
+        let linux = ytdlp_install_commands("linux", only(&["pacman", "pkexec", "pipx"]));
+        assert_eq!(linux[0].1, ["pipx", "install", "yt-dlp"]);
+        assert_eq!(linux[1], (InstallStrategy::PackageManager, ["pkexec", "pacman", "-S", "--noconfirm", "yt-dlp"].map(String::from).to_vec()));
+        assert!(ytdlp_install_commands("linux", only(&[])).is_empty());
+
+        assert_eq!(ytdlp_release_asset("linux", "x86_64"), Some("yt-dlp_linux"));
+        assert_eq!(ytdlp_release_asset("macos", "aarch64"), Some("yt-dlp_macos"));
+        assert_eq!(ytdlp_release_asset("linux", "riscv64"), None);
+    }
+
+    #[test]
+    fn test_describe_install_failure() {
+        let pep668 = "error: externally-managed-environment\n\n× This environment is externally managed\n╰─> To install Python packages system-wide, try apt install\n";
+        assert!(describe_install_failure(pep668).contains("PEP 668"));
+        assert_eq!(describe_install_failure("Collecting yt-dlp\nERROR: No matching distribution\n\n"), "ERROR: No matching distribution");
+        assert_eq!(describe_install_failure(""), "exited with an error");
+    }
+
+    #[test]
+    fn test_file_uri() {
+        assert_eq!(file_uri(Path::new("/home/me/Videos/clip.mp4")), "file:///home/me/Videos/clip.mp4");
+        assert_eq!(
+            file_uri(Path::new("/home/me/My Videos/a,b #1 é.mp4")),
+            "file:///home/me/My%20Videos/a%2Cb%20%231%20%C3%A9.mp4"
+        );
+    }
+
+    #[test]
+    fn test_open_with_default_app_rejects() {
+        // Each of these fails before anything is launched
+        let missing = std::env::temp_dir().join(format!("mediaforge-open-{}.mp4", uuid::Uuid::new_v4()));
+        assert!(open_with_default_app(&missing).unwrap_err().starts_with("Not a file"));
+        assert!(open_with_default_app(&std::env::temp_dir()).unwrap_err().starts_with("Not a file"));
+        assert!(open_with_default_app(Path::new("/etc/passwd")).is_err());
     }
 }
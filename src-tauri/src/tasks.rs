@@ -0,0 +1,91 @@
+use crate::error::MediaForgeError;
+use crate::events::TaskUpdateEmitter;
+use crate::types::*;
+use dashmap::DashMap;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+/// Handle for a spawned download or conversion task, used to cancel it
+#[derive(Debug)]
+pub struct TaskHandle {
+    pub join_handle: JoinHandle<()>,
+    pub cancellation_token: CancellationToken,
+}
+
+impl TaskHandle {
+    pub fn new(join_handle: JoinHandle<()>, cancellation_token: CancellationToken) -> Self {
+        Self {
+            join_handle,
+            cancellation_token,
+        }
+    }
+
+    /// Cancel the task and wait for it to complete
+    pub async fn cancel(self) -> Result<(), tokio::task::JoinError> {
+        self.cancellation_token.cancel();
+        self.join_handle.await
+    }
+
+    /// Check if the task is cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation_token.is_cancelled()
+    }
+}
+
+/// Shows which try a task is on and, on a retry, why the last one failed. Shared by
+/// the download and conversion managers as the retry callback.
+pub fn record_attempt(
+    tasks: &DashMap<String, TaskProgress>,
+    updates: &Arc<TaskUpdateEmitter>,
+    app_handle: &tauri::AppHandle,
+    task_id: &str,
+    attempt: u32,
+    max_attempts: u32,
+    previous_error: Option<&MediaForgeError>,
+) {
+    let Some(task) = tasks.get_mut(task_id).map(|mut task| {
+        task.record_attempt(attempt, max_attempts, previous_error.map(|e| e.to_string()));
+        task.clone()
+    }) else {
+        return;
+    };
+    if previous_error.is_some() {
+        updates.emit(app_handle, Some(task));
+    }
+}
+
+/// Marks every task in `task_handles` interrupted and cancels it, which kills its
+/// child process; the tasks get up to `timeout` to wind down and finish their
+/// bookkeeping before any still running are aborted. `kind` names the tasks in the
+/// log. Returns how many tasks were interrupted.
+pub async fn interrupt_all(
+    task_handles: &DashMap<String, TaskHandle>,
+    tasks: &DashMap<String, TaskProgress>,
+    timeout: Duration,
+    kind: &str,
+) -> usize {
+    let task_ids: Vec<String> = task_handles.iter().map(|entry| entry.key().clone()).collect();
+    let mut join_handles = Vec::new();
+    for task_id in &task_ids {
+        if let Some((_, task_handle)) = task_handles.remove(task_id) {
+            if let Some(mut task) = tasks.get_mut(task_id) {
+                let previous = task.status.clone();
+                task.mark_cancelled(true);
+                task.record_status_change(&previous, unix_millis());
+            }
+            task_handle.cancellation_token.cancel();
+            join_handles.push(task_handle.join_handle);
+        }
+    }
+
+    let count = join_handles.len();
+    let abort_handles: Vec<_> = join_handles.iter().map(|handle| handle.abort_handle()).collect();
+    if tokio::time::timeout(timeout, futures::future::join_all(join_handles)).await.is_err() {
+        warn!(timeout_secs = timeout.as_secs(), "{} did not stop in time, aborting them", kind);
+        abort_handles.iter().for_each(|handle| handle.abort());
+    }
+    count
+}
@@ -8,7 +8,7 @@ pub enum DownloadType {
     Playlist,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum MediaFormat {
     #[serde(rename = "mp4")]
     Mp4,
@@ -16,8 +16,12 @@ pub enum MediaFormat {
     Mp3,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Error on tasks that were still queued or running when the app quit
+pub const INTERRUPTED_BY_EXIT: &str = "Interrupted because the app closed";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub enum TaskStatus {
+    #[default]
     Queued,
     Downloading,
     Processing,
@@ -25,6 +29,8 @@ pub enum TaskStatus {
     Completed,
     Failed,
     Cancelled,
+    /// Stopped because the app quit while the task was queued or running
+    Interrupted,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,9 +48,62 @@ pub struct DownloadRequest {
     pub audio_quality: Option<String>,
     pub download_path: String,
     pub trim: Option<TrimSettings>,
+    /// Per-task timeout in seconds; `None` keeps the default (1 hour), 0 disables it
+    pub timeout_secs: Option<u64>,
+    /// Record a live stream from its start; disables the default timeout
+    #[serde(default)]
+    pub live: bool,
+    /// Write each chapter to its own file; cannot be combined with `trim`
+    #[serde(default)]
+    pub split_chapters: bool,
+    /// Queue priority for the created tasks; higher values start first (default 0)
+    pub priority: Option<i32>,
+    /// Accept any http(s) URL yt-dlp supports instead of only YouTube URLs
+    #[serde(default)]
+    pub allow_generic: bool,
+    /// Write only the subtitle files, without downloading the media
+    #[serde(default)]
+    pub subtitles_only: bool,
+    /// Subtitle languages such as `en` or `es.*`; `None` means English
+    pub subtitle_langs: Option<Vec<String>>,
+    /// Skip files larger than this, e.g. `500M` or `1.5G`
+    pub max_filesize: Option<String>,
+    /// yt-dlp output template relative to `download_path`, e.g.
+    /// `%(uploader)s/%(title)s [%(id)s].%(ext)s`; replaces `%(title)s.<ext>`
+    pub output_template: Option<String>,
+    /// What to do when the file a download would write already exists
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy,
+    /// Merge the best video and audio into one mp4 (unset merges). Off keeps them as
+    /// two separate files, e.g. for editing.
+    pub merge: Option<bool>,
+    /// Check with ffprobe that each downloaded file is readable media with a duration,
+    /// failing the task and deleting the files when it isn't
+    #[serde(default)]
+    pub verify: bool,
+    /// Write the video's chapters into the file as chapter markers; needs mp4
+    #[serde(default)]
+    pub embed_chapters: bool,
+    /// Program and arguments run on each output once the task succeeds, never through
+    /// a shell; `{file}` in an argument is replaced by the output's path
+    pub post_process_command: Option<Vec<String>>,
+    /// Only warn when the post-process command fails instead of failing the task
+    #[serde(default)]
+    pub post_process_ignore_failure: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Handling of an output file that already exists
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum ConflictPolicy {
+    /// Keep the existing file and report the task as completed with it
+    #[default]
+    Skip,
+    Overwrite,
+    /// Write to a free name such as `Title (1).mp4`
+    Rename,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TaskProgress {
     pub task_id: String,
     pub name: String,
@@ -54,6 +113,220 @@ pub struct TaskProgress {
     pub eta: Option<String>,
     pub error: Option<String>,
     pub file_path: Option<String>,
+    /// Parsed transfer speed; `None` while the tool reports it as unknown
+    pub speed_bytes_per_sec: Option<u64>,
+    /// Parsed time remaining; `None` while the tool reports it as unknown
+    pub eta_seconds: Option<u64>,
+    /// Total size of the file being transferred (may be an estimate)
+    pub total_bytes: Option<u64>,
+    /// Bytes transferred so far, derived from the percentage and total size
+    pub downloaded_bytes: Option<u64>,
+    /// Set for live stream recordings, which have no known duration or percentage
+    pub is_live: bool,
+    /// Recording time so far for live streams
+    pub elapsed_seconds: Option<u64>,
+    /// Files written when one input produces several outputs (chapters, PDF pages)
+    pub output_files: Vec<String>,
+    /// Queue priority; higher values start first among queued tasks
+    pub priority: Option<i32>,
+    /// Non-fatal issue worth showing alongside a completed task
+    pub warning: Option<String>,
+    /// Shared by all tasks created by one download or conversion request
+    pub batch_id: String,
+    /// Expected output size, estimated before a conversion starts
+    pub estimated_bytes: Option<u64>,
+    /// Size of the input file, reported by image conversions
+    pub input_size: Option<u64>,
+    /// Size of the output file(s), reported by image conversions
+    pub output_size: Option<u64>,
+    /// Current try, starting at 1; 0 until the task first runs
+    pub attempt: u32,
+    /// Tries allowed before the task fails
+    pub max_attempts: u32,
+    /// Errors of earlier tries, oldest first, at most `ERROR_HISTORY_LIMIT`
+    pub error_history: Vec<String>,
+    /// When the task first started downloading or processing, in Unix milliseconds
+    pub started_at: Option<i64>,
+    /// When the task completed, failed or was cancelled, in Unix milliseconds
+    pub finished_at: Option<i64>,
+}
+
+/// Current time in milliseconds since the Unix epoch
+pub fn unix_millis() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// Earlier errors kept on a task; older ones are dropped
+pub const ERROR_HISTORY_LIMIT: usize = 5;
+
+impl TaskProgress {
+    /// Records the start of a try, along with the error that ended the one before it
+    pub fn record_attempt(&mut self, attempt: u32, max_attempts: u32, previous_error: Option<String>) {
+        self.attempt = attempt;
+        self.max_attempts = max_attempts;
+        if let Some(error) = previous_error {
+            if self.error_history.len() == ERROR_HISTORY_LIMIT {
+                self.error_history.remove(0);
+            }
+            self.error_history.push(error);
+        }
+    }
+
+    /// Marks a task stopped by its cancellation token: cancelled by the user, or
+    /// interrupted when the app is quitting
+    pub fn mark_cancelled(&mut self, app_quitting: bool) {
+        if app_quitting {
+            self.status = TaskStatus::Interrupted;
+            self.error = Some(INTERRUPTED_BY_EXIT.to_string());
+        } else {
+            self.status = TaskStatus::Cancelled;
+            self.error = Some("Task was cancelled by user".to_string());
+        }
+    }
+
+    /// Adds a note to the warning, after any it already has
+    pub fn append_warning(&mut self, warning: String) {
+        self.warning = Some(match self.warning.take() {
+            Some(earlier) => format!("{}; {}", earlier, warning),
+            None => warning,
+        });
+    }
+
+    /// Stamps `started_at` and `finished_at` after the status changed from `previous`.
+    /// Pausing keeps the start time; queueing a finished task again clears both.
+    pub fn record_status_change(&mut self, previous: &TaskStatus, now_millis: i64) {
+        if self.status == *previous {
+            return;
+        }
+        match self.status {
+            TaskStatus::Queued => {
+                self.started_at = None;
+                self.finished_at = None;
+            }
+            TaskStatus::Downloading | TaskStatus::Processing => {
+                self.started_at.get_or_insert(now_millis);
+                self.finished_at = None;
+            }
+            TaskStatus::Paused => {}
+            TaskStatus::Completed | TaskStatus::Failed | TaskStatus::Cancelled | TaskStatus::Interrupted => {
+                self.finished_at = Some(now_millis);
+            }
+        }
+    }
+
+    /// Whether the task is done, successfully or not
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self.status,
+            TaskStatus::Completed | TaskStatus::Failed | TaskStatus::Cancelled | TaskStatus::Interrupted
+        )
+    }
+
+    /// Time from the first start to the end, once the task has finished
+    pub fn duration_ms(&self) -> Option<u64> {
+        let (started, finished) = (self.started_at?, self.finished_at?);
+        Some(finished.saturating_sub(started).max(0) as u64)
+    }
+}
+
+/// Totals for the tasks of one request, sent with the `batch-update` event
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BatchProgress {
+    pub batch_id: String,
+    pub total: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    /// Average progress of all tasks, counting finished ones as 100
+    pub overall_progress: f32,
+}
+
+/// Totals across every download and conversion, for a status bar
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TaskStats {
+    /// Tasks waiting for a slot
+    pub queue_depth: usize,
+    pub downloading: usize,
+    pub processing: usize,
+    pub paused: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    /// Bytes transferred so far by downloading and processing tasks
+    pub active_bytes: u64,
+    /// Combined speed of the running tasks that report one, in bytes per second
+    pub total_speed_bytes_per_sec: u64,
+}
+
+/// What the app as a whole is doing, for a taskbar or launcher progress indicator
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressState {
+    /// Nothing queued or running; the indicator should be cleared
+    #[default]
+    Idle,
+    Running,
+    /// Every unfinished task is paused
+    Paused,
+}
+
+/// Overall progress of unfinished downloads and conversions, sent with `global-progress`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct GlobalProgress {
+    /// Queued, running and paused tasks
+    pub active_tasks: usize,
+    /// Average progress of the active tasks with a known percentage, weighted by size (0-100)
+    pub overall_percent: f32,
+    pub state: ProgressState,
+}
+
+/// A finished download, kept in the history after it leaves the task list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub task_id: String,
+    pub title: String,
+    pub url: String,
+    /// Downloaded file, or the first of several (chapters, subtitles)
+    #[serde(default)]
+    pub output_path: Option<String>,
+    /// `mp4`, `mp3` or `subtitles`
+    pub format: String,
+    pub status: TaskStatus,
+    /// When the task finished, in seconds since the Unix epoch
+    pub timestamp: u64,
+    /// Combined size of the files written
+    #[serde(default)]
+    pub size_bytes: Option<u64>,
+    /// How long the download took, from start to end
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+}
+
+/// Filters for `get_history`; unset fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HistoryQuery {
+    pub status: Option<TaskStatus>,
+    /// Earliest finish time, in seconds since the Unix epoch
+    pub since: Option<u64>,
+    /// Latest finish time, in seconds since the Unix epoch
+    pub until: Option<u64>,
+    /// Case-insensitive title substring
+    pub search: Option<String>,
+    #[serde(default)]
+    pub offset: usize,
+    /// Page size; defaults to 50 and is capped at 500
+    pub limit: Option<usize>,
+}
+
+/// One page of history entries, newest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryPage {
+    pub entries: Vec<HistoryEntry>,
+    /// Matching entries across all pages
+    pub total: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -61,41 +334,562 @@ pub enum ConversionType {
     Image,
     Video,
     Audio,
+    /// Each input is converted as an image, video or audio file, judged by its extension
+    Auto,
+}
+
+/// Image input extensions, lowercase. AVIF and HEIC need ImageMagick delegates and
+/// PDF needs Ghostscript; both are checked at runtime.
+pub const IMAGE_INPUT_FORMATS: [&str; 15] = [
+    "png", "jpg", "jpeg", "webp", "gif", "bmp", "tiff", "tif", "ico", "psd", "avif", "heic", "heif", "svg", "pdf",
+];
+
+/// Video input extensions, lowercase
+pub const VIDEO_INPUT_FORMATS: [&str; 16] = [
+    "mp4", "m4v", "mkv", "mov", "avi", "webm", "flv", "wmv", "mpeg", "mpg", "ogv", "ts", "mts", "m2ts", "vob", "3gp",
+];
+
+/// Audio input extensions, lowercase. Audio conversions also take the sound from
+/// any video input.
+pub const AUDIO_INPUT_FORMATS: [&str; 13] = [
+    "mp3", "wav", "flac", "ogg", "oga", "opus", "m4a", "aac", "wma", "aiff", "aif", "ape", "wv",
+];
+
+impl ConversionType {
+    /// Input extensions this type converts; `Auto` takes any of them. Other extensions
+    /// are checked with ffprobe for video and audio conversions.
+    pub fn input_formats(&self) -> Vec<&'static str> {
+        match self {
+            ConversionType::Image => IMAGE_INPUT_FORMATS.to_vec(),
+            // Animated GIFs convert to video, though `Auto` treats them as images
+            ConversionType::Video => [&VIDEO_INPUT_FORMATS[..], &["gif"]].concat(),
+            ConversionType::Audio => [&AUDIO_INPUT_FORMATS[..], &VIDEO_INPUT_FORMATS].concat(),
+            ConversionType::Auto => [&IMAGE_INPUT_FORMATS[..], &VIDEO_INPUT_FORMATS, &AUDIO_INPUT_FORMATS].concat(),
+        }
+    }
+
+    /// The type that converts files with `extension`, in any case
+    pub fn for_input_extension(extension: &str) -> Option<Self> {
+        let extension = extension.to_lowercase();
+        [
+            (ConversionType::Image, &IMAGE_INPUT_FORMATS[..]),
+            (ConversionType::Video, &VIDEO_INPUT_FORMATS),
+            (ConversionType::Audio, &AUDIO_INPUT_FORMATS),
+        ]
+        .into_iter()
+        .find(|(_, formats)| formats.contains(&extension.as_str()))
+        .map(|(conversion_type, _)| conversion_type)
+    }
+}
+
+/// Output format for each kind of input in an `Auto` conversion. Inputs of a kind
+/// without one fail.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AutoFormats {
+    pub image: Option<String>,
+    pub video: Option<String>,
+    pub audio: Option<String>,
+}
+
+/// Where converted files are written
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub enum OutputMode {
+    /// Every output goes to `output_path`
+    #[default]
+    SingleDirectory,
+    /// Each output is written next to its input file
+    AlongsideInput,
+    /// Inputs' folder structure, relative to their common parent, is recreated under `root`
+    MirrorTree { root: String },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatermarkSettings {
+    /// Watermark image file
+    pub path: PathBuf,
+    /// Placement as a compass direction: northwest, north, northeast, west, center,
+    /// east, southwest, south or southeast
+    pub position: String,
+    /// Watermark opacity (0.0-1.0)
+    pub opacity: f32,
+    /// Multiplier applied to the watermark's own size (defaults to 1.0)
+    pub scale: Option<f32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct VideoSettings {
     pub resolution: Option<String>,
     pub bitrate: Option<String>,
+    pub watermark: Option<WatermarkSettings>,
+    /// Drop container and stream metadata from the output
+    #[serde(default)]
+    pub strip_metadata: bool,
+    /// Copy the input's global metadata and chapters; off leaves both out. Unset
+    /// copies them unless `strip_metadata` is set.
+    pub preserve_metadata: Option<bool>,
+    /// Output frame rate (1-240); also the frame rate of animated GIF/WebP/AVIF output
+    pub fps: Option<f32>,
+    /// Deinterlace with yadif before any other filter
+    #[serde(default)]
+    pub deinterlace: bool,
+    /// Smooth out camera shake with vid.stab, which analyses the whole video in an
+    /// extra pass before encoding
+    #[serde(default)]
+    pub stabilize: bool,
+    /// Tone-map HDR input (PQ or HLG) to SDR so it doesn't look washed out on ordinary
+    /// displays. Unset does so unless streams are copied; SDR input is left alone.
+    pub tonemap_sdr: Option<bool>,
+    /// "keep" (default), "remove" or "replace" the audio track
+    pub audio_mode: Option<String>,
+    /// Audio file whose first audio stream replaces the original with "replace"
+    pub replacement_audio: Option<PathBuf>,
+    /// End the output with the shorter of the video and the replacement audio
+    #[serde(default)]
+    pub shortest: bool,
+    /// Aim for an output of about this many megabytes (10^6 bytes) with a two-pass
+    /// encode, e.g. to fit an attachment limit. Sets the bitrate itself.
+    pub target_size_mb: Option<u32>,
+    /// Audio bitrate in kbps set aside from `target_size_mb` (default 128)
+    pub target_audio_kbps: Option<u32>,
+    /// Frames between keyframes (`-g`), e.g. two seconds' worth for streaming
+    pub keyframe_interval: Option<u32>,
+    /// Video codec to encode with: "h264" (the default) or "h265"
+    pub codec: Option<String>,
+    /// Profile of the codec: for H.264 "baseline", "main", "high", "high10", "high422"
+    /// or "high444"; for H.265 "main", "main10", "main422-10", "main444-8" or "main444-10"
+    pub profile: Option<String>,
+    /// Level of the codec such as "3.0" or "4.2"
+    pub level: Option<String>,
+    /// Keep `bitrate` and `resolution` within the source's instead of upscaling
+    #[serde(default)]
+    pub prevent_upscale: bool,
+    /// Remux into the new container without re-encoding. Files whose codecs the
+    /// container can't hold are re-encoded instead.
+    #[serde(default)]
+    pub copy_streams: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AudioSettings {
     pub bitrate: Option<String>,
     pub sample_rate: Option<String>,
+    /// Output channel count: 1 (mono) or 2 (stereo)
+    pub channels: Option<u8>,
+    /// Volume multiplier (0.1-10.0, 1.0 leaves the volume unchanged)
+    pub volume: Option<f32>,
+    /// Tags written to the output file
+    pub metadata: Option<AudioMetadata>,
+    /// Image embedded as album art (mp3, m4a and flac outputs only)
+    pub cover_art: Option<PathBuf>,
+    /// Sample bit depth for lossless outputs (wav and flac): 16 or 24
+    pub bit_depth: Option<u8>,
+    /// FLAC compression level (0-12)
+    pub compression_level: Option<u8>,
+    /// Drop tags carried over from the input (explicit `metadata` is still written)
+    #[serde(default)]
+    pub strip_metadata: bool,
+    /// Fade-in length from the start of the clip, in seconds
+    pub fade_in_secs: Option<f32>,
+    /// Fade-out length ending at the end of the clip, in seconds
+    pub fade_out_secs: Option<f32>,
+    /// Cut silence from the start and end; fades apply to the trimmed clip
+    #[serde(default)]
+    pub trim_silence: bool,
+    /// Keep `bitrate` within the source's instead of spending bits on nothing
+    #[serde(default)]
+    pub prevent_upscale: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AudioMetadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub year: Option<String>,
+    pub track: Option<String>,
+    pub genre: Option<String>,
+}
+
+impl AudioMetadata {
+    /// Returns the set tags as ffmpeg metadata key/value pairs
+    pub fn tags(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("title", &self.title),
+            ("artist", &self.artist),
+            ("album", &self.album),
+            ("date", &self.year),
+            ("track", &self.track),
+            ("genre", &self.genre),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| value.as_deref().map(|v| (key, v)))
+        .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ImageSettings {
     pub quality: Option<u32>,
     pub resize: Option<String>,
+    /// Crop region as ImageMagick geometry, e.g. "800x600+10+20"
+    pub crop: Option<String>,
+    /// Clockwise rotation in degrees (-360 to 360)
+    pub rotate: Option<i32>,
+    /// Mirror direction: "horizontal" or "vertical"
+    pub flip: Option<String>,
+    pub watermark: Option<WatermarkSettings>,
+    /// Drop EXIF, GPS and other profile data from the output
+    #[serde(default)]
+    pub strip_metadata: bool,
+    /// AVIF encoder effort (0-9, higher is slower but smaller)
+    pub effort: Option<u8>,
+    /// Rasterization density in DPI for vector inputs such as SVG (1-1200).
+    /// Combine with `resize` to hit an exact pixel size.
+    pub density: Option<u32>,
+    /// Keep animation for animated inputs (default) or, when false, convert
+    /// only the first frame
+    pub animation: Option<bool>,
+    /// Color effects: "grayscale", "sepia", "negate", "auto-level", "sharpen" and
+    /// "blur:<radius>". They are applied in that order whatever order they are listed in.
+    pub effects: Option<Vec<String>>,
+    /// Color transparent areas are flattened onto for outputs without alpha (JPEG),
+    /// as `#rgb` or `#rrggbb`. Defaults to white; ignored for outputs with alpha.
+    pub background: Option<String>,
+    /// Resolution written to the output's metadata in DPI (1-2400), e.g. 300 for
+    /// print. Pixels are left alone; unlike `density` this doesn't affect rasterization.
+    pub dpi: Option<u32>,
+    /// Shrink PNG, JPEG and WebP outputs with format-specific settings and, for JPEG
+    /// and WebP, a default quality; drops metadata for PNG and JPEG. Other formats are
+    /// converted as usual with a warning on the task.
+    #[serde(default)]
+    pub optimize: bool,
+    /// Convert the pixels to "sRGB", "CMYK" or "Gray". The input's embedded ICC
+    /// profile no longer matches afterwards, so it is dropped.
+    pub colorspace: Option<String>,
+    /// Drop the embedded ICC color profile but keep other metadata
+    #[serde(default)]
+    pub strip_icc: bool,
+    /// Keep the embedded ICC color profile when `strip_metadata` or `optimize`
+    /// drops the rest of the metadata
+    #[serde(default)]
+    pub keep_icc: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConvertRequest {
+    #[serde(default)]
     pub input_files: Vec<PathBuf>,
     pub conversion_type: ConversionType,
+    /// May be left empty when `preset` supplies the format
+    #[serde(default)]
     pub output_format: String,
     pub output_path: String,
+    /// Where outputs go; `output_path` is only used by `SingleDirectory`
+    #[serde(default)]
+    pub output_mode: OutputMode,
+    pub video_settings: Option<VideoSettings>,
+    pub audio_settings: Option<AudioSettings>,
+    pub image_settings: Option<ImageSettings>,
+    /// Per-task timeout in seconds; `None` keeps the default (2 hours), 0 disables it
+    pub timeout_secs: Option<u64>,
+    /// Output file name, e.g. `{stem}_1080p.{ext}`. Placeholders: `{stem}` input name
+    /// without extension, `{ext}` output extension, `{format}` input extension, `{date}`
+    /// as YYYY-MM-DD, `{counter}` the file's position in the batch and `{index}` the
+    /// same zero-padded to the batch size. The output extension is appended when `{ext}`
+    /// is absent.
+    pub filename_template: Option<String>,
+    /// What to do when the output file already exists; unset overwrites it. An output
+    /// that would replace its own input is refused unless renamed or skipped.
+    pub conflict_policy: Option<ConflictPolicy>,
+    /// Check that the output decodes (and for media, runs as long as the input)
+    /// after converting; defaults to on for video and audio, off for images
+    pub verify_output: Option<bool>,
+    /// Saved preset whose settings fill in whatever this request leaves unset
+    pub preset: Option<String>,
+    /// Output formats used instead of `output_format` by `Auto` conversions
+    #[serde(default)]
+    pub auto_formats: Option<AutoFormats>,
+    /// Delete the input once it converted successfully and the output is on disk.
+    /// Sources are kept when the output already existed or is the input itself.
+    #[serde(default)]
+    pub delete_source_on_success: bool,
+    /// Program and arguments run on each output once the task succeeds, never through
+    /// a shell; `{file}` in an argument is replaced by the output's path
+    pub post_process_command: Option<Vec<String>>,
+    /// Only warn when the post-process command fails instead of failing the task
+    #[serde(default)]
+    pub post_process_ignore_failure: bool,
+}
+
+/// Conversion settings stored in a preset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresetSettings {
+    pub conversion_type: ConversionType,
+    /// Used when the request leaves `output_format` empty
+    pub output_format: Option<String>,
     pub video_settings: Option<VideoSettings>,
     pub audio_settings: Option<AudioSettings>,
     pub image_settings: Option<ImageSettings>,
 }
 
+/// Named conversion settings saved in the app data folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversionPreset {
+    pub name: String,
+    #[serde(flatten)]
+    pub settings: PresetSettings,
+}
+
+/// Converts every matching file under a folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConvertFolderRequest {
+    pub folder: String,
+    /// Extensions (`"mp4"`) or file name patterns (`"clip_*.mov"`); empty matches every file
+    #[serde(default)]
+    pub filter: Vec<String>,
+    /// Levels of subfolders searched below `folder` (default 8, maximum 32)
+    pub max_depth: Option<u32>,
+    /// List the matching files without starting any conversions
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Settings applied to every match; its `input_files` are replaced by the matches
+    pub conversion: ConvertRequest,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConvertFolderResult {
+    pub matched_files: Vec<PathBuf>,
+    /// Empty for a dry run
+    pub task_ids: Vec<String>,
+}
+
+/// Whether one input file can be converted, from `validate_files`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileValidation {
+    pub path: PathBuf,
+    /// Why the file would be skipped; `None` when it can be converted
+    pub reason: Option<String>,
+    /// The kind of media its extension says it is
+    pub detected_type: Option<ConversionType>,
+    pub size_bytes: Option<u64>,
+}
+
+/// Estimated output size of one input file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileEstimate {
+    pub input_file: PathBuf,
+    pub estimated_bytes: u64,
+}
+
+/// Estimated output sizes of a conversion and whether the output disk can hold them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversionEstimate {
+    pub files: Vec<FileEstimate>,
+    pub total_bytes: u64,
+    /// Set when the output location lacks the space for the estimated total
+    pub disk_space_error: Option<String>,
+}
+
+/// What `extract_streams` writes out of a video container
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ExtractTarget {
+    /// The Nth subtitle track (`0:s:N`), written as SRT
+    Subtitle(u32),
+    /// All chapters, written as an FFmpeg metadata file
+    Chapters,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractStreamsRequest {
+    pub input_file: String,
+    /// Unset only lists the subtitle tracks and chapters
+    pub target: Option<ExtractTarget>,
+    /// Folder for the extracted file; defaults to the input's folder
+    pub output_dir: Option<String>,
+}
+
+/// An embedded subtitle track
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SubtitleTrack {
+    /// Position among the subtitle tracks, as used by `ExtractTarget::Subtitle`
+    pub index: u32,
+    /// Position among all streams of the file
+    pub stream_index: u32,
+    pub codec_name: Option<String>,
+    pub language: Option<String>,
+    pub title: Option<String>,
+    pub forced: bool,
+    /// Image-based tracks (PGS, VobSub) can't be written as SRT
+    pub text: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Chapter {
+    pub start_secs: f64,
+    pub end_secs: f64,
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExtractStreamsResult {
+    pub subtitles: Vec<SubtitleTrack>,
+    pub chapters: Vec<Chapter>,
+    /// The file written for the request's target
+    pub output_path: Option<PathBuf>,
+}
+
+/// Media file details reported by ffprobe
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MediaProbe {
+    /// Container format names, e.g. "mov,mp4,m4a,3gp,3g2,mj2"
+    pub container: String,
+    pub duration_secs: Option<f64>,
+    pub size_bytes: Option<u64>,
+    /// Overall bitrate in bits per second
+    pub bit_rate: Option<u64>,
+    pub streams: Vec<StreamProbe>,
+    #[serde(default)]
+    pub chapters: Vec<Chapter>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StreamProbe {
+    pub index: u32,
+    /// "video", "audio", "subtitle", "data" or "attachment"
+    pub codec_type: String,
+    pub codec_name: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<f64>,
+    /// "progressive", or "tt", "bb", "tb" or "bt" for interlaced video; unset when
+    /// the container doesn't say
+    pub field_order: Option<String>,
+    /// Set from `field_order`; such video is worth converting with `deinterlace`
+    #[serde(default)]
+    pub interlaced: bool,
+    /// Transfer characteristics, e.g. "bt709", or "smpte2084" (PQ) and "arib-std-b67"
+    /// (HLG) for HDR
+    pub color_transfer: Option<String>,
+    /// Set from `color_transfer`; converted to SDR unless `tonemap_sdr` is off
+    #[serde(default)]
+    pub hdr: bool,
+    /// Bits per second
+    pub bit_rate: Option<u64>,
+    pub channels: Option<u32>,
+    pub sample_rate: Option<u32>,
+}
+
+/// How a dependency was installed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum InstallStrategy {
+    Pip,
+    Pipx,
+    PackageManager,
+    Homebrew,
+    Winget,
+    /// Downloaded into the app's data folder and run from there
+    StandaloneBinary,
+}
+
+/// What `install_ytdlp` did
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YtdlpInstallResult {
+    pub strategy: InstallStrategy,
+    /// As `yt-dlp --version` reports it, e.g. "2025.01.15"
+    pub version: String,
+    pub path: Option<String>,
+}
+
+/// One line printed by a dependency installer, sent as an `install-progress` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallProgress {
+    pub dependency: String,
+    pub line: String,
+}
+
+/// What `copy_to_clipboard` puts on the clipboard
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardMode {
+    /// The absolute path, as text
+    Path,
+    /// The file itself, for pasting into a file manager
+    Uri,
+}
+
+/// A task failed because a program it runs isn't installed, sent as a
+/// `dependency-missing` event so the user can be offered a reinstall
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DependencyMissing {
+    pub dependency: String,
+    pub task_id: String,
+}
+
+/// Payload of `task-removed`, sent when tasks leave a manager's list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskRemoved {
+    pub task_ids: Vec<String>,
+}
+
+/// Payload of `task-failed`, sent once when a task ends in failure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskFailed {
+    pub task_id: String,
+    /// The message also shown on the task
+    pub error: String,
+    /// `MediaForgeError` variant the failure was classified as, e.g. "NetworkError"
+    pub kind: String,
+    /// Whether running the task again may succeed
+    pub retryable: bool,
+}
+
+/// What a dependency install did
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallResult {
+    /// Whether the installer ran and succeeded
+    pub installed: bool,
+    /// The installer that was run, when one was
+    pub command: Option<String>,
+    /// Why nothing was installed, including what to run by hand, or a note on a
+    /// successful install
+    pub message: Option<String>,
+    /// The dependencies found afterwards
+    pub system_info: SystemInfo,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemInfo {
     pub has_ytdlp: bool,
     pub has_ffmpeg: bool,
+    /// `magick` (ImageMagick 7) or, outside Windows, `convert` (ImageMagick 6)
+    pub has_imagemagick: bool,
     pub ytdlp_path: Option<String>,
     pub ffmpeg_path: Option<String>,
+    pub imagemagick_path: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_formats() {
+        assert_eq!(ConversionType::for_input_extension("MTS"), Some(ConversionType::Video));
+        assert_eq!(ConversionType::for_input_extension("vob"), Some(ConversionType::Video));
+        assert_eq!(ConversionType::for_input_extension("opus"), Some(ConversionType::Audio));
+        assert_eq!(ConversionType::for_input_extension("heic"), Some(ConversionType::Image));
+        assert_eq!(ConversionType::for_input_extension("gif"), Some(ConversionType::Image));
+        assert_eq!(ConversionType::for_input_extension("sh"), None);
+        assert!(ConversionType::Video.input_formats().contains(&"gif"));
+        assert!(ConversionType::Audio.input_formats().contains(&"mkv"));
+        assert_eq!(ConversionType::for_input_extension(""), None);
+
+        // Every extension belongs to exactly one type, so `Auto` is never ambiguous
+        let all = ConversionType::Auto.input_formats();
+        assert_eq!(all.len(), IMAGE_INPUT_FORMATS.len() + VIDEO_INPUT_FORMATS.len() + AUDIO_INPUT_FORMATS.len());
+        for (i, format) in all.iter().enumerate() {
+            assert!(!all[i + 1..].contains(format), "{} is listed twice", format);
+            assert_eq!(format.to_lowercase(), *format);
+        }
+    }
 }
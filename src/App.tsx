@@ -1,4 +1,6 @@
-import { useState } from 'react';
+import { useEffect, useState } from 'react';
+import { getCurrentWindow } from '@tauri-apps/api/window';
+import { ask } from '@tauri-apps/plugin-dialog';
 import Header from './components/Header';
 import DownloadSection from './components/DownloadSection';
 import ConvertSection from './components/ConvertSection';
@@ -6,6 +8,7 @@ import DownloadQueue from './components/DownloadQueue';
 import SplashScreen from './components/SplashScreen';
 import ErrorBoundary from './components/ErrorBoundary';
 import { ToastProvider } from './contexts/ToastContext';
+import { TauriAPI } from './api/tauri';
 
 type ActiveSection = 'download' | 'convert';
 
@@ -13,6 +16,23 @@ function App() {
   const [activeSection, setActiveSection] = useState<ActiveSection>('download');
   const [showSplash, setShowSplash] = useState(true);
 
+  // Running tasks are interrupted on quit, so confirm first
+  useEffect(() => {
+    const unlisten = getCurrentWindow().onCloseRequested(async (event) => {
+      const active = await TauriAPI.getActiveTaskCount().catch(() => 0);
+      if (active === 0) return;
+      const label = active === 1 ? 'task is' : 'tasks are';
+      const quit = await ask(`${active} ${label} still in progress and will be interrupted. Quit anyway?`, {
+        title: 'Quit MediaForge',
+        kind: 'warning',
+      });
+      if (!quit) event.preventDefault();
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
   if (showSplash) {
     return <SplashScreen onComplete={() => setShowSplash(false)} minDuration={2500} />;
   }
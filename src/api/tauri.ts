@@ -5,7 +5,30 @@ import type {
   DownloadRequest,
   TaskProgress,
   ConvertRequest,
+  ConversionType,
+  ConvertFolderRequest,
+  ConvertFolderResult,
+  MediaProbe,
+  ExtractStreamsRequest,
+  ExtractStreamsResult,
+  ConversionEstimate,
+  ConversionPreset,
+  PresetSettings,
+  BatchProgress,
+  TaskStats,
+  GlobalProgress,
+  HistoryQuery,
+  HistoryPage,
+  Config,
   SystemInfo,
+  InstallProgress,
+  InstallResult,
+  DependencyMissing,
+  TaskRemoved,
+  TaskFailed,
+  YtdlpInstallResult,
+  ClipboardMode,
+  FileValidation,
 } from '../types/tauri';
 
 export class TauriAPI {
@@ -14,8 +37,32 @@ export class TauriAPI {
     return invoke<SystemInfo>('check_dependencies');
   }
 
-  static async installYtdlp(): Promise<string> {
-    return invoke<string>('install_ytdlp_command');
+  /** Looks for the dependencies again instead of using the cached check */
+  static async refreshDependencies(): Promise<SystemInfo> {
+    return invoke<SystemInfo>('refresh_dependencies');
+  }
+
+  /** Tries brew or winget, pip, pipx, the Linux package manager, then the standalone binary */
+  static async installYtdlp(): Promise<YtdlpInstallResult> {
+    return invoke<YtdlpInstallResult>('install_ytdlp_command');
+  }
+
+  /** Installs ffmpeg with brew, winget or the Linux package manager; output arrives through onInstallProgress */
+  static async installFfmpeg(): Promise<InstallResult> {
+    return invoke<InstallResult>('install_ffmpeg');
+  }
+
+  static onInstallProgress(callback: (progress: InstallProgress) => void) {
+    return listen<InstallProgress>('install-progress', (event) => {
+      callback(event.payload);
+    });
+  }
+
+  /** Fires when a task fails because yt-dlp, ffmpeg, ffprobe or ImageMagick isn't installed */
+  static onDependencyMissing(callback: (missing: DependencyMissing) => void) {
+    return listen<DependencyMissing>('dependency-missing', (event) => {
+      callback(event.payload);
+    });
   }
 
   // Download commands
@@ -39,15 +86,91 @@ export class TauriAPI {
     return invoke<void>('cancel_download', { taskId });
   }
 
+  static async setTaskPriority(taskId: string, priority: number): Promise<void> {
+    return invoke<void>('set_task_priority', { taskId, priority });
+  }
+
   static async removeTask(taskId: string): Promise<void> {
     return invoke<void>('remove_task', { taskId });
   }
 
+  /** Removes every completed, failed and cancelled task; returns how many were removed */
+  static async clearFinishedTasks(): Promise<number> {
+    return invoke<number>('clear_finished_tasks');
+  }
+
+  static async getHistory(query: HistoryQuery = {}): Promise<HistoryPage> {
+    return invoke<HistoryPage>('get_history', { query });
+  }
+
+  // Settings
+  static async getConfig(): Promise<Config> {
+    return invoke<Config>('get_config');
+  }
+
+  static async setConfig(config: Config): Promise<void> {
+    return invoke<void>('set_config', { config });
+  }
+
+  /** Fires in every window when the settings are saved */
+  static onSettingsChanged(callback: (config: Config) => void) {
+    return listen<Config>('settings-changed', (event) => {
+      callback(event.payload);
+    });
+  }
+
   // Conversion commands
   static async startConversion(request: ConvertRequest): Promise<string[]> {
     return invoke<string[]>('start_conversion', { request });
   }
 
+  /** Converts matching files under a folder; a dry run only lists the matches */
+  static async convertFolder(request: ConvertFolderRequest): Promise<ConvertFolderResult> {
+    return invoke<ConvertFolderResult>('convert_folder', { request });
+  }
+
+  /** Input extensions a conversion type accepts, lowercase; 'Auto' takes all of them */
+  static async listSupportedInputFormats(conversionType: ConversionType): Promise<string[]> {
+    return invoke<string[]>('list_supported_input_formats', { conversionType });
+  }
+
+  /** Checks files before converting them, reporting why any would be skipped */
+  static async validateFiles(paths: string[], conversionType: ConversionType): Promise<FileValidation[]> {
+    return invoke<FileValidation[]>('validate_files', { paths, conversionType });
+  }
+
+  /** Reads container, duration and stream details of a local media file */
+  static async probeMedia(path: string): Promise<MediaProbe> {
+    return invoke<MediaProbe>('probe_media', { path });
+  }
+
+  /** Lists a video's subtitle tracks and chapters, writing one out when a target is given */
+  static async extractStreams(request: ExtractStreamsRequest): Promise<ExtractStreamsResult> {
+    return invoke<ExtractStreamsResult>('extract_streams', { request });
+  }
+
+  /** Returns [input path, estimated output bytes] for each input file */
+  static async estimateOutputSize(request: ConvertRequest): Promise<[string, number][]> {
+    return invoke<[string, number][]>('estimate_output_size', { request });
+  }
+
+  /** Estimates each input's output size and whether the output disk can hold them */
+  static async estimateConversion(request: ConvertRequest): Promise<ConversionEstimate> {
+    return invoke<ConversionEstimate>('estimate_conversion', { request });
+  }
+
+  static async savePreset(name: string, settings: PresetSettings): Promise<void> {
+    return invoke<void>('save_preset', { name, settings });
+  }
+
+  static async listPresets(): Promise<ConversionPreset[]> {
+    return invoke<ConversionPreset[]>('list_presets');
+  }
+
+  static async deletePreset(name: string): Promise<void> {
+    return invoke<void>('delete_preset', { name });
+  }
+
   static async getConversionTasks(): Promise<TaskProgress[]> {
     return invoke<TaskProgress[]>('get_conversion_tasks');
   }
@@ -61,6 +184,31 @@ export class TauriAPI {
     return invoke<void>('open_folder', { path });
   }
 
+  /** Shows a file selected in Finder, Explorer or the Linux file manager */
+  static async revealFile(path: string): Promise<void> {
+    return invoke<void>('reveal_file', { path });
+  }
+
+  /** Opens a file with its default application, e.g. plays a finished video */
+  static async openFile(path: string): Promise<void> {
+    return invoke<void>('open_file', { path });
+  }
+
+  /** Copies a finished task's file to the clipboard, as a path or as a file */
+  static async copyToClipboard(taskId: string, mode: ClipboardMode): Promise<void> {
+    return invoke<void>('copy_to_clipboard', { taskId, mode });
+  }
+
+  // Logs
+  static async getLogDirectory(): Promise<string> {
+    return invoke<string>('get_log_directory');
+  }
+
+  /** Zips the last `days` days of logs (default 7, at most 14) for a bug report; returns the zip's path */
+  static async exportLogs(destination: string, days?: number): Promise<string> {
+    return invoke<string>('export_logs', { destination, days });
+  }
+
   // File dialog
   static async selectDirectory(): Promise<string | null> {
     try {
@@ -77,6 +225,11 @@ export class TauriAPI {
     }
   }
 
+  /** Checks a chosen output folder as downloads and conversions will, returning the path they'll use */
+  static async validateOutputDirectory(path: string): Promise<string> {
+    return invoke<string>('validate_output_directory', { path });
+  }
+
   static async selectFiles(filters?: { name: string; extensions: string[] }[]): Promise<string[] | null> {
     try {
       const selected = await open({
@@ -106,4 +259,54 @@ export class TauriAPI {
       callback(event.payload);
     });
   }
+
+  static onTaskCreated(callback: (task: TaskProgress) => void) {
+    return listen<TaskProgress>('task-created', (event) => {
+      callback(event.payload);
+    });
+  }
+
+  static onTaskRemoved(callback: (removed: TaskRemoved) => void) {
+    return listen<TaskRemoved>('task-removed', (event) => {
+      callback(event.payload);
+    });
+  }
+
+  static onTaskFailed(callback: (failed: TaskFailed) => void) {
+    return listen<TaskFailed>('task-failed', (event) => {
+      callback(event.payload);
+    });
+  }
+
+  static async getBatch(batchId: string): Promise<BatchProgress | null> {
+    return invoke<BatchProgress | null>('get_batch', { batchId });
+  }
+
+  /** Task counts and combined throughput across downloads and conversions */
+  static async getStats(): Promise<TaskStats> {
+    return invoke<TaskStats>('get_stats');
+  }
+
+  /** Downloads and conversions that haven't finished, to confirm before quitting */
+  static async getActiveTaskCount(): Promise<number> {
+    return invoke<number>('get_active_task_count');
+  }
+
+  static async getGlobalProgress(): Promise<GlobalProgress> {
+    return invoke<GlobalProgress>('get_global_progress');
+  }
+
+  /** Sent a few times a second while progress changes, and once more when everything is done */
+  static onGlobalProgress(callback: (progress: GlobalProgress) => void) {
+    return listen<GlobalProgress>('global-progress', (event) => {
+      callback(event.payload);
+    });
+  }
+
+  /** Fires with updated totals whenever a task of a batch changes state */
+  static onBatchUpdate(callback: (batch: BatchProgress) => void) {
+    return listen<BatchProgress>('batch-update', (event) => {
+      callback(event.payload);
+    });
+  }
 }
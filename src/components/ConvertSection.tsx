@@ -1,7 +1,6 @@
-import { useState } from 'react';
-import { Upload, Image, Film, Headphones, FileType, FolderOpen } from 'lucide-react';
+import { useEffect, useState } from 'react';
+import { Upload, Image, Film, Headphones, FileType, FolderOpen, Trash2 } from 'lucide-react';
 import { TauriAPI } from '../api/tauri';
-import type { ConversionType } from '../types/tauri';
 import { useToastContext } from '../contexts/ToastContext';
 import { validateFileSize, validateOutputPath } from '../utils/validation';
 
@@ -9,11 +8,13 @@ type MediaType = 'Image' | 'Video' | 'Audio';
 
 function ConvertSection() {
   const { warning, success, error } = useToastContext();
-  const [mediaType, setMediaType] = useState<ConversionType>('Video');
+  const [mediaType, setMediaType] = useState<MediaType>('Video');
   const [outputFormat, setOutputFormat] = useState('mp4');
   const [outputPath, setOutputPath] = useState('~/Downloads');
+  const [outputMode, setOutputMode] = useState<'SingleDirectory' | 'AlongsideInput' | 'MirrorTree'>('SingleDirectory');
   const [selectedFiles, setSelectedFiles] = useState<string[]>([]);
   const [isConverting, setIsConverting] = useState(false);
+  const [deleteSources, setDeleteSources] = useState(false);
   
   // Image settings
   const [imageQuality, setImageQuality] = useState(85);
@@ -29,22 +30,37 @@ function ConvertSection() {
   const [audioBitrate, setAudioBitrate] = useState('192');
   const [audioSampleRate, setAudioSampleRate] = useState('44100');
 
+  // Start from the saved default output folder, and follow it when it changes
+  useEffect(() => {
+    TauriAPI.getConfig()
+      .then(config => setOutputPath(config.conversion_output_path))
+      .catch(err => console.error('Failed to load settings:', err));
+    const unsubscribe = TauriAPI.onSettingsChanged(config => setOutputPath(config.conversion_output_path));
+    return () => {
+      unsubscribe.then(unsub => unsub());
+    };
+  }, []);
+
   const formatOptions = {
-    Image: ['png', 'jpg', 'jpeg', 'webp', 'gif', 'bmp', 'tiff', 'ico', 'svg'],
-    Video: ['mp4', 'avi', 'mov', 'mkv', 'webm', 'flv', 'wmv', 'mpeg', 'ogv'],
+    Image: ['png', 'jpg', 'jpeg', 'webp', 'gif', 'apng', 'bmp', 'tiff', 'ico', 'svg', 'avif', 'heic', 'pdf'],
+    Video: ['mp4', 'avi', 'mov', 'mkv', 'webm', 'flv', 'wmv', 'mpeg', 'ogv', 'gif', 'webp', 'avif'],
     Audio: ['mp3', 'wav', 'ogg', 'flac', 'aac', 'm4a', 'wma', 'opus'],
   };
 
   const handleSelectFiles = async () => {
-    const filters = mediaType === 'Image' 
-      ? [{ name: 'Images', extensions: ['png', 'jpg', 'jpeg', 'webp', 'gif', 'bmp', 'tiff'] }]
-      : mediaType === 'Video'
-      ? [{ name: 'Videos', extensions: ['mp4', 'avi', 'mov', 'mkv', 'webm', 'flv', 'wmv'] }]
-      : [{ name: 'Audio', extensions: ['mp3', 'wav', 'ogg', 'flac', 'aac', 'm4a', 'wma'] }];
+    const names = { Image: 'Images', Video: 'Videos', Audio: 'Audio' };
+    const extensions = await TauriAPI.listSupportedInputFormats(mediaType);
+    const filters = [{ name: names[mediaType], extensions }];
 
     const selected = await TauriAPI.selectFiles(filters);
     if (selected) {
-      setSelectedFiles(selected);
+      // Leave out files the conversion would skip, and say why
+      const results = await TauriAPI.validateFiles(selected, mediaType);
+      const skipped = results.filter(result => result.reason);
+      if (skipped.length > 0) {
+        warning(`Skipping ${skipped.length} file(s)`, skipped[0].reason!);
+      }
+      setSelectedFiles(results.filter(result => !result.reason).map(result => result.path));
     }
   };
 
@@ -82,10 +98,11 @@ function ConvertSection() {
     try {
       const selected = await TauriAPI.selectDirectory();
       if (selected) {
-        setOutputPath(selected);
+        setOutputPath(await TauriAPI.validateOutputDirectory(selected));
       }
-    } catch (error) {
-      console.error('Failed to open directory selector:', error);
+    } catch (err) {
+      console.error('Failed to select directory:', err);
+      error('Folder Not Usable', String(err));
     }
   };
 
@@ -95,9 +112,9 @@ function ConvertSection() {
       return;
     }
 
-    // Validate output path
+    // Validate output path (not used when saving next to the source files)
     const pathValidation = validateOutputPath(outputPath);
-    if (!pathValidation.isValid) {
+    if (outputMode !== 'AlongsideInput' && !pathValidation.isValid) {
       warning('Invalid Output Path', pathValidation.message || 'Please select a valid output directory');
       return;
     }
@@ -126,6 +143,8 @@ function ConvertSection() {
         conversion_type: mediaType,
         output_format: outputFormat,
         output_path: outputPath,
+        output_mode: outputMode === 'MirrorTree' ? { MirrorTree: { root: outputPath } } : outputMode,
+        delete_source_on_success: deleteSources,
         video_settings: mediaType === 'Video' ? {
           resolution: videoResolution !== 'Keep Original' ? videoResolution : undefined,
           bitrate: videoBitrate !== 'Keep Original' ? videoBitrate : undefined,
@@ -300,6 +319,29 @@ function ConvertSection() {
                 <FolderOpen className="w-4 h-4 sm:w-5 sm:h-5" />
               </button>
             </div>
+            <select
+              value={outputMode}
+              onChange={(e) => setOutputMode(e.target.value as typeof outputMode)}
+              className="w-full mt-2 px-2 sm:px-4 py-1.5 sm:py-2 text-xs sm:text-sm bg-slate-900/50 border border-slate-600 rounded-lg focus:outline-none focus:border-cyan-500 focus:ring-2 focus:ring-cyan-500/20 transition-all"
+            >
+              <option value="SingleDirectory">All files in this folder</option>
+              <option value="AlongsideInput">Same folder as source</option>
+              <option value="MirrorTree">Recreate source folders in this folder</option>
+            </select>
+            <label className="flex items-center gap-2 sm:gap-3 cursor-pointer group mt-3">
+              <input
+                type="checkbox"
+                checked={deleteSources}
+                onChange={(e) => setDeleteSources(e.target.checked)}
+                className="w-4 h-4 sm:w-5 sm:h-5 rounded border-slate-600 bg-slate-900/50 text-cyan-500 focus:ring-2 focus:ring-cyan-500/20"
+              />
+              <div className="flex items-center gap-1 sm:gap-2">
+                <Trash2 className="w-3.5 h-3.5 sm:w-4 sm:h-4 text-slate-400 group-hover:text-red-400 transition-colors" />
+                <span className="text-xs sm:text-sm font-medium text-slate-300 group-hover:text-slate-100 transition-colors">
+                  Delete originals after converting
+                </span>
+              </div>
+            </label>
           </div>
         </div>
 
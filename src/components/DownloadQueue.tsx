@@ -1,5 +1,5 @@
 import { useState, useEffect } from 'react';
-import { ChevronRight, ChevronLeft, Pause, X, CheckCircle, AlertCircle, Loader2, FolderOpen } from 'lucide-react';
+import { ChevronRight, ChevronLeft, Pause, X, CheckCircle, AlertCircle, Loader2, FolderOpen, ArrowUp, Play, Copy } from 'lucide-react';
 import { TauriAPI } from '../api/tauri';
 import type { TaskProgress, TaskStatus } from '../types/tauri';
 
@@ -14,6 +14,26 @@ function DownloadQueue() {
     return parts[parts.length - 1] || path;
   };
 
+  // Live recordings have no percentage, so show recorded size and elapsed time
+  const formatBytes = (bytes: number): string => {
+    const units = ['B', 'KiB', 'MiB', 'GiB'];
+    let value = bytes;
+    let unit = 0;
+    while (value >= 1024 && unit < units.length - 1) {
+      value /= 1024;
+      unit++;
+    }
+    return `${value.toFixed(unit === 0 ? 0 : 1)} ${units[unit]}`;
+  };
+
+  const formatElapsed = (seconds: number): string => {
+    const h = Math.floor(seconds / 3600);
+    const m = Math.floor((seconds % 3600) / 60);
+    const s = seconds % 60;
+    const pad = (n: number) => n.toString().padStart(2, '0');
+    return h > 0 ? `${h}:${pad(m)}:${pad(s)}` : `${pad(m)}:${pad(s)}`;
+  };
+
   useEffect(() => {
     // Load initial tasks
     const loadTasks = async () => {
@@ -28,6 +48,16 @@ function DownloadQueue() {
 
     loadTasks();
 
+    const unsubscribeCreated = TauriAPI.onTaskCreated((createdTask) => {
+      setTasks((prevTasks) =>
+        prevTasks.some(t => t.task_id === createdTask.task_id) ? prevTasks : [...prevTasks, createdTask]
+      );
+    });
+
+    const unsubscribeRemoved = TauriAPI.onTaskRemoved(({ task_ids }) => {
+      setTasks((prevTasks) => prevTasks.filter(t => !task_ids.includes(t.task_id)));
+    });
+
     // Subscribe to task updates
     const unsubscribe = TauriAPI.onTaskUpdate((updatedTask) => {
       setTasks((prevTasks) => {
@@ -44,6 +74,8 @@ function DownloadQueue() {
 
     return () => {
       unsubscribe.then(unsub => unsub());
+      unsubscribeCreated.then(unsub => unsub());
+      unsubscribeRemoved.then(unsub => unsub());
     };
   }, []);
 
@@ -63,6 +95,16 @@ function DownloadQueue() {
     }
   };
 
+  // Move a queued task ahead of everything else waiting
+  const handleBump = async (taskId: string) => {
+    const highest = Math.max(0, ...tasks.map(t => t.priority ?? 0));
+    try {
+      await TauriAPI.setTaskPriority(taskId, highest + 1);
+    } catch (error) {
+      console.error('Failed to change task priority:', error);
+    }
+  };
+
   const handleRemove = async (taskId: string) => {
     try {
       await TauriAPI.removeTask(taskId);
@@ -72,19 +114,36 @@ function DownloadQueue() {
     }
   };
 
-  const handleOpenFolder = async (filePath: string) => {
+  const handleRevealFile = async (filePath: string) => {
     try {
-      await TauriAPI.openFolder(filePath);
+      await TauriAPI.revealFile(filePath);
     } catch (error) {
-      console.error('Failed to open folder:', error);
+      console.error('Failed to show file in folder:', error);
     }
   };
 
-  const handleClearCompleted = () => {
-    const completedTasks = tasks.filter(t => t.status === 'Completed' || t.status === 'Failed' || t.status === 'Cancelled');
-    completedTasks.forEach(task => {
-      handleRemove(task.task_id);
-    });
+  const handleOpenFile = async (filePath: string) => {
+    try {
+      await TauriAPI.openFile(filePath);
+    } catch (error) {
+      console.error('Failed to open file:', error);
+    }
+  };
+
+  const handleCopyPath = async (taskId: string) => {
+    try {
+      await TauriAPI.copyToClipboard(taskId, 'path');
+    } catch (error) {
+      console.error('Failed to copy file path:', error);
+    }
+  };
+
+  const handleClearCompleted = async () => {
+    try {
+      await TauriAPI.clearFinishedTasks();
+    } catch (error) {
+      console.error('Failed to clear finished tasks:', error);
+    }
   };
 
   const getStatusIcon = (status: TaskStatus) => {
@@ -102,6 +161,8 @@ function DownloadQueue() {
         return <Loader2 className="w-4 h-4 text-slate-400" />;
       case 'Cancelled':
         return <X className="w-4 h-4 text-slate-400" />;
+      case 'Interrupted':
+        return <AlertCircle className="w-4 h-4 text-yellow-400" />;
     }
   };
 
@@ -119,6 +180,7 @@ function DownloadQueue() {
       case 'Queued':
         return 'bg-slate-500';
       case 'Cancelled':
+      case 'Interrupted':
         return 'bg-slate-600';
     }
   };
@@ -160,13 +222,40 @@ function DownloadQueue() {
                     <div className="flex gap-1 flex-shrink-0">
                       {task.status === 'Completed' && task.file_path && (
                         <button 
-                          onClick={() => handleOpenFolder(task.file_path!)}
+                          onClick={() => handleRevealFile(task.file_path!)}
                           className="p-0.5 hover:bg-slate-700 rounded transition-all"
-                          title="Open folder"
+                          title="Show in folder"
                         >
                           <FolderOpen className="w-3 h-3 lg:w-3.5 lg:h-3.5 text-cyan-400" />
                         </button>
                       )}
+                      {task.status === 'Completed' && task.file_path && (
+                        <button 
+                          onClick={() => handleOpenFile(task.file_path!)}
+                          className="p-0.5 hover:bg-slate-700 rounded transition-all"
+                          title="Open file"
+                        >
+                          <Play className="w-3 h-3 lg:w-3.5 lg:h-3.5 text-cyan-400" />
+                        </button>
+                      )}
+                      {task.status === 'Completed' && task.file_path && (
+                        <button 
+                          onClick={() => handleCopyPath(task.task_id)}
+                          className="p-0.5 hover:bg-slate-700 rounded transition-all"
+                          title="Copy path"
+                        >
+                          <Copy className="w-3 h-3 lg:w-3.5 lg:h-3.5 text-cyan-400" />
+                        </button>
+                      )}
+                      {task.status === 'Queued' && (
+                        <button 
+                          onClick={() => handleBump(task.task_id)}
+                          className="p-0.5 hover:bg-slate-700 rounded transition-all"
+                          title="Start next"
+                        >
+                          <ArrowUp className="w-3 h-3 lg:w-3.5 lg:h-3.5 text-slate-400" />
+                        </button>
+                      )}
                       {task.status === 'Downloading' && (
                         <button 
                           onClick={() => handlePause(task.task_id)}
@@ -176,7 +265,7 @@ function DownloadQueue() {
                           <Pause className="w-3 h-3 lg:w-3.5 lg:h-3.5 text-slate-400" />
                         </button>
                       )}
-                      {task.status !== 'Completed' && task.status !== 'Cancelled' && (
+                      {task.status !== 'Completed' && task.status !== 'Cancelled' && task.status !== 'Interrupted' && (
                         <button 
                           onClick={() => handleCancel(task.task_id)}
                           className="p-0.5 hover:bg-slate-700 rounded transition-all"
@@ -185,7 +274,7 @@ function DownloadQueue() {
                           <X className="w-3 h-3 lg:w-3.5 lg:h-3.5 text-slate-400" />
                         </button>
                       )}
-                      {(task.status === 'Completed' || task.status === 'Failed' || task.status === 'Cancelled') && (
+                      {(task.status === 'Completed' || task.status === 'Failed' || task.status === 'Cancelled' || task.status === 'Interrupted') && (
                         <button 
                           onClick={() => handleRemove(task.task_id)}
                           className="p-0.5 hover:bg-slate-700 rounded transition-all"
@@ -200,15 +289,22 @@ function DownloadQueue() {
                   <div className="space-y-1.5">
                     <div className="w-full h-1.5 lg:h-2 bg-slate-900 rounded-full overflow-hidden">
                       <div
-                        className={`h-full ${getProgressColor(task.status)} transition-all duration-300 rounded-full`}
-                        style={{ width: `${task.progress}%` }}
+                        className={`h-full ${getProgressColor(task.status)} transition-all duration-300 rounded-full ${task.is_live && task.status === 'Downloading' ? 'animate-pulse' : ''}`}
+                        style={{ width: task.is_live && task.status === 'Downloading' ? '100%' : `${task.progress}%` }}
                       />
                     </div>
 
                     <div className="flex justify-between text-[10px] lg:text-xs text-slate-400">
-                      <span>{task.progress.toFixed(1)}%</span>
+                      {task.is_live ? (
+                        <span>
+                          LIVE {task.elapsed_seconds != null && formatElapsed(task.elapsed_seconds)}
+                          {task.downloaded_bytes != null && ` · ${formatBytes(task.downloaded_bytes)}`}
+                        </span>
+                      ) : (
+                        <span>{task.progress.toFixed(1)}%</span>
+                      )}
                       {task.speed && <span className="hidden lg:inline truncate">{task.speed}</span>}
-                      {task.eta && <span className="hidden lg:inline truncate">ETA: {task.eta}</span>}
+                      {task.eta && !task.is_live && <span className="hidden lg:inline truncate">ETA: {task.eta}</span>}
                     </div>
 
                     {task.error && (
@@ -1,9 +1,9 @@
-import { useState } from 'react';
-import { Link, Music, Video, List, Scissors, FolderOpen, Plus, X } from 'lucide-react';
+import { useEffect, useState } from 'react';
+import { Link, Music, Video, List, Scissors, FolderOpen, Plus, X, Radio, ListOrdered, Globe, FileText, Layers, ShieldCheck, Bookmark } from 'lucide-react';
 import { TauriAPI } from '../api/tauri';
 import { useToastContext } from '../contexts/ToastContext';
 import { validateUrls, validateTimeRange, validateOutputPath } from '../utils/validation';
-import type { DownloadType, MediaFormat } from '../types/tauri';
+import type { Config, ConflictPolicy, DownloadType, MediaFormat } from '../types/tauri';
 
 function DownloadSection() {
   const [downloadType, setDownloadType] = useState<DownloadType>('Single');
@@ -12,15 +12,40 @@ function DownloadSection() {
   const [quality, setQuality] = useState('1080');
   const [audioQuality, setAudioQuality] = useState('192');
   const [enableTrim, setEnableTrim] = useState(false);
+  const [isLive, setIsLive] = useState(false);
+  const [splitChapters, setSplitChapters] = useState(false);
+  const [keepSeparate, setKeepSeparate] = useState(false);
+  const [allowGeneric, setAllowGeneric] = useState(false);
+  const [subtitlesOnly, setSubtitlesOnly] = useState(false);
+  const [verify, setVerify] = useState(false);
+  const [embedChapters, setEmbedChapters] = useState(false);
+  const [subtitleLangs, setSubtitleLangs] = useState('en');
   const [startTime, setStartTime] = useState('00:00:00');
   const [endTime, setEndTime] = useState('00:00:00');
   const [downloadPath, setDownloadPath] = useState('~/Downloads');
+  const [maxFilesize, setMaxFilesize] = useState('');
+  const [conflictPolicy, setConflictPolicy] = useState<ConflictPolicy>('Skip');
   const [isDownloading, setIsDownloading] = useState(false);
   const [urlValidationResults, setUrlValidationResults] = useState<{ isValid: boolean; message?: string }[]>([{ isValid: true }]);
   const [timeValidationError, setTimeValidationError] = useState<string | null>(null);
   
   const { success, error, warning } = useToastContext();
 
+  // Start from the saved defaults, and follow them when they change
+  useEffect(() => {
+    const applyConfig = (config: Config) => {
+      setDownloadPath(config.download_path);
+      setFormat(config.download_format);
+    };
+    TauriAPI.getConfig()
+      .then(applyConfig)
+      .catch(err => console.error('Failed to load settings:', err));
+    const unsubscribe = TauriAPI.onSettingsChanged(applyConfig);
+    return () => {
+      unsubscribe.then(unsub => unsub());
+    };
+  }, []);
+
   const addUrlField = () => {
     setUrls([...urls, '']);
     setUrlValidationResults([...urlValidationResults, { isValid: true }]);
@@ -39,7 +64,7 @@ function DownloadSection() {
     setUrls(newUrls);
     
     // Validate URLs and update results
-    const { results } = validateUrls(newUrls);
+    const { results } = validateUrls(newUrls, allowGeneric);
     setUrlValidationResults(results);
   };
 
@@ -72,16 +97,17 @@ function DownloadSection() {
     try {
       const selected = await TauriAPI.selectDirectory();
       if (selected) {
-        setDownloadPath(selected);
+        setDownloadPath(await TauriAPI.validateOutputDirectory(selected));
       }
-    } catch (error) {
-      console.error('Failed to open directory selector:', error);
+    } catch (err) {
+      console.error('Failed to select directory:', err);
+      error('Folder Not Usable', String(err));
     }
   };
 
   const handleStartDownload = async () => {
     // Validate URLs
-    const { results, hasErrors } = validateUrls(urls);
+    const { results, hasErrors } = validateUrls(urls, allowGeneric);
     setUrlValidationResults(results);
     
     if (hasErrors) {
@@ -125,6 +151,18 @@ function DownloadSection() {
           start_time: startTime,
           end_time: endTime,
         } : undefined,
+        live: isLive,
+        split_chapters: splitChapters,
+        merge: format === 'mp4' && keepSeparate ? false : undefined,
+        allow_generic: allowGeneric,
+        subtitles_only: subtitlesOnly,
+        subtitle_langs: subtitlesOnly
+          ? subtitleLangs.split(',').map(lang => lang.trim()).filter(lang => lang !== '')
+          : undefined,
+        max_filesize: maxFilesize || undefined,
+        conflict_policy: conflictPolicy,
+        verify: subtitlesOnly ? undefined : verify,
+        embed_chapters: format === 'mp4' && !subtitlesOnly ? embedChapters : undefined,
       };
 
       const taskIds = await TauriAPI.startDownload(request);
@@ -133,6 +171,8 @@ function DownloadSection() {
       // Reset form
       setUrls(['']);
       setEnableTrim(false);
+      setIsLive(false);
+      setSplitChapters(false);
       success('Download Started', `Started ${taskIds.length} download(s) successfully!`);
     } catch (err) {
       console.error('Download failed:', err);
@@ -284,6 +324,36 @@ function DownloadSection() {
             )}
           </div>
 
+          <div>
+            <label className="block text-xs sm:text-sm font-medium text-slate-300 mb-2 sm:mb-3">Max File Size</label>
+            <select
+              value={maxFilesize}
+              onChange={(e) => setMaxFilesize(e.target.value)}
+              style={{ backgroundColor: '#0f172a', color: 'white' }}
+              className="w-full px-3 sm:px-4 py-2 sm:py-3 text-xs sm:text-sm bg-slate-900/50 border border-slate-600 rounded-lg focus:outline-none focus:border-cyan-500 focus:ring-2 focus:ring-cyan-500/20 transition-all"
+            >
+              <option value="" style={{ backgroundColor: '#0f172a', color: 'white' }}>No limit</option>
+              <option value="100M" style={{ backgroundColor: '#0f172a', color: 'white' }}>100 MB</option>
+              <option value="500M" style={{ backgroundColor: '#0f172a', color: 'white' }}>500 MB</option>
+              <option value="1G" style={{ backgroundColor: '#0f172a', color: 'white' }}>1 GB</option>
+              <option value="4G" style={{ backgroundColor: '#0f172a', color: 'white' }}>4 GB</option>
+            </select>
+          </div>
+
+          <div>
+            <label className="block text-xs sm:text-sm font-medium text-slate-300 mb-2 sm:mb-3">If File Exists</label>
+            <select
+              value={conflictPolicy}
+              onChange={(e) => setConflictPolicy(e.target.value as ConflictPolicy)}
+              style={{ backgroundColor: '#0f172a', color: 'white' }}
+              className="w-full px-3 sm:px-4 py-2 sm:py-3 text-xs sm:text-sm bg-slate-900/50 border border-slate-600 rounded-lg focus:outline-none focus:border-cyan-500 focus:ring-2 focus:ring-cyan-500/20 transition-all"
+            >
+              <option value="Skip" style={{ backgroundColor: '#0f172a', color: 'white' }}>Skip (keep existing file)</option>
+              <option value="Rename" style={{ backgroundColor: '#0f172a', color: 'white' }}>Save as a new copy</option>
+              <option value="Overwrite" style={{ backgroundColor: '#0f172a', color: 'white' }}>Overwrite</option>
+            </select>
+          </div>
+
           <div>
             <label className="block text-xs sm:text-sm font-medium text-slate-300 mb-2 sm:mb-3">Download Location</label>
             <div className="flex gap-2 min-w-0">
@@ -305,11 +375,165 @@ function DownloadSection() {
         </div>
 
         <div className="border-t border-slate-700 pt-4 sm:pt-6">
+          <label className="flex items-center gap-2 sm:gap-3 cursor-pointer group mb-3 sm:mb-4">
+            <input
+              type="checkbox"
+              checked={allowGeneric}
+              onChange={(e) => {
+                setAllowGeneric(e.target.checked);
+                setUrlValidationResults(validateUrls(urls, e.target.checked).results);
+              }}
+              className="w-4 h-4 sm:w-5 sm:h-5 rounded border-slate-600 bg-slate-900/50 text-cyan-500 focus:ring-2 focus:ring-cyan-500/20"
+            />
+            <div className="flex items-center gap-1 sm:gap-2">
+              <Globe className="w-3.5 h-3.5 sm:w-4 sm:h-4 text-slate-400 group-hover:text-cyan-400 transition-colors" />
+              <span className="text-xs sm:text-base font-medium text-slate-300 group-hover:text-slate-100 transition-colors">
+                Allow other sites (Vimeo, SoundCloud, Twitch...)
+              </span>
+            </div>
+          </label>
+
+          <label className="flex items-center gap-2 sm:gap-3 cursor-pointer group mb-3 sm:mb-4">
+            <input
+              type="checkbox"
+              checked={isLive}
+              onChange={(e) => {
+                setIsLive(e.target.checked);
+                if (e.target.checked) setKeepSeparate(false);
+              }}
+              className="w-4 h-4 sm:w-5 sm:h-5 rounded border-slate-600 bg-slate-900/50 text-cyan-500 focus:ring-2 focus:ring-cyan-500/20"
+            />
+            <div className="flex items-center gap-1 sm:gap-2">
+              <Radio className="w-3.5 h-3.5 sm:w-4 sm:h-4 text-slate-400 group-hover:text-cyan-400 transition-colors" />
+              <span className="text-xs sm:text-base font-medium text-slate-300 group-hover:text-slate-100 transition-colors">
+                Record live stream from start
+              </span>
+            </div>
+          </label>
+
+          <label className="flex items-center gap-2 sm:gap-3 cursor-pointer group mb-3 sm:mb-4">
+            <input
+              type="checkbox"
+              checked={splitChapters}
+              onChange={(e) => {
+                setSplitChapters(e.target.checked);
+                // Chapters are cut from the full video, so trimming doesn't apply
+                if (e.target.checked) {
+                  setEnableTrim(false);
+                  setKeepSeparate(false);
+                }
+              }}
+              className="w-4 h-4 sm:w-5 sm:h-5 rounded border-slate-600 bg-slate-900/50 text-cyan-500 focus:ring-2 focus:ring-cyan-500/20"
+            />
+            <div className="flex items-center gap-1 sm:gap-2">
+              <ListOrdered className="w-3.5 h-3.5 sm:w-4 sm:h-4 text-slate-400 group-hover:text-cyan-400 transition-colors" />
+              <span className="text-xs sm:text-base font-medium text-slate-300 group-hover:text-slate-100 transition-colors">
+                Split by chapters
+              </span>
+            </div>
+          </label>
+
+          {format === 'mp4' && !subtitlesOnly && (
+            <label className="flex items-center gap-2 sm:gap-3 cursor-pointer group mb-3 sm:mb-4">
+              <input
+                type="checkbox"
+                checked={embedChapters}
+                onChange={(e) => setEmbedChapters(e.target.checked)}
+                className="w-4 h-4 sm:w-5 sm:h-5 rounded border-slate-600 bg-slate-900/50 text-cyan-500 focus:ring-2 focus:ring-cyan-500/20"
+              />
+              <div className="flex items-center gap-1 sm:gap-2">
+                <Bookmark className="w-3.5 h-3.5 sm:w-4 sm:h-4 text-slate-400 group-hover:text-cyan-400 transition-colors" />
+                <span className="text-xs sm:text-base font-medium text-slate-300 group-hover:text-slate-100 transition-colors">
+                  Embed chapter markers
+                </span>
+              </div>
+            </label>
+          )}
+
+          {!subtitlesOnly && (
+            <label className="flex items-center gap-2 sm:gap-3 cursor-pointer group mb-3 sm:mb-4">
+              <input
+                type="checkbox"
+                checked={verify}
+                onChange={(e) => setVerify(e.target.checked)}
+                className="w-4 h-4 sm:w-5 sm:h-5 rounded border-slate-600 bg-slate-900/50 text-cyan-500 focus:ring-2 focus:ring-cyan-500/20"
+              />
+              <div className="flex items-center gap-1 sm:gap-2">
+                <ShieldCheck className="w-3.5 h-3.5 sm:w-4 sm:h-4 text-slate-400 group-hover:text-cyan-400 transition-colors" />
+                <span className="text-xs sm:text-base font-medium text-slate-300 group-hover:text-slate-100 transition-colors">
+                  Verify file after download
+                </span>
+              </div>
+            </label>
+          )}
+
+          {format === 'mp4' && (
+            <label className="flex items-center gap-2 sm:gap-3 cursor-pointer group mb-3 sm:mb-4">
+              <input
+                type="checkbox"
+                checked={keepSeparate}
+                onChange={(e) => {
+                  setKeepSeparate(e.target.checked);
+                  // Two unmerged files can't be split into chapters or recorded live
+                  if (e.target.checked) {
+                    setSplitChapters(false);
+                    setIsLive(false);
+                    setSubtitlesOnly(false);
+                  }
+                }}
+                className="w-4 h-4 sm:w-5 sm:h-5 rounded border-slate-600 bg-slate-900/50 text-cyan-500 focus:ring-2 focus:ring-cyan-500/20"
+              />
+              <div className="flex items-center gap-1 sm:gap-2">
+                <Layers className="w-3.5 h-3.5 sm:w-4 sm:h-4 text-slate-400 group-hover:text-cyan-400 transition-colors" />
+                <span className="text-xs sm:text-base font-medium text-slate-300 group-hover:text-slate-100 transition-colors">
+                  Keep video and audio as separate files
+                </span>
+              </div>
+            </label>
+          )}
+
+          <label className="flex items-center gap-2 sm:gap-3 cursor-pointer group mb-3 sm:mb-4">
+            <input
+              type="checkbox"
+              checked={subtitlesOnly}
+              onChange={(e) => {
+                setSubtitlesOnly(e.target.checked);
+                // Only the subtitle files are written, so media options don't apply
+                if (e.target.checked) {
+                  setEnableTrim(false);
+                  setSplitChapters(false);
+                  setIsLive(false);
+                  setKeepSeparate(false);
+                }
+              }}
+              className="w-4 h-4 sm:w-5 sm:h-5 rounded border-slate-600 bg-slate-900/50 text-cyan-500 focus:ring-2 focus:ring-cyan-500/20"
+            />
+            <div className="flex items-center gap-1 sm:gap-2">
+              <FileText className="w-3.5 h-3.5 sm:w-4 sm:h-4 text-slate-400 group-hover:text-cyan-400 transition-colors" />
+              <span className="text-xs sm:text-base font-medium text-slate-300 group-hover:text-slate-100 transition-colors">
+                Subtitles only
+              </span>
+            </div>
+          </label>
+
+          {subtitlesOnly && (
+            <input
+              type="text"
+              value={subtitleLangs}
+              onChange={(e) => setSubtitleLangs(e.target.value)}
+              placeholder="Languages, e.g. en, es"
+              className="w-full mb-3 sm:mb-4 px-3 sm:px-4 py-2 sm:py-3 text-xs sm:text-sm bg-slate-900/50 border border-slate-600 rounded-lg focus:outline-none focus:border-cyan-500 focus:ring-2 focus:ring-cyan-500/20 transition-all"
+            />
+          )}
+
           <label className="flex items-center gap-2 sm:gap-3 cursor-pointer group">
             <input
               type="checkbox"
               checked={enableTrim}
-              onChange={(e) => setEnableTrim(e.target.checked)}
+              onChange={(e) => {
+                setEnableTrim(e.target.checked);
+                if (e.target.checked) setSplitChapters(false);
+              }}
               className="w-4 h-4 sm:w-5 sm:h-5 rounded border-slate-600 bg-slate-900/50 text-cyan-500 focus:ring-2 focus:ring-cyan-500/20"
             />
             <div className="flex items-center gap-1 sm:gap-2">
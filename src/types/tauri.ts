@@ -1,6 +1,7 @@
 export type DownloadType = 'Single' | 'Bulk' | 'Playlist';
 export type MediaFormat = 'mp4' | 'mp3';
-export type TaskStatus = 'Queued' | 'Downloading' | 'Processing' | 'Paused' | 'Completed' | 'Failed' | 'Cancelled';
+export type ConflictPolicy = 'Skip' | 'Overwrite' | 'Rename';
+export type TaskStatus = 'Queued' | 'Downloading' | 'Processing' | 'Paused' | 'Completed' | 'Failed' | 'Cancelled' | 'Interrupted';
 
 export interface TrimSettings {
   start_time: string;
@@ -15,6 +16,21 @@ export interface DownloadRequest {
   audio_quality?: string;
   download_path: string;
   trim?: TrimSettings;
+  timeout_secs?: number; // 0 disables the timeout
+  live?: boolean;
+  split_chapters?: boolean; // cannot be combined with trim
+  priority?: number; // higher starts first
+  allow_generic?: boolean; // accept non-YouTube sites supported by yt-dlp
+  subtitles_only?: boolean; // write subtitle files without downloading the media
+  subtitle_langs?: string[]; // e.g. ["en", "es.*"]; defaults to English
+  max_filesize?: string; // e.g. "500M"; larger files are skipped
+  output_template?: string; // e.g. "%(uploader)s/%(title)s [%(id)s].%(ext)s", relative to download_path
+  conflict_policy?: ConflictPolicy; // defaults to 'Skip'
+  merge?: boolean; // false keeps the best video and audio as separate files (mp4 only)
+  verify?: boolean; // ffprobe each downloaded file; broken files fail the task and are deleted
+  embed_chapters?: boolean; // write chapter markers into the file (mp4 only)
+  post_process_command?: string[]; // program and args run on each output, no shell; '{file}' is the output path
+  post_process_ignore_failure?: boolean; // only warn when the command fails
 }
 
 export interface TaskProgress {
@@ -26,23 +42,179 @@ export interface TaskProgress {
   eta?: string;
   error?: string;
   file_path?: string;
+  speed_bytes_per_sec?: number;
+  eta_seconds?: number;
+  total_bytes?: number;
+  downloaded_bytes?: number;
+  is_live?: boolean;
+  elapsed_seconds?: number;
+  output_files?: string[];
+  priority?: number;
+  warning?: string;
+  batch_id?: string; // shared by the tasks of one request
+  estimated_bytes?: number; // expected conversion output size
+  input_size?: number; // image conversions: bytes before
+  output_size?: number; // image conversions: bytes after
+  attempt?: number; // current try, from 1; 0 until the task runs
+  max_attempts?: number;
+  error_history?: string[]; // errors of earlier tries, oldest first, at most 5
+  started_at?: number; // Unix milliseconds, when first downloading or processing
+  finished_at?: number; // Unix milliseconds, when completed, failed or cancelled
 }
 
-export type ConversionType = 'Image' | 'Video' | 'Audio';
+export interface BatchProgress {
+  batch_id: string;
+  total: number;
+  completed: number;
+  failed: number;
+  cancelled: number;
+  overall_progress: number; // 0-100, finished tasks count as 100
+}
+
+export interface TaskStats {
+  queue_depth: number; // tasks waiting for a slot
+  downloading: number;
+  processing: number;
+  paused: number;
+  completed: number;
+  failed: number;
+  cancelled: number;
+  active_bytes: number; // transferred so far by running tasks
+  total_speed_bytes_per_sec: number; // running tasks that report a speed
+}
+
+export type ProgressState = 'idle' | 'running' | 'paused'; // idle clears the indicator
+
+export interface GlobalProgress {
+  active_tasks: number; // queued, running and paused
+  overall_percent: number; // 0-100, weighted by size; live recordings left out
+  state: ProgressState;
+}
+
+export interface HistoryEntry {
+  task_id: string;
+  title: string;
+  url: string;
+  output_path?: string;
+  format: string; // 'mp4', 'mp3' or 'subtitles'
+  status: TaskStatus;
+  timestamp: number; // seconds since the Unix epoch
+  size_bytes?: number;
+  duration_ms?: number;
+}
+
+export interface HistoryQuery {
+  status?: TaskStatus;
+  since?: number; // seconds since the Unix epoch
+  until?: number;
+  search?: string; // case-insensitive title substring
+  offset?: number;
+  limit?: number; // defaults to 50, at most 500
+}
+
+export interface HistoryPage {
+  entries: HistoryEntry[];
+  total: number; // matches across all pages
+}
+
+export interface Config {
+  version?: number; // settings file layout, filled in by the backend
+  download_path: string;
+  download_format: MediaFormat;
+  conversion_output_path: string;
+  max_concurrent_downloads: number; // 1-10, applies from the next start
+  max_queue_size: number; // 1-10000 unfinished tasks each for downloads and conversions
+  download_timeout_secs: number; // 0 disables
+  conversion_timeout_secs: number; // 0 disables
+  notifications: boolean;
+}
+
+// Auto picks Image, Video or Audio for each input, converting it to the matching auto_formats entry
+export type ConversionType = 'Image' | 'Video' | 'Audio' | 'Auto';
+
+export interface AutoFormats {
+  image?: string;
+  video?: string;
+  audio?: string;
+}
+
+// Where converted files go; MirrorTree recreates the inputs' folders under root
+export type OutputMode = 'SingleDirectory' | 'AlongsideInput' | { MirrorTree: { root: string } };
+
+export interface WatermarkSettings {
+  path: string;
+  position: 'northwest' | 'north' | 'northeast' | 'west' | 'center' | 'east' | 'southwest' | 'south' | 'southeast';
+  opacity: number;
+  scale?: number;
+}
 
 export interface VideoSettings {
   resolution?: string;
   bitrate?: string;
+  watermark?: WatermarkSettings;
+  strip_metadata?: boolean;
+  preserve_metadata?: boolean; // copy metadata and chapters; unset does unless strip_metadata
+  fps?: number; // 1-240, also used for animated GIF/WebP/AVIF output
+  deinterlace?: boolean; // yadif, applied before the frame rate change
+  stabilize?: boolean; // vid.stab, in an extra analysis pass; needs ffmpeg with libvidstab
+  tonemap_sdr?: boolean; // HDR to SDR; unset does it for HDR input unless copying streams
+  audio_mode?: 'keep' | 'remove' | 'replace';
+  replacement_audio?: string; // required with 'replace'
+  shortest?: boolean; // end with the shorter of video and replacement audio
+  target_size_mb?: number; // two-pass encode to about this size; mp4/mkv/mov/avi/flv/webm, not with bitrate
+  target_audio_kbps?: number; // audio bitrate within the target size, 8-512; defaults to 128
+  keyframe_interval?: number; // frames between keyframes, 1-1000
+  codec?: 'h264' | 'h265'; // defaults to H.264; H.265 needs mp4/mkv/mov
+  profile?: 'baseline' | 'main' | 'high' | 'high10' | 'high422' | 'high444' // H.264; mp4/mkv/mov/avi/flv only
+    | 'main10' | 'main422-10' | 'main444-8' | 'main444-10'; // H.265 ('main' too)
+  level?: string; // e.g. '3.0' or '4.2'
+  prevent_upscale?: boolean; // lower bitrate and resolution to the source's; noted on the task
+  copy_streams?: boolean; // remux without re-encoding when the codecs fit the container
 }
 
 export interface AudioSettings {
   bitrate?: string;
   sample_rate?: string;
+  channels?: number;
+  volume?: number;
+  metadata?: AudioMetadata;
+  cover_art?: string;
+  bit_depth?: number;
+  compression_level?: number;
+  strip_metadata?: boolean;
+  fade_in_secs?: number;
+  fade_out_secs?: number;
+  trim_silence?: boolean; // cut leading and trailing silence; fades apply to the trimmed clip
+  prevent_upscale?: boolean; // lower bitrate to the source's; noted on the task
+}
+
+export interface AudioMetadata {
+  title?: string;
+  artist?: string;
+  album?: string;
+  year?: string;
+  track?: string;
+  genre?: string;
 }
 
 export interface ImageSettings {
   quality?: number;
   resize?: string;
+  crop?: string;
+  rotate?: number;
+  flip?: 'horizontal' | 'vertical';
+  watermark?: WatermarkSettings;
+  strip_metadata?: boolean;
+  effort?: number; // AVIF only, 0-9
+  density?: number; // DPI for SVG input, 1-1200
+  animation?: boolean; // false converts only the first frame
+  effects?: string[]; // 'grayscale', 'sepia', 'negate', 'auto-level', 'sharpen', 'blur:<radius>'
+  background?: string; // '#rgb' or '#rrggbb' behind transparency in JPEG output; defaults to white
+  dpi?: number; // 1-2400, written to the output's metadata without resampling
+  optimize?: boolean; // smaller PNG/JPEG/WebP output with a default quality; sizes are reported on the task
+  colorspace?: 'sRGB' | 'CMYK' | 'Gray'; // drops the input's ICC profile
+  strip_icc?: boolean; // drop only the ICC profile
+  keep_icc?: boolean; // keep the ICC profile when strip_metadata or optimize drops metadata
 }
 
 export interface ConvertRequest {
@@ -50,14 +222,164 @@ export interface ConvertRequest {
   conversion_type: ConversionType;
   output_format: string;
   output_path: string;
+  output_mode?: OutputMode;
+  video_settings?: VideoSettings;
+  audio_settings?: AudioSettings;
+  image_settings?: ImageSettings;
+  timeout_secs?: number; // 0 disables the timeout
+  filename_template?: string; // e.g. "{stem}_1080p.{ext}"; also {format}, {date}, {counter}, {index} (zero-padded)
+  conflict_policy?: ConflictPolicy; // when the output exists; unset overwrites, never the input itself
+  verify_output?: boolean; // defaults to on for video and audio, off for images
+  preset?: string; // saved preset filling in unset fields; output_format may then be ''
+  auto_formats?: AutoFormats; // with 'Auto'; inputs of a kind without a format fail
+  delete_source_on_success?: boolean; // kept if the output already existed or is the input
+  post_process_command?: string[]; // program and args run on each output, no shell; '{file}' is the output path
+  post_process_ignore_failure?: boolean; // only warn when the command fails
+}
+
+export interface PresetSettings {
+  conversion_type: ConversionType;
+  output_format?: string;
   video_settings?: VideoSettings;
   audio_settings?: AudioSettings;
   image_settings?: ImageSettings;
 }
 
+export interface ConversionPreset extends PresetSettings {
+  name: string;
+}
+
+export interface ConvertFolderRequest {
+  folder: string;
+  filter?: string[]; // extensions ("mp4") or name patterns ("clip_*.mov")
+  max_depth?: number; // default 8, max 32
+  dry_run?: boolean;
+  conversion: Omit<ConvertRequest, 'input_files'>;
+}
+
+export interface ConvertFolderResult {
+  matched_files: string[];
+  task_ids: string[]; // empty for a dry run
+}
+
+export interface StreamProbe {
+  index: number;
+  codec_type: string; // "video", "audio", "subtitle", ...
+  codec_name?: string;
+  width?: number;
+  height?: number;
+  frame_rate?: number;
+  field_order?: string; // 'progressive', or 'tt', 'bb', 'tb', 'bt' when interlaced
+  interlaced: boolean; // worth converting with deinterlace
+  color_transfer?: string; // 'smpte2084' (PQ) or 'arib-std-b67' (HLG) for HDR
+  hdr: boolean;
+  bit_rate?: number; // bits per second
+  channels?: number;
+  sample_rate?: number;
+}
+
+export interface FileEstimate {
+  input_file: string;
+  estimated_bytes: number;
+}
+
+export interface ConversionEstimate {
+  files: FileEstimate[];
+  total_bytes: number;
+  disk_space_error?: string; // set when the output disk is too small
+}
+
+export interface MediaProbe {
+  container: string;
+  duration_secs?: number;
+  size_bytes?: number;
+  bit_rate?: number;
+  streams: StreamProbe[];
+  chapters: Chapter[];
+}
+
+// { Subtitle: n } writes the nth subtitle track as SRT; 'Chapters' writes an FFmpeg metadata file
+export type ExtractTarget = { Subtitle: number } | 'Chapters';
+
+export interface ExtractStreamsRequest {
+  input_file: string;
+  target?: ExtractTarget; // unset only lists the tracks and chapters
+  output_dir?: string; // defaults to the input's folder
+}
+
+export interface SubtitleTrack {
+  index: number; // among subtitle tracks, as used by ExtractTarget
+  stream_index: number;
+  codec_name?: string;
+  language?: string;
+  title?: string;
+  forced: boolean;
+  text: boolean; // image-based tracks can't be written as SRT
+}
+
+export interface Chapter {
+  start_secs: number;
+  end_secs: number;
+  title?: string;
+}
+
+export interface ExtractStreamsResult {
+  subtitles: SubtitleTrack[];
+  chapters: Chapter[];
+  output_path?: string; // never overwrites; a numbered name is used instead
+}
+
+export interface FileValidation {
+  path: string;
+  reason?: string; // why the file would be skipped; absent when it can be converted
+  detected_type?: ConversionType;
+  size_bytes?: number;
+}
+
+// 'path' copies the absolute path as text; 'uri' copies the file for pasting into a file manager
+export type ClipboardMode = 'path' | 'uri';
+
+export type InstallStrategy = 'Pip' | 'Pipx' | 'PackageManager' | 'Homebrew' | 'Winget' | 'StandaloneBinary';
+
+export interface YtdlpInstallResult {
+  strategy: InstallStrategy; // StandaloneBinary lives in the app's data folder
+  version: string;
+  path?: string;
+}
+
+export interface InstallProgress {
+  dependency: string; // 'ffmpeg'
+  line: string; // one line of installer output
+}
+
+export interface DependencyMissing {
+  dependency: string; // program name: 'yt-dlp', 'ffmpeg', 'ffprobe', 'magick' or 'convert'
+  task_id: string;
+}
+
+export interface TaskRemoved {
+  task_ids: string[];
+}
+
+export interface TaskFailed {
+  task_id: string;
+  error: string; // same message as the task's error
+  kind: string; // error variant, e.g. 'NetworkError' or 'DiskSpaceError'
+  retryable: boolean; // whether running the task again may succeed
+}
+
+export interface InstallResult {
+  installed: boolean;
+  command?: string; // the installer that ran
+  message?: string; // why nothing was installed, with what to run by hand
+  system_info: SystemInfo; // dependencies found afterwards
+}
+
 export interface SystemInfo {
   has_ytdlp: boolean;
   has_ffmpeg: boolean;
+  has_imagemagick: boolean; // 'magick', or ImageMagick 6's 'convert' outside Windows
   ytdlp_path?: string;
   ffmpeg_path?: string;
+  imagemagick_path?: string;
 }
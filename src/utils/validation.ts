@@ -10,9 +10,10 @@ export interface ValidationResult {
 
 /**
  * Validates YouTube URLs on the client side for immediate feedback
+ * With allowGeneric, any http(s) URL passes the pattern check
  * Note: Backend performs more comprehensive security validation
  */
-export const validateYouTubeUrl = (url: string): ValidationResult => {
+export const validateYouTubeUrl = (url: string, allowGeneric = false): ValidationResult => {
   if (!url || url.trim() === '') {
     return { isValid: false, message: 'URL cannot be empty' };
   }
@@ -34,6 +35,12 @@ export const validateYouTubeUrl = (url: string): ValidationResult => {
     return { isValid: false, message: 'URL contains invalid characters or schemes' };
   }
 
+  if (allowGeneric) {
+    return /^https?:\/\/[\w.-]+(:\d{1,5})?([/?#]\S*)?$/.test(trimmedUrl)
+      ? { isValid: true }
+      : { isValid: false, message: 'Please enter an http or https URL' };
+  }
+
   // Valid YouTube URL patterns (client-side check)
   const youtubePatterns = [
     /^https?:\/\/(www\.)?youtube\.com\/watch\?v=[\w-]{11}(&.*)?$/,
@@ -41,12 +48,17 @@ export const validateYouTubeUrl = (url: string): ValidationResult => {
     /^https?:\/\/(www\.)?youtube\.com\/playlist\?list=[\w-]+(&.*)?$/,
     /^https?:\/\/(music\.)?youtube\.com\/watch\?v=[\w-]{11}(&.*)?$/,
     /^https?:\/\/(www\.)?youtube\.com\/shorts\/[\w-]{11}(\?.*)?$/,
+    /^https?:\/\/(www\.)?youtube\.com\/live\/[\w-]{11}(\?.*)?$/,
+    /^https?:\/\/(www\.)?youtube\.com\/(@[\w.-]+|channel\/[\w-]+|c\/[\w-]+)\/live\/?$/,
+    // Supported music sites
+    /^https?:\/\/((www|m|on)\.)?soundcloud\.com\/[\w-]+(\/[\w-]+)*\/?(\?\S*)?$/,
+    /^https?:\/\/([\w-]+\.)?bandcamp\.com(\/[\w-]+)*\/?(\?\S*)?$/,
   ];
 
   const isValidYouTube = youtubePatterns.some(pattern => pattern.test(trimmedUrl));
   
   if (!isValidYouTube) {
-    return { isValid: false, message: 'Please enter a valid YouTube URL' };
+    return { isValid: false, message: 'Please enter a valid YouTube, SoundCloud or Bandcamp URL' };
   }
 
   return { isValid: true };
@@ -55,8 +67,8 @@ export const validateYouTubeUrl = (url: string): ValidationResult => {
 /**
  * Validates multiple URLs and returns validation results for each
  */
-export const validateUrls = (urls: string[]): { results: ValidationResult[]; hasErrors: boolean } => {
-  const results = urls.map(url => validateYouTubeUrl(url));
+export const validateUrls = (urls: string[], allowGeneric = false): { results: ValidationResult[]; hasErrors: boolean } => {
+  const results = urls.map(url => validateYouTubeUrl(url, allowGeneric));
   const hasErrors = results.some(result => !result.isValid);
   
   return { results, hasErrors };